@@ -0,0 +1,89 @@
+use crate::modifiers::{Modifier, Source};
+use crate::notifications::{notify, Kind};
+use crate::{Game, Point, Tower};
+
+// What a tower's ability does while it's active
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Effect {
+    // Multiplies the tower's damage by the given factor
+    Overcharge(f32),
+    // Lets each of the tower's shots hit up to the given number of enemies
+    Volley(usize),
+}
+
+// An ability the player can trigger on a tower, which then has to recharge before it can be used again
+#[derive(Clone, Copy)]
+pub struct Ability {
+    // Stores the ability's name, as shown on the tower panel
+    pub name: &'static str,
+    // Stores what the ability does
+    pub effect: Effect,
+    // Stores how long the ability lasts once triggered, in seconds
+    pub duration: f32,
+    // Stores how long the ability takes to recharge after being triggered, in seconds
+    pub cooldown: f32,
+}
+
+impl Tower {
+    fn active_effect(&self) -> Option<Effect> {
+        self.tower_type
+            .ability
+            .filter(|_| self.ability_time_left > 0.)
+            .map(|ability| ability.effect)
+    }
+
+    pub fn ability_modifier(&self) -> Option<Modifier> {
+        // The change an active ability makes to the tower's stats, if it makes one
+        let ability = self.tower_type.ability?;
+        match self.active_effect() {
+            Some(Effect::Overcharge(multiplier)) => Some(Modifier {
+                damage: multiplier,
+                ..Modifier::new(Source::Ability(ability.name))
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn targets_per_shot(&self) -> usize {
+        match self.active_effect() {
+            Some(Effect::Volley(targets)) => targets,
+            _ => 1,
+        }
+    }
+
+    pub fn ability_ready(&self) -> bool {
+        self.tower_type.ability.is_some() && self.ability_cooldown <= 0.
+    }
+
+    pub fn update_ability(&mut self, dt: f32) {
+        // The tower's stats go back to normal once its ability wears off
+        if self.ability_time_left > 0. && self.ability_time_left <= dt {
+            self.invalidate_stats();
+        }
+        self.ability_time_left = (self.ability_time_left - dt).max(0.);
+        self.ability_cooldown = (self.ability_cooldown - dt).max(0.);
+    }
+}
+
+impl Game {
+    pub fn use_ability(&mut self, position: Point) {
+        // Trigger the ability of the tower at the given position, which recharges instantly in a sandbox
+        let sandbox = self.rules.sandbox;
+        let Some(tower) = self.tower_at(&position).map(|i| &mut self.state.towers[i]) else {
+            return;
+        };
+        let Some(ability) = tower.tower_type.ability else {
+            return;
+        };
+        if !tower.ability_ready() {
+            notify(
+                format!("{} is still recharging", ability.name),
+                Kind::Warning,
+            );
+            return;
+        }
+        tower.ability_time_left = ability.duration;
+        tower.invalidate_stats();
+        tower.ability_cooldown = if sandbox { 0. } else { ability.cooldown };
+    }
+}
@@ -0,0 +1,61 @@
+use crate::notifications::{notify, Kind};
+use crate::{Enemy, GameState};
+
+// Most the difficulty factor moves after a single wave
+const ADAPTIVE_STEP: f32 = 0.1;
+// Lowest and highest the difficulty factor can go
+const ADAPTIVE_RANGE: (f32, f32) = (0.6, 1.6);
+// Lives at or below which a leaking player gets twice the help
+const ADAPTIVE_LOW_LIVES: i32 = 3;
+// Resources left over at the end of a wave beyond which the player is taken to be coasting
+const ADAPTIVE_EXCESS_RESOURCES: i32 = 200;
+// Share of the change to enemies' hit points that's also made to their speed
+const ADAPTIVE_SPEED_SHARE: f32 = 0.25;
+
+fn adjustment(leaks: i32, lives: i32, resources: i32) -> f32 {
+    // Work out how much to nudge the difficulty after a wave, easing off when enemies got through, more so when the
+    // player is nearly out of lives, and pushing harder after a clean wave, more so when resources are piling up
+    if leaks > 0 {
+        if lives <= ADAPTIVE_LOW_LIVES {
+            -ADAPTIVE_STEP
+        } else {
+            -ADAPTIVE_STEP / 2.
+        }
+    } else if resources > ADAPTIVE_EXCESS_RESOURCES {
+        ADAPTIVE_STEP
+    } else {
+        ADAPTIVE_STEP / 2.
+    }
+}
+
+impl GameState {
+    pub fn adapt_difficulty(&mut self) {
+        // Nudge the difficulty by how cleanly the wave that just ended went, telling the player when it changes
+        let leaks = self.wave_start_lives - self.lives;
+        let difficulty = (self.difficulty + adjustment(leaks, self.lives, self.resources))
+            .clamp(ADAPTIVE_RANGE.0, ADAPTIVE_RANGE.1);
+        if difficulty == self.difficulty {
+            return;
+        }
+        let change = if difficulty > self.difficulty {
+            "raised"
+        } else {
+            "eased"
+        };
+        self.difficulty = difficulty;
+        notify(
+            format!("Difficulty {} to x{:.2}", change, difficulty),
+            Kind::Info,
+        );
+    }
+}
+
+impl Enemy {
+    pub fn scale_difficulty(&mut self, difficulty: f32) {
+        // Change this enemy's copy of its type, like a wave modifier, with its speed changed less than its hit points
+        let max_hit_points = self.enemy_type.max_hit_points as f32 * difficulty;
+        self.enemy_type.max_hit_points = (max_hit_points.round() as i32).max(1);
+        self.hit_points = self.enemy_type.max_hit_points;
+        self.enemy_type.speed *= 1. + (difficulty - 1.) * ADAPTIVE_SPEED_SHARE;
+    }
+}
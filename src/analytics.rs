@@ -0,0 +1,122 @@
+use piston_window::*;
+
+use crate::label::{self, Align, TextStyle};
+use crate::theme;
+use crate::{Tower, TILE_SIZE};
+
+// Seconds over which a tower's recent damage fades away, which is roughly how far back its DPS looks
+pub const ANALYTICS_WINDOW: f32 = 10.;
+// How see-through the overlay's colors are over the towers
+const OVERLAY_ALPHA: f32 = 0.55;
+
+// What the analytics overlay colors towers by, to show which of them are pulling their weight
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Overlay {
+    #[default]
+    Off,
+    // Damage done lately, per second
+    Dps,
+    // What the tower cost for each enemy it's defeated
+    Efficiency,
+}
+
+impl Overlay {
+    pub fn next(&self) -> Overlay {
+        match self {
+            Overlay::Off => Overlay::Dps,
+            Overlay::Dps => Overlay::Efficiency,
+            Overlay::Efficiency => Overlay::Off,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Overlay::Off => "off",
+            Overlay::Dps => "damage per second",
+            Overlay::Efficiency => "gold per kill",
+        }
+    }
+
+    fn measure(&self, tower: &Tower) -> Option<f32> {
+        // Towers that haven't defeated anything yet don't have a gold per kill
+        match self {
+            Overlay::Off => None,
+            Overlay::Dps => Some(tower.recent_damage / ANALYTICS_WINDOW),
+            Overlay::Efficiency => {
+                (tower.kills > 0).then(|| tower.tower_type.cost as f32 / tower.kills as f32)
+            }
+        }
+    }
+
+    fn label(&self, value: f32) -> String {
+        match self {
+            Overlay::Efficiency => format!("{:.0}g", value),
+            _ => format!("{:.0}", value),
+        }
+    }
+}
+
+fn heat(fraction: f32) -> [f32; 4] {
+    // Blend from a cool blue for the worst tower, through yellow, to a hot red for the best
+    let fraction = fraction.clamp(0., 1.);
+    [
+        fraction.min(0.5) * 2.,
+        1. - (fraction * 2. - 1.).abs() * 0.6,
+        1. - fraction,
+        OVERLAY_ALPHA,
+    ]
+}
+
+pub fn draw(overlay: Overlay, towers: &[Tower], c: Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    // Color every tower by where it falls between the worst and the best of them, where more damage is better
+    // and less gold per kill is better, with towers that can't be measured yet left gray
+    if overlay == Overlay::Off {
+        return;
+    }
+    let values: Vec<Option<f32>> = towers.iter().map(|tower| overlay.measure(tower)).collect();
+    let (low, high) = values
+        .iter()
+        .flatten()
+        .fold((f32::MAX, f32::MIN), |(low, high), &value| {
+            (low.min(value), high.max(value))
+        });
+    let style = TextStyle {
+        size: 10,
+        align: Align::Center,
+        shadow: Some(theme::palette().text_shadow),
+        ..Default::default()
+    };
+    for (tower, value) in towers.iter().zip(values) {
+        let transform = c
+            .transform
+            .trans(tower.position.x.into(), tower.position.y.into());
+        let Some(value) = value else {
+            rectangle(
+                [0.5, 0.5, 0.5, OVERLAY_ALPHA],
+                [-3.0, -3.0, 31.0, 31.0],
+                transform,
+                g,
+            );
+            continue;
+        };
+        let fraction = if high > low {
+            (value - low) / (high - low)
+        } else {
+            1.
+        };
+        let fraction = match overlay {
+            Overlay::Efficiency => 1. - fraction,
+            _ => fraction,
+        };
+        rectangle(heat(fraction), [-3.0, -3.0, 31.0, 31.0], transform, g);
+        label::draw(
+            &overlay.label(value),
+            &style,
+            tower.position.x as f64 + TILE_SIZE as f64 / 2.0,
+            tower.position.y as f64 + TILE_SIZE as f64 + 10.0,
+            glyphs,
+            c,
+            g,
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use piston_window::*;
+use rusttype::Font;
+
+use crate::error::Error;
+
+// Copy of the game's font built into the executable, used when the one on disk can't be loaded
+pub const EMBEDDED_FONT: &[u8] =
+    include_bytes!("../assets/fonts/Atkinson-Hyperlegible-Regular-102.otf");
+
+// How textures are sampled when they're drawn bigger or smaller than they are
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Filtering {
+    // Blends neighboring texels, which keeps text and smooth art soft at any zoom
+    #[default]
+    Linear,
+    // Takes the nearest texel, which keeps pixel art crisp
+    Nearest,
+}
+
+impl Filtering {
+    pub fn name(&self) -> &'static str {
+        // The name the filtering is saved under in the settings file
+        match self {
+            Filtering::Linear => "linear",
+            Filtering::Nearest => "nearest",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Filtering> {
+        [Filtering::Linear, Filtering::Nearest]
+            .into_iter()
+            .find(|filtering| filtering.name() == name)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Filtering::Linear => "Smooth",
+            Filtering::Nearest => "Crisp (pixel art)",
+        }
+    }
+
+    pub fn next(&self) -> Filtering {
+        match self {
+            Filtering::Linear => Filtering::Nearest,
+            Filtering::Nearest => Filtering::Linear,
+        }
+    }
+
+    pub fn texture_settings(&self) -> TextureSettings {
+        let filter = match self {
+            Filtering::Linear => Filter::Linear,
+            Filtering::Nearest => Filter::Nearest,
+        };
+        TextureSettings::new().filter(filter)
+    }
+}
+
+// A shared reference to a loaded asset, which sees the new version when the asset is reloaded
+pub type Handle<T> = Rc<RefCell<T>>;
+
+struct Cached<T> {
+    // Stores the handle given out for the asset
+    handle: Handle<T>,
+    // Stores where the asset was loaded from
+    path: PathBuf,
+    // Stores when the file was last modified as of loading it, if it was loaded from a file
+    modified: Option<SystemTime>,
+}
+
+pub struct Assets {
+    // Stores directories searched before the game's own, so mods can replace assets
+    override_dirs: Vec<PathBuf>,
+    // Stores the directory of the current theme's assets, searched after mods but before the game's own
+    theme_dir: Option<PathBuf>,
+    // Stores the fonts loaded so far, by the path they were asked for with
+    fonts: HashMap<String, Cached<Glyphs>>,
+    // Stores how every texture is sampled, fonts' included
+    filtering: Filtering,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Assets {
+            override_dirs: Vec::new(),
+            theme_dir: None,
+            fonts: HashMap::new(),
+            filtering: Filtering::default(),
+        }
+    }
+
+    pub fn add_override_dir(&mut self, dir: &str) {
+        // Later directories take priority over earlier ones
+        self.override_dirs.insert(0, PathBuf::from(dir));
+    }
+
+    pub fn set_theme_dir(&mut self, dir: Option<String>, window: &mut PistonWindow) -> usize {
+        // Switch to another theme's assets, reloading every asset that now comes from somewhere else, in place so
+        // existing handles see it
+        self.theme_dir = dir.map(PathBuf::from);
+        let mut reloaded = 0;
+        let found: Vec<(String, PathBuf)> = self
+            .fonts
+            .keys()
+            .map(|relative| (relative.clone(), self.find(relative)))
+            .collect();
+        for (relative, path) in found {
+            let Some(cached) = self.fonts.get_mut(&relative) else {
+                continue;
+            };
+            if cached.path == path {
+                continue;
+            }
+            match load_font(window, &path, self.filtering) {
+                Ok(glyphs) => {
+                    *cached.handle.borrow_mut() = glyphs;
+                    cached.modified = modified(&path);
+                    cached.path = path;
+                    reloaded += 1;
+                }
+                Err(error) => eprintln!("Couldn't load {}: {}", path.display(), error),
+            }
+        }
+        reloaded
+    }
+
+    pub fn set_filtering(&mut self, filtering: Filtering, window: &mut PistonWindow) -> usize {
+        // Switch how textures are sampled, reloading every asset with the new settings, in place so existing handles
+        // see it
+        if filtering == self.filtering {
+            return 0;
+        }
+        self.filtering = filtering;
+        let mut reloaded = 0;
+        for cached in self.fonts.values_mut() {
+            match load_font(window, &cached.path, filtering) {
+                Ok(glyphs) => {
+                    *cached.handle.borrow_mut() = glyphs;
+                    reloaded += 1;
+                }
+                Err(error) => eprintln!("Couldn't reload {}: {}", cached.path.display(), error),
+            }
+        }
+        reloaded
+    }
+
+    pub fn find(&self, relative: &str) -> PathBuf {
+        // Look in the override directories first, then the theme's directory, then next to the working directory, then next to the
+        // executable and the folders above it, so the game finds its assets wherever it's run from
+        let executable_dirs = env::current_exe().ok().into_iter().flat_map(|executable| {
+            executable
+                .ancestors()
+                .skip(1)
+                .take(4)
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        });
+        self.override_dirs
+            .iter()
+            .cloned()
+            .chain(self.theme_dir.clone())
+            .chain(env::current_dir().ok())
+            .chain(executable_dirs)
+            .map(|dir| dir.join(relative))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| PathBuf::from(relative))
+    }
+
+    pub fn font(
+        &mut self,
+        window: &mut PistonWindow,
+        relative: &str,
+    ) -> Result<Handle<Glyphs>, Error> {
+        // Hand out the cached font if it's been loaded before
+        if let Some(cached) = self.fonts.get(relative) {
+            return Ok(Rc::clone(&cached.handle));
+        }
+        let path = self.find(relative);
+        let font = read_font(&path);
+        self.add_font(window, relative, path, font)
+    }
+
+    pub fn add_font(
+        &mut self,
+        window: &mut PistonWindow,
+        relative: &str,
+        path: PathBuf,
+        font: Result<Font<'static>, String>,
+    ) -> Result<Handle<Glyphs>, Error> {
+        // Cache a font that's already been read, which only the window's graphics are needed for from here
+        let glyphs = glyphs(window, &path, font, self.filtering)?;
+        let handle = Rc::new(RefCell::new(glyphs));
+        self.fonts.insert(
+            relative.to_string(),
+            Cached {
+                handle: Rc::clone(&handle),
+                modified: modified(&path),
+                path,
+            },
+        );
+        Ok(handle)
+    }
+
+    pub fn reload_changed(&mut self, window: &mut PistonWindow) -> usize {
+        // Reload every asset whose file changed since it was loaded, in place so existing handles see it
+        let filtering = self.filtering;
+        let mut reloaded = 0;
+        for cached in self.fonts.values_mut() {
+            let modified = modified(&cached.path);
+            if modified.is_none() || modified == cached.modified {
+                continue;
+            }
+            match load_font(window, &cached.path, filtering) {
+                Ok(glyphs) => {
+                    *cached.handle.borrow_mut() = glyphs;
+                    cached.modified = modified;
+                    reloaded += 1;
+                }
+                Err(error) => eprintln!("Couldn't reload {}: {}", cached.path.display(), error),
+            }
+        }
+        reloaded
+    }
+
+    pub fn summary(&self) -> String {
+        // Count the handles held outside the cache itself
+        let handles: usize = self
+            .fonts
+            .values()
+            .map(|cached| Rc::strong_count(&cached.handle) - 1)
+            .sum();
+        format!("{} fonts loaded, {} handles", self.fonts.len(), handles)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub fn read_font(path: &Path) -> Result<Font<'static>, String> {
+    // Read and parse the font file, which doesn't need the window, so it can be done on another thread
+    let bytes = fs::read(path).map_err(|error| error.to_string())?;
+    Font::try_from_vec(bytes).ok_or_else(|| String::from("not a font file"))
+}
+
+fn load_font(
+    window: &mut PistonWindow,
+    path: &Path,
+    filtering: Filtering,
+) -> Result<Glyphs, Error> {
+    glyphs(window, path, read_font(path), filtering)
+}
+
+fn glyphs(
+    window: &mut PistonWindow,
+    path: &Path,
+    font: Result<Font<'static>, String>,
+    filtering: Filtering,
+) -> Result<Glyphs, Error> {
+    // Prefer the font on disk, so it can be swapped out, and fall back to the built-in copy
+    let error = match font {
+        Ok(font) => {
+            return Ok(Glyphs::from_font(
+                font,
+                window.create_texture_context(),
+                filtering.texture_settings(),
+            ))
+        }
+        Err(error) => error,
+    };
+    if path.exists() {
+        eprintln!(
+            "Couldn't load font {}, using the built-in one: {}",
+            path.display(),
+            error
+        );
+    }
+    Glyphs::from_bytes(
+        EMBEDDED_FONT,
+        window.create_texture_context(),
+        filtering.texture_settings(),
+    )
+    .map_err(|_| Error::Font(path.display().to_string(), error))
+}
@@ -0,0 +1,688 @@
+use std::fs;
+use std::io;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::beats::BeatMap;
+use crate::content;
+use crate::convoy::ConvoyUnit;
+use crate::items::Wall;
+use crate::level::{parse_number, Level};
+use crate::loot::{Loot, LootDrop};
+use crate::naval::Lane;
+use crate::run::RunSetup;
+use crate::shop::{Offer, ShopOffer};
+use crate::storage;
+use crate::{Enemy, Game, Point, QueuedSpawn, Tower};
+
+// File the run in progress is autosaved to
+pub const AUTOSAVE_PATH: &str = "autosave.sav";
+// Version of the autosave format written by this build, where version 2 saves everything an enemy is in the middle
+// of, not just where it is
+const AUTOSAVE_VERSION: u32 = 2;
+// Number of older autosaves kept as backups, alongside the newest one
+const AUTOSAVE_BACKUPS: usize = 2;
+
+pub struct SavedRun {
+    // Stores how the saved run was set up
+    pub setup: RunSetup,
+    // Stores the saved run, ready to carry on playing
+    pub game: Game,
+    // Stores what the player should know about the run having been saved with different game data, if it was
+    pub warning: Option<String>,
+}
+
+pub fn save(path: &str, setup: &RunSetup, game: &Game) -> io::Result<()> {
+    // Keep the last few autosaves as backups
+    let contents = encode(setup, game);
+    storage::rotate_backups(path, AUTOSAVE_BACKUPS);
+    storage::write_packed(path, contents.as_bytes())
+}
+
+pub fn encode(setup: &RunSetup, game: &Game) -> String {
+    let state = &game.state;
+    let mut lines = vec![
+        format!("version {}", AUTOSAVE_VERSION),
+        format!("data {}", content::data_version()),
+        format!("prestige {}", setup.prestige),
+    ];
+    if let Some(day) = setup.daily {
+        lines.push(format!("daily {}", day));
+    }
+    for mutator in setup.mutators.iter() {
+        lines.push(format!("mutator {}", mutator));
+    }
+    if let Some(beats) = &setup.beats {
+        lines.push(format!("beat_length {}", beats.length));
+        for beat in beats.beats.iter() {
+            lines.push(format!("beat {}", beat));
+        }
+    }
+    // Save exactly where the run's random numbers are up to, rather than reseeding them, so a resumed run rolls the
+    // same as one that never stopped, and saving never changes the run being saved, which would throw a recording
+    // out of step with its replay
+    let rng = &state.rng;
+    let seed: String = rng
+        .get_seed()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    lines.push(format!(
+        "rng {} {} {}",
+        seed,
+        rng.get_stream(),
+        rng.get_word_pos()
+    ));
+    lines.push(format!("resources {}", state.resources));
+    lines.push(format!("lives {}", state.lives));
+    lines.push(format!("difficulty {}", state.difficulty));
+    lines.push(format!("wave_start_lives {}", state.wave_start_lives));
+    lines.push(format!("wave {}", state.wave));
+    lines.push(format!("building {}", state.building));
+    lines.push(format!("wave_timer {}", state.wave_timer));
+    lines.push(format!("spawn_timer {}", state.spawn_timer));
+    lines.push(format!("spell_charges {}", state.spell_charges));
+    lines.push(format!(
+        "damage_boost {} {}",
+        state.damage_boost, state.damage_boost_timer
+    ));
+    // The director's pace carries over from one wave to the next, so a resumed run picks it up where it was
+    let director = &state.director;
+    lines.push(format!(
+        "director {} {} {}",
+        director.pace,
+        director.recent_leaks,
+        director
+            .lives_seen
+            .map_or(String::from("none"), |lives| lives.to_string())
+    ));
+    lines.push(format!("towers_placed {}", state.towers_placed));
+    lines.push(format!(
+        "selected_tower_type {} {}",
+        state.selected_tower_type, game.tower_types[state.selected_tower_type].name
+    ));
+    lines.push(format!("playtime {}", state.stats.playtime));
+    lines.push(format!("tick {}", state.tick));
+    lines.push(format!("leaks {}", state.stats.leaks));
+    lines.push(format!("sold {}", state.stats.towers_sold));
+    lines.push(format!("rewinds {}", game.rewind.charges));
+    if game.rules.rewound {
+        lines.push(String::from("rewound"));
+    }
+    lines.push(format!(
+        "convoy_totals {} {}",
+        state.convoy_arrived, state.convoy_lost
+    ));
+    for split in state.stats.splits.iter() {
+        lines.push(format!("split {}", split));
+    }
+    // Anything saved by the index of its type has the type's name after it, for finding it again after a balance
+    // change
+    for &item in state.inventory.iter() {
+        lines.push(format!("item {} {}", item, game.item_types[item].name));
+    }
+    for (tower_type, discount) in state.tower_discounts.iter().enumerate() {
+        if let Some(name) = game
+            .tower_types
+            .get(tower_type)
+            .map(|tower_type| &tower_type.name)
+        {
+            lines.push(format!("discount {} {} {}", tower_type, discount, name));
+        }
+    }
+    if let Some(wave) = state.shop.restocked_on {
+        lines.push(format!("restocked_on {}", wave));
+    }
+    for shop_offer in state.shop.offers.iter() {
+        let (offer, name) = match shop_offer.offer {
+            Offer::Item(item) => (format!("item {}", item), Some(&game.item_types[item].name)),
+            Offer::TowerDiscount {
+                tower_type,
+                discount,
+            } => (
+                format!("discount {} {}", tower_type, discount),
+                Some(&game.tower_types[tower_type].name),
+            ),
+            Offer::ExtraLife => (String::from("life"), None),
+        };
+        let mut line = format!("offer {} {} {}", offer, shop_offer.price, shop_offer.sold);
+        if let Some(name) = name {
+            line += &format!(" {}", name);
+        }
+        lines.push(line);
+    }
+    // Gates come before the enemies, whose distances are along the path the gates lay out
+    for (i, gate) in game.gates.iter().enumerate() {
+        lines.push(format!("gate {} {} {}", i, gate.closed, gate.cooldown));
+    }
+    // The landing comes after the gates, which lay the lane out afresh
+    if let Some(lane) = &game.lane {
+        lines.push(match lane.landing {
+            Some((lane_distance, land_distance)) => {
+                format!("landing {} {}", lane_distance, land_distance)
+            }
+            None => String::from("landing none"),
+        });
+    }
+    for tower in state.towers.iter() {
+        let tower_type = game
+            .tower_types
+            .iter()
+            .position(|tower_type| tower_type.name == tower.tower_type.name)
+            .unwrap_or(0);
+        lines.push(format!(
+            "tower {} {} {} {} {} {} {} {}",
+            tower.id,
+            tower_type,
+            tower.position.x,
+            tower.position.y,
+            tower.cooldown,
+            tower.ability_cooldown,
+            tower.ability_time_left,
+            tower.tower_type.name
+        ));
+    }
+    for enemy in state.enemies.iter() {
+        let enemy_type = game
+            .enemy_types
+            .iter()
+            .position(|enemy_type| enemy_type.name == enemy.enemy_type.name)
+            .unwrap_or(0);
+        // The enemy's speed and hit points are saved as well as its type, since a wave's modifiers change them
+        lines.push(format!(
+            "enemy {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            enemy_type,
+            enemy.distance,
+            enemy.hit_points,
+            enemy.armor_shred,
+            enemy.shred_time_left,
+            enemy.on_lane as u8,
+            enemy.offset,
+            enemy.velocity.x,
+            enemy.velocity.y,
+            enemy.congestion,
+            enemy.time_since_damaged,
+            enemy.regenerated,
+            enemy.revealed as u8,
+            enemy.movement_timer,
+            enemy.burrowed as u8,
+            enemy
+                .last_hit_by
+                .map_or(String::from("none"), |tower| tower.to_string()),
+            enemy.attacking as u8,
+            enemy.wind.x,
+            enemy.wind.y,
+            enemy.enemy_type.speed,
+            enemy.enemy_type.max_hit_points,
+            enemy.enemy_type.name
+        ));
+    }
+    for spawn in state.spawn_queue.iter() {
+        lines.push(format!(
+            "queued {} {} {} {}",
+            spawn.enemy_type, spawn.delay, spawn.offset, game.enemy_types[spawn.enemy_type].name
+        ));
+    }
+    for unit in state.convoy.iter() {
+        lines.push(format!(
+            "convoy {} {} {}",
+            unit.distance, unit.hit_points, unit.delay
+        ));
+    }
+    for wall in state.walls.iter() {
+        lines.push(format!(
+            "wall {} {} {}",
+            wall.position.x, wall.position.y, wall.time_left
+        ));
+    }
+    for drop in state.loot.iter() {
+        let loot = match drop.loot {
+            Loot::Resources(amount) => format!("resources {}", amount),
+            Loot::DamageBoost {
+                multiplier,
+                duration,
+            } => format!("boost {} {}", multiplier, duration),
+            Loot::SpellCharge => String::from("spell"),
+            Loot::Item(item) => format!("item {} {}", item, game.item_types[item].name),
+        };
+        lines.push(format!(
+            "loot {} {} {} {}",
+            drop.position.x, drop.position.y, drop.time_left, loot
+        ));
+    }
+    for (name, count) in state.stats.kills.iter() {
+        lines.push(format!("kill {} {}", name, count));
+    }
+    for (name, count) in state.stats.towers_built.iter() {
+        lines.push(format!("built {} {}", name, count));
+    }
+    for (name, lives) in state.stats.leaked.iter() {
+        lines.push(format!("leaked {} {}", name, lives));
+    }
+
+    // The level goes last, since it spans the rest of the file
+    lines.push(String::from("level"));
+    lines.join("\n") + "\n" + &setup.level.to_string()
+}
+
+pub fn load(path: &str) -> Result<Option<SavedRun>, String> {
+    // No autosave just means there's no run to resume
+    let Some(contents) = storage::read_packed(path)? else {
+        return Ok(None);
+    };
+    let contents =
+        String::from_utf8(contents).map_err(|_| format!("{} isn't an autosave", path))?;
+    let (head, level) = contents
+        .split_once("\nlevel\n")
+        .ok_or("Autosave is missing its level")?;
+    let lines: Vec<(&str, &str)> = head
+        .lines()
+        .map(|line| line.split_once(' ').unwrap_or((line, "")))
+        .collect();
+    let version = match lines.first() {
+        Some(("version", version)) => parse_number::<u32>(version)?,
+        _ => 0,
+    };
+    if version == 0 || version > AUTOSAVE_VERSION {
+        return Err(String::from("Autosave version isn't supported"));
+    }
+
+    // Set the run up the same way it was first started, then put the saved state back on top
+    let mut setup = RunSetup {
+        level: Level::parse(level)?,
+        prestige: 0,
+        daily: None,
+        mutators: Vec::new(),
+        sandbox: false,
+        reverse: false,
+        beats: None,
+    };
+    let mut beats = String::new();
+    for &(key, value) in lines.iter() {
+        match key {
+            "prestige" => setup.prestige = parse_number(value)?,
+            "daily" => setup.daily = Some(parse_number(value)?),
+            "mutator" => setup.mutators.push(value.to_string()),
+            "beat_length" => beats += &format!("length {}\n", value),
+            "beat" => beats += &format!("beat {}\n", value),
+            _ => {}
+        }
+    }
+    if !beats.is_empty() {
+        setup.beats = Some(BeatMap::parse(&beats)?);
+    }
+    let mut game = setup.start()?;
+
+    // A run saved with different game data finds its towers and enemies again by name, and leaves out any the game
+    // no longer has, rather than trusting the indices they were saved under. Saves from before the data was
+    // versioned are taken to match it
+    let saved_data = lines
+        .iter()
+        .find(|(key, _)| *key == "data")
+        .map(|(_, value)| parse_number::<u64>(value))
+        .transpose()?;
+    let rebalanced = saved_data.is_some_and(|data| data != content::data_version());
+    let mut left_out = 0;
+
+    let state = &mut game.state;
+    for &(key, value) in lines.iter() {
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or("");
+        match key {
+            // Saves from before the random numbers' state was saved have a seed they were reseeded from instead
+            "seed" => state.rng = ChaCha12Rng::seed_from_u64(parse_number(value)?),
+            "rng" => {
+                let mut seed = [0; 32];
+                for (i, byte) in seed.iter_mut().enumerate() {
+                    *byte = field(0)
+                        .get(i * 2..i * 2 + 2)
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        .ok_or_else(|| format!("Expected a seed: {}", value))?;
+                }
+                state.rng = ChaCha12Rng::from_seed(seed);
+                state.rng.set_stream(parse_number(field(1))?);
+                state.rng.set_word_pos(parse_number(field(2))?);
+            }
+            "resources" => state.resources = parse_number(value)?,
+            "lives" => state.lives = parse_number(value)?,
+            "difficulty" => state.difficulty = parse_number(value)?,
+            "wave_start_lives" => state.wave_start_lives = parse_number(value)?,
+            "wave" => state.wave = parse_number(value)?,
+            "building" => state.building = value == "true",
+            "wave_timer" => state.wave_timer = parse_number(value)?,
+            "spawn_timer" => state.spawn_timer = parse_number(value)?,
+            "spell_charges" => state.spell_charges = parse_number(value)?,
+            "damage_boost" => {
+                state.set_damage_boost(parse_number(field(0))?);
+                state.damage_boost_timer = parse_number(field(1))?;
+            }
+            "director" => {
+                state.director.pace = parse_number(field(0))?;
+                state.director.recent_leaks = parse_number(field(1))?;
+                state.director.lives_seen = match field(2) {
+                    "none" => None,
+                    lives => Some(parse_number(lives)?),
+                };
+            }
+            "towers_placed" => state.towers_placed = parse_number(value)?,
+            "selected_tower_type" => {
+                let names = game.tower_types.iter().map(|tower_type| &tower_type.name);
+                state.selected_tower_type =
+                    find_type(names, "tower type", field(0), &fields, 1, rebalanced)?.unwrap_or(0);
+            }
+            "playtime" => state.stats.playtime = parse_number(value)?,
+            "tick" => state.tick = parse_number(value)?,
+            "leaks" => state.stats.leaks = parse_number(value)?,
+            "sold" => state.stats.towers_sold = parse_number(value)?,
+            "rewinds" => game.rewind.charges = parse_number(value)?,
+            "rewound" => game.rules.rewound = true,
+            "split" => state.stats.splits.push(parse_number(value)?),
+            "item" => {
+                let names = game.item_types.iter().map(|item_type| &item_type.name);
+                let Some(item) = find_type(names, "item type", field(0), &fields, 1, rebalanced)?
+                else {
+                    left_out += 1;
+                    continue;
+                };
+                state.inventory.push(item);
+            }
+            "discount" => {
+                let names = game.tower_types.iter().map(|tower_type| &tower_type.name);
+                let Some(tower_type) =
+                    find_type(names, "tower type", field(0), &fields, 2, rebalanced)?
+                else {
+                    left_out += 1;
+                    continue;
+                };
+                if state.tower_discounts.len() <= tower_type {
+                    state.tower_discounts.resize(tower_type + 1, 0.);
+                }
+                state.tower_discounts[tower_type] = parse_number(field(1))?;
+            }
+            "restocked_on" => state.shop.restocked_on = Some(parse_number(value)?),
+            "offer" => {
+                // Items and discounts have their type's name after the price and whether they're sold
+                let item_names = game.item_types.iter().map(|item_type| &item_type.name);
+                let tower_names = game.tower_types.iter().map(|tower_type| &tower_type.name);
+                let (offer, rest) = match field(0) {
+                    "item" => {
+                        match find_type(item_names, "item type", field(1), &fields, 4, rebalanced)?
+                        {
+                            Some(item) => (Offer::Item(item), 2),
+                            None => {
+                                left_out += 1;
+                                continue;
+                            }
+                        }
+                    }
+                    "discount" => {
+                        match find_type(
+                            tower_names,
+                            "tower type",
+                            field(1),
+                            &fields,
+                            5,
+                            rebalanced,
+                        )? {
+                            Some(tower_type) => (
+                                Offer::TowerDiscount {
+                                    tower_type,
+                                    discount: parse_number(field(2))?,
+                                },
+                                3,
+                            ),
+                            None => {
+                                left_out += 1;
+                                continue;
+                            }
+                        }
+                    }
+                    "life" => (Offer::ExtraLife, 1),
+                    _ => return Err(format!("Unknown shop offer: {}", value)),
+                };
+                state.shop.offers.push(ShopOffer {
+                    offer,
+                    price: parse_number(field(rest))?,
+                    sold: field(rest + 1) == "true",
+                });
+            }
+            "gate" => {
+                let gate = game
+                    .gates
+                    .get_mut(parse_number::<usize>(field(0))?)
+                    .ok_or_else(|| format!("Unknown gate: {}", value))?;
+                gate.closed = field(1) == "true";
+                gate.cooldown = parse_number(field(2))?;
+                game.path = game.route.build(&game.gates).0;
+                game.lane = game
+                    .lane
+                    .take()
+                    .map(|lane| Lane::new(lane.path, &game.path));
+            }
+            "landing" => {
+                if let Some(lane) = &mut game.lane {
+                    lane.landing = match field(0) {
+                        "none" => None,
+                        lane_distance => {
+                            Some((parse_number(lane_distance)?, parse_number(field(1))?))
+                        }
+                    };
+                }
+            }
+            "tower" => {
+                let names = game.tower_types.iter().map(|tower_type| &tower_type.name);
+                let Some(tower_type) =
+                    find_type(names, "tower type", field(1), &fields, 7, rebalanced)?
+                else {
+                    left_out += 1;
+                    continue;
+                };
+                let tower_type = &game.tower_types[tower_type];
+                let position = Point::new(parse_number(field(2))?, parse_number(field(3))?);
+                let mut tower = Tower::new(parse_number(field(0))?, position, tower_type.clone());
+                tower.cooldown = parse_number(field(4))?;
+                // Saves from before abilities don't have their timers, which leaves them ready
+                if fields.len() > 5 {
+                    tower.ability_cooldown = parse_number(field(5))?;
+                    tower.ability_time_left = parse_number(field(6))?;
+                }
+                // Timers left over from the old balance can't run longer than the current one allows
+                if rebalanced {
+                    tower.cooldown = tower.cooldown.min(1. / tower_type.rate_of_fire);
+                    let (cooldown, duration) = tower_type
+                        .ability
+                        .as_ref()
+                        .map_or((0., 0.), |ability| (ability.cooldown, ability.duration));
+                    tower.ability_cooldown = tower.ability_cooldown.min(cooldown);
+                    tower.ability_time_left = tower.ability_time_left.min(duration);
+                }
+                state.towers.push(tower);
+            }
+            "enemy" => {
+                // Version 1 saves have the name straight after whether the enemy's on the lane
+                let name_field = if version < 2 { 6 } else { 21 };
+                let names = game.enemy_types.iter().map(|enemy_type| &enemy_type.name);
+                let Some(enemy_type) = find_type(
+                    names,
+                    "enemy type",
+                    field(0),
+                    &fields,
+                    name_field,
+                    rebalanced,
+                )?
+                else {
+                    left_out += 1;
+                    continue;
+                };
+                let mut enemy = Enemy::new(game.enemy_types[enemy_type].clone(), &game.path);
+                enemy.distance = parse_number(field(1))?;
+                enemy.hit_points = parse_number(field(2))?;
+                if version >= 2 {
+                    enemy.offset = parse_number(field(6))?;
+                    enemy.velocity = Point::new(parse_number(field(7))?, parse_number(field(8))?);
+                    enemy.congestion = parse_number(field(9))?;
+                    enemy.time_since_damaged = parse_number(field(10))?;
+                    enemy.regenerated = parse_number(field(11))?;
+                    enemy.revealed = parse_number::<u8>(field(12))? == 1;
+                    enemy.movement_timer = parse_number(field(13))?;
+                    enemy.burrowed = parse_number::<u8>(field(14))? == 1;
+                    enemy.last_hit_by = match field(15) {
+                        "none" => None,
+                        tower => Some(parse_number(tower)?),
+                    };
+                    enemy.attacking = parse_number::<u8>(field(16))? == 1;
+                    enemy.wind = Point::new(parse_number(field(17))?, parse_number(field(18))?);
+                    // After a balance change the enemy takes on its type's new speed and hit points instead
+                    if !rebalanced {
+                        enemy.enemy_type.speed = parse_number(field(19))?;
+                        enemy.enemy_type.max_hit_points = parse_number(field(20))?;
+                    }
+                }
+                if rebalanced {
+                    enemy.hit_points = enemy.hit_points.min(enemy.enemy_type.max_hit_points);
+                }
+                // Saves from before armor shredding don't have it, which leaves the enemy's armor intact
+                if fields.len() > 3 {
+                    enemy.armor_shred = parse_number(field(3))?;
+                    enemy.shred_time_left = parse_number(field(4))?;
+                }
+                // Saves from before water lanes have every enemy on the land path
+                if fields.len() > 5 {
+                    enemy.on_lane = parse_number::<u8>(field(5))? == 1 && game.lane.is_some();
+                }
+                let route = enemy.route(&game.path, game.lane.as_ref());
+                enemy.heading = route.heading_at(enemy.distance);
+                enemy.step_aside(enemy.offset, route);
+                enemy.layer = route.layer_at(enemy.distance);
+                state.enemies.push(enemy);
+            }
+            "queued" => {
+                let names = game.enemy_types.iter().map(|enemy_type| &enemy_type.name);
+                let Some(enemy_type) =
+                    find_type(names, "enemy type", field(0), &fields, 3, rebalanced)?
+                else {
+                    left_out += 1;
+                    continue;
+                };
+                state.spawn_queue.push(QueuedSpawn {
+                    enemy_type,
+                    delay: parse_number(field(1))?,
+                    offset: parse_number(field(2))?,
+                });
+            }
+            "convoy_totals" => {
+                state.convoy_arrived = parse_number(field(0))?;
+                state.convoy_lost = parse_number(field(1))?;
+            }
+            "convoy" => {
+                let mut unit = ConvoyUnit::new(parse_number(field(2))?);
+                unit.distance = parse_number(field(0))?;
+                unit.hit_points = parse_number(field(1))?;
+                unit.position = game.path.point_at(game.path.length() - unit.distance);
+                unit.previous_position = unit.position;
+                state.convoy.push(unit);
+            }
+            "wall" => state.walls.push(Wall {
+                position: Point::new(parse_number(field(0))?, parse_number(field(1))?),
+                time_left: parse_number(field(2))?,
+            }),
+            "loot" => {
+                // Items have their type's name after their index
+                let loot = match field(3) {
+                    "resources" => Loot::Resources(parse_number(field(4))?),
+                    "boost" => Loot::DamageBoost {
+                        multiplier: parse_number(field(4))?,
+                        duration: parse_number(field(5))?,
+                    },
+                    "spell" => Loot::SpellCharge,
+                    "item" => {
+                        let names = game.item_types.iter().map(|item_type| &item_type.name);
+                        match find_type(names, "item type", field(4), &fields, 5, rebalanced)? {
+                            Some(item) => Loot::Item(item),
+                            None => {
+                                left_out += 1;
+                                continue;
+                            }
+                        }
+                    }
+                    _ => return Err(format!("Unknown loot: {}", value)),
+                };
+                let position = Point::new(parse_number(field(0))?, parse_number(field(1))?);
+                let mut drop = LootDrop::new(position, loot);
+                drop.time_left = parse_number(field(2))?;
+                state.loot.push(drop);
+            }
+            "kill" | "built" | "leaked" => {
+                let (name, count) = value
+                    .rsplit_once(' ')
+                    .ok_or_else(|| format!("Expected a name and count: {}", value))?;
+                let counts = match key {
+                    "kill" => &mut state.stats.kills,
+                    "built" => &mut state.stats.towers_built,
+                    _ => &mut state.stats.leaked,
+                };
+                counts.insert(name.to_string(), parse_number(count)?);
+            }
+            _ => {}
+        }
+    }
+    let warning = rebalanced.then(|| {
+        let mut warning = String::from(
+            "This run was saved with different game data, so its towers, enemies, and items now use the current balance",
+        );
+        if left_out > 0 {
+            warning += &format!(", and {} the game no longer has were left out", left_out);
+        }
+        warning
+    });
+    Ok(Some(SavedRun {
+        setup,
+        game,
+        warning,
+    }))
+}
+
+fn find_type<'a>(
+    names: impl Iterator<Item = &'a String>,
+    kind: &str,
+    index: &str,
+    fields: &[&str],
+    name_field: usize,
+    rebalanced: bool,
+) -> Result<Option<usize>, String> {
+    // The type at the saved index, or after a balance change the type with the saved name, or nothing if the game no
+    // longer has it, which is only to be expected after a balance change. Names start at the given field and run to
+    // the end of the line, and saves from before they were written only have the index
+    let index: usize = parse_number(index)?;
+    let names: Vec<&String> = names.collect();
+    let found = match fields.get(name_field..) {
+        Some(name) if rebalanced && !name.is_empty() => {
+            let name = name.join(" ");
+            names.iter().position(|type_name| **type_name == name)
+        }
+        _ => (index < names.len()).then_some(index),
+    };
+    match found {
+        None if !rebalanced => Err(format!("Unknown {}: {}", kind, fields.join(" "))),
+        found => Ok(found),
+    }
+}
+
+pub fn load_backup(path: &str) -> Option<SavedRun> {
+    // Fall back to the newest backup that's still intact
+    (1..=AUTOSAVE_BACKUPS)
+        .find_map(|backup| load(&storage::backup_path(path, backup)).ok().flatten())
+}
+
+pub fn discard(path: &str) {
+    // Delete the autosave and its backups, which are fine to find already gone
+    let backups = (1..=AUTOSAVE_BACKUPS).map(|backup| storage::backup_path(path, backup));
+    for path in std::iter::once(path.to_string()).chain(backups) {
+        if let Err(error) = fs::remove_file(&path) {
+            if error.kind() != io::ErrorKind::NotFound {
+                eprintln!("Couldn't delete {}: {}", path, error);
+            }
+        }
+    }
+}
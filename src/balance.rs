@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+
+use crate::bot::{self, STRATEGIES};
+use crate::error::Error;
+use crate::json::Json;
+use crate::level::{self, Level};
+use crate::{content, Outcome};
+
+// Games played for each combination of level, strategy, and difficulty, unless `--runs` says otherwise
+const DEFAULT_RUNS: u64 = 100;
+// New Game+ levels the games are played at, standing in for difficulty settings
+const DIFFICULTIES: [u32; 3] = [0, 1, 2];
+
+struct GameResult {
+    // Stores which level, strategy, and difficulty the game was played with, as indices into their lists
+    setup: (usize, usize, u32),
+    // Stores whether the strategy won
+    won: bool,
+    // Stores the lives lost to enemies reaching the base
+    leaks: u32,
+    // Stores the wave the game ended on
+    wave: usize,
+    // Stores the number of towers built, by tower type name
+    towers_built: BTreeMap<String, u32>,
+}
+
+#[derive(Default)]
+struct Summary {
+    // Stores the number of games played
+    runs: u32,
+    // Stores the number of those games won
+    wins: u32,
+    // Stores the lives lost across all the games
+    leaks: u32,
+    // Stores the waves reached across all the games
+    waves: usize,
+    // Stores the towers built across all the games, by tower type name
+    towers_built: BTreeMap<String, u32>,
+}
+
+impl Summary {
+    fn add(&mut self, result: &GameResult) {
+        self.runs += 1;
+        self.wins += result.won as u32;
+        self.leaks += result.leaks;
+        self.waves += result.wave;
+        for (name, count) in result.towers_built.iter() {
+            *self.towers_built.entry(name.clone()).or_default() += count;
+        }
+    }
+
+    fn average(&self, total: f64) -> f64 {
+        total / self.runs.max(1) as f64
+    }
+}
+
+fn play(level: &Level, setup: (usize, usize, u32), seed: u64) -> Result<GameResult, String> {
+    let (_, strategy, prestige) = setup;
+    let mut game = content::new_game(level, prestige)?;
+    game.state.rng = ChaCha12Rng::seed_from_u64(seed);
+    let Some(mut strategy) = bot::from_name(STRATEGIES[strategy]) else {
+        return Err(format!("Unknown strategy: {}", STRATEGIES[strategy]));
+    };
+    bot::play(&mut game, strategy.as_mut());
+    Ok(GameResult {
+        setup,
+        won: game.state.outcome == Some(Outcome::Won),
+        leaks: game.state.stats.leaks,
+        wave: game.state.wave,
+        towers_built: game.state.stats.towers_built.clone(),
+    })
+}
+
+fn csv_field(field: &str) -> String {
+    // Quote fields that would otherwise be split up or misread by a spreadsheet
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), Error> {
+    // Play the built-in level and every level given with `--level`, with every strategy at every difficulty
+    let mut levels = vec![content::default_level()];
+    for pair in args.windows(2).filter(|pair| pair[0] == "--level") {
+        levels.push(Level::load(&pair[1]).map_err(Error::Level)?);
+    }
+    let runs: u64 = match args.windows(2).find(|pair| pair[0] == "--runs") {
+        Some(pair) => level::parse_number(&pair[1]).map_err(Error::Level)?,
+        None => DEFAULT_RUNS,
+    };
+    let json = args
+        .windows(2)
+        .any(|pair| pair[0] == "--format" && pair[1] == "json");
+    let tower_names: Vec<String> = content::new_game(&levels[0], 0)
+        .map_err(Error::Level)?
+        .tower_types
+        .iter()
+        .map(|tower_type| tower_type.name.clone())
+        .collect();
+
+    // Play every game on its own thread, since they don't share anything
+    let mut games = Vec::new();
+    for level in 0..levels.len() {
+        for strategy in 0..STRATEGIES.len() {
+            for &prestige in DIFFICULTIES.iter() {
+                for seed in 0..runs {
+                    games.push(((level, strategy, prestige), seed));
+                }
+            }
+        }
+    }
+    let start = Instant::now();
+    let results = games
+        .into_par_iter()
+        .map(|(setup, seed)| play(&levels[setup.0], setup, seed))
+        .collect::<Result<Vec<GameResult>, String>>()
+        .map_err(Error::Level)?;
+    eprintln!(
+        "Played {} games in {:.1}s",
+        results.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    let mut summaries: BTreeMap<(usize, usize, u32), Summary> = BTreeMap::new();
+    for result in results.iter() {
+        summaries.entry(result.setup).or_default().add(result);
+    }
+
+    // Print one row per combination, as CSV by default or as JSON with `--format json`
+    if json {
+        let rows = summaries
+            .iter()
+            .map(|(&(level, strategy, prestige), summary)| {
+                let towers_built = tower_names
+                    .iter()
+                    .map(|name| {
+                        let count = summary.towers_built.get(name).copied().unwrap_or(0);
+                        (name.clone(), Json::Number(summary.average(count as f64)))
+                    })
+                    .collect();
+                Json::Object(vec![
+                    (
+                        String::from("level"),
+                        Json::String(levels[level].name.clone()),
+                    ),
+                    (
+                        String::from("strategy"),
+                        Json::String(STRATEGIES[strategy].to_string()),
+                    ),
+                    (String::from("difficulty"), Json::Number(prestige as f64)),
+                    (String::from("runs"), Json::Number(summary.runs as f64)),
+                    (String::from("wins"), Json::Number(summary.wins as f64)),
+                    (
+                        String::from("win_rate"),
+                        Json::Number(summary.average(summary.wins as f64)),
+                    ),
+                    (
+                        String::from("average_leaks"),
+                        Json::Number(summary.average(summary.leaks as f64)),
+                    ),
+                    (
+                        String::from("average_wave"),
+                        Json::Number(summary.average(summary.waves as f64)),
+                    ),
+                    (String::from("towers_built"), Json::Object(towers_built)),
+                ])
+            })
+            .collect();
+        println!("{}", Json::Array(rows));
+    } else {
+        let mut header =
+            String::from("level,strategy,difficulty,runs,wins,win_rate,average_leaks,average_wave");
+        for name in tower_names.iter() {
+            header += &format!(",{}", csv_field(&format!("{} built", name)));
+        }
+        println!("{}", header);
+        for (&(level, strategy, prestige), summary) in summaries.iter() {
+            let mut row = format!(
+                "{},{},{},{},{},{:.3},{:.2},{:.2}",
+                csv_field(&levels[level].name),
+                STRATEGIES[strategy],
+                prestige,
+                summary.runs,
+                summary.wins,
+                summary.average(summary.wins as f64),
+                summary.average(summary.leaks as f64),
+                summary.average(summary.waves as f64)
+            );
+            for name in tower_names.iter() {
+                let count = summary.towers_built.get(name).copied().unwrap_or(0);
+                row += &format!(",{:.2}", summary.average(count as f64));
+            }
+            println!("{}", row);
+        }
+    }
+    Ok(())
+}
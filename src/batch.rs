@@ -0,0 +1,54 @@
+use piston_window::math::{transform_pos, Matrix2d};
+use piston_window::*;
+
+pub struct RectBatch {
+    // Stores the corners of every triangle queued this frame, already transformed to screen space
+    vertices: Vec<[f32; 2]>,
+    // Stores the color of each queued vertex
+    colors: Vec<[f32; 4]>,
+}
+
+impl RectBatch {
+    pub fn new() -> Self {
+        RectBatch {
+            vertices: Vec::new(),
+            colors: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, color: [f32; 4], rect: [f64; 4], transform: Matrix2d) {
+        // Queue the rectangle as two triangles
+        let [x, y, w, h] = rect;
+        let corner = |cx: f64, cy: f64| {
+            let [tx, ty] = transform_pos(transform, [cx, cy]);
+            [tx as f32, ty as f32]
+        };
+        let (top_left, top_right) = (corner(x, y), corner(x + w, y));
+        let (bottom_left, bottom_right) = (corner(x, y + h), corner(x + w, y + h));
+        self.vertices.extend_from_slice(&[
+            top_left,
+            top_right,
+            bottom_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        ]);
+        self.colors.extend_from_slice(&[color; 6]);
+    }
+
+    pub fn draw(&mut self, draw_state: &DrawState, g: &mut G2d) {
+        // Submit every queued rectangle in as few draw calls as the backend's vertex buffer allows
+        let vertices = &self.vertices;
+        let colors = &self.colors;
+        g.tri_list_c(draw_state, |f| {
+            for (vertices, colors) in vertices
+                .chunks(BACK_END_MAX_VERTEX_COUNT)
+                .zip(colors.chunks(BACK_END_MAX_VERTEX_COUNT))
+            {
+                f(vertices, colors);
+            }
+        });
+        self.vertices.clear();
+        self.colors.clear();
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+use std::fs;
+
+use crate::level::parse_number;
+
+// Number of audio frames whose loudness is measured together when finding the beats in a track
+const ANALYSIS_WINDOW: usize = 1024;
+// Seconds of loudness a window is compared against, to tell a beat from a loud stretch of the track
+const ANALYSIS_HISTORY: f32 = 1.;
+// How many times louder than the track around it a window has to be to count as a beat
+const BEAT_SENSITIVITY: f32 = 1.4;
+// Shortest time between two beats found in a track, in seconds
+const MIN_BEAT_GAP: f32 = 0.25;
+
+// The beats of a music track, which rhythm mode times enemy spawns to, looping once the track runs out
+#[derive(Clone)]
+pub struct BeatMap {
+    // Stores the time of each beat into the track, in seconds, in order
+    pub beats: Vec<f32>,
+    // Stores how long the track is, in seconds, after which it starts again from the top
+    pub length: f32,
+}
+
+impl BeatMap {
+    pub fn load(path: &str) -> Result<BeatMap, String> {
+        // WAV files are analyzed for their beats, and anything else is read as a beat map
+        if path.ends_with(".wav") {
+            let bytes =
+                fs::read(path).map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+            return BeatMap::analyze(&bytes);
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+        BeatMap::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<BeatMap, String> {
+        // Beat maps list the time of each beat in seconds, like `beat 1.5`, or give a steady `bpm` starting from an
+        // `offset`, along with the track's `length`, which defaults to the last beat
+        let mut beats = Vec::new();
+        let (mut bpm, mut offset, mut length) = (None, 0., None);
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "beat" => beats.push(parse_number::<f32>(value)?),
+                "bpm" => bpm = Some(parse_number::<f32>(value)?),
+                "offset" => offset = parse_number(value)?,
+                "length" => length = Some(parse_number::<f32>(value)?),
+                _ => return Err(format!("Unknown beat map line: {}", line)),
+            }
+        }
+        if let Some(bpm) = bpm {
+            let Some(length) = length.filter(|_| bpm > 0.) else {
+                return Err(String::from("A steady bpm needs the track's length"));
+            };
+            let interval = 60. / bpm;
+            beats.extend(
+                (0..)
+                    .map(|i| offset + i as f32 * interval)
+                    .take_while(|&time| time < length),
+            );
+        }
+        beats.sort_by(f32::total_cmp);
+        beats.dedup();
+        let length = length.unwrap_or_else(|| beats.last().copied().unwrap_or(0.));
+        if beats.is_empty() || length <= 0. {
+            return Err(String::from("Beat map doesn't have any beats"));
+        }
+        if beats.iter().any(|&beat| beat < 0. || beat > length) {
+            return Err(String::from("Beats have to fall within the track"));
+        }
+        Ok(BeatMap { beats, length })
+    }
+
+    pub fn analyze(bytes: &[u8]) -> Result<BeatMap, String> {
+        // Find the beats in 16-bit PCM audio by looking for windows much louder than the second of the track around
+        // them, mixing every channel together
+        let (channels, sample_rate, samples) = read_wav(bytes)?;
+        let frames = samples.len() / channels;
+        let loudness: Vec<f32> = samples
+            .chunks(channels * ANALYSIS_WINDOW)
+            .map(|window| {
+                window
+                    .iter()
+                    .map(|&sample| (sample as f32 / i16::MAX as f32).powi(2))
+                    .sum::<f32>()
+                    / window.len() as f32
+            })
+            .collect();
+        let window_time = ANALYSIS_WINDOW as f32 / sample_rate as f32;
+        let history = ((ANALYSIS_HISTORY / window_time) as usize).max(1);
+        let mut beats: Vec<f32> = Vec::new();
+        for (i, &energy) in loudness.iter().enumerate() {
+            let around =
+                &loudness[i.saturating_sub(history / 2)..(i + history / 2).min(loudness.len())];
+            let average = around.iter().sum::<f32>() / around.len() as f32;
+            let time = i as f32 * window_time;
+            if energy > average * BEAT_SENSITIVITY
+                && beats.last().is_none_or(|&last| time - last >= MIN_BEAT_GAP)
+            {
+                beats.push(time);
+            }
+        }
+        if beats.is_empty() {
+            return Err(String::from("Couldn't find any beats in the track"));
+        }
+        Ok(BeatMap {
+            beats,
+            length: frames as f32 / sample_rate as f32,
+        })
+    }
+
+    pub fn beat_after(&self, time: f32, count: usize) -> f32 {
+        // Find the time of the given number of beats after the first one at or after the given time, in seconds into
+        // the run, where the track loops for as long as the run goes on
+        let loops = (time / self.length).floor();
+        let into = time - loops * self.length;
+        let first = self.beats.partition_point(|&beat| beat < into);
+        let index = first + count;
+        let (extra_loops, index) = (index / self.beats.len(), index % self.beats.len());
+        (loops + extra_loops as f32) * self.length + self.beats[index]
+    }
+}
+
+impl fmt::Display for BeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "length {}", self.length)?;
+        for beat in self.beats.iter() {
+            writeln!(f, "beat {}", beat)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_wav(bytes: &[u8]) -> Result<(usize, u32, Vec<i16>), String> {
+    // Read the channel count, sample rate, and samples out of a WAV file's chunks
+    let not_wav = || String::from("Track isn't a WAV file");
+    if bytes.get(0..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+        return Err(not_wav());
+    }
+    let u16_at = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut format = None;
+    let mut position = 12;
+    while let (Some(id), Some(size)) = (bytes.get(position..position + 4), u32_at(position + 4)) {
+        let start = position + 8;
+        let size = size as usize;
+        match id {
+            b"fmt " => {
+                let (Some(encoding), Some(channels), Some(sample_rate), Some(bits)) = (
+                    u16_at(start),
+                    u16_at(start + 2),
+                    u32_at(start + 4),
+                    u16_at(start + 14),
+                ) else {
+                    return Err(not_wav());
+                };
+                if encoding != 1 || bits != 16 || channels == 0 || sample_rate == 0 {
+                    return Err(String::from("Only 16-bit PCM tracks can be analyzed"));
+                }
+                format = Some((channels as usize, sample_rate));
+            }
+            b"data" => {
+                let (channels, sample_rate) = format.ok_or_else(not_wav)?;
+                let data = &bytes[start..(start + size).min(bytes.len())];
+                let samples = data
+                    .chunks_exact(2)
+                    .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                    .collect();
+                return Ok((channels, sample_rate, samples));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even number of bytes
+        position = start + size + size % 2;
+    }
+    Err(not_wav())
+}
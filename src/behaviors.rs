@@ -0,0 +1,76 @@
+use std::sync::RwLock;
+
+use crate::{Enemy, Tower};
+
+pub trait TowerBehavior: Sync {
+    // Returns the name levels use to give a tower type the behavior
+    fn name(&self) -> &'static str;
+    // Called every tick for each tower with the behavior, before it looks for targets
+    fn on_tick(&self, _tower: &mut Tower, _dt: f32) {}
+    // Picks up to `count` enemies for the tower to shoot from the ones it can hit, which come ordered by how little
+    // of their route they have left, so by default it shoots the ones closest to the base
+    fn choose_target(
+        &self,
+        _tower: &Tower,
+        _enemies: &[Enemy],
+        candidates: &[usize],
+        count: usize,
+    ) -> Vec<usize> {
+        candidates.iter().take(count).copied().collect()
+    }
+    // Called after the tower fires, once its shots have hit the given enemies
+    fn on_fire(&self, _tower: &mut Tower, _enemies: &mut [Enemy], _targets: &[usize]) {}
+}
+
+// Shoots the enemies with the most hit points left, instead of the ones closest to the base, for towers that hit
+// too hard to waste on weak enemies
+pub struct Strongest;
+
+impl TowerBehavior for Strongest {
+    fn name(&self) -> &'static str {
+        "strongest"
+    }
+
+    fn choose_target(
+        &self,
+        _tower: &Tower,
+        enemies: &[Enemy],
+        candidates: &[usize],
+        count: usize,
+    ) -> Vec<usize> {
+        // The sort is stable, so enemies equally strong are still taken closest to the base first
+        let mut candidates = candidates.to_vec();
+        candidates.sort_by_key(|&i| std::cmp::Reverse(enemies[i].hit_points));
+        candidates.truncate(count);
+        candidates
+    }
+}
+
+// Behaviors tower types can be given by name, which other modules and crates can add to without touching the towers
+static REGISTRY: RwLock<Vec<&'static dyn TowerBehavior>> = RwLock::new(Vec::new());
+
+pub fn register(behavior: &'static dyn TowerBehavior) {
+    // Add a behavior, in place of any already registered under the same name, before any levels are loaded
+    let mut registry = REGISTRY.write().unwrap_or_else(|error| error.into_inner());
+    registry.retain(|existing| existing.name() != behavior.name());
+    registry.push(behavior);
+}
+
+pub fn register_built_in() {
+    // Add the built-in behaviors, leaving any registered earlier under the same name in their place
+    let built_in: [&'static dyn TowerBehavior; 1] = [&Strongest];
+    for behavior in built_in {
+        if from_name(behavior.name()).is_none() {
+            register(behavior);
+        }
+    }
+}
+
+pub fn from_name(name: &str) -> Option<&'static dyn TowerBehavior> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .iter()
+        .find(|behavior| behavior.name() == name)
+        .copied()
+}
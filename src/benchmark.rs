@@ -0,0 +1,65 @@
+use crate::error::Error;
+use crate::replay;
+use crate::{content, Point, Tower, TILE_SIZE, UPDATES_PER_SECOND};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use std::time::{Duration, Instant};
+
+// Number of enemies kept on the field while benchmarking
+const BENCHMARK_ENEMIES: usize = 3000;
+// Number of towers placed along the path while benchmarking
+const BENCHMARK_TOWERS: usize = 60;
+// Number of simulation ticks each run is timed over
+const BENCHMARK_TICKS: usize = 1200;
+
+pub fn run() -> Result<(), Error> {
+    // Time the same crowded simulation with and without parallel updates, and make sure they agree
+    let (serial_time, serial_checksum) = simulate(false)?;
+    let (parallel_time, parallel_checksum) = simulate(true)?;
+    println!(
+        "{} ticks with {} enemies and {} towers",
+        BENCHMARK_TICKS, BENCHMARK_ENEMIES, BENCHMARK_TOWERS
+    );
+    println!("serial:   {:.1} ms", serial_time.as_secs_f64() * 1000.);
+    println!("parallel: {:.1} ms", parallel_time.as_secs_f64() * 1000.);
+    println!(
+        "speedup:  {:.2}x",
+        serial_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+    if serial_checksum != parallel_checksum {
+        eprintln!("Serial and parallel simulations diverged");
+    }
+    Ok(())
+}
+
+fn simulate(parallel: bool) -> Result<(Duration, u64), Error> {
+    let mut game = content::new_game(&content::default_level(), 0).map_err(Error::Level)?;
+    game.state.parallel = parallel;
+    game.state.rng = ChaCha12Rng::seed_from_u64(0);
+    game.state.lives = i32::MAX;
+
+    // Line the path with towers, alternating sides
+    for i in 0..BENCHMARK_TOWERS {
+        let distance = game.path.length() * i as f32 / BENCHMARK_TOWERS as f32;
+        let side = if i % 2 == 0 { 1. } else { -1. };
+        let position = game
+            .path
+            .point_at(distance)
+            .plus(&Point::new(side * TILE_SIZE, side * TILE_SIZE));
+        let tower_type = game.tower_types[i % 2].clone();
+        game.state
+            .towers
+            .push(Tower::new(i + 1, position, tower_type));
+    }
+
+    // Keep the field topped up with enemies so every tick does the same amount of work
+    let dt = 1. / UPDATES_PER_SECOND as f32;
+    let start = Instant::now();
+    for tick in 0..BENCHMARK_TICKS {
+        while game.state.enemies.len() < BENCHMARK_ENEMIES {
+            game.spawn_enemy(tick % game.enemy_types.len());
+        }
+        game.update(dt);
+    }
+    Ok((start.elapsed(), replay::checksum(&game)))
+}
@@ -0,0 +1,125 @@
+// A compact binary encoding for files that would be too big as text, where whole numbers are written seven bits to
+// a byte, so small ones take a single byte, and strings are written as their length followed by their bytes
+
+#[derive(Default)]
+pub struct Writer {
+    // Stores everything written so far
+    pub bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer::default()
+    }
+
+    pub fn number(&mut self, value: u64) {
+        // Write the low seven bits at a time, with the top bit of each byte set while more follow
+        let mut value = value;
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    pub fn fixed(&mut self, value: u64) {
+        // Checksums and seeds use every bit, so they're written whole
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    pub fn flag(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.number(value.len() as u64);
+        self.bytes.extend(value.as_bytes());
+    }
+}
+
+pub struct Reader<'a> {
+    // Stores the bytes being read
+    bytes: &'a [u8],
+    // Stores how far through the bytes reading has got
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        // A corrupt length can be as big as a number gets, so it's checked against what's left before it's used
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or("File ends sooner than expected")?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn number(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(String::from("Number is too long"))
+    }
+
+    pub fn fixed(&mut self) -> Result<u64, String> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+
+    pub fn flag(&mut self) -> Result<bool, String> {
+        Ok(self.take(1)?[0] != 0)
+    }
+
+    pub fn string(&mut self) -> Result<String, String> {
+        let length = usize::try_from(self.number()?).map_err(|_| "String is too long")?;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| String::from("String isn't valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_round_trip() {
+        let mut writer = Writer::new();
+        writer.string("Meadow");
+        writer.number(300);
+        let mut reader = Reader::new(&writer.bytes);
+        assert_eq!(reader.string(), Ok(String::from("Meadow")));
+        assert_eq!(reader.number(), Ok(300));
+    }
+
+    #[test]
+    fn truncated_string_is_an_error() {
+        let mut writer = Writer::new();
+        writer.string("Meadow");
+        let bytes = &writer.bytes[..writer.bytes.len() - 1];
+        assert!(Reader::new(bytes).string().is_err());
+    }
+
+    #[test]
+    fn garbage_length_is_an_error() {
+        // A length at the top of the range overflows the position it's added to once anything has been read
+        let mut writer = Writer::new();
+        writer.flag(true);
+        writer.number(u64::MAX);
+        writer.bytes.extend(b"Meadow");
+        let mut reader = Reader::new(&writer.bytes);
+        assert_eq!(reader.flag(), Ok(true));
+        assert!(reader.string().is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use std::cmp::Reverse;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::commands::{Command, CommandQueue};
+use crate::error::Error;
+use crate::level::Level;
+use crate::{content, Game, Outcome, Point, TILE_SIZE, UPDATES_PER_SECOND};
+
+// Longest a simulated run can go on for before it's given up on, in seconds of game time
+const SIMULATION_TIME_LIMIT: f32 = 3600.;
+// Distance between the points along the path a building spot's coverage is measured at, in pixels
+const COVERAGE_STEP: f32 = 5.;
+// Names of the strategies that can be picked for simulations
+pub const STRATEGIES: [&str; 2] = ["greedy", "cheap"];
+
+pub trait Strategy {
+    // Decides what the player does this tick, given a look at the game that can't change it
+    fn decide(&mut self, game: &Game) -> Vec<Command>;
+}
+
+#[derive(Default)]
+pub struct GreedyBot {
+    // Stores the spots worth building on, covering the most path first, worked out the first time the bot
+    // looks at the map
+    spots: Option<Vec<Point>>,
+}
+
+impl GreedyBot {
+    pub fn new() -> Self {
+        GreedyBot::default()
+    }
+
+    fn find_spots(game: &Game, range: f32) -> Vec<Point> {
+        // Score every open tile off the path, or every slot on a level with them, by how much of the path a tower
+        // there could reach, not counting the stretches enemies teleport past
+        let samples: Vec<Point> = (0..(game.path.length() / COVERAGE_STEP) as usize)
+            .map(|i| i as f32 * COVERAGE_STEP)
+            .filter(|&distance| !game.path.skips(distance))
+            .map(|distance| game.path.point_at(distance))
+            .collect();
+        let (width, height) = game.map.size;
+        let mut spots = Vec::new();
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let spot = Point::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE);
+                if game.map.is_blocked(x, y)
+                    || game.map.is_water(x, y)
+                    || !game.slots.is_empty() && game.slot_at(&spot).is_none()
+                {
+                    continue;
+                }
+                let on_path =
+                    game.path.waypoints.windows(2).any(|segment| {
+                        spot.distance_to_segment(&segment[0], &segment[1]) < TILE_SIZE
+                    });
+                let coverage = samples
+                    .iter()
+                    .filter(|sample| game.map.can_hit(&spot, range, sample))
+                    .count();
+                if !on_path && coverage > 0 {
+                    spots.push((coverage, spot));
+                }
+            }
+        }
+        spots.sort_by_key(|(coverage, _)| Reverse(*coverage));
+        spots.into_iter().map(|(_, spot)| spot).collect()
+    }
+}
+
+fn collect_loot(game: &Game, commands: &mut Vec<Command>) {
+    // Pick up any loot lying around
+    for drop in game.state.loot.iter() {
+        commands.push(Command::CollectLoot {
+            position: drop
+                .position
+                .plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.)),
+        });
+    }
+}
+
+fn build(
+    spots: &mut Option<Vec<Point>>,
+    game: &Game,
+    tower_type: usize,
+    commands: &mut Vec<Command>,
+) {
+    // Build the tower on the free spot covering the most path, as soon as it's affordable
+    if !game.rules.sandbox && game.state.resources < game.tower_cost(tower_type) {
+        return;
+    }
+    let range = game.tower_types[tower_type].range;
+    let spots = spots.get_or_insert_with(|| GreedyBot::find_spots(game, range));
+    if let Some(position) = spots.iter().find(|spot| game.tower_at(spot).is_none()) {
+        commands.push(Command::Build {
+            tower_type,
+            position: *position,
+        });
+    }
+}
+
+fn unlocked_tower_types(game: &Game) -> impl Iterator<Item = usize> + '_ {
+    // The bot only looks for spots on land, so it leaves floating towers alone
+    (0..game.tower_types.len()).filter(|&i| {
+        game.tower_types[i].prestige_required <= game.prestige && !game.tower_types[i].floating
+    })
+}
+
+impl Strategy for GreedyBot {
+    fn decide(&mut self, game: &Game) -> Vec<Command> {
+        let mut commands = Vec::new();
+        collect_loot(game, &mut commands);
+
+        // Build whichever unlocked tower deals the most damage for its cost
+        let value = |i: usize| {
+            let tower_type = &game.tower_types[i];
+            tower_type.damage as f32 * tower_type.rate_of_fire / game.tower_cost(i).max(1) as f32
+        };
+        if let Some(tower_type) =
+            unlocked_tower_types(game).max_by(|&a, &b| value(a).total_cmp(&value(b)))
+        {
+            build(&mut self.spots, game, tower_type, &mut commands);
+        }
+        commands
+    }
+}
+
+#[derive(Default)]
+pub struct CheapBot {
+    // Stores the spots worth building on, like the greedy bot's
+    spots: Option<Vec<Point>>,
+}
+
+impl Strategy for CheapBot {
+    fn decide(&mut self, game: &Game) -> Vec<Command> {
+        let mut commands = Vec::new();
+        collect_loot(game, &mut commands);
+
+        // Build as many towers as possible by always picking the cheapest one
+        if let Some(tower_type) = unlocked_tower_types(game).min_by_key(|&i| game.tower_cost(i)) {
+            build(&mut self.spots, game, tower_type, &mut commands);
+        }
+        commands
+    }
+}
+
+pub fn from_name(name: &str) -> Option<Box<dyn Strategy>> {
+    // Look up a strategy by the name used to select it on the command line
+    match name {
+        "greedy" => Some(Box::new(GreedyBot::new())),
+        "cheap" => Some(Box::new(CheapBot::default())),
+        _ => None,
+    }
+}
+
+pub fn play(game: &mut Game, strategy: &mut dyn Strategy) {
+    // Let the strategy play the game headless until it's over, or until it's clearly never going to be
+    play_for(game, strategy, SIMULATION_TIME_LIMIT);
+}
+
+pub fn play_for(game: &mut Game, strategy: &mut dyn Strategy, seconds: f32) {
+    // Let the strategy play the game headless until it's over or the given seconds of play have gone by
+    let dt = 1. / UPDATES_PER_SECOND as f32;
+    let mut commands = CommandQueue::new();
+    while game.state.outcome.is_none() && game.state.stats.playtime < seconds {
+        for command in strategy.decide(game) {
+            commands.issue(command, game, false);
+        }
+        game.update(dt);
+    }
+}
+
+pub fn simulate(level: &Level, runs: u64) -> Result<(), Error> {
+    // Play the level headless with the greedy bot, once per seed, and report how it went
+    let mut wins = 0;
+    for seed in 0..runs {
+        let mut game = content::new_game(level, 0).map_err(Error::Level)?;
+        game.state.rng = ChaCha12Rng::seed_from_u64(seed);
+        play(&mut game, &mut GreedyBot::new());
+        let result = match game.state.outcome {
+            Some(Outcome::Won) => "won",
+            Some(Outcome::Lost) => "lost",
+            None => "timed out",
+        };
+        if game.state.outcome == Some(Outcome::Won) {
+            wins += 1;
+        }
+        println!(
+            "seed {}: {} on wave {} with {} lives, {} towers, score {}",
+            seed,
+            result,
+            game.state.wave,
+            game.state.lives,
+            game.state.towers.len(),
+            game.score()
+        );
+    }
+    println!(
+        "{} won {} of {} runs ({:.0}%)",
+        level.name,
+        wins,
+        runs,
+        wins as f32 / runs.max(1) as f32 * 100.
+    );
+    Ok(())
+}
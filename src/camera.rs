@@ -0,0 +1,176 @@
+use piston_window::{Button, Event, MouseButton, PressEvent, ReleaseEvent};
+
+use crate::{Point, TILE_SIZE};
+
+// Distance the mouse has to move with a button held before it counts as a drag rather than a click, in pixels
+const DRAG_THRESHOLD: f32 = 4.;
+// Distance the camera can be panned past the edges of the map, so the build menu never hides the bottom of it,
+// in pixels
+const PAN_MARGIN: f32 = TILE_SIZE * 2.;
+// Furthest the camera can zoom out and in, as how many times bigger than usual the map is drawn
+const ZOOM_RANGE: (f32, f32) = (0.5, 4.);
+// Zooms the camera steps between with integer scaling on, which draw every texel as a whole number of pixels, or
+// every two texels as one
+const INTEGER_ZOOMS: [f32; 5] = [0.5, 1., 2., 3., 4.];
+
+// What clicking on the map does at the moment
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    // Clicking pulls levers, selects towers, and picks up loot
+    Idle,
+    // Clicking builds a tower of the given type where the mouse is
+    Placing(usize),
+    // The tower with the given number is selected, with its range showing
+    Selecting(usize),
+    // The camera follows the mouse while a button is held
+    Panning,
+}
+
+// A mouse button held down on the map, which pans the camera if it's dragged, or cancels if it's just clicked
+struct Drag {
+    // Stores which button is held
+    button: MouseButton,
+    // Stores where the mouse was when the button went down, in the window
+    start: Point,
+    // Stores where the camera was when the button went down
+    camera: Point,
+    // Stores the mode to go back to once the drag is over
+    resume: Mode,
+}
+
+pub struct Pointer {
+    // Stores what clicking on the map does at the moment
+    pub mode: Mode,
+    // Stores how far the view is scrolled across the map, in pixels
+    pub camera: Point,
+    // Stores how many times bigger than usual the map is drawn
+    pub zoom: f32,
+    // Stores whether the camera can be panned anywhere, rather than only around the map
+    pub free: bool,
+    // Stores whether the zoom is kept to whole multiples and the view to whole pixels, so pixel art stays crisp
+    integer_scaling: bool,
+    // Stores the mouse button held down on the map, if there is one
+    drag: Option<Drag>,
+}
+
+impl Pointer {
+    pub fn new() -> Self {
+        Pointer {
+            mode: Mode::Idle,
+            camera: Point::new(0., 0.),
+            zoom: 1.,
+            free: false,
+            integer_scaling: false,
+            drag: None,
+        }
+    }
+
+    pub fn to_world(&self, position: &Point) -> Point {
+        // Find the point on the map under the given point in the window
+        position.scale(1. / self.zoom).plus(&self.camera)
+    }
+
+    pub fn zoom_at(&mut self, mouse: &Point, factor: f32) {
+        // Zoom in or out by the given factor, keeping the point on the map under the mouse where it is, or a whole step
+        // at a time with integer scaling on
+        let anchor = self.to_world(mouse);
+        self.zoom = if !self.integer_scaling {
+            (self.zoom * factor).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1)
+        } else if factor > 1. {
+            INTEGER_ZOOMS
+                .into_iter()
+                .find(|&zoom| zoom > self.zoom)
+                .unwrap_or(self.zoom)
+        } else {
+            INTEGER_ZOOMS
+                .into_iter()
+                .rev()
+                .find(|&zoom| zoom < self.zoom)
+                .unwrap_or(self.zoom)
+        };
+        self.camera = anchor.minus(&mouse.scale(1. / self.zoom));
+    }
+
+    pub fn set_integer_scaling(&mut self, integer_scaling: bool) {
+        // Snap the zoom to the nearest whole step when integer scaling is turned on
+        self.integer_scaling = integer_scaling;
+        if integer_scaling {
+            let zoom = self.zoom;
+            self.zoom = INTEGER_ZOOMS
+                .into_iter()
+                .min_by(|a, b| (a - zoom).abs().total_cmp(&(b - zoom).abs()))
+                .unwrap_or(1.);
+        }
+    }
+
+    pub fn view_offset(&self) -> Point {
+        // Where the view is scrolled to for drawing, rounded to whole pixels with integer scaling on so texels line
+        // up with them
+        if !self.integer_scaling {
+            return self.camera;
+        }
+        Point::new(
+            (self.camera.x * self.zoom).round() / self.zoom,
+            (self.camera.y * self.zoom).round() / self.zoom,
+        )
+    }
+
+    pub fn handle(
+        &mut self,
+        event: &Event,
+        mouse: &Point,
+        cancel: MouseButton,
+        on_ui: bool,
+    ) -> bool {
+        // Start a drag when the middle button or the cancel button goes down on the map, and end it when it comes
+        // back up, cancelling placing or selecting if the mouse never moved far enough to pan, returning whether
+        // the event was used up
+        if let Some(Button::Mouse(button)) = event.press_args() {
+            if on_ui || (button != MouseButton::Middle && button != cancel) {
+                return false;
+            }
+            self.drag = Some(Drag {
+                button,
+                start: *mouse,
+                camera: self.camera,
+                resume: self.mode,
+            });
+            return true;
+        }
+        if let Some(Button::Mouse(button)) = event.release_args() {
+            let Some(drag) = self.drag.take_if(|drag| drag.button == button) else {
+                return false;
+            };
+            self.mode = match self.mode {
+                Mode::Panning => drag.resume,
+                _ if button == cancel => Mode::Idle,
+                mode => mode,
+            };
+            return true;
+        }
+        false
+    }
+
+    pub fn drag_to(&mut self, mouse: &Point, map_size: &Point, view_size: &Point) {
+        // Once the mouse has moved far enough with the button held, the map follows it, going no further past its
+        // edges than the margin unless the camera is free
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let moved = mouse.minus(&drag.start);
+        if self.mode != Mode::Panning && moved.length() < DRAG_THRESHOLD {
+            return;
+        }
+        self.mode = Mode::Panning;
+        let camera = drag.camera.minus(&moved.scale(1. / self.zoom));
+        if self.free {
+            self.camera = camera;
+            return;
+        }
+        let furthest = map_size.minus(view_size);
+        self.camera = Point::new(
+            camera.x.clamp(-PAN_MARGIN, furthest.x.max(0.) + PAN_MARGIN),
+            camera.y.clamp(-PAN_MARGIN, furthest.y.max(0.) + PAN_MARGIN),
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::notifications::{notify, Kind};
+use crate::storage;
+
+// Number of waves the player must survive to beat the campaign
+pub const CAMPAIGN_WAVES: usize = 10;
+// File the player's campaign progress is saved to
+pub const CAMPAIGN_SAVE_PATH: &str = "campaign.sav";
+
+#[derive(Default)]
+pub struct CampaignSave {
+    // Stores how many times the player has beaten the campaign, which is also the highest New Game+ level unlocked
+    pub completions: u32,
+    // Stores the most bonus objectives completed in a single win of each level, by level name, each worth a star
+    pub stars: BTreeMap<String, u32>,
+}
+
+impl CampaignSave {
+    pub fn load(path: &str) -> Self {
+        // A missing or damaged save just means the player is starting fresh
+        let mut save = CampaignSave::default();
+        let contents = match storage::read_checked(path) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return save,
+            Err(error) => {
+                eprintln!("Couldn't load campaign progress: {}", error);
+                notify(
+                    format!("Couldn't load campaign progress, starting over: {}", error),
+                    Kind::Warning,
+                );
+                return save;
+            }
+        };
+        for line in contents.lines() {
+            match line.rsplit_once('=') {
+                Some(("completions", value)) => {
+                    save.completions = value.trim().parse().unwrap_or(0);
+                }
+                Some((key, value)) => {
+                    if let Some(level) = key.strip_prefix("stars:") {
+                        let stars = value.trim().parse().unwrap_or(0);
+                        save.stars.insert(level.to_string(), stars);
+                    }
+                }
+                None => {}
+            }
+        }
+        save
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = format!("completions={}\n", self.completions);
+        for (level, stars) in self.stars.iter() {
+            contents += &format!("stars:{}={}\n", level, stars);
+        }
+        storage::write_checked(path, &contents)
+    }
+
+    pub fn new_game_plus_unlocked(&self) -> bool {
+        self.completions > 0
+    }
+
+    pub fn record_win(&mut self, prestige: u32) {
+        // Beating a New Game+ level unlocks the next one
+        self.completions = self.completions.max(prestige + 1);
+    }
+
+    pub fn record_stars(&mut self, level: &str, stars: u32) -> bool {
+        // Only a level's best win counts, so replaying it can't earn the same stars twice, returning whether this was
+        // a new best
+        let best = self.stars.entry(level.to_string()).or_default();
+        if stars <= *best {
+            return false;
+        }
+        *best = stars;
+        true
+    }
+
+    pub fn total_stars(&self) -> u32 {
+        self.stars.values().sum()
+    }
+}
@@ -0,0 +1,111 @@
+use crate::label::{self, TextStyle};
+use crate::notifications::{notify, Kind};
+use crate::rewind::Moment;
+use crate::ui::{Frame, Ui};
+use crate::{Game, Outcome};
+
+// Number of waves between checkpoints, which are taken as the build phase after every this many starts
+const CHECKPOINT_INTERVAL: usize = 5;
+// Most checkpoints the picker lists, counting back from the most recent
+const MAX_LISTED: usize = 4;
+
+// A copy of the run taken as a build phase started, which a player who loses can restart from
+pub struct Checkpoint {
+    // Stores the wave the build phase was for
+    pub wave: usize,
+    // Stores the player's lives at the checkpoint
+    lives: i32,
+    // Stores the player's resources at the checkpoint
+    resources: i32,
+    // Stores the run as it was
+    moment: Moment,
+}
+
+impl Game {
+    pub fn take_checkpoint(&mut self) {
+        // Copy the run at the start of every few build phases, replacing any checkpoint from as late or later that a
+        // rewind took the run back past, with none in sandbox, reverse, or puzzle runs, which have no need of them
+        let wave = self.state.wave;
+        if self.rules.sandbox
+            || self.rules.reverse
+            || self.rules.puzzle
+            || wave <= 1
+            || !(wave - 1).is_multiple_of(CHECKPOINT_INTERVAL)
+        {
+            return;
+        }
+        self.checkpoints.retain(|checkpoint| checkpoint.wave < wave);
+        self.checkpoints.push(Checkpoint {
+            wave,
+            lives: self.state.lives,
+            resources: self.state.resources,
+            moment: self.capture(),
+        });
+    }
+
+    pub fn restart_from_checkpoint(&mut self, i: usize) {
+        // Pick the run up from a checkpoint, which is kept so it can be tried again while the ones after it, from
+        // the run that was lost, are dropped, with the run only counting as practice from then on, like a rewound one
+        let Some(checkpoint) = self.checkpoints.get(i) else {
+            return;
+        };
+        let moment = checkpoint.moment.clone();
+        self.checkpoints.truncate(i + 1);
+        self.rules.rewound = true;
+        self.restore(moment);
+    }
+}
+
+pub fn restart(game: &mut Game, i: usize) {
+    // Restart the run from the checkpoint the player picked, letting them know it only counts as practice now
+    game.restart_from_checkpoint(i);
+    notify(
+        format!(
+            "Restarted from the checkpoint at wave {}, and the run is now just for practice",
+            game.state.wave
+        ),
+        Kind::Info,
+    );
+}
+
+pub fn draw(game: &Game, offered: bool, ui: &mut Ui, frame: &mut Frame) -> Option<usize> {
+    // Offer a lost run the checkpoints it passed, if they can be offered at all, listing the most recent under the
+    // scores, newest first, each with a button to restart from it, returning the one picked
+    let checkpoints = &game.checkpoints;
+    if !offered || game.state.outcome != Some(Outcome::Lost) || checkpoints.is_empty() {
+        return None;
+    }
+    let listed = checkpoints.len().min(MAX_LISTED);
+    ui.panel([170.0, 310.0, 300.0, 32.0 + listed as f64 * 27.0], frame);
+    label::draw(
+        "Restart from a checkpoint",
+        &TextStyle {
+            size: 14,
+            ..Default::default()
+        },
+        180.0,
+        328.0,
+        frame.glyphs,
+        frame.c,
+        frame.g,
+    );
+    let mut picked = None;
+    for (row, (i, checkpoint)) in checkpoints
+        .iter()
+        .enumerate()
+        .rev()
+        .take(MAX_LISTED)
+        .enumerate()
+    {
+        let caption = format!(
+            "Wave {}: {} lives, {} resources",
+            checkpoint.wave, checkpoint.lives, checkpoint.resources
+        );
+        let rect = [180.0, 336.0 + row as f64 * 27.0, 280.0, 22.0];
+        let id = format!("checkpoint {}", checkpoint.wave);
+        if ui.button(&id, &caption, rect, true, false, frame) {
+            picked = Some(i);
+        }
+    }
+    picked
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+
+use crate::level::parse_number;
+use crate::replay::{Input, Replay};
+use crate::{Game, Point, Tower};
+
+// A player action that changes the game, kept apart from carrying it out so it can wait for later,
+// be undone, or be replayed
+#[derive(Clone, Copy)]
+pub enum Command {
+    // Build a tower of the given type at the given position
+    Build { tower_type: usize, position: Point },
+    // Sell the tower at the given position
+    Sell { position: Point },
+    // Pick the type of tower to build next
+    SelectTowerType(usize),
+    // Open or close the shop
+    ToggleShop,
+    // Buy the shop offer with the given index
+    Buy(usize),
+    // Use the item in the given inventory slot at the given position
+    UseItem { slot: usize, position: Point },
+    // Pick up loot at the given position
+    CollectLoot { position: Point },
+    // Trigger the ability of the tower at the given position
+    UseAbility { position: Point },
+    // Pull the lever of the gate with the given index, opening or closing it
+    PullLever(usize),
+    // Spawn an enemy of the given type, in a sandbox
+    SpawnEnemy(usize),
+    // End the build phase early, in a sandbox
+    StartWave,
+    // Pay for an enemy of the given type and add it to the send queue, in reverse mode
+    QueueEnemy(usize),
+    // Send every enemy in the send queue, in reverse mode
+    SendWave,
+}
+
+impl Command {
+    fn deferrable(&self) -> bool {
+        // Only changes to the player's defenses wait for the game to be unpaused
+        matches!(self, Command::Build { .. } | Command::Sell { .. })
+    }
+
+    fn allowed(&self, game: &Game) -> bool {
+        // In reverse mode the defense belongs to the AI, so all the player can do is send enemies at it
+        !game.rules.reverse || matches!(self, Command::QueueEnemy(_) | Command::SendWave)
+    }
+
+    pub fn parse(text: &str) -> Result<Command, String> {
+        // Read a command back from the form it's displayed in, like `build 0 120 80`
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or("");
+        let position = |i: usize| -> Result<Point, String> {
+            Ok(Point::new(
+                parse_number(field(i))?,
+                parse_number(field(i + 1))?,
+            ))
+        };
+        Ok(match field(0) {
+            "build" => Command::Build {
+                tower_type: parse_number(field(1))?,
+                position: position(2)?,
+            },
+            "sell" => Command::Sell {
+                position: position(1)?,
+            },
+            "select" => Command::SelectTowerType(parse_number(field(1))?),
+            "shop" => Command::ToggleShop,
+            "buy" => Command::Buy(parse_number(field(1))?),
+            "use" => Command::UseItem {
+                slot: parse_number(field(1))?,
+                position: position(2)?,
+            },
+            "loot" => Command::CollectLoot {
+                position: position(1)?,
+            },
+            "ability" => Command::UseAbility {
+                position: position(1)?,
+            },
+            "lever" => Command::PullLever(parse_number(field(1))?),
+            "spawn" => Command::SpawnEnemy(parse_number(field(1))?),
+            "start-wave" => Command::StartWave,
+            "queue" => Command::QueueEnemy(parse_number(field(1))?),
+            "send" => Command::SendWave,
+            _ => return Err(format!("Unknown command: {}", text)),
+        })
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Positions are written in full, so a command read back lands on exactly the same spot
+        match self {
+            Command::Build {
+                tower_type,
+                position,
+            } => write!(f, "build {} {} {}", tower_type, position.x, position.y),
+            Command::Sell { position } => write!(f, "sell {} {}", position.x, position.y),
+            Command::SelectTowerType(tower_type) => write!(f, "select {}", tower_type),
+            Command::ToggleShop => write!(f, "shop"),
+            Command::Buy(offer) => write!(f, "buy {}", offer),
+            Command::UseItem { slot, position } => {
+                write!(f, "use {} {} {}", slot, position.x, position.y)
+            }
+            Command::CollectLoot { position } => write!(f, "loot {} {}", position.x, position.y),
+            Command::UseAbility { position } => {
+                write!(f, "ability {} {}", position.x, position.y)
+            }
+            Command::PullLever(gate) => write!(f, "lever {}", gate),
+            Command::SpawnEnemy(enemy_type) => write!(f, "spawn {}", enemy_type),
+            Command::StartWave => write!(f, "start-wave"),
+            Command::QueueEnemy(enemy_type) => write!(f, "queue {}", enemy_type),
+            Command::SendWave => write!(f, "send"),
+        }
+    }
+}
+
+// What it takes to reverse a command that was carried out
+enum Undo {
+    // Remove the tower with the given number, giving back what it cost
+    Build {
+        id: usize,
+        spent: i32,
+        discounts: Vec<f32>,
+    },
+    // Put the sold tower back where it was, taking back the refund
+    Sell {
+        index: usize,
+        tower: Tower,
+        refund: i32,
+    },
+}
+
+impl Game {
+    fn execute(&mut self, command: Command) -> Option<Undo> {
+        // Carry out the command, returning how to undo it if it changed the player's defenses
+        match command {
+            Command::Build {
+                tower_type,
+                position,
+            } => {
+                let (resources, placed) = (self.state.resources, self.state.towers_placed);
+                let discounts = self.state.tower_discounts.clone();
+                self.build_tower(tower_type, position);
+                (self.state.towers_placed != placed).then(|| Undo::Build {
+                    id: self.state.towers_placed,
+                    spent: resources - self.state.resources,
+                    discounts,
+                })
+            }
+            Command::Sell { position } => {
+                let index = self.tower_at(&position)?;
+                let tower = self.state.towers[index].clone();
+                let (resources, count) = (self.state.resources, self.state.towers.len());
+                self.sell_tower_at(&position);
+                (self.state.towers.len() != count).then(|| Undo::Sell {
+                    index,
+                    tower,
+                    refund: self.state.resources - resources,
+                })
+            }
+            Command::SelectTowerType(tower_type) => {
+                self.select_tower_type(tower_type);
+                None
+            }
+            Command::ToggleShop => {
+                self.toggle_shop();
+                None
+            }
+            Command::Buy(offer) => {
+                self.buy(offer);
+                None
+            }
+            Command::UseItem { slot, position } => {
+                self.use_item(slot, position);
+                None
+            }
+            Command::CollectLoot { position } => {
+                self.collect_loot(position);
+                None
+            }
+            Command::UseAbility { position } => {
+                self.use_ability(position);
+                None
+            }
+            Command::PullLever(gate) => {
+                self.pull_lever(gate);
+                None
+            }
+            Command::SpawnEnemy(enemy_type) => {
+                self.spawn_enemy(enemy_type);
+                None
+            }
+            Command::StartWave => {
+                self.state.start_wave();
+                None
+            }
+            Command::QueueEnemy(enemy_type) => {
+                self.queue_enemy(enemy_type);
+                None
+            }
+            Command::SendWave => {
+                self.send_wave();
+                None
+            }
+        }
+    }
+
+    pub fn apply(&mut self, command: Command) {
+        // Carry out a command that doesn't come from the player, so it isn't recorded and can't be undone
+        self.execute(command);
+    }
+
+    fn undo(&mut self, undo: Undo) {
+        match undo {
+            Undo::Build {
+                id,
+                spent,
+                discounts,
+            } => {
+                self.state.towers.retain(|tower| tower.id != id);
+                self.state.resources += spent;
+                self.state.tower_discounts = discounts;
+            }
+            Undo::Sell {
+                index,
+                tower,
+                refund,
+            } => {
+                let index = index.min(self.state.towers.len());
+                self.state.towers.insert(index, tower);
+                self.state.resources -= refund;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CommandQueue {
+    // Stores the commands issued while the game was paused, in the order they were issued
+    pending: Vec<Command>,
+    // Stores the commands that can be undone, oldest first, with how to undo each
+    done: Vec<(Command, Undo)>,
+    // Stores the commands that were undone and can be redone, most recently undone last
+    undone: Vec<Command>,
+    // Stores the wave whose build phase the undo history belongs to
+    wave: usize,
+    // Stores the replay every change to the game is being recorded into, if there is one
+    pub recording: Option<Replay>,
+    // Stores the replay just the builds, sales, and undos that took effect are recorded into, with redone changes
+    // recorded again as they're redone, to keep as a ghost of the run if it turns out to be the player's best on its
+    // level
+    pub ghost: Option<Replay>,
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        CommandQueue::default()
+    }
+
+    pub fn issue(&mut self, command: Command, game: &mut Game, defer: bool) {
+        // Carry out the command straight away, unless it's deferred until the game is unpaused
+        if !command.allowed(game) {
+            return;
+        }
+        if defer && command.deferrable() {
+            self.pending.push(command);
+        } else {
+            self.run(command, game);
+            self.undone.clear();
+        }
+    }
+
+    fn run(&mut self, command: Command, game: &mut Game) {
+        self.forget_if_stale(game);
+        self.record(game, Input::Command(command));
+        if let Some(undo) = game.execute(command) {
+            self.record_ghost(game, Input::Command(command));
+            self.done.push((command, undo));
+        }
+    }
+
+    fn record(&mut self, game: &Game, input: Input) {
+        if let Some(replay) = &mut self.recording {
+            replay.inputs.push((game.state.tick, input));
+        }
+    }
+
+    fn record_ghost(&mut self, game: &Game, input: Input) {
+        // Keep a change once it's taken effect, with a tower where it was actually built rather than where the
+        // player clicked
+        let Some(ghost) = &mut self.ghost else {
+            return;
+        };
+        let input = match input {
+            Input::Command(Command::Build {
+                tower_type,
+                position,
+            }) => Input::Command(Command::Build {
+                tower_type,
+                position: game
+                    .state
+                    .towers
+                    .last()
+                    .map_or(position, |tower| tower.position),
+            }),
+            input => input,
+        };
+        ghost.inputs.push((game.state.tick, input));
+    }
+
+    fn forget_if_stale(&mut self, game: &Game) {
+        // Changes can only be undone during the build phase they were made in, so waves can't be replayed
+        // with hindsight
+        if !game.state.building || game.state.wave != self.wave {
+            self.done.clear();
+            self.undone.clear();
+            self.wave = game.state.wave;
+        }
+    }
+
+    pub fn flush(&mut self, game: &mut Game) {
+        // Carry out every deferred command, in the order they were issued
+        for command in std::mem::take(&mut self.pending) {
+            self.run(command, game);
+        }
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self, game: &mut Game) {
+        // Take back the most recent change, or the most recent queued one while they're waiting
+        if self.pending.pop().is_some() {
+            return;
+        }
+        self.forget_if_stale(game);
+        if let Some((command, undo)) = self.done.pop() {
+            self.record(game, Input::Undo);
+            self.record_ghost(game, Input::Undo);
+            game.undo(undo);
+            self.undone.push(command);
+        }
+    }
+
+    pub fn redo(&mut self, game: &mut Game) {
+        self.forget_if_stale(game);
+        if let Some(command) = self.undone.pop() {
+            self.record(game, Input::Redo);
+            if let Some(undo) = game.execute(command) {
+                self.record_ghost(game, Input::Command(command));
+                self.done.push((command, undo));
+            }
+        }
+    }
+
+    pub fn pending(&self) -> &[Command] {
+        &self.pending
+    }
+}
@@ -0,0 +1,160 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::level::{Formation, Group, Wave, WaveModifier, DEFAULT_SPACING};
+use crate::math;
+use crate::naval::Terrain;
+use crate::storage;
+use crate::{EnemyType, Game};
+
+// Points an endless wave has to spend on the enemies it sends each second, for every wave into the run
+const BUDGET_PER_WAVE: f32 = 12.;
+// How much bigger each endless wave's budget is than it would be if budgets only grew in step with the waves
+const BUDGET_GROWTH: f32 = 1.03;
+// Most groups an ordinary endless wave is split into
+const MAX_GROUPS: usize = 3;
+// Seconds between the groups of an endless wave starting to be sent
+const GROUP_DELAY: f32 = 4.;
+// Chance an endless wave that's allowed a modifier gets one
+const MODIFIER_CHANCE: f64 = 0.3;
+// Percentage faster or tougher a modifier makes a wave's enemies, which costs the same share of its budget
+const MODIFIER_PERCENT: f32 = 25.;
+// Chance an endless wave is a boss wave, when the one before wasn't
+const BOSS_CHANCE: f64 = 0.2;
+// Most waves in a row an endless run goes without a boss wave
+const BOSS_INTERVAL: usize = 5;
+// Share of a boss wave's budget spent on its bosses, with the rest on the escort sent ahead of them
+const BOSS_SHARE: f32 = 0.6;
+// Percentage more hit points every enemy in a boss wave has
+const BOSS_TOUGHNESS: f32 = 50.;
+// Seconds into a boss wave before its bosses follow the escort
+const BOSS_DELAY: f32 = 8.;
+
+fn enemy_cost(enemy_type: &EnemyType) -> f32 {
+    // What an enemy is worth to a wave's budget, going up with how much it takes to stop and what it costs to let by
+    enemy_type.max_hit_points as f32 * enemy_type.speed / 2. + 5. * enemy_type.lives_cost as f32
+}
+
+fn budget(wave: usize) -> f32 {
+    BUDGET_PER_WAVE * wave as f32 * math::powi(BUDGET_GROWTH, wave.saturating_sub(1))
+}
+
+fn group(enemy_type: usize, points: f32, cost: f32, formation: Formation, delay: f32) -> Group {
+    Group {
+        enemy_type,
+        count: ((points / cost) as usize).max(1),
+        formation,
+        spacing: DEFAULT_SPACING,
+        delay,
+    }
+}
+
+fn compose(
+    number: usize,
+    enemy_types: &[(usize, f32)],
+    previous: &[Wave],
+    rng: &mut ChaCha12Rng,
+) -> Wave {
+    // Spend the wave's budget on enemies from the given types and their costs, keeping to the pacing rules: never
+    // two boss waves in a row, and never more than BOSS_INTERVAL waves without one
+    let budget = budget(number);
+    let since_boss = previous.iter().rev().take_while(|wave| !wave.boss).count();
+    let boss = since_boss > 0 && (since_boss >= BOSS_INTERVAL || rng.gen_bool(BOSS_CHANCE));
+    if boss {
+        // The strongest enemies come last, made tougher, behind an escort of the weakest
+        let toughness = 1. + BOSS_TOUGHNESS / 100.;
+        let by_cost = |a: &&(usize, f32), b: &&(usize, f32)| a.1.total_cmp(&b.1);
+        let (weakest, weakest_cost) = *enemy_types.iter().min_by(by_cost).unwrap_or(&(0, 1.));
+        let (strongest, strongest_cost) = *enemy_types.iter().max_by(by_cost).unwrap_or(&(0, 1.));
+        return Wave {
+            groups: vec![
+                group(
+                    weakest,
+                    budget * (1. - BOSS_SHARE),
+                    weakest_cost * toughness,
+                    Formation::Clump,
+                    0.,
+                ),
+                group(
+                    strongest,
+                    budget * BOSS_SHARE,
+                    strongest_cost * toughness,
+                    Formation::Line,
+                    BOSS_DELAY,
+                ),
+            ],
+            modifiers: vec![WaveModifier::Tougher(BOSS_TOUGHNESS)],
+            boss: true,
+        };
+    }
+
+    // An ordinary wave might be faster or tougher for a share of its budget, then splits the rest between groups of
+    // random enemies, each starting a little after the last
+    let mut modifiers = Vec::new();
+    let mut markup = 1.;
+    if rng.gen_bool(MODIFIER_CHANCE) {
+        modifiers.push(if rng.gen_bool(0.5) {
+            WaveModifier::Faster(MODIFIER_PERCENT)
+        } else {
+            WaveModifier::Tougher(MODIFIER_PERCENT)
+        });
+        markup += MODIFIER_PERCENT / 100.;
+    }
+    let count = rng.gen_range(1..=MAX_GROUPS);
+    let formations = [Formation::Line, Formation::Clump, Formation::Staggered];
+    let groups = (0..count)
+        .map(|i| {
+            let (enemy_type, cost) = enemy_types
+                .get(rng.gen_range(0..enemy_types.len().max(1)))
+                .copied()
+                .unwrap_or((0, 1.));
+            let formation = formations[rng.gen_range(0..formations.len())];
+            group(
+                enemy_type,
+                budget / count as f32,
+                cost * markup,
+                formation,
+                i as f32 * GROUP_DELAY,
+            )
+        })
+        .collect();
+    Wave {
+        groups,
+        modifiers,
+        boss: false,
+    }
+}
+
+impl Game {
+    pub fn compose_endless_waves(&mut self) {
+        // Keep the waves at least one past the current one in an endless run, so the next wave can be announced and
+        // previewed, composing each from a seed taken from the level's path, with a generator whose numbers are the
+        // same in every build, so a level's endless waves are the same every run and replays of them play back
+        if !self.rules.endless || self.waves.len() > self.state.wave {
+            return;
+        }
+        let bytes: Vec<u8> = self
+            .route
+            .waypoints
+            .iter()
+            .flat_map(|point| [point.x.to_le_bytes(), point.y.to_le_bytes()])
+            .flatten()
+            .collect();
+        let seed = storage::checksum(&bytes);
+
+        // Boats can only be sent on levels with water for them
+        let enemy_types: Vec<(usize, f32)> = self
+            .enemy_types
+            .iter()
+            .enumerate()
+            .filter(|(_, enemy_type)| enemy_type.terrain == Terrain::Land || self.lane.is_some())
+            .map(|(i, enemy_type)| (i, enemy_cost(enemy_type)))
+            .collect();
+        while self.waves.len() <= self.state.wave {
+            let number = self.waves.len() + 1;
+            let mut rng = ChaCha12Rng::seed_from_u64(seed ^ number as u64);
+            let wave = compose(number, &enemy_types, &self.waves, &mut rng);
+            self.waves.push(wave);
+        }
+    }
+}
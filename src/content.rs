@@ -0,0 +1,502 @@
+use crate::abilities::{Ability, Effect};
+use crate::behaviors;
+use crate::gates::{Gate, Route};
+use crate::items::{ItemEffect, ItemType};
+use crate::level::{Group, Level, TileMap, Wave, WaveModifier};
+use crate::loot::{DropChance, Loot};
+use crate::naval::{Lane, Terrain};
+use crate::objectives;
+use crate::storage;
+use crate::{EnemyType, Flocking, Game, Movement, Path, Point, TowerType, TILE_SIZE};
+
+pub fn default_level() -> Level {
+    // The map the game is played on when no level file is given, with waypoints in tiles
+    Level {
+        name: String::from("Meadow"),
+        author: String::from("cypressf"),
+        difficulty: 1,
+        map: TileMap::new((26, 20)),
+        waypoints: vec![
+            Point::new(25.6, 16.0),
+            Point::new(18.0, 16.0),
+            Point::new(18.0, 6.0),
+            Point::new(8.0, 6.0),
+            Point::new(8.0, 0.5),
+            Point::new(2.0, 0.5),
+        ],
+        layers: Vec::new(),
+        lane: Vec::new(),
+        teleporters: Vec::new(),
+        hazards: Vec::new(),
+        winds: Vec::new(),
+        gates: Vec::new(),
+        slots: Vec::new(),
+        waves: Vec::new(),
+        objectives: Vec::new(),
+        bonus_objectives: Vec::new(),
+        convoys: Vec::new(),
+        behaviors: Vec::new(),
+        puzzle: None,
+    }
+}
+
+pub fn enemy_types() -> Vec<EnemyType> {
+    // Every enemy the game knows about, whether or not a level sends it
+    let enemy_type_1 = EnemyType {
+        name: String::from("Goblin"),
+        lore: "Quick, greedy, and never alone for long. Goblins are the vanguard of every horde, and now and then one is caught carrying a purse worth picking up.",
+        max_hit_points: 10,
+        speed: 2.0,
+        reward: 20,
+        lives_cost: 1,
+        drops: vec![DropChance {
+            chance: 0.05,
+            loot: Loot::Resources(25),
+        }],
+        ..Default::default()
+    };
+
+    let enemy_type_2 = EnemyType {
+        name: String::from("Orc"),
+        lore: "Thick-skinned brutes in scavenged plate. Leave one wounded and out of reach and it will shrug the wounds off before long.",
+        max_hit_points: 20,
+        speed: 1.5,
+        reward: 30,
+        lives_cost: 2,
+        armor: 2,
+        regeneration: 2.0,
+        regeneration_delay: 3.0,
+        drops: vec![
+            DropChance {
+                chance: 0.05,
+                loot: Loot::Item(0),
+            },
+            DropChance {
+                chance: 0.05,
+                loot: Loot::Item(1),
+            },
+            DropChance {
+                chance: 0.05,
+                loot: Loot::Item(2),
+            },
+        ],
+        ..Default::default()
+    };
+
+    let enemy_type_3 = EnemyType {
+        name: String::from("Vampire"),
+        lore: "Drawn to the slaughter at the front of the line, a vampire drinks in the lives of the fallen around it to close its own wounds.",
+        max_hit_points: 15,
+        speed: 1.8,
+        reward: 40,
+        lives_cost: 2,
+        life_steal: 5,
+        life_steal_range: 50.0,
+        drops: vec![DropChance {
+            chance: 0.1,
+            loot: Loot::SpellCharge,
+        }],
+        ..Default::default()
+    };
+
+    let enemy_type_4 = EnemyType {
+        name: String::from("Shade"),
+        lore: "A wisp of shadow that ordinary eyes slide straight past. Only a tower that can see through magic can draw a bead on one.",
+        max_hit_points: 12,
+        speed: 2.2,
+        reward: 35,
+        lives_cost: 1,
+        stealth: true,
+        ..Default::default()
+    };
+
+    let enemy_type_5 = EnemyType {
+        name: String::from("Imp"),
+        lore: "Small and spiteful, imps vanish in a puff of brimstone and reappear a little further down the road.",
+        max_hit_points: 8,
+        speed: 1.5,
+        reward: 25,
+        lives_cost: 1,
+        movement: Movement::Blink {
+            interval: 3.0,
+            distance: 3.0,
+        },
+        ..Default::default()
+    };
+
+    let enemy_type_6 = EnemyType {
+        name: String::from("Burrower"),
+        lore: "Armored diggers that tunnel under the path every few seconds, out of reach of any tower until they surface again.",
+        max_hit_points: 25,
+        speed: 1.2,
+        reward: 35,
+        lives_cost: 2,
+        armor: 3,
+        movement: Movement::Burrow {
+            interval: 4.0,
+            duration: 2.0,
+        },
+        ..Default::default()
+    };
+
+    let enemy_type_7 = EnemyType {
+        name: String::from("Knight"),
+        lore: "A fallen champion behind a great shield. Shots that hit the shield head on glance off, so catch it from the side or behind.",
+        max_hit_points: 30,
+        speed: 1.0,
+        reward: 45,
+        lives_cost: 3,
+        armor: 4,
+        shield_angle: std::f32::consts::FRAC_PI_4,
+        drops: vec![
+            DropChance {
+                chance: 0.1,
+                loot: Loot::DamageBoost {
+                    multiplier: 2.0,
+                    duration: 10.0,
+                },
+            },
+            DropChance {
+                chance: 0.1,
+                loot: Loot::Resources(50),
+            },
+        ],
+        ..Default::default()
+    };
+
+    let enemy_type_8 = EnemyType {
+        name: String::from("Rat"),
+        lore: "Never one rat. They pour down the road in a squealing swarm that sticks together and is gone as soon as it arrived.",
+        max_hit_points: 4,
+        speed: 2.5,
+        reward: 5,
+        lives_cost: 1,
+        flocking: Some(Flocking {
+            radius: 20.0,
+            separation: 4.0,
+            alignment: 2.0,
+        }),
+        ..Default::default()
+    };
+
+    let enemy_type_9 = EnemyType {
+        name: String::from("Longboat"),
+        lore: "Raiders who take the water rather than the road, rowing straight for the base along the river.",
+        max_hit_points: 25,
+        speed: 1.2,
+        reward: 20,
+        lives_cost: 2,
+        terrain: Terrain::Water,
+        ..Default::default()
+    };
+
+    let enemy_type_10 = EnemyType {
+        name: String::from("Lizardman"),
+        lore: "At home in the water and on land, lizardmen swim the river and haul themselves ashore wherever the way to the base is shortest.",
+        max_hit_points: 12,
+        speed: 1.4,
+        reward: 15,
+        lives_cost: 1,
+        terrain: Terrain::Amphibious,
+        ..Default::default()
+    };
+
+    vec![
+        enemy_type_1,
+        enemy_type_2,
+        enemy_type_3,
+        enemy_type_4,
+        enemy_type_5,
+        enemy_type_6,
+        enemy_type_7,
+        enemy_type_8,
+        enemy_type_9,
+        enemy_type_10,
+    ]
+}
+
+pub fn tower_types() -> Vec<TowerType> {
+    // Every tower the game knows about, whether or not a level can have it built
+    let tower_type_1 = TowerType {
+        name: String::from("Archer Tower"),
+        lore: "A simple wooden tower manned by steady archers. Cheap, reliable, and able to loose a volley when the road gets crowded.",
+        cost: 50,
+        damage: 5,
+        range: 100.0,
+        rate_of_fire: 1.0,
+        ability: Some(Ability {
+            name: "Volley",
+            effect: Effect::Volley(3),
+            duration: 5.0,
+            cooldown: 20.0,
+        }),
+        ..Default::default()
+    };
+
+    let tower_type_2 = TowerType {
+        name: String::from("Mage Tower"),
+        lore: "Its wizards see what is hidden and strike from far away. At need, they can overcharge the tower to fire faster.",
+        cost: 75,
+        damage: 10,
+        range: 200.0,
+        rate_of_fire: 2.0,
+        detects_stealth: true,
+        light: Some([0.6, 0.4, 1.0, 1.0]),
+        ability: Some(Ability {
+            name: "Overcharge",
+            effect: Effect::Overcharge(2.0),
+            duration: 5.0,
+            cooldown: 30.0,
+        }),
+        ..Default::default()
+    };
+
+    let tower_type_3 = TowerType {
+        name: String::from("Arcane Tower"),
+        lore: "Built only by those who have beaten the horde before. It hurls bolts of raw magic across half the map.",
+        cost: 150,
+        damage: 25,
+        range: 250.0,
+        rate_of_fire: 2.0,
+        detects_stealth: true,
+        prestige_required: 1,
+        ability: None,
+        armor_shred: 0,
+        ground_only: false,
+        floating: false,
+        behavior: None,
+        light: Some([0.3, 0.8, 1.0, 1.0]),
+    };
+
+    let tower_type_4 = TowerType {
+        name: String::from("Shredder Tower"),
+        lore: "A spinning mass of blades that barely scratches its targets, but strips their armor away a little with every hit.",
+        cost: 60,
+        damage: 1,
+        range: 125.0,
+        rate_of_fire: 3.0,
+        armor_shred: 1,
+        ground_only: true,
+        ..Default::default()
+    };
+
+    let tower_type_5 = TowerType {
+        name: String::from("Harpoon Raft"),
+        lore: "A platform lashed together out of driftwood and moored on the water, with a harpoon gun to keep raiders off the river.",
+        cost: 70,
+        damage: 8,
+        range: 150.0,
+        rate_of_fire: 1.0,
+        floating: true,
+        ..Default::default()
+    };
+
+    vec![
+        tower_type_1,
+        tower_type_2,
+        tower_type_3,
+        tower_type_4,
+        tower_type_5,
+    ]
+}
+
+pub fn data_version() -> u64 {
+    // A fingerprint of every tower's, enemy's, and item's balance, saved with a run so loading it after a balance
+    // change can tell the numbers it was played with are gone
+    let mut data = String::new();
+    for tower_type in tower_types() {
+        data += &format!(
+            "tower {} {} {} {} {} {} {} {} {} {}",
+            tower_type.name,
+            tower_type.cost,
+            tower_type.damage,
+            tower_type.range,
+            tower_type.rate_of_fire,
+            tower_type.detects_stealth,
+            tower_type.prestige_required,
+            tower_type.armor_shred,
+            tower_type.ground_only,
+            tower_type.floating
+        );
+        if let Some(ability) = &tower_type.ability {
+            data += &format!(
+                " {} {:?} {} {}",
+                ability.name, ability.effect, ability.duration, ability.cooldown
+            );
+        }
+        data += "\n";
+    }
+    for enemy_type in enemy_types() {
+        data += &format!(
+            "enemy {} {} {} {} {} {} {} {} {} {} {} {} {:?} {:?} {:?} {:?}\n",
+            enemy_type.name,
+            enemy_type.max_hit_points,
+            enemy_type.speed,
+            enemy_type.reward,
+            enemy_type.lives_cost,
+            enemy_type.armor,
+            enemy_type.regeneration,
+            enemy_type.regeneration_delay,
+            enemy_type.life_steal,
+            enemy_type.life_steal_range,
+            enemy_type.stealth,
+            enemy_type.shield_angle,
+            enemy_type.movement,
+            enemy_type.terrain,
+            enemy_type.flocking,
+            enemy_type.drops
+        );
+    }
+    for item_type in item_types() {
+        data += &format!("item {} {:?}\n", item_type.name, item_type.effect);
+    }
+    storage::checksum(data.as_bytes())
+}
+
+pub fn item_types() -> Vec<ItemType> {
+    // Every item the game knows about, which every level has
+    let item_type_1 = ItemType {
+        name: String::from("Airstrike"),
+        effect: ItemEffect::Airstrike {
+            damage: 15,
+            radius: 75.0,
+        },
+    };
+
+    let item_type_2 = ItemType {
+        name: String::from("Wall"),
+        effect: ItemEffect::Wall { duration: 5.0 },
+    };
+
+    let item_type_3 = ItemType {
+        name: String::from("Resource Cache"),
+        effect: ItemEffect::ResourceCache(50),
+    };
+
+    vec![item_type_1, item_type_2, item_type_3]
+}
+
+pub fn new_game(level: &Level, prestige: u32) -> Result<Game, String> {
+    // Boats and amphibious enemies only come on levels with a lane for them to travel, and the raft only on levels
+    // with water to float it on
+    let mut enemy_types = enemy_types();
+    if level.lane.is_empty() {
+        enemy_types.retain(|enemy_type| enemy_type.terrain == Terrain::Land);
+    }
+    let mut tower_types = tower_types();
+    if level.map.water_tiles().next().is_none() {
+        tower_types.retain(|tower_type| !tower_type.floating);
+    }
+
+    // Levels can give tower types a behavior from the registry, changing how they pick targets or what their shots do
+    for (behavior, name) in level.behaviors.iter() {
+        let tower_type = tower_types
+            .iter_mut()
+            .find(|tower_type| tower_type.name == *name)
+            .ok_or_else(|| format!("Unknown tower type: {}", name))?;
+        tower_type.behavior = Some(
+            behaviors::from_name(behavior)
+                .ok_or_else(|| format!("Unknown tower behavior: {}", behavior))?,
+        );
+    }
+
+    // Levels name the enemies their waves send, which have to be ones the game knows about, and a wave limited to
+    // one type of enemy sends it in place of every group's own
+    let mut waves = Vec::new();
+    for wave in level.waves.iter() {
+        let only = wave.modifiers.iter().find_map(|modifier| match modifier {
+            WaveModifier::Only(enemy_type) => Some(enemy_type),
+            _ => None,
+        });
+        let mut groups = Vec::new();
+        for group in wave.groups.iter() {
+            let name = only.unwrap_or(&group.enemy_type);
+            let enemy_type = enemy_types
+                .iter()
+                .position(|enemy_type| enemy_type.name == *name)
+                .ok_or_else(|| format!("Unknown enemy type: {}", name))?;
+            groups.push(Group {
+                enemy_type,
+                count: group.count,
+                formation: group.formation,
+                spacing: group.spacing,
+                delay: group.delay,
+            });
+        }
+        waves.push(Wave {
+            groups,
+            modifiers: wave.modifiers.clone(),
+            boss: false,
+        });
+    }
+
+    // The path is laid out from the level's route with any gates that start closed already sending enemies round
+    let route = Route::new(
+        level.path_points(),
+        level.path_layers(),
+        level
+            .teleporters
+            .iter()
+            .map(|(entry, exit)| (entry.scale(TILE_SIZE), exit.scale(TILE_SIZE)))
+            .collect(),
+    );
+    let gates: Vec<Gate> = level
+        .gates
+        .iter()
+        .map(|gate| gate.scale(TILE_SIZE))
+        .collect();
+    let (path, _) = route.build(&gates);
+    let lane = (!level.lane.is_empty()).then(|| Lane::new(Path::new(level.lane_points()), &path));
+    let mut game = Game::new(
+        tower_types,
+        enemy_types,
+        item_types(),
+        path,
+        level.map.clone(),
+        waves,
+        prestige,
+    );
+    game.lane = lane;
+    game.hazards = level
+        .hazards
+        .iter()
+        .map(|hazard| hazard.scale(TILE_SIZE))
+        .collect();
+    game.winds = level
+        .winds
+        .iter()
+        .map(|wind| wind.scale(TILE_SIZE))
+        .collect();
+    game.route = route;
+    game.gates = gates;
+    game.slots = level
+        .slots
+        .iter()
+        .map(|slot| slot.scale(TILE_SIZE))
+        .collect();
+    game.convoys = level.convoys.clone();
+
+    // Puzzle levels give a fixed amount of resources with nothing more to earn, and are solved by stopping every
+    // enemy of their waves, which come on exactly their own timing
+    if let Some(resources) = level.puzzle {
+        game.rules.puzzle = true;
+        game.rules.director = false;
+        game.state.resources = resources;
+        game.objectives = vec![Box::new(objectives::SolvePuzzle)];
+    }
+
+    // Levels that don't give their own objectives are won by clearing every wave, or solving the puzzle
+    if !level.objectives.is_empty() {
+        game.objectives = level
+            .objectives
+            .iter()
+            .map(|spec| objectives::from_spec(spec))
+            .collect::<Result<_, _>>()?;
+    }
+    game.bonus_objectives = level
+        .bonus_objectives
+        .iter()
+        .map(|spec| objectives::from_spec(spec))
+        .collect::<Result<_, _>>()?;
+    Ok(game)
+}
@@ -0,0 +1,143 @@
+use crate::events::GameEvent;
+use crate::notifications::{notify, Kind};
+use crate::{Enemy, Game, Point, TILE_SIZE};
+
+// Hit points of each convoy unit
+pub const CONVOY_HIT_POINTS: f32 = 100.;
+// Speed of convoy units, in tiles per second
+const CONVOY_SPEED: f32 = 0.8;
+// Seconds between the units of a convoy setting off
+const CONVOY_SPACING: f32 = 1.5;
+// Distance within which an enemy stops to attack a convoy unit instead of heading for the base
+const CONVOY_AGGRO_RANGE: f32 = TILE_SIZE;
+// Damage each attacking enemy does to a convoy unit, per second
+const CONVOY_DAMAGE_PER_SECOND: f32 = 10.;
+
+// A convoy the level sends out from the player's base once a wave starts
+#[derive(Clone, Copy)]
+pub struct ConvoySpec {
+    // Stores the wave the convoy sets off at the start of
+    pub wave: usize,
+    // Stores the number of units in the convoy
+    pub count: u32,
+}
+
+// A friendly unit travelling from the player's base back up the path, which enemies will stop to attack
+#[derive(Clone)]
+pub struct ConvoyUnit {
+    // Stores the unit's position on the map
+    pub position: Point,
+    // Stores the unit's position at the start of the last simulation tick
+    pub previous_position: Point,
+    // Stores how far from the player's base the unit has travelled, in pixels
+    pub distance: f32,
+    // Stores the unit's current hit points
+    pub hit_points: f32,
+    // Stores the time until the unit sets off, in seconds
+    pub delay: f32,
+}
+
+impl ConvoyUnit {
+    pub fn new(delay: f32) -> Self {
+        ConvoyUnit {
+            position: Point::new(0., 0.),
+            previous_position: Point::new(0., 0.),
+            distance: 0.,
+            hit_points: CONVOY_HIT_POINTS,
+            delay,
+        }
+    }
+
+    pub fn on_map(&self) -> bool {
+        // Units waiting to set off are still inside the base, out of harm's way
+        self.delay <= 0.
+    }
+
+    pub fn interpolated_position(&self, alpha: f32) -> Point {
+        self.previous_position
+            .plus(&self.position.minus(&self.previous_position).scale(alpha))
+    }
+}
+
+fn target(units: &[ConvoyUnit], enemy: &Enemy) -> Option<usize> {
+    // Enemies go for the nearest convoy unit in reach, and otherwise carry on towards the base
+    if !enemy.is_alive() || enemy.burrowed {
+        return None;
+    }
+    units
+        .iter()
+        .enumerate()
+        .filter(|(_, unit)| unit.on_map())
+        .map(|(i, unit)| (i, unit.position.distance_to(&enemy.position)))
+        .filter(|&(_, distance)| distance < CONVOY_AGGRO_RANGE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+impl Game {
+    pub fn send_convoys(&mut self) {
+        // Line up the units of every convoy leaving at the start of this wave
+        let count: u32 = self
+            .convoys
+            .iter()
+            .filter(|convoy| convoy.wave == self.state.wave)
+            .map(|convoy| convoy.count)
+            .sum();
+        if count == 0 {
+            return;
+        }
+        for i in 0..count {
+            self.state
+                .convoy
+                .push(ConvoyUnit::new(i as f32 * CONVOY_SPACING));
+        }
+        notify(
+            format!("A convoy of {} is leaving the base", count),
+            Kind::Info,
+        );
+    }
+
+    pub fn update_convoy(&mut self, dt: f32) {
+        // Move convoy units up the path from the base, towards where the enemies come from
+        let length = self.path.length();
+        for unit in self.state.convoy.iter_mut() {
+            unit.delay -= dt;
+            if !unit.on_map() {
+                continue;
+            }
+            unit.previous_position = unit.position;
+            unit.distance += CONVOY_SPEED * TILE_SIZE * dt;
+            unit.position = self.path.point_at(length - unit.distance);
+        }
+
+        // Enemies in reach of a unit stop to attack it, applied in enemy order so the outcome is the same every time
+        let units = &mut self.state.convoy;
+        for enemy in self.state.enemies.iter_mut() {
+            let target = target(units, enemy);
+            enemy.attacking = target.is_some();
+            if let Some(i) = target {
+                units[i].hit_points -= CONVOY_DAMAGE_PER_SECOND * dt;
+            }
+        }
+
+        // Units are lost when they run out of hit points, and safe once they reach the end of the path
+        for unit in self.state.convoy.iter() {
+            if unit.hit_points <= 0. {
+                self.state.convoy_lost += 1;
+                self.state.events.push(GameEvent::ConvoyLost);
+                notify("A convoy unit was destroyed!", Kind::Warning);
+            } else if unit.distance >= length {
+                self.state.convoy_arrived += 1;
+                self.state.events.push(GameEvent::ConvoyArrived);
+            }
+        }
+        self.state
+            .convoy
+            .retain(|unit| unit.hit_points > 0. && unit.distance < length);
+    }
+
+    pub fn convoy_size(&self) -> u32 {
+        // The total number of units the level's convoys send over the whole run
+        self.convoys.iter().map(|convoy| convoy.count).sum()
+    }
+}
@@ -0,0 +1,148 @@
+use piston_window::*;
+
+use crate::label::{self, Align, TextStyle};
+use crate::{theme, Game, Layer, Point, TILE_SIZE};
+
+// Colors of the path where no tower reaches it, where one does, where two do, and where three or more do
+const COVERAGE_COLORS: [[f32; 4]; 4] = [
+    [0.9, 0.15, 0.15, 0.7],
+    [0.95, 0.8, 0.1, 0.7],
+    [0.5, 0.85, 0.2, 0.7],
+    [0.1, 0.65, 0.2, 0.7],
+];
+
+// A stretch of the path reached by the same number of towers all the way along
+pub struct Stretch {
+    // Stores where the stretch starts and ends, in the same corner-of-the-tile terms as the path's waypoints
+    pub start: Point,
+    pub end: Point,
+    // Stores how many towers reach the stretch
+    pub towers: usize,
+}
+
+// How much of the path the player's towers reach, stretch by stretch
+pub struct Coverage {
+    // Stores the stretches in order along the path
+    pub stretches: Vec<Stretch>,
+    // Stores the share of the path's length at least one tower reaches, from 0 to 1
+    pub covered: f32,
+}
+
+fn circle_overlap(start: &Point, end: &Point, center: &Point, radius: f32) -> Option<(f32, f32)> {
+    // Find the part of the segment inside the circle, as how far along the segment it starts and ends from 0 to 1,
+    // by solving for where the line through the segment crosses the circle
+    let direction = end.minus(start);
+    let from_center = start.minus(center);
+    let a = direction.dot(&direction);
+    let b = 2. * direction.dot(&from_center);
+    let c = from_center.dot(&from_center) - radius * radius;
+    if a == 0. {
+        return (c < 0.).then_some((0., 1.));
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant <= 0. {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let enter = ((-b - root) / (2. * a)).max(0.);
+    let leave = ((-b + root) / (2. * a)).min(1.);
+    (enter < leave).then_some((enter, leave))
+}
+
+impl Game {
+    pub fn coverage(&self, ghost: Option<(Point, f32)>) -> Coverage {
+        // Split every segment of the path at the points where it enters or leaves a tower's range, counting the
+        // towers reaching each piece, with the ghost of a tower being placed counted like a built one. Tunnels are
+        // left out, since nothing can reach enemies in them. Reach goes by range alone, so high ground blocking a
+        // tower's view isn't taken into account
+        let reaches: Vec<(Point, f32)> = self
+            .state
+            .towers
+            .iter()
+            .map(|tower| (tower.position, tower.stats.range))
+            .chain(ghost)
+            .map(|(position, range)| (position, self.map.range_from(&position, range)))
+            .collect();
+        let mut stretches: Vec<Stretch> = Vec::new();
+        let (mut length, mut covered) = (0., 0.);
+        for (i, segment) in self.path.waypoints.windows(2).enumerate() {
+            if self.path.segment_layer(i) == Layer::Tunnel {
+                continue;
+            }
+            let (start, end) = (&segment[0], &segment[1]);
+            let overlaps: Vec<(f32, f32)> = reaches
+                .iter()
+                .filter_map(|(center, radius)| circle_overlap(start, end, center, *radius))
+                .collect();
+            let mut cuts: Vec<f32> = overlaps
+                .iter()
+                .flat_map(|&(enter, leave)| [enter, leave])
+                .chain([0., 1.])
+                .collect();
+            cuts.sort_by(f32::total_cmp);
+            cuts.dedup();
+            let segment_length = start.distance_to(end);
+            length += segment_length;
+            for piece in cuts.windows(2) {
+                let middle = (piece[0] + piece[1]) / 2.;
+                let towers = overlaps
+                    .iter()
+                    .filter(|&&(enter, leave)| enter <= middle && middle <= leave)
+                    .count();
+                if towers > 0 {
+                    covered += (piece[1] - piece[0]) * segment_length;
+                }
+                let point_at = |along: f32| start.plus(&end.minus(start).scale(along));
+                // Pieces next to each other with the same count are joined into one stretch
+                match stretches.last_mut() {
+                    Some(last) if piece[0] > 0. && last.towers == towers => {
+                        last.end = point_at(piece[1])
+                    }
+                    _ => stretches.push(Stretch {
+                        start: point_at(piece[0]),
+                        end: point_at(piece[1]),
+                        towers,
+                    }),
+                }
+            }
+        }
+        Coverage {
+            stretches,
+            covered: if length > 0. { covered / length } else { 0. },
+        }
+    }
+}
+
+pub fn draw(coverage: &Coverage, ghost: &Point, c: Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    // Color the path by how many towers reach it, and say how much of it is reached under the ghost
+    for stretch in coverage.stretches.iter() {
+        let color = COVERAGE_COLORS[stretch.towers.min(COVERAGE_COLORS.len() - 1)];
+        line(
+            color,
+            TILE_SIZE as f64 / 4.0,
+            [
+                stretch.start.x as f64 + TILE_SIZE as f64 / 2.0,
+                stretch.start.y as f64 + TILE_SIZE as f64 / 2.0,
+                stretch.end.x as f64 + TILE_SIZE as f64 / 2.0,
+                stretch.end.y as f64 + TILE_SIZE as f64 / 2.0,
+            ],
+            c.transform,
+            g,
+        );
+    }
+    let style = TextStyle {
+        size: 12,
+        align: Align::Center,
+        shadow: Some(theme::palette().text_shadow),
+        ..Default::default()
+    };
+    label::draw(
+        &format!("{:.0}% of path covered", coverage.covered * 100.),
+        &style,
+        ghost.x as f64 + TILE_SIZE as f64 / 2.0,
+        ghost.y as f64 + TILE_SIZE as f64 + 14.0,
+        glyphs,
+        c,
+        g,
+    );
+}
@@ -0,0 +1,133 @@
+use std::backtrace::Backtrace;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::autosave;
+use crate::notifications::{notify, Kind};
+use crate::run::RunSetup;
+use crate::saves::format_timestamp;
+use crate::Game;
+
+// Folder crash reports are written to
+const CRASHES_DIR: &str = "crashes";
+// File naming the latest crash report, until the player has been told about it on the next launch
+const UNSEEN_PATH: &str = "crashes/unseen";
+// Number of the game's most recent events a crash report includes
+const CRASH_LOG_LINES: usize = 50;
+
+// Path of the report written for the panic in progress, so the game's state can be added to it as it unwinds
+static REPORT: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn install() {
+    // Write a report of any panic, with its message, where it happened, and how it got there, before the usual
+    // message is printed
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        match write_report(info) {
+            Ok(path) => {
+                eprintln!("Saved a crash report to {}", path);
+                *REPORT.lock().unwrap_or_else(|error| error.into_inner()) = Some(path);
+            }
+            Err(error) => eprintln!("Couldn't write a crash report: {}", error),
+        }
+        default_hook(info);
+    }));
+}
+
+fn write_report(info: &PanicHookInfo) -> io::Result<String> {
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("(no message)")
+    };
+    let location = info
+        .location()
+        .map_or_else(|| String::from("unknown"), |location| location.to_string());
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    fs::create_dir_all(CRASHES_DIR)?;
+    let path = format!("{}/crash-{}.txt", CRASHES_DIR, seconds);
+    fs::write(
+        &path,
+        format!(
+            "Tower Defense crashed at {}\nPanic: {}\nLocation: {}\n\nBacktrace:\n{}\n",
+            format_timestamp(seconds),
+            message,
+            location,
+            Backtrace::force_capture()
+        ),
+    )?;
+    fs::write(UNSEEN_PATH, &path)?;
+    Ok(path)
+}
+
+pub fn guard(setup: &RunSetup, game: &mut Game, step: impl FnOnce(&mut Game)) {
+    // Run a step of the game, adding what was going on to the crash report if it panics before letting the panic
+    // carry on
+    if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| step(game))) {
+        if let Err(error) = add_state(setup, game) {
+            eprintln!(
+                "Couldn't add the game's state to the crash report: {}",
+                error
+            );
+        }
+        panic::resume_unwind(panic);
+    }
+}
+
+fn add_state(setup: &RunSetup, game: &Game) -> io::Result<()> {
+    // Append the game's latest events and the run as it would be autosaved, which can be loaded to reproduce the
+    // crash
+    let Some(path) = REPORT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .take()
+    else {
+        return Ok(());
+    };
+    let events: Vec<String> = game
+        .state
+        .events
+        .recent()
+        .take(CRASH_LOG_LINES)
+        .map(|event| event.to_string())
+        .collect();
+    let mut report = OpenOptions::new().append(true).open(path)?;
+    write!(
+        report,
+        "\nRecent events, newest first:\n{}\n\nGame state:\n{}",
+        events.join("\n"),
+        autosave::encode(setup, game)
+    )
+}
+
+pub fn unseen_report() -> Option<String> {
+    // Find the report of a crash the player hasn't been told about yet, only telling them once
+    let path = fs::read_to_string(UNSEEN_PATH).ok()?;
+    if let Err(error) = fs::remove_file(UNSEEN_PATH) {
+        eprintln!("Couldn't remove {}: {}", UNSEEN_PATH, error);
+    }
+    fs::metadata(&path).is_ok().then_some(path)
+}
+
+pub fn open(path: &str) {
+    // Open the report in whatever the system opens text files with
+    let result = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", "start", "", path]).spawn()
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg(path).spawn()
+    } else {
+        Command::new("xdg-open").arg(path).spawn()
+    };
+    if let Err(error) = result {
+        eprintln!("Couldn't open {}: {}", path, error);
+        notify(format!("The crash report is in {}", path), Kind::Warning);
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::mutators::Mutator;
+use crate::Game;
+
+// Number of modifiers each daily challenge applies
+const DAILY_MODIFIER_COUNT: usize = 2;
+
+#[derive(Clone, Copy)]
+pub enum DailyModifier {
+    // Multiplies the cost of every tower
+    TowerCost(f32),
+    // Multiplies the speed of every enemy
+    EnemySpeed(f32),
+    // Multiplies the hit points of every enemy
+    EnemyHitPoints(f32),
+    // Multiplies the player's starting resources
+    StartingResources(f32),
+    // Sets the player's starting lives
+    StartingLives(i32),
+}
+
+impl Mutator for DailyModifier {
+    fn name(&self) -> String {
+        match self {
+            DailyModifier::TowerCost(factor) => format!("Towers cost x{}", factor),
+            DailyModifier::EnemySpeed(factor) => {
+                format!("Enemies {:.0}% faster", (factor - 1.) * 100.)
+            }
+            DailyModifier::EnemyHitPoints(factor) => {
+                format!("Enemies have {:.0}% more hit points", (factor - 1.) * 100.)
+            }
+            DailyModifier::StartingResources(factor) => {
+                format!("Starting resources x{}", factor)
+            }
+            DailyModifier::StartingLives(lives) => format!("Only {} lives", lives),
+        }
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        // Everyone plays the same daily modifiers, so they don't need to be rewarded
+        1.
+    }
+
+    fn apply(&self, game: &mut Game) {
+        match *self {
+            DailyModifier::TowerCost(factor) => {
+                for tower_type in game.tower_types.iter_mut() {
+                    tower_type.cost = (tower_type.cost as f32 * factor).round() as i32;
+                }
+            }
+            DailyModifier::EnemySpeed(factor) => {
+                for enemy_type in game.enemy_types.iter_mut() {
+                    enemy_type.speed *= factor;
+                }
+            }
+            DailyModifier::EnemyHitPoints(factor) => {
+                for enemy_type in game.enemy_types.iter_mut() {
+                    enemy_type.max_hit_points =
+                        (enemy_type.max_hit_points as f32 * factor).round() as i32;
+                }
+            }
+            DailyModifier::StartingResources(factor) => {
+                game.state.resources = (game.state.resources as f32 * factor).round() as i32;
+            }
+            DailyModifier::StartingLives(lives) => game.state.lives = lives,
+        }
+    }
+}
+
+pub struct DailyChallenge {
+    // Stores the number of days since the Unix epoch the challenge is for
+    pub day: u64,
+    // Stores the seed every player's run uses on this day
+    pub seed: u64,
+    // Stores the modifiers every player's run uses on this day
+    pub modifiers: Vec<DailyModifier>,
+}
+
+impl DailyChallenge {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        DailyChallenge::for_day(seconds / 86400)
+    }
+
+    pub fn for_day(day: u64) -> Self {
+        // Everything about the challenge is derived from the day, with a generator whose numbers are the same in every
+        // build, so all players get the same run
+        let seed = day.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ 0xDA11_7C4A_11E5_6E5D;
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let modifiers = [
+            DailyModifier::TowerCost(2.),
+            DailyModifier::EnemySpeed(1.2),
+            DailyModifier::EnemyHitPoints(1.5),
+            DailyModifier::StartingResources(2.),
+            DailyModifier::StartingLives(5),
+        ]
+        .choose_multiple(&mut rng, DAILY_MODIFIER_COUNT)
+        .copied()
+        .collect();
+        DailyChallenge {
+            day,
+            seed,
+            modifiers,
+        }
+    }
+
+    pub fn leaderboard_category(&self) -> String {
+        format!("daily-{}", self.day)
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{Enemy, Point, Tower, TILE_SIZE};
+
+// Distance from a defeated enemy within which its overkill damage carries over to another one, in pixels
+const OVERKILL_RANGE: f32 = TILE_SIZE * 2.;
+// Most shots a kill preview counts before giving up on the tower ever defeating the enemy
+const MAX_PREVIEW_SHOTS: u32 = 999;
+
+// How long one tower would take to defeat one enemy on its own, if nothing else about the fight changed
+pub struct KillPreview {
+    // Stores the number of shots it would take
+    pub shots: u32,
+    // Stores the time until the last of those shots lands, in seconds
+    pub seconds: f32,
+}
+
+pub fn shot_damage(tower: &Tower) -> i32 {
+    // Work out how much damage each of the tower's shots does before armor, with any boosts it has
+    tower.stats.damage.round() as i32
+}
+
+pub fn preview_kill(tower: &Tower, enemy: &Enemy) -> Option<KillPreview> {
+    // Fire the tower at a copy of the enemy until it's defeated, taking armor and shredding into account, with
+    // nothing if the enemy's shield blocks the tower or it would take more shots than are worth counting
+    let mut enemy = enemy.clone();
+    let damage = shot_damage(tower);
+    for shots in 1..=MAX_PREVIEW_SHOTS {
+        if !enemy.apply_hit(damage, &tower.position) {
+            return None;
+        }
+        enemy.shred_armor(tower.tower_type.armor_shred);
+        if !enemy.is_alive() {
+            let seconds = tower.cooldown.max(0.) + (shots - 1) as f32 / tower.stats.rate_of_fire;
+            return Some(KillPreview { shots, seconds });
+        }
+    }
+    None
+}
+
+pub fn resolve_hit(
+    enemies: &mut [Enemy],
+    target: usize,
+    damage: i32,
+    tower: &Tower,
+    overkill: bool,
+) -> i32 {
+    // Hit the target, shredding its armor if the tower does that, then, when overkill carries over, pass whatever
+    // damage it didn't need on to the nearest enemy still standing, and so on down the line until it's used up,
+    // returning how many hit points the tower took off enemies in all
+    let mut dealt = 0;
+    let Some(mut excess) = hit(
+        &mut enemies[target],
+        damage,
+        &tower.position,
+        tower.id,
+        &mut dealt,
+    ) else {
+        return dealt;
+    };
+    enemies[target].shred_armor(tower.tower_type.armor_shred);
+    let mut source = enemies[target].position;
+    while overkill && excess > 0 {
+        let Some(next) = nearest_standing(enemies, &source) else {
+            break;
+        };
+        let Some(left) = hit(&mut enemies[next], excess, &source, tower.id, &mut dealt) else {
+            break;
+        };
+        excess = left;
+        source = enemies[next].position;
+    }
+    dealt
+}
+
+fn hit(
+    enemy: &mut Enemy,
+    damage: i32,
+    source: &Point,
+    tower: usize,
+    dealt: &mut i32,
+) -> Option<i32> {
+    // Land a hit unless a shield blocks it, adding up the hit points it took, and returning the damage beyond what
+    // it took to defeat the enemy
+    let hit_points = enemy.hit_points;
+    if !enemy.apply_hit(damage, source) {
+        return None;
+    }
+    enemy.last_hit_by = Some(tower);
+    *dealt += hit_points.max(0) - enemy.hit_points.max(0);
+    Some(hit_points - enemy.hit_points - hit_points.max(0))
+}
+
+fn nearest_standing(enemies: &[Enemy], position: &Point) -> Option<usize> {
+    enemies
+        .iter()
+        .enumerate()
+        .filter(|(_, enemy)| enemy.is_alive() && enemy.is_targetable())
+        .map(|(i, enemy)| (i, enemy.position.distance_to(position)))
+        .filter(|&(_, distance)| distance < OVERKILL_RANGE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modifiers::Stats;
+    use crate::{EnemyType, Path, TowerType};
+
+    fn enemies_at(hit_points: i32, distances: &[f32]) -> Vec<Enemy> {
+        // Line enemies up along a straight path at the given distances from its start
+        let path = Path::new(vec![Point::new(0., 0.), Point::new(1000., 0.)]);
+        let enemy_type = EnemyType {
+            max_hit_points: hit_points,
+            ..Default::default()
+        };
+        distances
+            .iter()
+            .map(|&distance| {
+                let mut enemy = Enemy::new(enemy_type.clone(), &path);
+                enemy.distance = distance;
+                enemy.position = path.point_at(distance);
+                enemy
+            })
+            .collect()
+    }
+
+    fn tower(armor_shred: i32) -> Tower {
+        let tower_type = TowerType {
+            armor_shred,
+            damage: 5,
+            rate_of_fire: 2.,
+            ..Default::default()
+        };
+        Tower::new(1, Point::new(0., 0.), tower_type)
+    }
+
+    #[test]
+    fn overkill_carries_to_nearest_enemy() {
+        let mut enemies = enemies_at(10, &[100., 140., 110.]);
+        resolve_hit(&mut enemies, 0, 25, &tower(0), true);
+        assert!(!enemies[0].is_alive());
+        assert!(!enemies[2].is_alive());
+        assert_eq!(enemies[1].hit_points, 5);
+        assert_eq!(enemies[1].last_hit_by, Some(1));
+    }
+
+    #[test]
+    fn hits_count_only_the_hit_points_taken() {
+        let mut enemies = enemies_at(10, &[100., 110.]);
+        assert_eq!(resolve_hit(&mut enemies, 0, 25, &tower(0), true), 20);
+        assert_eq!(resolve_hit(&mut enemies, 0, 25, &tower(0), false), 0);
+    }
+
+    #[test]
+    fn overkill_is_lost_without_the_option() {
+        let mut enemies = enemies_at(10, &[100., 110.]);
+        resolve_hit(&mut enemies, 0, 25, &tower(0), false);
+        assert!(!enemies[0].is_alive());
+        assert_eq!(enemies[1].hit_points, 10);
+    }
+
+    #[test]
+    fn overkill_stops_at_enemies_out_of_range() {
+        let mut enemies = enemies_at(10, &[100., 100. + OVERKILL_RANGE * 2.]);
+        resolve_hit(&mut enemies, 0, 25, &tower(0), true);
+        assert_eq!(enemies[1].hit_points, 10);
+    }
+
+    #[test]
+    fn overkill_on_a_defeated_enemy_carries_in_full() {
+        // A second tower firing on an enemy that's already down this tick doesn't waste its shot
+        let mut enemies = enemies_at(10, &[100., 110.]);
+        resolve_hit(&mut enemies, 0, 10, &tower(0), true);
+        resolve_hit(&mut enemies, 0, 4, &tower(0), true);
+        assert_eq!(enemies[1].hit_points, 6);
+    }
+
+    #[test]
+    fn shredding_strips_armor_for_later_hits() {
+        let mut enemies = enemies_at(100, &[100.]);
+        enemies[0].enemy_type.armor = 4;
+        let shredder = tower(2);
+        resolve_hit(&mut enemies, 0, 5, &shredder, false);
+        assert_eq!(enemies[0].hit_points, 99);
+        resolve_hit(&mut enemies, 0, 5, &shredder, false);
+        assert_eq!(enemies[0].hit_points, 96);
+        resolve_hit(&mut enemies, 0, 5, &shredder, false);
+        assert_eq!(enemies[0].hit_points, 91);
+        assert_eq!(enemies[0].armor(), 0);
+    }
+
+    #[test]
+    fn preview_counts_shots_through_armor() {
+        let mut enemies = enemies_at(20, &[100.]);
+        enemies[0].enemy_type.armor = 3;
+        let preview = preview_kill(&tower(0), &enemies[0]).unwrap();
+        assert_eq!(preview.shots, 10);
+        assert_eq!(preview.seconds, 4.5);
+        assert_eq!(enemies[0].hit_points, 20);
+    }
+
+    #[test]
+    fn preview_includes_shredding_and_boosts() {
+        let mut enemies = enemies_at(20, &[100.]);
+        enemies[0].enemy_type.armor = 4;
+        assert_eq!(preview_kill(&tower(2), &enemies[0]).unwrap().shots, 6);
+        let mut boosted = tower(0);
+        boosted.stats = Stats::resolve(&boosted.tower_type, &boosted.modifiers(2.));
+        assert_eq!(preview_kill(&boosted, &enemies[0]).unwrap().shots, 4);
+    }
+}
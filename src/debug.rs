@@ -0,0 +1,75 @@
+use crate::replay;
+use crate::Game;
+
+// Where the debug panel sits, lined up against the top right corner so it stays clear of the HUD
+const PANEL_OFFSET: [f32; 2] = [-5.0, 5.0];
+
+// What the player clicked on in the debug panel
+pub enum DebugChoice {
+    ToggleParallel,
+    ReloadAssets,
+}
+
+// The numbers the debug panel shows that the game itself doesn't keep
+pub struct DebugStats {
+    // Stores how many enemies were on screen and drawn last frame
+    pub enemies_drawn: usize,
+    // Stores how many times the HUD's text has been laid out again
+    pub hud_rebuilds: usize,
+    // Stores how many textures and fonts are loaded, and how many were shared
+    pub assets: String,
+}
+
+pub fn show(ctx: &egui::Context, game: &Game, stats: &DebugStats) -> Option<DebugChoice> {
+    // Show entity pool statistics and how much work each frame does, with switches for the parallel update and
+    // reloading assets that changed on disk, returning the one clicked
+    let mut choice = None;
+    egui::Window::new("Debug")
+        .anchor(egui::Align2::RIGHT_TOP, PANEL_OFFSET)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Enemies: {} live, {} pooled, {} created, {} reused",
+                game.state.enemies.len(),
+                game.state.enemy_pool.available(),
+                game.state.enemy_pool.created,
+                game.state.enemy_pool.reused
+            ));
+            ui.label(format!(
+                "Particles: {} live, {} pooled, {} created, {} reused",
+                game.state.particles.len(),
+                game.state.particle_pool.available(),
+                game.state.particle_pool.created,
+                game.state.particle_pool.reused
+            ));
+            ui.label(format!(
+                "Drawn: {} of {} enemies, HUD rebuilt {} times",
+                stats.enemies_drawn,
+                game.state.enemies.len(),
+                stats.hud_rebuilds
+            ));
+            ui.label(format!(
+                "Tower stats resolved {} times for {} towers",
+                game.state.stat_resolves,
+                game.state.towers.len()
+            ));
+            ui.label(format!("Assets: {}", stats.assets));
+            ui.label(format!(
+                "Tick {}, pacing x{:.2}, state checksum {:016x}",
+                game.state.tick,
+                game.state.director.pace,
+                replay::checksum(game)
+            ));
+            ui.horizontal(|ui| {
+                let mut parallel = game.state.parallel;
+                if ui.checkbox(&mut parallel, "Update in parallel").changed() {
+                    choice = Some(DebugChoice::ToggleParallel);
+                }
+                if ui.button("Reload assets").clicked() {
+                    choice = Some(DebugChoice::ReloadAssets);
+                }
+            });
+        });
+    choice
+}
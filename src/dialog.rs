@@ -0,0 +1,120 @@
+use piston_window::*;
+
+use crate::label::{self, TextStyle};
+use crate::ui::{Frame, Ui};
+use crate::{Game, Point};
+
+// Size of a confirmation dialog, which sits in the middle of the screen
+const DIALOG_SIZE: [f64; 2] = [320.0, 120.0];
+
+// A destructive action waiting for the player to confirm it
+#[derive(Clone, Copy)]
+pub enum Confirmation {
+    // Selling the tower at the given position
+    Sell(Point),
+    // Quitting with a run in progress
+    Quit,
+    // Starting a new run in place of the interrupted one, whose autosave is discarded
+    DiscardRun,
+    // Saving over the run in the given save slot
+    OverwriteSlot(usize),
+    // Deleting the run in the given save slot
+    DeleteSlot(usize),
+    // Loading the run in the given save slot in place of the run in progress
+    LoadSlot(usize),
+    // Opening the report of the crash the last time the game was played
+    ViewCrashReport,
+}
+
+impl Confirmation {
+    fn message(&self) -> &'static str {
+        match self {
+            Confirmation::Sell(_) => {
+                "Sell this tower? Its ability goes with it, and only part of its cost comes back."
+            }
+            Confirmation::Quit => "Quit the run in progress? It's autosaved, so it can be resumed.",
+            Confirmation::DiscardRun => {
+                "Start a new run? The interrupted run's autosave will be overwritten."
+            }
+            Confirmation::OverwriteSlot(_) => {
+                "Save over this slot? The run saved in it will be lost."
+            }
+            Confirmation::DeleteSlot(_) => "Delete this save? It can't be brought back.",
+            Confirmation::LoadSlot(_) => "Load this save? The run in progress will be lost.",
+            Confirmation::ViewCrashReport => {
+                "Sorry, the game crashed last time. A report was saved in the crashes folder. View it?"
+            }
+        }
+    }
+}
+
+// A modal dialog asking the player to confirm a destructive action, which holds back the rest of the game's input
+// until it's answered, by clicking, with Y or Enter to go ahead, or with N or Escape to back out
+pub struct Dialog {
+    // Stores the action waiting on the player's answer
+    pub confirmation: Confirmation,
+}
+
+impl Dialog {
+    pub fn new(confirmation: Confirmation) -> Self {
+        Dialog { confirmation }
+    }
+
+    pub fn handle(&self, event: &Event) -> Option<bool> {
+        // Answer the dialog from the keyboard, returning whether the action goes ahead
+        match event.press_args() {
+            Some(Button::Keyboard(Key::Y | Key::Return | Key::NumPadEnter)) => Some(true),
+            Some(Button::Keyboard(Key::N | Key::Escape)) => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn draw(&self, ui: &mut Ui, frame: &mut Frame) -> Option<bool> {
+        // Dim everything behind the dialog, then draw it with its buttons, returning whether the action goes ahead
+        // if one of them was clicked
+        ui.unlock();
+        let c = frame.c;
+        let [width, height] = c.get_view_size();
+        rectangle(
+            [0.0, 0.0, 0.0, 0.4],
+            [0.0, 0.0, width, height],
+            c.transform,
+            frame.g,
+        );
+        let [dialog_width, dialog_height] = DIALOG_SIZE;
+        let (x, y) = ((width - dialog_width) / 2.0, (height - dialog_height) / 2.0);
+        ui.panel([x, y, dialog_width, dialog_height], frame);
+        let style = TextStyle {
+            size: 14,
+            wrap_width: Some(dialog_width - 20.0),
+            ..Default::default()
+        };
+        label::draw(
+            self.confirmation.message(),
+            &style,
+            x + 10.0,
+            y + 25.0,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+        let buttons_y = y + dialog_height - 35.0;
+        let yes = [x + dialog_width - 200.0, buttons_y, 90.0, 25.0];
+        if ui.button("dialog yes", "Yes (Y)", yes, true, false, frame) {
+            return Some(true);
+        }
+        let no = [x + dialog_width - 100.0, buttons_y, 90.0, 25.0];
+        if ui.button("dialog no", "No (N)", no, true, false, frame) {
+            return Some(false);
+        }
+        None
+    }
+}
+
+impl Game {
+    pub fn sale_needs_confirming(&self, position: &Point) -> bool {
+        // Selling a tower with an ability gives up more than a plain one, so it's checked with the player first
+        self.tower_at(position)
+            .is_some_and(|i| self.state.towers[i].tower_type.ability.is_some())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::math;
+use crate::GameState;
+
+// Number of enemies on the map at once that the director aims for, with fewer sped up towards and more slowed down from
+const DIRECTOR_CROWD: f32 = 8.;
+// Lives lost lately that count for as much as a full crowd, so a leaking defense is given room to recover
+const DIRECTOR_LEAK_TOLERANCE: f32 = 3.;
+// Seconds over which the director forgets most of a leak
+const DIRECTOR_LEAK_MEMORY: f32 = 10.;
+// Slowest and fastest the director runs a wave's spawns, as a factor of their usual pace
+const DIRECTOR_PACE_RANGE: (f32, f32) = (0.7, 1.3);
+// Seconds the pace takes to move most of the way to where the director wants it, so it changes gradually
+const DIRECTOR_RESPONSE: f32 = 2.;
+
+// Watches how busy a wave is and speeds its spawns up or slows them down a little, filling in lulls where the map's
+// empty and spreading enemies out when they're piling up or getting through
+#[derive(Clone)]
+pub struct Director {
+    // Stores the factor the wave's spawns are currently sped up or slowed down by
+    pub pace: f32,
+    // Stores the lives lost lately, fading away over DIRECTOR_LEAK_MEMORY seconds
+    pub recent_leaks: f32,
+    // Stores the player's lives when the director last looked, or nothing before it first has
+    pub lives_seen: Option<i32>,
+}
+
+impl Default for Director {
+    fn default() -> Self {
+        Director {
+            pace: 1.,
+            recent_leaks: 0.,
+            lives_seen: None,
+        }
+    }
+}
+
+fn target_pace(enemies: usize, recent_leaks: f32) -> f32 {
+    // Hurry along an empty map, and otherwise aim for a crowd of the usual size, counting recent leaks towards it
+    if enemies == 0 {
+        return DIRECTOR_PACE_RANGE.1;
+    }
+    let intensity = enemies as f32 / DIRECTOR_CROWD + recent_leaks / DIRECTOR_LEAK_TOLERANCE;
+    (1. / intensity).clamp(DIRECTOR_PACE_RANGE.0, DIRECTOR_PACE_RANGE.1)
+}
+
+impl GameState {
+    pub fn direct_pacing(&mut self, dt: f32) -> f32 {
+        // Take in how the wave is going and move the pace towards what it calls for, returning the factor this
+        // tick's spawns count down by
+        let director = &mut self.director;
+        let leaks = director
+            .lives_seen
+            .map_or(0, |lives_seen| (lives_seen - self.lives).max(0));
+        director.lives_seen = Some(self.lives);
+        director.recent_leaks =
+            director.recent_leaks * math::exp(-dt / DIRECTOR_LEAK_MEMORY) + leaks as f32;
+        let enemies = self.enemies.iter().filter(|enemy| enemy.is_alive()).count();
+        let target = target_pace(enemies, director.recent_leaks);
+        director.pace += (target - director.pace) * (1. - math::exp(-dt / DIRECTOR_RESPONSE));
+        director.pace
+    }
+}
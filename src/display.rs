@@ -0,0 +1,219 @@
+use glutin::dpi::{LogicalSize, PhysicalPosition};
+use glutin::window::Fullscreen;
+use piston_window::*;
+
+use crate::label;
+use crate::settings::Settings;
+use crate::theme;
+
+// Size of the area everything is laid out in, in pixels, which is scaled to fit the window
+pub const LAYOUT_SIZE: [f64; 2] = [640.0, 480.0];
+
+// How the window is shown
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum DisplayMode {
+    // An ordinary window with a frame, which can be resized
+    #[default]
+    Windowed,
+    // A window without a frame covering the whole monitor, which is quick to switch away from
+    Borderless,
+    // The monitor switched to its largest video mode for the game alone
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub fn name(&self) -> &'static str {
+        // The name the display mode is saved under in the settings file
+        match self {
+            DisplayMode::Windowed => "windowed",
+            DisplayMode::Borderless => "borderless",
+            DisplayMode::Fullscreen => "fullscreen",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DisplayMode> {
+        [
+            DisplayMode::Windowed,
+            DisplayMode::Borderless,
+            DisplayMode::Fullscreen,
+        ]
+        .into_iter()
+        .find(|mode| mode.name() == name)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            DisplayMode::Windowed => "Window",
+            DisplayMode::Borderless => "Borderless",
+            DisplayMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    pub fn next(&self) -> DisplayMode {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Windowed,
+        }
+    }
+}
+
+pub fn monitor_count(window: &PistonWindow) -> usize {
+    window.window.ctx.window().available_monitors().count()
+}
+
+pub fn window_size(settings: &Settings) -> [f64; 2] {
+    // The size of the window, before any scaling the display asks for, which is the layout at the player's UI scale
+    [
+        LAYOUT_SIZE[0] * settings.ui_scale,
+        LAYOUT_SIZE[1] * settings.ui_scale,
+    ]
+}
+
+pub fn apply(window: &PistonWindow, settings: &Settings) {
+    // Show the window on the chosen monitor in the chosen mode, using the monitor the window's on if the chosen one
+    // has been unplugged, and going borderless if the monitor has no video modes to go fullscreen in
+    let window = window.window.ctx.window();
+    let monitor = window
+        .available_monitors()
+        .nth(settings.monitor)
+        .or_else(|| window.current_monitor());
+    match settings.display {
+        DisplayMode::Windowed => {
+            window.set_fullscreen(None);
+            let [width, height] = window_size(settings);
+            window.set_inner_size(LogicalSize::new(width, height));
+            if let Some(monitor) = monitor {
+                let (position, size, outer) =
+                    (monitor.position(), monitor.size(), window.outer_size());
+                window.set_outer_position(PhysicalPosition::new(
+                    position.x + (size.width as i32 - outer.width as i32).max(0) / 2,
+                    position.y + (size.height as i32 - outer.height as i32).max(0) / 2,
+                ));
+            }
+        }
+        DisplayMode::Borderless => window.set_fullscreen(Some(Fullscreen::Borderless(monitor))),
+        DisplayMode::Fullscreen => {
+            let video_mode = monitor.as_ref().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|video_mode| {
+                    let size = video_mode.size();
+                    (size.width * size.height, video_mode.refresh_rate())
+                })
+            });
+            window.set_fullscreen(Some(match video_mode {
+                Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                None => Fullscreen::Borderless(monitor),
+            }));
+        }
+    }
+}
+
+// Where the layout sits in the window: as large as it fits without changing shape, and centered, with bars of the
+// background color filling the rest
+#[derive(Clone, Copy)]
+pub struct Layout {
+    // Stores how many window pixels each pixel of the layout covers
+    scale: f64,
+    // Stores where the layout's top left corner is in the window, in pixels
+    offset: [f64; 2],
+    // Stores the window's size, in pixels
+    window: [f64; 2],
+    // Stores how many of the display's physical pixels each window pixel covers, which is more than one on a high-DPI
+    // display
+    density: f64,
+}
+
+impl Layout {
+    pub fn fit(window: &PistonWindow) -> Self {
+        // Lay the game out in the window, which is measured in the display's logical pixels, with text rasterized
+        // at the size it ends up in the physical pixels of a high-DPI display
+        let (size, draw_size) = (window.size(), window.draw_size());
+        let window = [size.width.max(1.0), size.height.max(1.0)];
+        let scale = (window[0] / LAYOUT_SIZE[0]).min(window[1] / LAYOUT_SIZE[1]);
+        let density = draw_size.width.max(1.0) / window[0];
+        label::set_scale(scale * density);
+        Layout {
+            scale,
+            offset: [
+                (window[0] - LAYOUT_SIZE[0] * scale) / 2.0,
+                (window[1] - LAYOUT_SIZE[1] * scale) / 2.0,
+            ],
+            window,
+            density,
+        }
+    }
+
+    pub fn pixels_per_point(&self) -> f64 {
+        // How many of the display's physical pixels each pixel of the layout covers
+        self.scale * self.density
+    }
+
+    pub fn scissor(&self, [left, top, right, bottom]: [f32; 4]) -> Option<[u32; 4]> {
+        // Turn the corners of a rectangle in the layout into the scissor rectangle covering it in the framebuffer,
+        // which counts physical pixels up from the bottom left corner, or nothing if none of it is on screen
+        let [width, height] = [self.window[0] * self.density, self.window[1] * self.density];
+        let ratio = self.pixels_per_point();
+        let x = |x: f32| (self.offset[0] * self.density + x as f64 * ratio).clamp(0.0, width);
+        let y = |y: f32| (self.offset[1] * self.density + y as f64 * ratio).clamp(0.0, height);
+        let (left, right) = (x(left).floor(), x(right).ceil());
+        let (top, bottom) = (y(top).floor(), y(bottom).ceil());
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some([
+            left as u32,
+            (height - bottom) as u32,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ])
+    }
+
+    pub fn in_layout(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        // Find the point in the layout under the given point in the window
+        [
+            (x - self.offset[0]) / self.scale,
+            (y - self.offset[1]) / self.scale,
+        ]
+    }
+
+    pub fn map_event(&self, event: Event) -> Event {
+        // Move the mouse's position into the layout, so everything that reads it can ignore where the layout is
+        match event.mouse_cursor_args() {
+            Some(position) => Event::Input(
+                Input::Move(Motion::MouseCursor(self.in_layout(position))),
+                None,
+            ),
+            None => event,
+        }
+    }
+
+    pub fn apply(&self, c: Context) -> Context {
+        // Draw in the layout's pixels, with the view covering the layout rather than the window
+        let transform = c
+            .transform
+            .trans(self.offset[0], self.offset[1])
+            .scale(self.scale, self.scale);
+        Context {
+            view: transform,
+            transform,
+            ..c
+        }
+    }
+
+    pub fn draw_bars(&self, c: Context, g: &mut G2d) {
+        // Cover whatever was drawn outside the layout, given the window's own context
+        let color = theme::palette().background;
+        let [x, y] = self.offset;
+        let [width, height] = self.window;
+        for rect in [
+            [0.0, 0.0, x, height],
+            [width - x, 0.0, x, height],
+            [0.0, 0.0, width, y],
+            [0.0, height - y, width, y],
+        ] {
+            if rect[2] > 0.0 && rect[3] > 0.0 {
+                rectangle(color, rect, c.transform, g);
+            }
+        }
+    }
+}
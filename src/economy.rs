@@ -0,0 +1,28 @@
+use crate::math;
+
+pub struct RewardCurve {
+    // Stores the fraction of the previous wave's reward multiplier lost each wave
+    pub decay_per_wave: f32,
+    // Stores the lowest the reward multiplier can fall to
+    pub minimum: f32,
+}
+
+impl RewardCurve {
+    pub fn multiplier(&self, wave: usize) -> f32 {
+        // Compound the decay over every wave after the first, but never drop below the minimum
+        math::powi(1. - self.decay_per_wave, wave.saturating_sub(1)).max(self.minimum)
+    }
+
+    pub fn reward(&self, base_reward: i32, wave: usize) -> i32 {
+        (base_reward as f32 * self.multiplier(wave)).round() as i32
+    }
+}
+
+impl Default for RewardCurve {
+    fn default() -> Self {
+        RewardCurve {
+            decay_per_wave: 0.03,
+            minimum: 0.5,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::level::TileMap;
+use crate::{Point, TILE_SIZE};
+
+// Fraction of its range a tower gains for each tier of high ground it stands on
+const RANGE_BONUS_PER_TIER: f32 = 0.15;
+// Fraction of its range within which a tower can hit an enemy down in a valley, from anywhere higher up
+const VALLEY_RANGE_FACTOR: f32 = 0.6;
+// Distance between the points checked along a line of sight, in pixels
+const SIGHT_STEP: f32 = TILE_SIZE / 4.;
+
+fn tile_of(point: &Point) -> (i32, i32) {
+    // Positions are the top left corner of whatever stands there, so the tile is the one under its center
+    (
+        ((point.x + TILE_SIZE / 2.) / TILE_SIZE).floor() as i32,
+        ((point.y + TILE_SIZE / 2.) / TILE_SIZE).floor() as i32,
+    )
+}
+
+impl TileMap {
+    pub fn elevation_at(&self, point: &Point) -> i8 {
+        let (x, y) = tile_of(point);
+        self.elevation(x, y)
+    }
+
+    pub fn range_from(&self, position: &Point, range: f32) -> f32 {
+        // High ground extends a tower's range, while a tower down in a valley keeps its usual one
+        let tiers = self.elevation_at(position).max(0);
+        range * (1. + RANGE_BONUS_PER_TIER * tiers as f32)
+    }
+
+    pub fn in_sight(&self, from: &Point, to: &Point) -> bool {
+        // Ground rising above both ends of the line blocks it, so a ridge hides whatever's behind it from anything
+        // lower down, while the tiles at either end never block their own view
+        let height = self.elevation_at(from).max(self.elevation_at(to));
+        let ends = [tile_of(from), tile_of(to)];
+        let steps = (from.distance_to(to) / SIGHT_STEP).ceil() as usize;
+        (1..steps).all(|i| {
+            let point = from.plus(&to.minus(from).scale(i as f32 / steps as f32));
+            let tile = tile_of(&point);
+            ends.contains(&tile) || self.elevation(tile.0, tile.1) <= height
+        })
+    }
+
+    pub fn can_hit(&self, position: &Point, range: f32, target: &Point) -> bool {
+        // A tower reaches further from high ground, but only gets a shot into a valley from close by, and only at
+        // what it can see
+        let mut reach = self.range_from(position, range);
+        let (height, target_height) = (self.elevation_at(position), self.elevation_at(target));
+        if target_height < 0 && height > target_height {
+            reach *= VALLEY_RANGE_FACTOR;
+        }
+        position.distance_to(target) < reach && self.in_sight(position, target)
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::io;
+
+use crate::content;
+use crate::events::{GameEvent, Subscriber};
+use crate::label::{self, TextStyle};
+use crate::naval::Terrain;
+use crate::notifications::{notify, Kind};
+use crate::storage;
+use crate::ui::{Frame, Ui};
+use crate::{EnemyType, Movement, TowerType};
+
+// File the towers and enemies the player has come across are saved to, as entries in the encyclopedia only unlock
+// once they have
+pub const ENCYCLOPEDIA_PATH: &str = "bestiary.txt";
+// Seconds the callout for a newly unlocked entry stays up if the player doesn't dismiss it
+const CALLOUT_DURATION: f32 = 10.;
+
+// An entry unlocked for the first time, called out with its stats until the player dismisses it or it times out
+struct Callout {
+    // Stores whether the entry is an enemy's rather than a tower's
+    enemy: bool,
+    // Stores the index of the entry in its list
+    index: usize,
+    // Stores how many more seconds the callout stays up
+    time_left: f32,
+}
+
+// A screen describing every tower and enemy in the game, each left a mystery until the player first comes across it
+pub struct Encyclopedia {
+    // Stores the names of the tower types the player has been able to build, in any run
+    towers_unlocked: BTreeSet<String>,
+    // Stores the names of the enemy types the player has come across, in any run
+    enemies_unlocked: BTreeSet<String>,
+    // Stores every tower type, in the order the build menu lists them
+    tower_types: Vec<TowerType>,
+    // Stores every enemy type, in the order they're defined
+    enemy_types: Vec<EnemyType>,
+    // Stores the entries unlocked but not yet called out, oldest first, with the one being called out at the front
+    callouts: VecDeque<Callout>,
+    // Stores whether the screen is up
+    pub open: bool,
+    // Stores whether the enemies are listed rather than the towers
+    showing_enemies: bool,
+    // Stores the index of the entry being read, in whichever list is showing
+    selected: usize,
+}
+
+impl Encyclopedia {
+    pub fn load(path: &str) -> Self {
+        // Each line holds whether it's a tower or an enemy, then its name, with a line holding only a name being an
+        // enemy met before towers were tracked, and a file that can't be read leaves everything to be met again
+        let mut encyclopedia = Encyclopedia {
+            towers_unlocked: BTreeSet::new(),
+            enemies_unlocked: BTreeSet::new(),
+            tower_types: content::tower_types(),
+            enemy_types: content::enemy_types(),
+            callouts: VecDeque::new(),
+            open: false,
+            showing_enemies: false,
+            selected: 0,
+        };
+        let contents = match storage::read_checked(path) {
+            Ok(contents) => contents.unwrap_or_default(),
+            Err(error) => {
+                eprintln!("Couldn't load the encyclopedia: {}", error);
+                notify(
+                    format!(
+                        "Couldn't load the encyclopedia, starting it over: {}",
+                        error
+                    ),
+                    Kind::Warning,
+                );
+                return encyclopedia;
+            }
+        };
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["tower", name] => {
+                    encyclopedia.towers_unlocked.insert(name.to_string());
+                }
+                ["enemy", name] | [name] if !name.is_empty() => {
+                    encyclopedia.enemies_unlocked.insert(name.to_string());
+                }
+                _ => {}
+            }
+        }
+        encyclopedia
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = String::new();
+        for name in self.towers_unlocked.iter() {
+            contents += &format!("tower\t{}\n", name);
+        }
+        for name in self.enemies_unlocked.iter() {
+            contents += &format!("enemy\t{}\n", name);
+        }
+        storage::write_checked(path, &contents)
+    }
+
+    fn unlock(&mut self, enemy: bool, name: &str) {
+        // Remember the entry for good the first time it's met, and call it out unless it's one the game doesn't
+        // describe
+        let unlocked = if enemy {
+            &mut self.enemies_unlocked
+        } else {
+            &mut self.towers_unlocked
+        };
+        if !unlocked.insert(name.to_string()) {
+            return;
+        }
+        let index = if enemy {
+            self.enemy_types
+                .iter()
+                .position(|enemy_type| enemy_type.name == name)
+        } else {
+            self.tower_types
+                .iter()
+                .position(|tower_type| tower_type.name == name)
+        };
+        if let Some(index) = index {
+            self.callouts.push_back(Callout {
+                enemy,
+                index,
+                time_left: CALLOUT_DURATION,
+            });
+        }
+        if let Err(error) = self.save(ENCYCLOPEDIA_PATH) {
+            eprintln!("Couldn't save the encyclopedia: {}", error);
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        // Count down the callout that's up, moving on to the next once it's been up long enough
+        if let Some(callout) = self.callouts.front_mut() {
+            callout.time_left -= dt;
+            if callout.time_left <= 0. {
+                self.callouts.pop_front();
+            }
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        // Opening the encyclopedia while an entry's being called out goes straight to the entry
+        if self.open {
+            self.open = false;
+        } else if let Some(callout) = self.callouts.pop_front() {
+            self.view(callout.enemy, callout.index);
+        } else {
+            self.open = true;
+        }
+    }
+
+    fn view(&mut self, enemy: bool, index: usize) {
+        self.open = true;
+        self.showing_enemies = enemy;
+        self.selected = index;
+    }
+
+    fn entry(&self, enemy: bool, index: usize) -> Option<(&str, Vec<String>, &'static str)> {
+        // The entry's name, stats, and story, if it's been unlocked
+        if enemy {
+            self.enemy_types
+                .get(index)
+                .filter(|enemy_type| self.enemies_unlocked.contains(&enemy_type.name))
+                .map(|enemy_type| {
+                    (
+                        enemy_type.name.as_str(),
+                        enemy_lines(enemy_type),
+                        enemy_type.lore,
+                    )
+                })
+        } else {
+            self.tower_types
+                .get(index)
+                .filter(|tower_type| self.towers_unlocked.contains(&tower_type.name))
+                .map(|tower_type| {
+                    (
+                        tower_type.name.as_str(),
+                        tower_lines(tower_type),
+                        tower_type.lore,
+                    )
+                })
+        }
+    }
+
+    pub fn draw(&mut self, callouts: bool, ui: &mut Ui, frame: &mut Frame) {
+        // Draw the encyclopedia over the game while it's open, or the latest entry unlocked while it isn't, if there's
+        // room for it
+        if self.open {
+            self.draw_entries(ui, frame);
+        } else if callouts {
+            self.draw_callout(ui, frame);
+        }
+    }
+
+    fn draw_entries(&mut self, ui: &mut Ui, frame: &mut Frame) {
+        // Cover the map with a list of entries down the left, and the one being read on the right
+        ui.panel([20.0, 20.0, 600.0, 440.0], frame);
+        let total = self.tower_types.len() + self.enemy_types.len();
+        let unlocked = (0..self.tower_types.len())
+            .filter(|&i| self.entry(false, i).is_some())
+            .count()
+            + (0..self.enemy_types.len())
+                .filter(|&i| self.entry(true, i).is_some())
+                .count();
+        label::draw(
+            &format!("Encyclopedia - {} of {} entries unlocked", unlocked, total),
+            &TextStyle {
+                size: 18,
+                ..Default::default()
+            },
+            30.0,
+            45.0,
+            frame.glyphs,
+            frame.c,
+            frame.g,
+        );
+        for (i, caption) in ["Towers", "Enemies"].into_iter().enumerate() {
+            let showing_enemies = i == 1;
+            if ui.button(
+                &format!("encyclopedia {}", caption),
+                caption,
+                [30.0 + i as f64 * 85.0, 60.0, 80.0, 22.0],
+                true,
+                self.showing_enemies == showing_enemies,
+                frame,
+            ) && self.showing_enemies != showing_enemies
+            {
+                self.showing_enemies = showing_enemies;
+                self.selected = 0;
+            }
+        }
+
+        // Entries the player hasn't unlocked are listed, so it's clear how many are left, but can't be read
+        let count = if self.showing_enemies {
+            self.enemy_types.len()
+        } else {
+            self.tower_types.len()
+        };
+        let mut selected = self.selected;
+        for i in 0..count {
+            let entry = self.entry(self.showing_enemies, i);
+            if ui.button(
+                &format!("encyclopedia entry {}", i),
+                entry.as_ref().map_or("???", |(name, ..)| name),
+                [30.0, 95.0 + i as f64 * 27.0, 165.0, 22.0],
+                entry.is_some(),
+                i == self.selected,
+                frame,
+            ) {
+                selected = i;
+            }
+        }
+        self.selected = selected;
+
+        // The entry's name, then what it does, then its story
+        let style = TextStyle {
+            size: 12,
+            ..Default::default()
+        };
+        let Some((name, lines, lore)) = self.entry(self.showing_enemies, self.selected) else {
+            label::draw(
+                "Not encountered yet. Come across one in a run to fill in this entry.",
+                &style,
+                210.0,
+                110.0,
+                frame.glyphs,
+                frame.c,
+                frame.g,
+            );
+            return;
+        };
+        label::draw(
+            name,
+            &TextStyle {
+                size: 18,
+                ..Default::default()
+            },
+            210.0,
+            110.0,
+            frame.glyphs,
+            frame.c,
+            frame.g,
+        );
+        let mut y = 135.0;
+        for line in lines.iter() {
+            label::draw(line, &style, 210.0, y, frame.glyphs, frame.c, frame.g);
+            y += 18.0;
+        }
+        label::draw(
+            lore,
+            &TextStyle {
+                wrap_width: Some(395.0),
+                ..style
+            },
+            210.0,
+            y + 12.0,
+            frame.glyphs,
+            frame.c,
+            frame.g,
+        );
+    }
+
+    fn draw_callout(&mut self, ui: &mut Ui, frame: &mut Frame) {
+        // Call out the newest unlock in the bottom left corner, above the build menu, with its stats and a shortcut
+        // to its entry
+        let Some(callout) = self.callouts.front() else {
+            return;
+        };
+        let (enemy, index) = (callout.enemy, callout.index);
+        let Some((name, lines, _)) = self.entry(enemy, index) else {
+            self.callouts.pop_front();
+            return;
+        };
+        let height = 70.0 + lines.len() as f64 * 16.0;
+        let top = 440.0 - height;
+        ui.panel([10.0, top, 260.0, height], frame);
+        let title = if enemy {
+            format!("New enemy: {}", name)
+        } else {
+            format!("New tower: {}", name)
+        };
+        label::draw(
+            &title,
+            &TextStyle {
+                size: 16,
+                ..Default::default()
+            },
+            20.0,
+            top + 22.0,
+            frame.glyphs,
+            frame.c,
+            frame.g,
+        );
+        let style = TextStyle {
+            size: 12,
+            ..Default::default()
+        };
+        for (i, line) in lines.iter().enumerate() {
+            let y = top + 42.0 + i as f64 * 16.0;
+            label::draw(line, &style, 20.0, y, frame.glyphs, frame.c, frame.g);
+        }
+        let y = top + height - 28.0;
+        let viewing = ui.button(
+            "view callout",
+            "View in encyclopedia",
+            [20.0, y, 160.0, 22.0],
+            true,
+            false,
+            frame,
+        );
+        let dismissing = ui.button(
+            "dismiss callout",
+            "Dismiss",
+            [185.0, y, 75.0, 22.0],
+            true,
+            false,
+            frame,
+        );
+        if viewing || dismissing {
+            self.callouts.pop_front();
+        }
+        if viewing {
+            self.view(enemy, index);
+        }
+    }
+}
+
+impl Subscriber for Encyclopedia {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::TowerAvailable { tower } => self.unlock(false, tower),
+            GameEvent::EnemySighted { enemy } => self.unlock(true, enemy),
+            _ => {}
+        }
+    }
+}
+
+fn tower_lines(tower_type: &TowerType) -> Vec<String> {
+    // The tower's stats as it's defined, before any upgrades, mutators, or level changes
+    let mut lines = vec![
+        format!("Cost: {}", tower_type.cost),
+        format!(
+            "Damage: {} per shot, {} shots a second",
+            tower_type.damage, tower_type.rate_of_fire
+        ),
+        format!("Range: {}", tower_type.range),
+    ];
+    if tower_type.detects_stealth {
+        lines.push(String::from("Reveals stealthed enemies in range"));
+    }
+    if tower_type.armor_shred > 0 {
+        lines.push(format!(
+            "Strips {} armor from its target with every hit",
+            tower_type.armor_shred
+        ));
+    }
+    if tower_type.ground_only {
+        lines.push(String::from("Can't hit enemies up on bridges"));
+    }
+    if tower_type.floating {
+        lines.push(String::from("Built on water instead of land"));
+    }
+    if let Some(ability) = &tower_type.ability {
+        lines.push(format!(
+            "Ability: {}, lasting {}s and recharging in {}s",
+            ability.name, ability.duration, ability.cooldown
+        ));
+    }
+    if tower_type.prestige_required > 0 {
+        lines.push(format!(
+            "Unlocked in New Game+ {}",
+            tower_type.prestige_required
+        ));
+    }
+    lines
+}
+
+fn enemy_lines(enemy_type: &EnemyType) -> Vec<String> {
+    // The enemy's stats as it's defined, before any waves or mutators make it tougher
+    let mut lines = vec![
+        format!("Hit points: {}", enemy_type.max_hit_points),
+        format!("Speed: {}", enemy_type.speed),
+        format!(
+            "Reward: {} resources, lives taken: {}",
+            enemy_type.reward, enemy_type.lives_cost
+        ),
+    ];
+    if enemy_type.armor > 0 {
+        lines.push(format!("Armor: {}", enemy_type.armor));
+    }
+    if enemy_type.regeneration > 0. {
+        lines.push(format!(
+            "Regenerates {} hit points a second after {}s out of combat",
+            enemy_type.regeneration, enemy_type.regeneration_delay
+        ));
+    }
+    if enemy_type.life_steal > 0 {
+        lines.push(format!(
+            "Heals {} hit points when an enemy dies nearby",
+            enemy_type.life_steal
+        ));
+    }
+    if enemy_type.stealth {
+        lines.push(String::from(
+            "Stealthed, so it can only be hit once revealed",
+        ));
+    }
+    match enemy_type.movement {
+        Movement::Walk => {}
+        Movement::Blink { interval, .. } => {
+            lines.push(format!("Blinks further along the path every {}s", interval))
+        }
+        Movement::Burrow { interval, duration } => lines.push(format!(
+            "Burrows out of reach for {}s every {}s",
+            duration, interval
+        )),
+    }
+    match enemy_type.terrain {
+        Terrain::Land => {}
+        Terrain::Water => lines.push(String::from("Sails the water lane")),
+        Terrain::Amphibious => lines.push(String::from("Swims the water lane and comes ashore")),
+    }
+    if enemy_type.shield_angle > 0. {
+        lines.push(String::from("Shielded against hits from the front"));
+    }
+    if enemy_type.flocking.is_some() {
+        lines.push(String::from("Moves in a swarm"));
+    }
+    if !enemy_type.drops.is_empty() {
+        lines.push(String::from("Sometimes drops loot"));
+    }
+    lines
+}
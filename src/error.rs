@@ -0,0 +1,37 @@
+use std::fmt;
+
+pub enum Error {
+    // The game window couldn't be opened
+    Window(String),
+    // Neither the font at the given path nor the built-in one could be loaded
+    Font(String, String),
+    // The level couldn't be started
+    Level(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Say what went wrong and what the player can do about it
+        match self {
+            Error::Window(error) => write!(
+                f,
+                "Couldn't open the game window ({}). Check that your graphics drivers support OpenGL 3.2.",
+                error
+            ),
+            Error::Font(path, error) => write!(
+                f,
+                "Couldn't load the font {} or the built-in one ({}). The game's files may be damaged.",
+                path, error
+            ),
+            Error::Level(error) => write!(f, "Couldn't start the level ({}).", error),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+// Number of events the log remembers before forgetting the oldest
+pub const EVENT_LOG_CAPACITY: usize = 200;
+
+#[derive(Clone)]
+pub enum GameEvent {
+    // An enemy was defeated, by the tower with the given name and number if a tower landed the final hit
+    EnemyKilled {
+        enemy: String,
+        tower: Option<(String, usize)>,
+        reward: i32,
+    },
+    // The player placed a tower with the given name and number
+    TowerPlaced {
+        tower: String,
+        number: usize,
+    },
+    // The given wave started spawning enemies
+    WaveStarted {
+        wave: usize,
+    },
+    // An enemy reached the player's base
+    BaseDamaged {
+        enemy: String,
+        lives_lost: i32,
+    },
+    // A convoy unit made it all the way across the map
+    ConvoyArrived,
+    // A convoy unit was destroyed by enemies
+    ConvoyLost,
+    // An enemy of the given type appeared on the map for the first time this run
+    EnemySighted {
+        enemy: String,
+    },
+    // The given tower type can be built this run
+    TowerAvailable {
+        tower: String,
+    },
+}
+
+pub trait Subscriber {
+    // Reacts to a gameplay event, called once per event in the order they happened
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+#[derive(Clone, Default)]
+pub struct EventReader {
+    // Stores the sequence number of the next event this reader hasn't seen
+    next: u64,
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameEvent::EnemyKilled {
+                enemy,
+                tower: Some((tower, number)),
+                reward,
+            } => write!(f, "{} #{} killed {} (+{})", tower, number, enemy, reward),
+            GameEvent::EnemyKilled {
+                enemy,
+                tower: None,
+                reward,
+            } => write!(f, "{} was killed (+{})", enemy, reward),
+            GameEvent::TowerPlaced { tower, number } => write!(f, "{} #{} placed", tower, number),
+            GameEvent::WaveStarted { wave } => write!(f, "Wave {} started", wave),
+            GameEvent::BaseDamaged { enemy, lives_lost } => {
+                let lives = if *lives_lost == 1 { "life" } else { "lives" };
+                write!(f, "{} leaked (-{} {})", enemy, lives_lost, lives)
+            }
+            GameEvent::ConvoyArrived => write!(f, "Convoy unit arrived safely"),
+            GameEvent::ConvoyLost => write!(f, "Convoy unit destroyed"),
+            GameEvent::EnemySighted { enemy } => write!(f, "{} sighted", enemy),
+            GameEvent::TowerAvailable { tower } => write!(f, "{} available", tower),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventLog {
+    // Stores the most recent events, oldest first
+    events: VecDeque<GameEvent>,
+    // Stores the sequence number of the oldest event still in the log
+    first: u64,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        EventLog {
+            events: VecDeque::with_capacity(EVENT_LOG_CAPACITY),
+            first: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        // Make room by forgetting the oldest event once the log is full
+        if self.events.len() == EVENT_LOG_CAPACITY {
+            self.events.pop_front();
+            self.first += 1;
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn dispatch(&self, reader: &mut EventReader, subscriber: &mut impl Subscriber) {
+        // Deliver every event the reader hasn't seen yet, skipping any that have already been forgotten
+        let start = reader.next.max(self.first) - self.first;
+        for event in self.events.range(start as usize..) {
+            subscriber.on_event(event);
+        }
+        reader.next = self.first + self.events.len() as u64;
+    }
+
+    pub fn recent(&self) -> impl Iterator<Item = &GameEvent> {
+        // Iterate from the newest event to the oldest
+        self.events.iter().rev()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+}
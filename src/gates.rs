@@ -0,0 +1,261 @@
+use crate::level::{parse_number, Layer};
+use crate::naval::Lane;
+use crate::notifications::{notify, Kind};
+use crate::teleporters::Teleporter;
+use crate::{Game, Path, Point, TILE_SIZE};
+use std::fmt;
+
+// Seconds a lever takes to reset after being pulled
+const LEVER_COOLDOWN: f32 = 10.;
+// Distance from a lever within which a click pulls it, in pixels
+const LEVER_REACH: f32 = TILE_SIZE * 0.75;
+// Distance between the points checked along a detour when finding where an enemy rejoins it, in pixels
+const REJOIN_STEP: f32 = 5.;
+
+// A gate across a stretch of the path, which sends enemies round a detour while it's closed, with positions in tiles
+// in a level file and in pixels once the game starts
+#[derive(Clone)]
+pub struct Gate {
+    // Stores where the lever that opens and closes the gate is
+    pub lever: Point,
+    // Stores the index of the waypoint the gated stretch starts at, where the detour leaves the path
+    pub from: usize,
+    // Stores the index of the waypoint the gated stretch ends at, where the detour rejoins the path
+    pub to: usize,
+    // Stores the points the detour passes through on its way between the two
+    pub detour: Vec<Point>,
+    // Stores whether the gate is closed
+    pub closed: bool,
+    // Stores the time until the lever can be pulled again, in seconds
+    pub cooldown: f32,
+}
+
+impl Gate {
+    pub fn parse(value: &str) -> Result<Gate, String> {
+        // Gates are written as the lever's tile and the waypoints the gated stretch runs between, then closed if the
+        // gate starts out that way, like "7 3 2 4 closed", with the detour following on its own lines
+        let (numbers, closed) = match value.strip_suffix("closed") {
+            Some(numbers) => (numbers, true),
+            None => (value, false),
+        };
+        let numbers = numbers
+            .split_whitespace()
+            .map(parse_number)
+            .collect::<Result<Vec<f32>, _>>()?;
+        let [x, y, from, to] = numbers[..] else {
+            return Err(format!("Expected four numbers: {}", value));
+        };
+        if from < 0. || to <= from || from.fract() != 0. || to.fract() != 0. {
+            return Err(format!(
+                "Gate needs a stretch between two waypoints: {}",
+                value
+            ));
+        }
+        Ok(Gate {
+            lever: Point::new(x, y),
+            from: from as usize,
+            to: to as usize,
+            detour: Vec::new(),
+            closed,
+            cooldown: 0.,
+        })
+    }
+
+    pub fn scale(&self, factor: f32) -> Gate {
+        Gate {
+            lever: self.lever.scale(factor),
+            detour: self
+                .detour
+                .iter()
+                .map(|point| point.scale(factor))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl Gate {
+    pub fn stretch(&self, route: &Route, closed: bool) -> Vec<Point> {
+        // The points enemies pass between the fork and where the two ways meet again, round the detour when the gate
+        // is closed and along the level's path when it's open
+        if closed {
+            std::iter::once(route.waypoints[self.from])
+                .chain(self.detour.iter().copied())
+                .chain(std::iter::once(route.waypoints[self.to]))
+                .collect()
+        } else {
+            route.waypoints[self.from..=self.to].to_vec()
+        }
+    }
+}
+
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.lever.x, self.lever.y, self.from, self.to
+        )?;
+        if self.closed {
+            write!(f, " closed")?;
+        }
+        Ok(())
+    }
+}
+
+// The path as the level lays it out, before any gates reroute it
+#[derive(Default)]
+pub struct Route {
+    // Stores the path's waypoints, in pixels
+    pub waypoints: Vec<Point>,
+    // Stores the layer of each segment between two waypoints
+    layers: Vec<Layer>,
+    // Stores the entry and exit of each teleporter, in pixels
+    teleporters: Vec<(Point, Point)>,
+}
+
+impl Route {
+    pub fn new(
+        waypoints: Vec<Point>,
+        layers: Vec<Layer>,
+        teleporters: Vec<(Point, Point)>,
+    ) -> Self {
+        Route {
+            waypoints,
+            layers,
+            teleporters,
+        }
+    }
+
+    pub fn build(&self, gates: &[Gate]) -> (Path, Vec<usize>) {
+        // Lay the path out with every closed gate's stretch swapped for its detour, returning the index each of the
+        // level's waypoints that's still on the path ends up at
+        let mut waypoints = Vec::new();
+        let mut layers = Vec::new();
+        let mut indices = vec![0; self.waypoints.len()];
+        let mut i = 0;
+        while i < self.waypoints.len() {
+            indices[i] = waypoints.len();
+            waypoints.push(self.waypoints[i]);
+            match gates.iter().find(|gate| gate.closed && gate.from == i) {
+                Some(gate) => {
+                    for point in gate.detour.iter() {
+                        layers.push(Layer::Ground);
+                        waypoints.push(*point);
+                    }
+                    layers.push(Layer::Ground);
+                    i = gate.to;
+                }
+                None => {
+                    layers.push(self.layers.get(i).copied().unwrap_or_default());
+                    i += 1;
+                }
+            }
+        }
+        layers.pop();
+
+        // Teleporters on a stretch that's been closed off stop working until it opens again
+        let path = Path::new(waypoints).with_layers(layers);
+        let teleporters = self
+            .teleporters
+            .iter()
+            .filter_map(|(entry, exit)| Teleporter::between(&path, entry, exit).ok())
+            .collect();
+        (path.with_teleporters(teleporters), indices)
+    }
+}
+
+impl Path {
+    fn waypoint_distance(&self, index: usize) -> f32 {
+        self.waypoints
+            .windows(2)
+            .take(index)
+            .map(|segment| segment[0].distance_to(&segment[1]))
+            .sum()
+    }
+
+    fn closest_between(&self, point: &Point, start: f32, end: f32) -> f32 {
+        // Find the distance between the two given ones where the path passes closest to the given point
+        (0..=((end - start) / REJOIN_STEP) as usize)
+            .map(|i| start + i as f32 * REJOIN_STEP)
+            .min_by(|a, b| {
+                let a = self.point_at(*a).distance_to(point);
+                let b = self.point_at(*b).distance_to(point);
+                a.total_cmp(&b)
+            })
+            .unwrap_or(start)
+    }
+}
+
+impl Game {
+    pub fn lever_at(&self, position: &Point) -> Option<usize> {
+        self.gates.iter().position(|gate| {
+            gate.lever
+                .plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.))
+                .distance_to(position)
+                < LEVER_REACH
+        })
+    }
+
+    pub fn pull_lever(&mut self, gate: usize) {
+        // Open or close the gate, which resets after a while except in a sandbox, and reroute the enemies already
+        // on the path
+        let sandbox = self.rules.sandbox;
+        let Some(pulled) = self.gates.get_mut(gate) else {
+            return;
+        };
+        if pulled.cooldown > 0. {
+            notify("The lever is still resetting", Kind::Warning);
+            return;
+        }
+        let (old_path, old_indices) = self.route.build(&self.gates);
+        let pulled = &mut self.gates[gate];
+        pulled.closed = !pulled.closed;
+        pulled.cooldown = if sandbox { 0. } else { LEVER_COOLDOWN };
+        let (from, to) = (pulled.from, pulled.to);
+        let message = if pulled.closed {
+            "Gate closed, enemies are taking the detour"
+        } else {
+            "Gate opened"
+        };
+        notify(message, Kind::Info);
+        let (path, indices) = self.route.build(&self.gates);
+        self.path = path;
+        self.lane = self
+            .lane
+            .take()
+            .map(|lane| Lane::new(lane.path, &self.path));
+        self.weigh_landing();
+
+        // Enemies short of the fork carry on as they were, ones past where the two ways meet again keep their place
+        // from there, and ones in between move across to the nearest point on the new way round
+        let fork = old_path.waypoint_distance(old_indices[from]);
+        let (old_join, new_join) = (
+            old_path.waypoint_distance(old_indices[to]),
+            self.path.waypoint_distance(indices[to]),
+        );
+        for enemy in self.state.enemies.iter_mut().filter(|enemy| !enemy.on_lane) {
+            if enemy.distance <= fork {
+                continue;
+            }
+            enemy.distance = if enemy.distance >= old_join {
+                enemy.distance - old_join + new_join
+            } else {
+                self.path.closest_between(&enemy.position, fork, new_join)
+            };
+            enemy.heading = self.path.heading_at(enemy.distance);
+            enemy.position = self
+                .path
+                .point_at(enemy.distance)
+                .plus(&enemy.heading.normal().scale(enemy.offset));
+            enemy.previous_position = enemy.position;
+            enemy.layer = self.path.layer_at(enemy.distance);
+        }
+    }
+
+    pub fn update_gates(&mut self, dt: f32) {
+        for gate in self.gates.iter_mut() {
+            gate.cooldown = (gate.cooldown - dt).max(0.);
+        }
+    }
+}
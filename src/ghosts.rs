@@ -0,0 +1,154 @@
+use std::fs;
+
+use piston_window::*;
+
+use crate::commands::Command;
+use crate::level::parse_number;
+use crate::replay::{Input, Replay};
+use crate::storage;
+use crate::theme;
+use crate::{Point, TILE_SIZE, UPDATES_PER_SECOND};
+
+// Folder the replay of the best run on each level is kept in, to show as a ghost
+const GHOSTS_DIR: &str = "ghosts";
+// Seconds before a ghost tower was placed that its outline starts fading in, so there's time to follow it
+const GHOST_LEAD: f32 = 5.;
+// How see-through a ghost tower is drawn, at its most solid
+const GHOST_ALPHA: f32 = 0.35;
+
+// A tower from the best run on a level, with when it was built and sold
+struct GhostTower {
+    // Stores where the tower was built
+    position: Point,
+    // Stores the tick the tower was built on
+    placed: u64,
+    // Stores the tick the tower was sold on, if it was
+    sold: Option<u64>,
+}
+
+// Where and when the player built towers in their best run on a level, shown faintly during later runs of it
+pub struct Ghost {
+    // Stores the score the run finished with
+    pub score: i32,
+    // Stores the towers built during the run, in the order they were built
+    towers: Vec<GhostTower>,
+}
+
+fn ghost_path(level: &str, extension: &str) -> String {
+    // Level names can have anything in them, so only letters and digits are kept in the file's name
+    let name: String = level
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{}/{}.{}", GHOSTS_DIR, name, extension)
+}
+
+impl Ghost {
+    pub fn load(level: &str) -> Option<Ghost> {
+        // A level without a ghost just hasn't been finished yet, and one that can't be read is left out, with the
+        // reason logged
+        let read = || -> Result<Option<Ghost>, String> {
+            let Some(meta) = storage::read_checked(&ghost_path(level, "meta"))? else {
+                return Ok(None);
+            };
+            let score = meta
+                .lines()
+                .find_map(|line| line.strip_prefix("score "))
+                .map_or(Ok(0), parse_number)?;
+            let replay = Replay::load(&ghost_path(level, "rep"))?;
+            Ok(Some(Ghost::from_replay(&replay, score)))
+        };
+        read().unwrap_or_else(|error| {
+            eprintln!("Couldn't read the ghost of {}: {}", level, error);
+            None
+        })
+    }
+
+    pub fn save(level: &str, replay: &Replay, score: i32) -> Result<Ghost, String> {
+        // Keep the replay first, so the score is never written for a ghost that isn't there
+        fs::create_dir_all(GHOSTS_DIR).map_err(|error| error.to_string())?;
+        replay
+            .save(&ghost_path(level, "rep"))
+            .map_err(|error| error.to_string())?;
+        storage::write_checked(&ghost_path(level, "meta"), &format!("score {}\n", score))
+            .map_err(|error| error.to_string())?;
+        Ok(Ghost::from_replay(replay, score))
+    }
+
+    fn from_replay(replay: &Replay, score: i32) -> Ghost {
+        // Play the builds and sales back without the game, undoing them in reverse order like the command queue
+        // does, with each change remembered as the index of the tower it built or sold
+        let mut towers: Vec<GhostTower> = Vec::new();
+        let mut done: Vec<(usize, bool)> = Vec::new();
+        for &(tick, input) in replay.inputs.iter() {
+            match input {
+                Input::Command(Command::Build { position, .. }) => {
+                    done.push((towers.len(), true));
+                    towers.push(GhostTower {
+                        position,
+                        placed: tick,
+                        sold: None,
+                    });
+                }
+                Input::Command(Command::Sell { position }) => {
+                    if let Some(i) = towers.iter().position(|tower| {
+                        tower.sold.is_none() && tower.position.distance_to(&position) < TILE_SIZE
+                    }) {
+                        towers[i].sold = Some(tick);
+                        done.push((i, false));
+                    }
+                }
+                Input::Undo => match done.pop() {
+                    Some((i, true)) => {
+                        towers.remove(i);
+                    }
+                    Some((i, false)) => towers[i].sold = None,
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+        Ghost { score, towers }
+    }
+
+    pub fn draw(&self, tick: u64, c: Context, g: &mut G2d) {
+        // Draw the towers standing at this point in the best run, and outline the ones about to be built, fading in
+        // as the moment they were built comes closer
+        let color = theme::palette().tower;
+        let lead = (GHOST_LEAD * UPDATES_PER_SECOND as f32) as u64;
+        for tower in self.towers.iter() {
+            if tower.placed > tick + lead || tower.sold.is_some_and(|sold| sold <= tick) {
+                continue;
+            }
+            let rect = [
+                tower.position.x as f64,
+                tower.position.y as f64,
+                TILE_SIZE as f64,
+                TILE_SIZE as f64,
+            ];
+            if tower.placed <= tick {
+                ellipse(theme::faded(color, GHOST_ALPHA / 2.), rect, c.transform, g);
+                Ellipse::new_border(theme::faded(color, GHOST_ALPHA), 1.0).draw(
+                    rect,
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            } else {
+                let fade = 1. - (tower.placed - tick) as f32 / lead as f32;
+                Ellipse::new_border(theme::faded(color, GHOST_ALPHA * fade), 1.0).draw(
+                    rect,
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ::image::RgbaImage;
+use egui::epaint::{ImageData, ImageDelta, Primitive, Rgba};
+use egui::{ClippedPrimitive, Color32, FontData, FontDefinitions, FontFamily, Pos2, RawInput};
+use egui::{TextureId, ViewportId, ViewportInfo};
+use piston_window::math::transform_pos;
+use piston_window::*;
+
+use crate::assets;
+use crate::display::{Layout, LAYOUT_SIZE};
+use crate::theme;
+
+// Name the game's font is registered under with egui
+const FONT_NAME: &str = "game";
+
+// Adapter that feeds piston's input to egui and draws what egui lays out with piston's graphics, for the settings and
+// debug panels. Everything is measured in the layout's pixels, which egui calls points, so the panels scale with the
+// rest of the game
+pub struct Gui {
+    // Stores egui's state, which remembers the panels and widgets from one frame to the next
+    context: egui::Context,
+    // Stores the input that's arrived since the last frame, in egui's terms
+    events: Vec<egui::Event>,
+    // Stores where the mouse is in the layout
+    pointer: Pos2,
+    // Stores which modifier keys are held down
+    modifiers: egui::Modifiers,
+    // Stores when the adapter was made, which egui measures time from for animations and double clicks
+    start: Instant,
+    // Stores every texture egui has asked for, with a copy of its pixels so parts of it can be patched later
+    textures: HashMap<TextureId, (RgbaImage, G2dTexture)>,
+    // Stores the triangles laid out last frame, ready to be drawn
+    primitives: Vec<ClippedPrimitive>,
+}
+
+impl Gui {
+    pub fn new() -> Self {
+        // Show egui's text in the game's font, and its panels and widgets in the colors of the game's own
+        let context = egui::Context::default();
+        let mut fonts = FontDefinitions::default();
+        fonts.font_data.insert(
+            FONT_NAME.to_string(),
+            FontData::from_static(assets::EMBEDDED_FONT),
+        );
+        for family in [FontFamily::Proportional, FontFamily::Monospace] {
+            fonts
+                .families
+                .entry(family)
+                .or_default()
+                .insert(0, FONT_NAME.to_string());
+        }
+        context.set_fonts(fonts);
+        Gui {
+            context,
+            events: Vec::new(),
+            pointer: Pos2::ZERO,
+            modifiers: egui::Modifiers::default(),
+            start: Instant::now(),
+            textures: HashMap::new(),
+            primitives: Vec::new(),
+        }
+    }
+
+    pub fn handle(&mut self, event: &Event) -> bool {
+        // Pass the event on to egui, returning whether it was meant for one of its panels, so nothing behind them
+        // sees it: a click or scroll over a panel, the release of a click that started on one, or typing into one
+        let over_panel = self.context.is_pointer_over_area();
+        if let Some([x, y]) = event.mouse_cursor_args() {
+            self.pointer = Pos2::new(x as f32, y as f32);
+            self.events.push(egui::Event::PointerMoved(self.pointer));
+        }
+        if let Some([x, y]) = event.mouse_scroll_args() {
+            self.events.push(egui::Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Line,
+                delta: egui::vec2(x as f32, y as f32),
+                modifiers: self.modifiers,
+            });
+            return over_panel;
+        }
+        if let Some(false) = event.cursor_args() {
+            self.events.push(egui::Event::PointerGone);
+        }
+        if let Some(text) = event.text_args() {
+            self.events.push(egui::Event::Text(text));
+            return self.context.wants_keyboard_input();
+        }
+        let (button, pressed) = match (event.press_args(), event.release_args()) {
+            (Some(button), _) => (button, true),
+            (_, Some(button)) => (button, false),
+            _ => return false,
+        };
+        match button {
+            Button::Mouse(button) => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    _ => return false,
+                };
+                let dragging = self.context.is_using_pointer();
+                self.events.push(egui::Event::PointerButton {
+                    pos: self.pointer,
+                    button,
+                    pressed,
+                    modifiers: self.modifiers,
+                });
+                if pressed {
+                    over_panel
+                } else {
+                    dragging
+                }
+            }
+            Button::Keyboard(key) => {
+                match key {
+                    Key::LCtrl | Key::RCtrl => self.modifiers.ctrl = pressed,
+                    Key::LShift | Key::RShift => self.modifiers.shift = pressed,
+                    Key::LAlt | Key::RAlt => self.modifiers.alt = pressed,
+                    _ => {}
+                }
+                self.modifiers.command = self.modifiers.ctrl;
+                let Some(key) = egui_key(key) else {
+                    return false;
+                };
+                self.events.push(egui::Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed,
+                    repeat: false,
+                    modifiers: self.modifiers,
+                });
+                self.context.wants_keyboard_input()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn frame(
+        &mut self,
+        window: &mut PistonWindow,
+        layout: &Layout,
+        run: impl FnMut(&egui::Context),
+    ) {
+        // Lay out this frame's panels with the input that's arrived since the last one, then get the textures and
+        // triangles egui asks for ready to draw
+        self.context.set_visuals(visuals());
+        let mut input = RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                Pos2::ZERO,
+                egui::vec2(LAYOUT_SIZE[0] as f32, LAYOUT_SIZE[1] as f32),
+            )),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            focused: true,
+            ..Default::default()
+        };
+        input.viewports.insert(
+            ViewportId::ROOT,
+            ViewportInfo {
+                native_pixels_per_point: Some(layout.pixels_per_point() as f32),
+                ..Default::default()
+            },
+        );
+        let output = self.context.run(input, run);
+        for (id, delta) in output.textures_delta.set {
+            self.set_texture(id, delta, window);
+        }
+        self.primitives = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        for id in output.textures_delta.free {
+            self.textures.remove(&id);
+        }
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: ImageDelta, window: &mut PistonWindow) {
+        // Copy the new pixels into the texture's image, in full or as a patch, and upload it again
+        let [width, height] = delta.image.size();
+        let pixels: Vec<Color32> = match &delta.image {
+            ImageData::Color(image) => image.pixels.clone(),
+            ImageData::Font(image) => image.srgba_pixels(None).collect(),
+        };
+        let patch = RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+            ::image::Rgba(pixels[y as usize * width + x as usize].to_srgba_unmultiplied())
+        });
+        let image = match (delta.pos, self.textures.remove(&id)) {
+            (Some([x, y]), Some((mut image, _))) => {
+                for (px, py, pixel) in patch.enumerate_pixels() {
+                    image.put_pixel(x as u32 + px, y as u32 + py, *pixel);
+                }
+                image
+            }
+            _ => patch,
+        };
+        if let Ok(texture) = Texture::from_image(
+            &mut window.create_texture_context(),
+            &image,
+            &TextureSettings::new(),
+        ) {
+            self.textures.insert(id, (image, texture));
+        }
+    }
+
+    pub fn draw(&self, layout: &Layout, window_context: Context, g: &mut G2d) {
+        // Draw last frame's triangles over the layout, each mesh cut off at its clipping rectangle
+        let transform = layout.apply(window_context).transform;
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in self.primitives.iter()
+        {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let (Some((_, texture)), Some(scissor)) = (
+                self.textures.get(&mesh.texture_id),
+                layout.scissor([
+                    clip_rect.min.x,
+                    clip_rect.min.y,
+                    clip_rect.max.x,
+                    clip_rect.max.y,
+                ]),
+            ) else {
+                continue;
+            };
+            let mut vertices = Vec::with_capacity(mesh.indices.len());
+            let mut uvs = Vec::with_capacity(mesh.indices.len());
+            let mut colors = Vec::with_capacity(mesh.indices.len());
+            for &index in mesh.indices.iter() {
+                let vertex = &mesh.vertices[index as usize];
+                let [x, y] = transform_pos(transform, [vertex.pos.x as f64, vertex.pos.y as f64]);
+                vertices.push([x as f32, y as f32]);
+                uvs.push([vertex.uv.x, vertex.uv.y]);
+                colors.push(Rgba::from(vertex.color).to_rgba_unmultiplied());
+            }
+            // Whole triangles go in each batch, so none is split between draw calls
+            let batch = BACK_END_MAX_VERTEX_COUNT / 3 * 3;
+            g.tri_list_uv_c(&DrawState::new_alpha().scissor(scissor), texture, |f| {
+                for ((vertices, uvs), colors) in vertices
+                    .chunks(batch)
+                    .zip(uvs.chunks(batch))
+                    .zip(colors.chunks(batch))
+                {
+                    f(vertices, uvs, colors);
+                }
+            });
+        }
+    }
+}
+
+fn egui_key(key: Key) -> Option<egui::Key> {
+    // The keys egui's widgets use to move between and edit themselves
+    Some(match key {
+        Key::Tab => egui::Key::Tab,
+        Key::Return => egui::Key::Enter,
+        Key::Space => egui::Key::Space,
+        Key::Backspace => egui::Key::Backspace,
+        Key::Delete => egui::Key::Delete,
+        Key::Home => egui::Key::Home,
+        Key::End => egui::Key::End,
+        Key::Left => egui::Key::ArrowLeft,
+        Key::Right => egui::Key::ArrowRight,
+        Key::Up => egui::Key::ArrowUp,
+        Key::Down => egui::Key::ArrowDown,
+        _ => return None,
+    })
+}
+
+fn color(color: [f32; 4]) -> Color32 {
+    // The palette's colors are linear, like everything piston draws into the window's sRGB framebuffer
+    let [r, g, b, a] = color;
+    Color32::from(Rgba::from_rgba_unmultiplied(r, g, b, a))
+}
+
+fn visuals() -> egui::Visuals {
+    // egui's dark style, recolored with the current theme's palette
+    let palette = theme::palette();
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(color(palette.text));
+    visuals.window_fill = color(palette.panel);
+    visuals.panel_fill = color(palette.panel);
+    visuals.window_stroke.color = color(palette.border);
+    for widget in [
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+    ] {
+        widget.bg_stroke.color = color(palette.border);
+    }
+    visuals.widgets.inactive.weak_bg_fill = color(palette.widget);
+    visuals.widgets.inactive.bg_fill = color(palette.widget);
+    visuals.widgets.hovered.weak_bg_fill = color(palette.hover);
+    visuals.widgets.hovered.bg_fill = color(palette.hover);
+    visuals.widgets.active.weak_bg_fill = color(palette.pressed);
+    visuals.widgets.active.bg_fill = color(palette.pressed);
+    visuals.selection.bg_fill = color(palette.accent);
+    visuals
+}
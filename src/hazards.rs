@@ -0,0 +1,131 @@
+use crate::level::{parse_number, Layer};
+use crate::math;
+use crate::{Game, Point, TILE_SIZE};
+use std::f32::consts::TAU;
+use std::fmt;
+
+// Speed boulders roll at, in tiles per second
+const BOULDER_SPEED: f32 = 2.;
+// Distance from a boulder's center within which it crushes an enemy, in pixels
+pub const BOULDER_RADIUS: f32 = TILE_SIZE * 0.6;
+// Speed flame jets sweep round at, in radians per second
+const FLAME_TURN_SPEED: f32 = 1.2;
+// Distance from the middle of a flame jet within which it burns an enemy, in pixels
+pub const FLAME_WIDTH: f32 = TILE_SIZE * 0.3;
+// Seconds between each time hazards hurt the enemies touching them
+const HAZARD_INTERVAL: f32 = 0.5;
+// Damage a hazard does to each enemy touching it, every interval
+const HAZARD_DAMAGE: i32 = 3;
+
+// Something on the map that moves on its own and hurts any enemy on the ground it touches, with positions in tiles
+// in a level file and in pixels once the game starts
+#[derive(Clone)]
+pub enum Hazard {
+    // A boulder rolling back and forth between two points
+    Boulder { from: Point, to: Point },
+    // A jet of flame sweeping round and round a pivot, reaching the given length
+    Flame { pivot: Point, length: f32 },
+}
+
+impl Hazard {
+    pub fn parse(value: &str) -> Result<Hazard, String> {
+        // Hazards are written as their kind followed by their points and sizes, like "boulder 4 10 12 10"
+        let (kind, numbers) = value.split_once(' ').unwrap_or((value, ""));
+        let numbers = numbers
+            .split_whitespace()
+            .map(parse_number)
+            .collect::<Result<Vec<f32>, _>>()?;
+        match (kind, &numbers[..]) {
+            ("boulder", &[x1, y1, x2, y2]) => Ok(Hazard::Boulder {
+                from: Point::new(x1, y1),
+                to: Point::new(x2, y2),
+            }),
+            ("flame", &[x, y, length]) if length > 0. => Ok(Hazard::Flame {
+                pivot: Point::new(x, y),
+                length,
+            }),
+            _ => Err(format!("Unknown hazard: {}", value)),
+        }
+    }
+
+    pub fn scale(&self, factor: f32) -> Hazard {
+        match self {
+            Hazard::Boulder { from, to } => Hazard::Boulder {
+                from: from.scale(factor),
+                to: to.scale(factor),
+            },
+            Hazard::Flame { pivot, length } => Hazard::Flame {
+                pivot: pivot.scale(factor),
+                length: length * factor,
+            },
+        }
+    }
+
+    pub fn boulder_at(from: &Point, to: &Point, time: f32) -> (Point, f32) {
+        // Find where a boulder is at the given time into the run, and how far it's rolled in all, which it spins by
+        let length = from.distance_to(to);
+        if length <= 0. {
+            return (*from, 0.);
+        }
+        let rolled = BOULDER_SPEED * TILE_SIZE * time;
+        let along = rolled % (length * 2.);
+        let t = if along > length {
+            2. - along / length
+        } else {
+            along / length
+        };
+        (from.plus(&to.minus(from).scale(t)), rolled)
+    }
+
+    pub fn flame_tip(pivot: &Point, length: f32, time: f32) -> Point {
+        // Find the far end of a flame jet at the given time into the run
+        let angle = (FLAME_TURN_SPEED * time) % TAU;
+        pivot.plus(&Point::new(math::cos(angle), math::sin(angle)).scale(length))
+    }
+
+    fn touches(&self, position: &Point, time: f32) -> bool {
+        match self {
+            Hazard::Boulder { from, to } => {
+                Hazard::boulder_at(from, to, time).0.distance_to(position) < BOULDER_RADIUS
+            }
+            Hazard::Flame { pivot, length } => {
+                let tip = Hazard::flame_tip(pivot, *length, time);
+                position.distance_to_segment(pivot, &tip) < FLAME_WIDTH
+            }
+        }
+    }
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Hazard::Boulder { from, to } => {
+                write!(f, "boulder {} {} {} {}", from.x, from.y, to.x, to.y)
+            }
+            Hazard::Flame { pivot, length } => {
+                write!(f, "flame {} {} {}", pivot.x, pivot.y, length)
+            }
+        }
+    }
+}
+
+impl Game {
+    pub fn update_hazards(&mut self, dt: f32) {
+        // Hazards move with the run's clock, so where they are never needs saving, and hurt what they touch each
+        // time another interval passes
+        let time = self.state.stats.playtime;
+        if (time / HAZARD_INTERVAL).floor() == ((time - dt) / HAZARD_INTERVAL).floor() {
+            return;
+        }
+        for hazard in self.hazards.iter() {
+            for enemy in self.state.enemies.iter_mut().filter(|enemy| {
+                enemy.is_alive()
+                    && !enemy.burrowed
+                    && enemy.layer == Layer::Ground
+                    && hazard.touches(&enemy.position, time)
+            }) {
+                enemy.apply_damage(HAZARD_DAMAGE);
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use crate::objectives::Status;
+use crate::stats::format_time;
+use crate::{default_group, Game, CAMPAIGN_WAVES};
+
+pub struct HudLine {
+    // Stores the line's text
+    pub text: String,
+    // Stores the line's font size
+    pub size: u32,
+    // Stores the line's distance from the top of the window, in pixels
+    pub y: f64,
+}
+
+// The values shown on the HUD, as they'd be displayed, so the text is only rebuilt when one changes
+#[derive(PartialEq)]
+struct HudSnapshot {
+    resources: Option<i32>,
+    lives: i32,
+    seconds: u32,
+    selected_tower_type: usize,
+    tower_cost: i32,
+    prestige: u32,
+    wave: usize,
+    build_seconds: Option<i32>,
+    spell_charges: u32,
+    damage_boost: Option<(f32, i32)>,
+    difficulty: Option<f32>,
+    mutators: usize,
+    sandbox_enemy_type: Option<usize>,
+    objectives: Vec<u32>,
+    bonus_objectives: Vec<(Status, u32)>,
+}
+
+pub struct Hud {
+    // Stores the values the HUD lines were last built from
+    snapshot: Option<HudSnapshot>,
+    // Stores the HUD's lines of text, top to bottom
+    pub lines: Vec<HudLine>,
+    // Stores how many times the HUD text has been rebuilt
+    pub rebuilds: usize,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud {
+            snapshot: None,
+            lines: Vec::new(),
+            rebuilds: 0,
+        }
+    }
+
+    pub fn update(&mut self, game: &Game, daily: bool, sandbox_enemy_type: usize) {
+        // Skip rebuilding the text when nothing it shows has changed since the last frame
+        let snapshot = HudSnapshot {
+            resources: (!game.rules.sandbox).then_some(game.state.resources),
+            lives: game.state.lives,
+            seconds: game.state.stats.playtime as u32,
+            selected_tower_type: game.state.selected_tower_type,
+            tower_cost: game.tower_cost(game.state.selected_tower_type),
+            prestige: game.prestige,
+            wave: game.state.wave,
+            build_seconds: game
+                .state
+                .building
+                .then_some(game.state.wave_timer.round() as i32),
+            spell_charges: game.state.spell_charges,
+            damage_boost: (game.state.damage_boost_timer > 0.).then_some((
+                game.state.damage_boost,
+                game.state.damage_boost_timer.round() as i32,
+            )),
+            difficulty: game.rules.adaptive.then_some(game.state.difficulty),
+            mutators: game.rules.mutators.len(),
+            sandbox_enemy_type: game.rules.sandbox.then_some(sandbox_enemy_type),
+            objectives: game
+                .objectives
+                .iter()
+                .map(|objective| (objective.progress(game).clamp(0., 1.) * 100.) as u32)
+                .collect(),
+            bonus_objectives: game
+                .bonus_objectives
+                .iter()
+                .map(|objective| {
+                    let percent = (objective.progress(game).clamp(0., 1.) * 100.) as u32;
+                    (objective.status(game), percent)
+                })
+                .collect(),
+        };
+        if self.snapshot.as_ref() == Some(&snapshot) {
+            return;
+        }
+
+        self.lines.clear();
+        self.lines.push(HudLine {
+            text: match snapshot.resources {
+                Some(resources) => format!("Resources: {}", resources),
+                None => String::from("Resources: unlimited"),
+            },
+            size: 20,
+            y: 50.,
+        });
+        self.lines.push(HudLine {
+            text: format!(
+                "Lives: {}  Time: {}",
+                snapshot.lives,
+                format_time(snapshot.seconds as f32)
+            ),
+            size: 20,
+            y: 70.,
+        });
+        self.lines.push(HudLine {
+            text: format!(
+                "Tower: {} ({})",
+                game.tower_types[snapshot.selected_tower_type].name, snapshot.tower_cost
+            ),
+            size: 20,
+            y: 90.,
+        });
+
+        // Show the current wave and how much enemies are worth in it
+        let mut wave = String::new();
+        if snapshot.prestige > 0 {
+            wave += &format!("NG+{}  ", snapshot.prestige);
+        }
+        if game.rules.endless {
+            wave += &format!("Wave: {}", snapshot.wave);
+        } else if game.rules.puzzle {
+            wave += &format!("Wave: {}/{}", snapshot.wave, game.waves.len());
+        } else {
+            wave += &format!("Wave: {}/{}", snapshot.wave, CAMPAIGN_WAVES);
+        }
+        // Puzzles have no shop, no bounties, and no countdown to the next wave
+        if !game.rules.puzzle {
+            if let Some(seconds) = snapshot.build_seconds {
+                wave += &format!(" in {}s (Tab: shop)", seconds);
+            }
+            wave += &format!(
+                "  Bounty: {:.0}%",
+                game.reward_curve.multiplier(snapshot.wave) * 100.
+            );
+        }
+        self.lines.push(HudLine {
+            text: wave,
+            size: 20,
+            y: 110.,
+        });
+
+        // Show the player's spell charges and any active buff
+        let mut status = format!("Spell charges: {}", snapshot.spell_charges);
+        if let Some((boost, seconds)) = snapshot.damage_boost {
+            status += &format!("  Damage x{} ({}s)", boost, seconds);
+        }
+        if let Some(difficulty) = snapshot.difficulty {
+            status += &format!("  Difficulty x{:.2}", difficulty);
+        }
+        self.lines.push(HudLine {
+            text: status,
+            size: 20,
+            y: 130.,
+        });
+
+        // Show the mutators changing this run's rules, such as the daily challenge's modifiers
+        if snapshot.mutators > 0 {
+            self.lines.push(HudLine {
+                text: format!(
+                    "{}: {} (score x{:.2})",
+                    if daily { "Daily" } else { "Mutators" },
+                    game.rules.mutators.join(", "),
+                    game.rules.score_multiplier
+                ),
+                size: 16,
+                y: 150.,
+            });
+        }
+
+        // Show the sandbox controls
+        if let Some(enemy_type) = snapshot.sandbox_enemy_type {
+            self.lines.push(HudLine {
+                text: format!(
+                    "Sandbox: [ ] pick {}, Enter spawns it, N starts the wave",
+                    game.enemy_types[enemy_type].name
+                ),
+                size: 16,
+                y: 170.,
+            });
+        }
+
+        // Show the puzzle controls
+        if game.rules.puzzle {
+            self.lines.push(HudLine {
+                text: String::from("Puzzle: no income, N starts the wave, Ctrl+R retries"),
+                size: 16,
+                y: 170.,
+            });
+        }
+
+        // Show how far along the level's objectives are
+        for (i, (objective, percent)) in game
+            .objectives
+            .iter()
+            .zip(snapshot.objectives.iter())
+            .enumerate()
+        {
+            self.lines.push(HudLine {
+                text: format!("Objective: {} ({}%)", objective.describe(), percent),
+                size: 16,
+                y: 190. + i as f64 * 18.,
+            });
+        }
+
+        // Show the level's bonus objectives under them, with whether each is still on track for its star
+        let mut y = 190. + snapshot.objectives.len() as f64 * 18.;
+        for (objective, (status, percent)) in game
+            .bonus_objectives
+            .iter()
+            .zip(snapshot.bonus_objectives.iter())
+        {
+            let progress = match status {
+                Status::Pending => format!("{}%", percent),
+                Status::Complete => String::from("complete"),
+                Status::Failed => String::from("failed"),
+            };
+            self.lines.push(HudLine {
+                text: format!("Bonus: {} ({})", objective.describe(), progress),
+                size: 16,
+                y,
+            });
+            y += 18.;
+        }
+
+        // Show anything unusual about the wave that's on, or coming up next during the build phase
+        if let Some(announcement) = game
+            .waves
+            .get(snapshot.wave - 1)
+            .and_then(|wave| wave.announcement())
+        {
+            let when = if snapshot.build_seconds.is_some() {
+                "Next wave"
+            } else {
+                "This wave"
+            };
+            self.lines.push(HudLine {
+                text: format!("{}: {}", when, announcement),
+                size: 16,
+                y,
+            });
+            y += 18.;
+        }
+
+        // Preview what the next wave sends at each spawn, with the lives each enemy costs if it gets through
+        if snapshot.build_seconds.is_some() && !game.rules.reverse {
+            let default_wave = [default_group(snapshot.wave, game.enemy_types.len())];
+            let groups = game
+                .waves
+                .get(snapshot.wave - 1)
+                .map_or(&default_wave[..], |wave| &wave.groups);
+            let preview: Vec<String> = groups
+                .iter()
+                .map(|group| {
+                    let enemy_type = &game.enemy_types[group.enemy_type];
+                    let lives = if enemy_type.lives_cost == 1 {
+                        "life"
+                    } else {
+                        "lives"
+                    };
+                    format!(
+                        "{}x {} (-{} {})",
+                        group.count, enemy_type.name, enemy_type.lives_cost, lives
+                    )
+                })
+                .collect();
+            self.lines.push(HudLine {
+                text: format!("Next wave sends: {}", preview.join(", ")),
+                size: 16,
+                y,
+            });
+        }
+
+        self.snapshot = Some(snapshot);
+        self.rebuilds += 1;
+    }
+}
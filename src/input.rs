@@ -0,0 +1,835 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+use piston_window::*;
+
+use crate::label::{self, Align, TextStyle};
+use crate::storage;
+use crate::theme;
+use crate::ui::{Frame, Ui};
+
+// File the player's key and mouse bindings are saved to
+pub const BINDINGS_PATH: &str = "bindings.cfg";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveLeft,
+    MoveDown,
+    MoveRight,
+    // Selects a tower type, or buys a shop offer while the shop is open
+    Choose(usize),
+    ToggleShop,
+    UseItem(usize),
+    PlaceTower,
+    PlaceTowerAtMouse,
+    SellTower,
+    UseAbility,
+    Undo,
+    Redo,
+    CollectLoot,
+    Pause,
+    ToggleStats,
+    ToggleDebug,
+    ToggleCommunity,
+    ReloadAssets,
+    ToggleSettings,
+    ToggleControls,
+    ToggleCombatLog,
+    ScrollLogUp,
+    ScrollLogDown,
+    PreviousEnemy,
+    NextEnemy,
+    SpawnEnemy,
+    StartWave,
+    PhotoMode,
+    ToggleOverlay,
+    ToggleGhost,
+    Retry,
+    Rewind,
+    ToggleCheatSheet,
+    ToggleSaves,
+    ToggleEncyclopedia,
+}
+
+// Every action that can be bound, in the order the controls panel lists them
+pub const ACTIONS: [Action; 43] = [
+    Action::MoveUp,
+    Action::MoveLeft,
+    Action::MoveDown,
+    Action::MoveRight,
+    Action::Choose(0),
+    Action::Choose(1),
+    Action::Choose(2),
+    Action::Choose(3),
+    Action::ToggleShop,
+    Action::UseItem(0),
+    Action::UseItem(1),
+    Action::UseItem(2),
+    Action::UseItem(3),
+    Action::UseItem(4),
+    Action::PlaceTower,
+    Action::PlaceTowerAtMouse,
+    Action::SellTower,
+    Action::UseAbility,
+    Action::Undo,
+    Action::Redo,
+    Action::CollectLoot,
+    Action::Pause,
+    Action::ToggleStats,
+    Action::ToggleDebug,
+    Action::ToggleCommunity,
+    Action::ReloadAssets,
+    Action::ToggleSettings,
+    Action::ToggleControls,
+    Action::ToggleCombatLog,
+    Action::ScrollLogUp,
+    Action::ScrollLogDown,
+    Action::PreviousEnemy,
+    Action::NextEnemy,
+    Action::SpawnEnemy,
+    Action::StartWave,
+    Action::PhotoMode,
+    Action::ToggleOverlay,
+    Action::ToggleGhost,
+    Action::Retry,
+    Action::Rewind,
+    Action::ToggleCheatSheet,
+    Action::ToggleSaves,
+    Action::ToggleEncyclopedia,
+];
+
+// Groups the cheat sheet sorts actions into, in the order it lists them
+const CATEGORIES: [&str; 6] = ["Cursor", "Building", "Items", "Game", "Panels", "Sandbox"];
+// Height of each line of the cheat sheet, and the lowest a line can go before it starts a new column
+const CHEAT_SHEET_LINE: f64 = 15.0;
+const CHEAT_SHEET_BOTTOM: f64 = 450.0;
+
+// Keys that can be bound, besides the modifiers, leaving Escape to always quit
+const KEYS: [Key; 66] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::D0,
+    Key::D1,
+    Key::D2,
+    Key::D3,
+    Key::D4,
+    Key::D5,
+    Key::D6,
+    Key::D7,
+    Key::D8,
+    Key::D9,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::Space,
+    Key::Return,
+    Key::Tab,
+    Key::Backspace,
+    Key::Delete,
+    Key::Insert,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::LeftBracket,
+    Key::RightBracket,
+    Key::Minus,
+    Key::Equals,
+];
+
+// Mouse buttons that can be bound, with the names they're saved under
+const MOUSE_BUTTONS: [(MouseButton, &str); 5] = [
+    (MouseButton::Left, "mouse-left"),
+    (MouseButton::Right, "mouse-right"),
+    (MouseButton::Middle, "mouse-middle"),
+    (MouseButton::X1, "mouse-back"),
+    (MouseButton::X2, "mouse-forward"),
+];
+
+// A built-in set of bindings the player can start from, picked in the settings panel
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    // WASD moves the cursor, with placing and selling on the keys around it
+    #[default]
+    Standard,
+    // The arrow keys move the cursor, with placing and selling on the keys above them
+    Arrows,
+    // ESDF moves the cursor, leaving the keys to the left of it free
+    Esdf,
+    // The arrow keys move the cursor, and the mouse buttons are swapped for a mouse in the left hand
+    LeftHanded,
+}
+
+// Every preset, in the order the settings panel cycles through them
+pub const PRESETS: [Preset; 4] = [
+    Preset::Standard,
+    Preset::Arrows,
+    Preset::Esdf,
+    Preset::LeftHanded,
+];
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        // The name the preset is saved under in the settings file
+        match self {
+            Preset::Standard => "standard",
+            Preset::Arrows => "arrows",
+            Preset::Esdf => "esdf",
+            Preset::LeftHanded => "left-handed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        PRESETS.iter().copied().find(|preset| preset.name() == name)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Preset::Standard => "WASD",
+            Preset::Arrows => "Arrow keys",
+            Preset::Esdf => "ESDF",
+            Preset::LeftHanded => "Left-handed",
+        }
+    }
+
+    pub fn next(&self) -> Preset {
+        let i = PRESETS
+            .iter()
+            .position(|preset| preset == self)
+            .unwrap_or(0);
+        PRESETS[(i + 1) % PRESETS.len()]
+    }
+}
+
+impl Action {
+    fn name(&self) -> String {
+        // The name the action is saved under in the bindings file
+        match self {
+            Action::MoveUp => String::from("move-up"),
+            Action::MoveLeft => String::from("move-left"),
+            Action::MoveDown => String::from("move-down"),
+            Action::MoveRight => String::from("move-right"),
+            Action::Choose(i) => format!("choose-{}", i + 1),
+            Action::ToggleShop => String::from("shop"),
+            Action::UseItem(i) => format!("item-{}", i + 1),
+            Action::PlaceTower => String::from("place-tower"),
+            Action::PlaceTowerAtMouse => String::from("place-tower-at-mouse"),
+            Action::SellTower => String::from("sell-tower"),
+            Action::UseAbility => String::from("tower-ability"),
+            Action::Undo => String::from("undo"),
+            Action::Redo => String::from("redo"),
+            Action::CollectLoot => String::from("collect-loot"),
+            Action::Pause => String::from("pause"),
+            Action::ToggleStats => String::from("statistics"),
+            Action::ToggleDebug => String::from("debug"),
+            Action::ToggleCommunity => String::from("community-levels"),
+            Action::ReloadAssets => String::from("reload-assets"),
+            Action::ToggleSettings => String::from("settings"),
+            Action::ToggleControls => String::from("controls"),
+            Action::ToggleCombatLog => String::from("combat-log"),
+            Action::ScrollLogUp => String::from("combat-log-up"),
+            Action::ScrollLogDown => String::from("combat-log-down"),
+            Action::PreviousEnemy => String::from("sandbox-previous-enemy"),
+            Action::NextEnemy => String::from("sandbox-next-enemy"),
+            Action::SpawnEnemy => String::from("sandbox-spawn-enemy"),
+            Action::StartWave => String::from("sandbox-start-wave"),
+            Action::PhotoMode => String::from("photo-mode"),
+            Action::ToggleOverlay => String::from("analytics-overlay"),
+            Action::ToggleGhost => String::from("ghost"),
+            Action::Retry => String::from("retry-puzzle"),
+            Action::Rewind => String::from("rewind"),
+            Action::ToggleCheatSheet => String::from("cheat-sheet"),
+            Action::ToggleSaves => String::from("saves"),
+            Action::ToggleEncyclopedia => String::from("encyclopedia"),
+        }
+    }
+
+    fn description(&self) -> String {
+        // The action as the controls panel describes it
+        match self {
+            Action::MoveUp => String::from("Move cursor up"),
+            Action::MoveLeft => String::from("Move cursor left"),
+            Action::MoveDown => String::from("Move cursor down"),
+            Action::MoveRight => String::from("Move cursor right"),
+            Action::Choose(i) => format!("Tower / offer {}", i + 1),
+            Action::ToggleShop => String::from("Shop"),
+            Action::UseItem(i) => format!("Use item {}", i + 1),
+            Action::PlaceTower => String::from("Place tower"),
+            Action::PlaceTowerAtMouse => String::from("Place tower at mouse"),
+            Action::SellTower => String::from("Sell tower"),
+            Action::UseAbility => String::from("Use tower ability"),
+            Action::Undo => String::from("Undo build or sale"),
+            Action::Redo => String::from("Redo build or sale"),
+            Action::CollectLoot => String::from("Collect loot"),
+            Action::Pause => String::from("Pause"),
+            Action::ToggleStats => String::from("Statistics"),
+            Action::ToggleDebug => String::from("Debug panel"),
+            Action::ToggleCommunity => String::from("Community levels"),
+            Action::ReloadAssets => String::from("Reload assets"),
+            Action::ToggleSettings => String::from("Settings"),
+            Action::ToggleControls => String::from("Controls"),
+            Action::ToggleCombatLog => String::from("Combat log"),
+            Action::ScrollLogUp => String::from("Scroll log up"),
+            Action::ScrollLogDown => String::from("Scroll log down"),
+            Action::PreviousEnemy => String::from("Sandbox: previous enemy"),
+            Action::NextEnemy => String::from("Sandbox: next enemy"),
+            Action::SpawnEnemy => String::from("Sandbox: spawn enemy"),
+            Action::StartWave => String::from("Sandbox / puzzle: start wave"),
+            Action::PhotoMode => String::from("Photo mode"),
+            Action::ToggleOverlay => String::from("Analytics overlay"),
+            Action::ToggleGhost => String::from("Ghost of best run"),
+            Action::Retry => String::from("Retry puzzle"),
+            Action::Rewind => String::from("Rewind 10 seconds"),
+            Action::ToggleCheatSheet => String::from("Key cheat sheet"),
+            Action::ToggleSaves => String::from("Saved runs"),
+            Action::ToggleEncyclopedia => String::from("Encyclopedia"),
+        }
+    }
+
+    fn category(&self) -> &'static str {
+        // The group the cheat sheet lists the action under, which is one of CATEGORIES
+        match self {
+            Action::MoveUp | Action::MoveLeft | Action::MoveDown | Action::MoveRight => "Cursor",
+            Action::Choose(_)
+            | Action::PlaceTower
+            | Action::PlaceTowerAtMouse
+            | Action::SellTower
+            | Action::UseAbility
+            | Action::Undo
+            | Action::Redo => "Building",
+            Action::ToggleShop | Action::UseItem(_) | Action::CollectLoot => "Items",
+            Action::Pause
+            | Action::PhotoMode
+            | Action::ToggleOverlay
+            | Action::ToggleGhost
+            | Action::Retry
+            | Action::Rewind
+            | Action::ToggleCombatLog
+            | Action::ScrollLogUp
+            | Action::ScrollLogDown => "Game",
+            Action::ToggleStats
+            | Action::ToggleDebug
+            | Action::ToggleCommunity
+            | Action::ReloadAssets
+            | Action::ToggleSettings
+            | Action::ToggleControls
+            | Action::ToggleCheatSheet
+            | Action::ToggleSaves
+            | Action::ToggleEncyclopedia => "Panels",
+            Action::PreviousEnemy | Action::NextEnemy | Action::SpawnEnemy | Action::StartWave => {
+                "Sandbox"
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    // Stores whether either Shift key is held
+    pub shift: bool,
+    // Stores whether either Ctrl key is held
+    pub ctrl: bool,
+    // Stores whether either Alt key is held
+    pub alt: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    // Stores the modifier keys that have to be held
+    pub modifiers: Modifiers,
+    // Stores the key or mouse button that's pressed while they're held
+    pub button: Button,
+}
+
+impl Chord {
+    fn new(button: Button) -> Self {
+        Chord {
+            modifiers: Modifiers::default(),
+            button,
+        }
+    }
+
+    fn with(self, modifier: &str) -> Self {
+        let mut chord = self;
+        match modifier {
+            "shift" => chord.modifiers.shift = true,
+            "ctrl" => chord.modifiers.ctrl = true,
+            _ => chord.modifiers.alt = true,
+        }
+        chord
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        // Chords are written like "ctrl+s" or "shift+mouse-left"
+        let mut parts: Vec<&str> = text.trim().split('+').map(str::trim).collect();
+        let button = parts.pop()?.to_lowercase();
+        let button = KEYS
+            .iter()
+            .find(|key| key_name(**key) == button)
+            .map(|key| Button::Keyboard(*key))
+            .or_else(|| {
+                MOUSE_BUTTONS
+                    .iter()
+                    .find(|(_, name)| *name == button)
+                    .map(|(mouse_button, _)| Button::Mouse(*mouse_button))
+            })?;
+        let mut chord = Chord::new(button);
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                modifier @ ("shift" | "ctrl" | "alt") => chord = chord.with(modifier),
+                _ => return None,
+            }
+        }
+        Some(chord)
+    }
+}
+
+fn key_name(key: Key) -> String {
+    // Digit keys are named by their digit, and the rest by piston's name for them
+    let name = format!("{:?}", key).to_lowercase();
+    match name.strip_prefix('d') {
+        Some(digit) if digit.len() == 1 => digit.to_string(),
+        _ => name,
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "alt+")?;
+        }
+        match self.button {
+            Button::Keyboard(key) => write!(f, "{}", key_name(key)),
+            Button::Mouse(mouse_button) => {
+                let name = MOUSE_BUTTONS
+                    .iter()
+                    .find(|(button, _)| *button == mouse_button)
+                    .map_or("mouse", |(_, name)| name);
+                write!(f, "{}", name)
+            }
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+pub struct Bindings {
+    // Stores the chords bound to each action, in the same order as ACTIONS
+    chords: Vec<Vec<Chord>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::preset(Preset::default())
+    }
+}
+
+impl Bindings {
+    pub fn preset(preset: Preset) -> Self {
+        // Every preset shares the same menu keys, and differs in how the cursor moves, which keys place, sell,
+        // and trigger abilities, and which mouse button does what
+        let key = |key| Chord::new(Button::Keyboard(key));
+        let mouse = |button| Chord::new(Button::Mouse(button));
+        let (up, left, down, right) = match preset {
+            Preset::Standard => (Key::W, Key::A, Key::S, Key::D),
+            Preset::Esdf => (Key::E, Key::S, Key::D, Key::F),
+            Preset::Arrows | Preset::LeftHanded => (Key::Up, Key::Left, Key::Down, Key::Right),
+        };
+        let arrows = matches!(preset, Preset::Arrows | Preset::LeftHanded);
+        let (place, sell, ability) = match preset {
+            Preset::Standard => (key(Key::Space), key(Key::Backspace), key(Key::Q)),
+            Preset::Esdf => (key(Key::Space), key(Key::G), key(Key::R)),
+            Preset::Arrows | Preset::LeftHanded => {
+                (key(Key::Return), key(Key::Delete), key(Key::Insert))
+            }
+        };
+        let primary = match preset {
+            Preset::LeftHanded => MouseButton::Right,
+            _ => MouseButton::Left,
+        };
+        let chords = ACTIONS
+            .iter()
+            .map(|action| match action {
+                Action::MoveUp => vec![key(up)],
+                Action::MoveLeft => vec![key(left)],
+                Action::MoveDown => vec![key(down)],
+                Action::MoveRight => vec![key(right)],
+                Action::Choose(i) => vec![key([Key::D1, Key::D2, Key::D3, Key::D4][*i])],
+                Action::ToggleShop => vec![key(Key::Tab)],
+                Action::UseItem(i) => vec![key([Key::Z, Key::X, Key::C, Key::V, Key::B][*i])],
+                Action::PlaceTower => vec![place],
+                Action::PlaceTowerAtMouse => vec![mouse(primary).with("shift")],
+                Action::SellTower => vec![sell, key(Key::S).with("ctrl")],
+                Action::UseAbility => vec![ability],
+                Action::Undo => vec![key(Key::Z).with("ctrl")],
+                Action::Redo => vec![
+                    key(Key::Y).with("ctrl"),
+                    key(Key::Z).with("ctrl").with("shift"),
+                ],
+                Action::CollectLoot => vec![mouse(primary)],
+                Action::Pause => vec![key(Key::P)],
+                Action::ToggleStats => vec![key(Key::F2)],
+                Action::ToggleDebug => vec![key(Key::F3)],
+                Action::ToggleCommunity => vec![key(Key::F4)],
+                Action::ReloadAssets => vec![key(Key::F5)],
+                Action::ToggleSettings => vec![key(Key::F6)],
+                Action::ToggleControls => vec![key(Key::F7)],
+                Action::ToggleCombatLog => vec![key(Key::L)],
+                Action::ScrollLogUp => vec![key(Key::PageUp)],
+                Action::ScrollLogDown => vec![key(Key::PageDown)],
+                Action::PreviousEnemy => vec![key(Key::LeftBracket)],
+                Action::NextEnemy => vec![key(Key::RightBracket)],
+                Action::SpawnEnemy if arrows => vec![key(Key::Home)],
+                Action::SpawnEnemy => vec![key(Key::Return)],
+                Action::StartWave => vec![key(Key::N)],
+                Action::PhotoMode => vec![key(Key::F8)],
+                Action::ToggleOverlay => vec![key(Key::F9)],
+                Action::ToggleGhost => vec![key(Key::F11)],
+                Action::Retry => vec![key(Key::R).with("ctrl")],
+                Action::Rewind => vec![key(Key::Backspace).with("ctrl")],
+                Action::ToggleCheatSheet => vec![key(Key::F1)],
+                Action::ToggleSaves => vec![key(Key::F10)],
+                Action::ToggleEncyclopedia => vec![key(Key::F12)],
+            })
+            .collect();
+        Bindings { chords }
+    }
+
+    pub fn load(path: &str, preset: Preset) -> Self {
+        // Actions missing from the file, or with bindings that can't be read, keep the preset's
+        let mut bindings = Bindings::preset(preset);
+        let Ok(contents) = fs::read_to_string(path) else {
+            return bindings;
+        };
+        for line in contents.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let Some(i) = ACTIONS
+                .iter()
+                .position(|action| action.name() == name.trim())
+            else {
+                continue;
+            };
+            let chords: Option<Vec<Chord>> = value
+                .split(',')
+                .filter(|chord| !chord.trim().is_empty())
+                .map(Chord::parse)
+                .collect();
+            match chords {
+                Some(chords) => bindings.chords[i] = chords,
+                None => eprintln!("Couldn't read the binding for {}: {}", name.trim(), value),
+            }
+        }
+        bindings
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = String::new();
+        for (action, chords) in ACTIONS.iter().zip(self.chords.iter()) {
+            let chords: Vec<String> = chords.iter().map(Chord::to_string).collect();
+            contents += &format!("{}={}\n", action.name(), chords.join(", "));
+        }
+        storage::write_atomically(path, &contents)
+    }
+
+    pub fn action(&self, chord: &Chord) -> Option<Action> {
+        // Prefer an exact match, but let a key without a chord of its own work with modifiers held,
+        // so moving the cursor with Shift held still works
+        let find = |matches: &dyn Fn(&Chord) -> bool| {
+            ACTIONS
+                .iter()
+                .zip(self.chords.iter())
+                .find(|(_, chords)| chords.iter().any(matches))
+                .map(|(action, _)| *action)
+        };
+        find(&|bound| bound == chord).or_else(|| {
+            find(&|bound| bound.button == chord.button && bound.modifiers == Modifiers::default())
+        })
+    }
+
+    fn chords_for(&self, action: Action) -> &[Chord] {
+        ACTIONS
+            .iter()
+            .position(|other| *other == action)
+            .map_or(&[], |i| &self.chords[i])
+    }
+
+    pub fn bind(&mut self, action: Action, chord: Chord) {
+        // Replace the action's first chord, keeping any others it has
+        let Some(i) = ACTIONS.iter().position(|other| *other == action) else {
+            return;
+        };
+        match self.chords[i].first_mut() {
+            Some(first) => *first = chord,
+            None => self.chords[i].push(chord),
+        }
+    }
+
+    pub fn conflicts(&self) -> Vec<(Action, Action, Chord)> {
+        // Find every pair of actions sharing a chord, since only the first of them would ever happen
+        let mut conflicts = Vec::new();
+        for (i, chords) in self.chords.iter().enumerate() {
+            for (j, others) in self.chords.iter().enumerate().skip(i + 1) {
+                for chord in chords.iter().filter(|chord| others.contains(chord)) {
+                    conflicts.push((ACTIONS[i], ACTIONS[j], *chord));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[derive(Default)]
+pub struct Input {
+    // Stores the modifier keys currently held
+    modifiers: Modifiers,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Input::default()
+    }
+
+    pub fn chord(&mut self, event: &Event) -> Option<Chord> {
+        // Keep track of the modifier keys, returning the chord for any other key or mouse button pressed
+        if let Some(false) = event.focus_args() {
+            self.modifiers = Modifiers::default();
+        }
+        let (button, held) = match (event.press_args(), event.release_args()) {
+            (Some(button), _) => (button, true),
+            (_, Some(button)) => (button, false),
+            _ => return None,
+        };
+        match button {
+            Button::Keyboard(Key::LShift | Key::RShift) => self.modifiers.shift = held,
+            Button::Keyboard(Key::LCtrl | Key::RCtrl) => self.modifiers.ctrl = held,
+            Button::Keyboard(Key::LAlt | Key::RAlt) => self.modifiers.alt = held,
+            _ if held => {
+                return Some(Chord {
+                    modifiers: self.modifiers,
+                    button,
+                })
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+pub fn draw(
+    bindings: &Bindings,
+    rebinding: Option<Action>,
+    ui: &mut Ui,
+    frame: &mut Frame,
+) -> Option<Action> {
+    // List every action with a button showing its bindings, returning the action whose button was clicked
+    ui.panel([10.0, 10.0, 620.0, 430.0], frame);
+    let c = frame.c;
+    let style = TextStyle {
+        size: 14,
+        ..Default::default()
+    };
+    label::draw(
+        "Controls",
+        &TextStyle { size: 18, ..style },
+        20.0,
+        32.0,
+        frame.glyphs,
+        c,
+        frame.g,
+    );
+    let mut hint = String::from("Click a binding, then press the new key or mouse button");
+    if let Some(chord) = bindings.chords_for(Action::ToggleControls).first() {
+        hint += &format!(", or {} to close", chord);
+    }
+    label::draw(
+        &hint,
+        &TextStyle {
+            size: 12,
+            align: Align::Right,
+            ..style
+        },
+        620.0,
+        30.0,
+        frame.glyphs,
+        c,
+        frame.g,
+    );
+
+    // Point out actions sharing a binding, since only one of them can happen
+    let conflicts = bindings.conflicts();
+    let conflicting = |action: &Action| {
+        conflicts
+            .iter()
+            .any(|(first, second, _)| first == action || second == action)
+    };
+    if let Some((first, second, chord)) = conflicts.first() {
+        label::draw(
+            &format!(
+                "{} and {} are both bound to {}",
+                first.description(),
+                second.description(),
+                chord
+            ),
+            &TextStyle {
+                color: [0.8, 0.1, 0.1, 1.0],
+                ..style
+            },
+            20.0,
+            430.0,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+    }
+
+    let mut clicked = None;
+    let rows = ACTIONS.len().div_ceil(2);
+    for (i, (action, chords)) in ACTIONS.iter().zip(bindings.chords.iter()).enumerate() {
+        let x = 20.0 + (i / rows) as f64 * 305.0;
+        let y = 42.0 + (i % rows) as f64 * 19.0;
+        let color = if conflicting(action) {
+            [0.8, 0.1, 0.1, 1.0]
+        } else {
+            theme::palette().text
+        };
+        label::draw(
+            &action.description(),
+            &TextStyle { color, ..style },
+            x,
+            y + 14.0,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+        let caption = if rebinding == Some(*action) {
+            String::from("Press a key...")
+        } else if chords.is_empty() {
+            String::from("unbound")
+        } else {
+            let chords: Vec<String> = chords.iter().map(Chord::to_string).collect();
+            chords.join(" / ")
+        };
+        if ui.button(
+            &action.name(),
+            &caption,
+            [x + 155.0, y, 135.0, 17.0],
+            true,
+            rebinding == Some(*action),
+            frame,
+        ) {
+            clicked = Some(*action);
+        }
+    }
+    clicked
+}
+
+pub fn draw_cheat_sheet(bindings: &Bindings, c: Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    // Cover the map with every action's current bindings, grouped by category and flowing down one column after
+    // another, read straight from the bindings so rebinding an action shows up here right away
+    let palette = theme::palette();
+    rectangle(palette.panel, [10.0, 10.0, 620.0, 460.0], c.transform, g);
+    let style = TextStyle {
+        size: 11,
+        ..Default::default()
+    };
+    label::draw(
+        "Keys",
+        &TextStyle { size: 18, ..style },
+        20.0,
+        32.0,
+        glyphs,
+        c,
+        g,
+    );
+    let (mut x, mut y) = (20.0, 45.0);
+    let mut next_line = |lines: f64| {
+        if y + CHEAT_SHEET_LINE * lines > CHEAT_SHEET_BOTTOM {
+            x += 205.0;
+            y = 45.0;
+        }
+        y += CHEAT_SHEET_LINE;
+        (x, y)
+    };
+    for category in CATEGORIES {
+        // Keep each heading with at least the first action under it
+        let (heading_x, heading_y) = next_line(2.);
+        let heading = TextStyle {
+            size: 13,
+            color: palette.accent,
+            ..style
+        };
+        label::draw(category, &heading, heading_x, heading_y, glyphs, c, g);
+        for (action, chords) in ACTIONS
+            .iter()
+            .zip(bindings.chords.iter())
+            .filter(|(action, _)| action.category() == category)
+        {
+            let keys = if chords.is_empty() {
+                String::from("unbound")
+            } else {
+                let chords: Vec<String> = chords.iter().map(Chord::to_string).collect();
+                chords.join(" / ")
+            };
+            let (x, y) = next_line(1.);
+            label::draw(&action.description(), &style, x, y, glyphs, c, g);
+            label::draw(
+                &keys,
+                &TextStyle {
+                    align: Align::Right,
+                    ..style
+                },
+                x + 195.0,
+                y,
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::Point;
+
+// Number of items the player can carry at once
+pub const INVENTORY_SIZE: usize = 5;
+
+#[derive(Clone)]
+pub struct ItemType {
+    // Stores the item's name
+    pub name: String,
+    // Stores what happens when the item is used
+    pub effect: ItemEffect,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ItemEffect {
+    // Deals `damage` to every enemy within `radius` of the cursor
+    Airstrike { damage: i32, radius: f32 },
+    // Places a wall at the cursor that stops enemies for `duration` seconds
+    Wall { duration: f32 },
+    // Grants the player resources immediately
+    ResourceCache(i32),
+}
+
+#[derive(Clone)]
+pub struct Wall {
+    // Stores the wall's position on the map
+    pub position: Point,
+    // Stores the time until the wall crumbles, in seconds
+    pub time_left: f32,
+}
+
+impl Wall {
+    pub fn blocks(&self, position: &Point, heading: &Point, size: f32) -> bool {
+        // Walls only stop enemies walking into them, not ones already past them
+        let to_wall = self.position.minus(position);
+        to_wall.length() < size && to_wall.dot(heading) > 0.
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Mutex;
+
+use piston_window::*;
+
+use crate::theme;
+
+// How many pixels on the display each pixel of text covers, which text is rasterized at so it stays sharp however far
+// the game is scaled up, on high-DPI displays especially
+static SCALE: Mutex<f64> = Mutex::new(1.);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    // Stores the font size
+    pub size: u32,
+    // Stores the color of the text itself
+    pub color: [f32; 4],
+    // Stores which side of the anchor point the text lines up against
+    pub align: Align,
+    // Stores the color of a shadow drawn just below and to the right of the text, if it has one
+    pub shadow: Option<[f32; 4]>,
+    // Stores the color of an outline drawn around the text, if it has one
+    pub outline: Option<[f32; 4]>,
+    // Stores the widest a line can be before it wraps onto the next, in pixels, if it wraps
+    pub wrap_width: Option<f64>,
+    // Stores the distance between the baselines of wrapped lines, as a multiple of the font size
+    pub line_spacing: f64,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            size: 20,
+            color: theme::palette().text,
+            align: Align::Left,
+            shadow: None,
+            outline: None,
+            wrap_width: None,
+            line_spacing: 1.25,
+        }
+    }
+}
+
+pub fn set_scale(scale: f64) {
+    *SCALE.lock().unwrap_or_else(|error| error.into_inner()) = scale.max(0.1);
+}
+
+fn scale() -> f64 {
+    *SCALE.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+pub fn measure(message: &str, size: u32, glyphs: &mut Glyphs) -> f64 {
+    let scale = scale();
+    glyphs
+        .width((size as f64 * scale).round() as u32, message)
+        .unwrap_or(0.)
+        / scale
+}
+
+pub fn text(
+    color: [f32; 4],
+    size: u32,
+    message: &str,
+    glyphs: &mut Glyphs,
+    transform: math::Matrix2d,
+    g: &mut G2d,
+) {
+    // Draw a line of text with its baseline at the transform's origin, rasterized at the size it covers on the display
+    // and shrunk back down to the size asked for
+    let scale = scale();
+    piston_window::text(
+        color,
+        (size as f64 * scale).round() as u32,
+        message,
+        glyphs,
+        transform.scale(1. / scale, 1. / scale),
+        g,
+    )
+    .ok();
+}
+
+pub fn wrap(text: &str, size: u32, width: f64, glyphs: &mut Glyphs) -> Vec<String> {
+    // Break lines between words, only splitting a word if it doesn't fit on a line by itself
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if line.is_empty() || measure(&candidate, size, glyphs) <= width {
+            line = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+pub fn draw(
+    message: &str,
+    style: &TextStyle,
+    x: f64,
+    y: f64,
+    glyphs: &mut Glyphs,
+    c: Context,
+    g: &mut G2d,
+) -> f64 {
+    // Draw the text with its baseline at y, returning how far down the last line ended up
+    let lines = match style.wrap_width {
+        Some(width) => wrap(message, style.size, width, glyphs),
+        None => vec![message.to_string()],
+    };
+    let line_height = style.size as f64 * style.line_spacing;
+    for (i, line) in lines.iter().enumerate() {
+        let width = measure(line, style.size, glyphs);
+        let left = match style.align {
+            Align::Left => x,
+            Align::Center => x - width / 2.,
+            Align::Right => x - width,
+        };
+        let baseline = y + i as f64 * line_height;
+
+        // Shadows and outlines are the text drawn offset underneath it, then the text goes on top
+        let mut offsets = Vec::new();
+        if let Some(color) = style.shadow {
+            offsets.push((color, 1.5, 1.5));
+        }
+        if let Some(color) = style.outline {
+            for (dx, dy) in [(-1., 0.), (1., 0.), (0., -1.), (0., 1.)] {
+                offsets.push((color, dx, dy));
+            }
+        }
+        offsets.push((style.color, 0., 0.));
+        for (color, dx, dy) in offsets {
+            text(
+                color,
+                style.size,
+                line,
+                glyphs,
+                c.transform.trans(left + dx, baseline + dy),
+                g,
+            );
+        }
+    }
+    y + (lines.len() as f64 - 1.) * line_height
+}
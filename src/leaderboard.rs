@@ -0,0 +1,112 @@
+use std::io;
+
+use crate::notifications::{notify, Kind};
+use crate::storage;
+
+// File the player's best scores are saved to
+pub const LEADERBOARD_PATH: &str = "leaderboard.txt";
+
+pub struct LeaderboardEntry {
+    // Stores the kind of run the score was set in, such as "campaign" or a daily challenge
+    pub category: String,
+    // Stores the score the run finished with
+    pub score: i32,
+}
+
+pub struct TimeEntry {
+    // Stores the kind of run that was won, like a score's category
+    pub category: String,
+    // Stores how long the run took to win, in seconds of play
+    pub seconds: f32,
+}
+
+#[derive(Default)]
+pub struct Leaderboard {
+    // Stores every recorded score
+    pub entries: Vec<LeaderboardEntry>,
+    // Stores how long every recorded win took, for speedrunners
+    pub times: Vec<TimeEntry>,
+}
+
+impl Leaderboard {
+    pub fn load(path: &str) -> Self {
+        // Each line holds one entry as "category score", or a win's time as "time category seconds",
+        // and unreadable lines are skipped
+        let mut leaderboard = Leaderboard::default();
+        let contents = match storage::read_checked(path) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return leaderboard,
+            Err(error) => {
+                eprintln!("Couldn't load leaderboard: {}", error);
+                notify(
+                    format!("Couldn't load the leaderboard, starting it over: {}", error),
+                    Kind::Warning,
+                );
+                return leaderboard;
+            }
+        };
+        for line in contents.lines() {
+            if let Some((category, seconds)) = line
+                .strip_prefix("time ")
+                .and_then(|line| line.rsplit_once(' '))
+            {
+                if let Ok(seconds) = seconds.parse() {
+                    leaderboard.times.push(TimeEntry {
+                        category: category.to_string(),
+                        seconds,
+                    });
+                }
+            } else if let Some((category, score)) = line.rsplit_once(' ') {
+                if let Ok(score) = score.parse() {
+                    leaderboard.entries.push(LeaderboardEntry {
+                        category: category.to_string(),
+                        score,
+                    });
+                }
+            }
+        }
+        leaderboard
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{} {}\n", entry.category, entry.score))
+            .collect();
+        for entry in self.times.iter() {
+            contents += &format!("time {} {}\n", entry.category, entry.seconds);
+        }
+        storage::write_checked(path, &contents)
+    }
+
+    pub fn record(&mut self, category: &str, score: i32) {
+        self.entries.push(LeaderboardEntry {
+            category: category.to_string(),
+            score,
+        });
+    }
+
+    pub fn best(&self, category: &str) -> Option<i32> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.category == category)
+            .map(|entry| entry.score)
+            .max()
+    }
+
+    pub fn record_time(&mut self, category: &str, seconds: f32) {
+        self.times.push(TimeEntry {
+            category: category.to_string(),
+            seconds,
+        });
+    }
+
+    pub fn best_time(&self, category: &str) -> Option<f32> {
+        self.times
+            .iter()
+            .filter(|entry| entry.category == category)
+            .map(|entry| entry.seconds)
+            .min_by(f32::total_cmp)
+    }
+}
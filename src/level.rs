@@ -0,0 +1,778 @@
+use crate::convoy::ConvoySpec;
+use crate::gates::Gate;
+use crate::hazards::Hazard;
+use crate::teleporters::Teleporter;
+use crate::winds::Wind;
+use crate::{behaviors, objectives, tiled, Path, Point, TILE_SIZE};
+use std::fmt;
+use std::fs;
+
+// Version of the level format written by this build, which older files are migrated up to
+pub const LEVEL_VERSION: u32 = 4;
+// Size of the map, in tiles, for levels that don't say
+const DEFAULT_MAP_SIZE: (u32, u32) = (26, 20);
+// Lowest and highest elevation a tile can have, where 0 is level ground, below it a valley, and above it high ground
+pub const ELEVATION_RANGE: (i8, i8) = (-1, 2);
+// Seconds between the enemies of a group, or between the rows of a clump, for waves that don't say
+pub const DEFAULT_SPACING: f32 = 0.2;
+
+// How a group of enemies is arranged as it enters the map
+#[derive(Clone, Copy, PartialEq)]
+pub enum Formation {
+    // One behind the other, down the middle of the path
+    Line,
+    // Side by side in rows across the path
+    Clump,
+    // One behind the other, alternating between the sides of the path
+    Staggered,
+}
+
+impl Formation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Formation::Line => "line",
+            Formation::Clump => "clump",
+            Formation::Staggered => "staggered",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Formation> {
+        [Formation::Line, Formation::Clump, Formation::Staggered]
+            .into_iter()
+            .find(|formation| formation.name() == name)
+    }
+
+    pub fn place(&self, i: usize, spacing: f32) -> (f32, f32) {
+        // Work out when the group's i-th enemy enters the map after the group is sent, in seconds, and how far
+        // to the side of the path, in pixels
+        match self {
+            Formation::Line => (i as f32 * spacing, 0.),
+            Formation::Clump => (
+                (i / 3) as f32 * spacing,
+                (i % 3) as f32 * TILE_SIZE / 3. - TILE_SIZE / 3.,
+            ),
+            Formation::Staggered => {
+                let side = if i.is_multiple_of(2) { -1. } else { 1. };
+                (i as f32 * spacing, side * TILE_SIZE / 4.)
+            }
+        }
+    }
+}
+
+// Which level of a crossing a stretch of path runs on, where it meets another stretch of the same path
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Layer {
+    // On the ground, like most of the path
+    #[default]
+    Ground,
+    // Up on a bridge, over any path it crosses
+    Bridge,
+    // Down in a tunnel, under any path it crosses
+    Tunnel,
+}
+
+impl Layer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Ground => "ground",
+            Layer::Bridge => "bridge",
+            Layer::Tunnel => "tunnel",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Layer> {
+        [Layer::Ground, Layer::Bridge, Layer::Tunnel]
+            .into_iter()
+            .find(|layer| layer.name() == name)
+    }
+}
+
+// A change to how a whole wave plays out, announced before the wave starts so the player can prepare for it
+#[derive(Clone, PartialEq)]
+pub enum WaveModifier {
+    // Enemies move the given percentage faster
+    Faster(f32),
+    // Enemies have the given percentage more hit points
+    Tougher(f32),
+    // Every group sends the named enemy type instead of its own
+    Only(String),
+}
+
+impl WaveModifier {
+    fn parse(value: &str) -> Result<WaveModifier, String> {
+        // Modifiers are written as a name and its value, like `faster 30` or `only Imp`
+        let (name, value) = value.split_once(' ').unwrap_or((value, ""));
+        match name {
+            "faster" => Ok(WaveModifier::Faster(parse_number(value)?)),
+            "tougher" => Ok(WaveModifier::Tougher(parse_number(value)?)),
+            "only" if !value.is_empty() => Ok(WaveModifier::Only(value.to_string())),
+            _ => Err(format!("Unknown wave modifier: {} {}", name, value)),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            WaveModifier::Faster(percent) => format!("{}% faster", percent),
+            WaveModifier::Tougher(percent) => format!("{}% more hit points", percent),
+            WaveModifier::Only(enemy_type) => format!("{} only", enemy_type),
+        }
+    }
+}
+
+impl fmt::Display for WaveModifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaveModifier::Faster(percent) => write!(f, "faster {}", percent),
+            WaveModifier::Tougher(percent) => write!(f, "tougher {}", percent),
+            WaveModifier::Only(enemy_type) => write!(f, "only {}", enemy_type),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GroupSpec {
+    // Stores the name of the enemy type the group is made of
+    pub enemy_type: String,
+    // Stores how many enemies are in the group
+    pub count: usize,
+    // Stores how the group's enemies are arranged
+    pub formation: Formation,
+    // Stores the seconds between the group's enemies, or between its rows for a clump
+    pub spacing: f32,
+    // Stores the seconds into the wave before the group starts being sent
+    pub delay: f32,
+}
+
+#[derive(Clone)]
+pub struct WaveSpec {
+    // Stores the groups of enemies the wave sends at each spawn
+    pub groups: Vec<GroupSpec>,
+    // Stores the modifiers changing how the wave plays out
+    pub modifiers: Vec<WaveModifier>,
+}
+
+pub struct Group {
+    // Stores the index of the enemy type the group is made of
+    pub enemy_type: usize,
+    // Stores how many enemies are in the group
+    pub count: usize,
+    // Stores how the group's enemies are arranged
+    pub formation: Formation,
+    // Stores the seconds between the group's enemies, or between its rows for a clump
+    pub spacing: f32,
+    // Stores the seconds into the wave before the group starts being sent
+    pub delay: f32,
+}
+
+pub struct Wave {
+    // Stores the groups of enemies the wave sends at each spawn
+    pub groups: Vec<Group>,
+    // Stores the modifiers changing how the wave plays out, with any `only` already applied to the groups
+    pub modifiers: Vec<WaveModifier>,
+    // Stores whether the wave is a boss wave, which endless runs never send two of in a row
+    pub boss: bool,
+}
+
+impl Wave {
+    pub fn announcement(&self) -> Option<String> {
+        // Sum up the wave's modifiers in a line, if it has any, led by a warning for a boss wave
+        let mut parts: Vec<String> = self.modifiers.iter().map(WaveModifier::describe).collect();
+        if self.boss {
+            parts.insert(0, String::from("Boss wave"));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+#[derive(Clone)]
+pub struct TileMap {
+    // Stores the width and height of the map, in tiles
+    pub size: (u32, u32),
+    // Stores whether each tile is blocked by scenery, row by row
+    blocked: Vec<bool>,
+    // Stores each tile's elevation, row by row
+    elevation: Vec<i8>,
+    // Stores whether each tile is water, which only floating towers can be built on, row by row
+    water: Vec<bool>,
+}
+
+impl TileMap {
+    pub fn new(size: (u32, u32)) -> Self {
+        TileMap {
+            size,
+            blocked: vec![false; (size.0 * size.1) as usize],
+            elevation: vec![0; (size.0 * size.1) as usize],
+            water: vec![false; (size.0 * size.1) as usize],
+        }
+    }
+
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        // Anything off the edge of the map is left open
+        if x < 0 || y < 0 || x >= self.size.0 as i32 || y >= self.size.1 as i32 {
+            return false;
+        }
+        self.blocked[(y as u32 * self.size.0 + x as u32) as usize]
+    }
+
+    pub fn set_blocked(&mut self, x: u32, y: u32) {
+        if x < self.size.0 && y < self.size.1 {
+            self.blocked[(y * self.size.0 + x) as usize] = true;
+        }
+    }
+
+    pub fn is_water(&self, x: i32, y: i32) -> bool {
+        // Anything off the edge of the map is dry land
+        if x < 0 || y < 0 || x >= self.size.0 as i32 || y >= self.size.1 as i32 {
+            return false;
+        }
+        self.water[(y as u32 * self.size.0 + x as u32) as usize]
+    }
+
+    pub fn set_water(&mut self, x: u32, y: u32) {
+        if x < self.size.0 && y < self.size.1 {
+            self.water[(y * self.size.0 + x) as usize] = true;
+        }
+    }
+
+    pub fn water_tiles(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = self.size.0;
+        self.water
+            .iter()
+            .enumerate()
+            .filter(|(_, water)| **water)
+            .map(move |(i, _)| (i as u32 % width, i as u32 / width))
+    }
+
+    pub fn elevation(&self, x: i32, y: i32) -> i8 {
+        // Anything off the edge of the map is level ground
+        if x < 0 || y < 0 || x >= self.size.0 as i32 || y >= self.size.1 as i32 {
+            return 0;
+        }
+        self.elevation[(y as u32 * self.size.0 + x as u32) as usize]
+    }
+
+    pub fn set_elevation(&mut self, x: u32, y: u32, elevation: i8) {
+        if x < self.size.0 && y < self.size.1 {
+            self.elevation[(y * self.size.0 + x) as usize] =
+                elevation.clamp(ELEVATION_RANGE.0, ELEVATION_RANGE.1);
+        }
+    }
+
+    pub fn elevated_tiles(&self) -> impl Iterator<Item = (u32, u32, i8)> + '_ {
+        // Every tile above or below level ground, with its elevation
+        let width = self.size.0;
+        self.elevation
+            .iter()
+            .enumerate()
+            .filter(|(_, elevation)| **elevation != 0)
+            .map(move |(i, elevation)| (i as u32 % width, i as u32 / width, *elevation))
+    }
+
+    fn symbol(&self, x: u32, y: u32) -> char {
+        // How a tile is written in a row line: # for scenery, ~ for water, v for a valley, a digit for high ground, or
+        // . for level ground
+        if self.is_blocked(x as i32, y as i32) {
+            return '#';
+        }
+        if self.is_water(x as i32, y as i32) {
+            return '~';
+        }
+        match self.elevation(x as i32, y as i32) {
+            0 => '.',
+            elevation if elevation < 0 => 'v',
+            elevation => (b'0' + elevation as u8) as char,
+        }
+    }
+
+    pub fn blocked_tiles(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let width = self.size.0;
+        self.blocked
+            .iter()
+            .enumerate()
+            .filter(|(_, blocked)| **blocked)
+            .map(move |(i, _)| (i as u32 % width, i as u32 / width))
+    }
+}
+
+#[derive(Clone)]
+pub struct Level {
+    // Stores the level's name
+    pub name: String,
+    // Stores the name of whoever made the level
+    pub author: String,
+    // Stores the level's difficulty rating, from 1 up
+    pub difficulty: u32,
+    // Stores the map's size and which of its tiles are blocked
+    pub map: TileMap,
+    // Stores the waypoints of the enemies' path, in tiles
+    pub waypoints: Vec<Point>,
+    // Stores the layer of the stretch of path leading on from each waypoint, where any left out are on the ground
+    pub layers: Vec<Layer>,
+    // Stores the waypoints of the water lane boats follow, in tiles, or none if the level has no lane
+    pub lane: Vec<Point>,
+    // Stores the entry and exit tiles of each teleporter on the path
+    pub teleporters: Vec<(Point, Point)>,
+    // Stores the hazards moving about the map, with positions in tiles
+    pub hazards: Vec<Hazard>,
+    // Stores the areas where wind or conveyors push enemies along, in tiles
+    pub winds: Vec<Wind>,
+    // Stores the gates the player can close to send enemies round a detour, with positions in tiles
+    pub gates: Vec<Gate>,
+    // Stores the tiles towers can be built on, or none if they can be built on any open tile
+    pub slots: Vec<Point>,
+    // Stores the waves the level sends, in order, with later waves following the default progression
+    pub waves: Vec<WaveSpec>,
+    // Stores what the player has to do to win the level, as written in the level file
+    pub objectives: Vec<String>,
+    // Stores the optional bonus objectives that earn stars when the level is won, as written in the level file
+    pub bonus_objectives: Vec<String>,
+    // Stores the convoys the level sends out from the player's base
+    pub convoys: Vec<ConvoySpec>,
+    // Stores the behaviors the level gives tower types, as the behavior's name and the tower type's name
+    pub behaviors: Vec<(String, String)>,
+    // Stores the resources a puzzle level gives the player to stop its waves with, or none if it isn't a puzzle
+    pub puzzle: Option<i32>,
+}
+
+impl Level {
+    pub fn load(path: &str) -> Result<Level, String> {
+        // Maps made in the Tiled editor are imported, named after their file unless they say otherwise
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+        let path = std::path::Path::new(path);
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("Untitled");
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("tmx") => tiled::parse_tmx(&contents, name),
+            Some("json") => tiled::parse_json(&contents, name),
+            _ => Level::parse(&contents),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Level, String> {
+        // Blank lines and lines starting with # are ignored, and the first line must declare the version
+        let mut lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let version: u32 = match lines.next().and_then(|line| line.strip_prefix("version ")) {
+            Some(version) => parse_number(version)?,
+            None => return Err(String::from("Level is missing its version header")),
+        };
+        if version == 0 || version > LEVEL_VERSION {
+            return Err(format!(
+                "Level version {} isn't supported, the newest is {}",
+                version, LEVEL_VERSION
+            ));
+        }
+
+        let mut level = Level {
+            name: String::from("Untitled"),
+            author: String::from("Unknown"),
+            difficulty: 1,
+            map: TileMap::new(DEFAULT_MAP_SIZE),
+            waypoints: Vec::new(),
+            layers: Vec::new(),
+            lane: Vec::new(),
+            teleporters: Vec::new(),
+            hazards: Vec::new(),
+            winds: Vec::new(),
+            gates: Vec::new(),
+            slots: Vec::new(),
+            waves: Vec::new(),
+            objectives: Vec::new(),
+            bonus_objectives: Vec::new(),
+            convoys: Vec::new(),
+            behaviors: Vec::new(),
+            puzzle: None,
+        };
+        let mut rows = 0;
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "name" => level.name = value.to_string(),
+                "author" => level.author = value.to_string(),
+                "difficulty" => level.difficulty = parse_number(value)?,
+                "map" => {
+                    let (width, height) = parse_pair(value)?;
+                    level.map = TileMap::new((parse_number(width)?, parse_number(height)?));
+                    rows = 0;
+                }
+                "row" => {
+                    // Each row line lists one row of tiles from the top, with # for blocked ones, ~ for water, v for
+                    // valleys, and 1 or 2 for high ground
+                    if rows >= level.map.size.1 || value.len() > level.map.size.0 as usize {
+                        return Err(format!("Row doesn't fit on the map: {}", line));
+                    }
+                    for (x, tile) in value.chars().enumerate() {
+                        match tile {
+                            '#' => level.map.set_blocked(x as u32, rows),
+                            '~' => level.map.set_water(x as u32, rows),
+                            'v' => level.map.set_elevation(x as u32, rows, -1),
+                            '1'..='9' => {
+                                level
+                                    .map
+                                    .set_elevation(x as u32, rows, tile as i8 - b'0' as i8)
+                            }
+                            _ => {}
+                        }
+                    }
+                    rows += 1;
+                }
+                "waypoint" => {
+                    // A waypoint can end with bridge or tunnel, taking the path on from it over or under the rest
+                    let (x, rest) = parse_pair(value)?;
+                    let (y, layer) = rest.trim().split_once(' ').unwrap_or((rest, "ground"));
+                    level
+                        .waypoints
+                        .push(Point::new(parse_number(x)?, parse_number(y)?));
+                    level.layers.push(
+                        Layer::from_name(layer.trim())
+                            .ok_or_else(|| format!("Unknown path layer: {}", layer))?,
+                    );
+                }
+                "lane" => {
+                    let (x, y) = parse_pair(value)?;
+                    level
+                        .lane
+                        .push(Point::new(parse_number(x)?, parse_number(y)?));
+                }
+                "teleporter" => {
+                    let numbers = value
+                        .split_whitespace()
+                        .map(parse_number)
+                        .collect::<Result<Vec<f32>, _>>()?;
+                    let [x1, y1, x2, y2] = numbers[..] else {
+                        return Err(format!("Expected four numbers: {}", value));
+                    };
+                    level
+                        .teleporters
+                        .push((Point::new(x1, y1), Point::new(x2, y2)));
+                }
+                "hazard" => level.hazards.push(Hazard::parse(value)?),
+                "wind" => level.winds.push(Wind::parse(value)?),
+                "gate" => level.gates.push(Gate::parse(value)?),
+                "detour" => {
+                    let (x, y) = parse_pair(value)?;
+                    level
+                        .gates
+                        .last_mut()
+                        .ok_or_else(|| format!("Detour comes before any gate: {}", line))?
+                        .detour
+                        .push(Point::new(parse_number(x)?, parse_number(y)?));
+                }
+                "slot" => {
+                    let (x, y) = parse_pair(value)?;
+                    level
+                        .slots
+                        .push(Point::new(parse_number(x)?, parse_number(y)?));
+                }
+                "wave" => level.waves.push(WaveSpec {
+                    groups: vec![parse_group(value)?],
+                    modifiers: Vec::new(),
+                }),
+                "modifier" => level
+                    .waves
+                    .last_mut()
+                    .ok_or_else(|| format!("Modifier comes before any wave: {}", line))?
+                    .modifiers
+                    .push(WaveModifier::parse(value)?),
+                "objective" => {
+                    objectives::from_spec(value)?;
+                    level.objectives.push(value.to_string());
+                }
+                "bonus" => {
+                    objectives::from_spec(value)?;
+                    level.bonus_objectives.push(value.to_string());
+                }
+                "convoy" => {
+                    let (wave, count) = parse_pair(value)?;
+                    level.convoys.push(ConvoySpec {
+                        wave: parse_number(wave)?,
+                        count: parse_number(count)?,
+                    });
+                }
+                "behavior" => {
+                    let (behavior, tower_type) = value
+                        .split_once(' ')
+                        .ok_or_else(|| format!("Expected a behavior and tower type: {}", value))?;
+                    behaviors::from_name(behavior)
+                        .ok_or_else(|| format!("Unknown tower behavior: {}", behavior))?;
+                    level
+                        .behaviors
+                        .push((behavior.to_string(), tower_type.to_string()));
+                }
+                "puzzle" => level.puzzle = Some(parse_number(value)?),
+                "group" => level
+                    .waves
+                    .last_mut()
+                    .ok_or_else(|| format!("Group comes before any wave: {}", line))?
+                    .groups
+                    .push(parse_group(value)?),
+                _ => return Err(format!("Unknown level line: {}", line)),
+            }
+        }
+
+        // Bring older levels up to date one version at a time, where version 2 levels just have no blocked tiles
+        if version < 2 {
+            migrate_v1(&mut level);
+        }
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.waypoints.len() < 2 {
+            return Err(String::from("Level needs at least two waypoints"));
+        }
+        if self.lane.len() == 1 {
+            return Err(String::from("Lane needs at least two waypoints"));
+        }
+        for (kind, waypoints) in [("Path", &self.waypoints), ("Lane", &self.lane)] {
+            if let Some(pair) = waypoints
+                .windows(2)
+                .find(|pair| pair[0].x == pair[1].x && pair[0].y == pair[1].y)
+            {
+                return Err(format!(
+                    "{} repeats the waypoint {}, {}, which leaves a stretch with no length",
+                    kind, pair[0].x, pair[0].y
+                ));
+            }
+        }
+        let (width, height) = self.map.size;
+        if let Some(waypoint) = self
+            .waypoints
+            .iter()
+            .chain(self.lane.iter())
+            .find(|waypoint| {
+                waypoint.x < 0.
+                    || waypoint.y < 0.
+                    || waypoint.x > width as f32
+                    || waypoint.y > height as f32
+            })
+        {
+            return Err(format!(
+                "Waypoint {}, {} is outside the {}x{} map",
+                waypoint.x, waypoint.y, width, height
+            ));
+        }
+        self.path_teleporters()?;
+        if let Some(slot) = self.slots.iter().find(|slot| {
+            slot.x < 0. || slot.y < 0. || slot.x >= width as f32 || slot.y >= height as f32
+        }) {
+            return Err(format!(
+                "Slot {}, {} is outside the {}x{} map",
+                slot.x, slot.y, width, height
+            ));
+        }
+        let mut gates = self.gates.iter().collect::<Vec<_>>();
+        gates.sort_by_key(|gate| gate.from);
+        if gates.iter().any(|gate| gate.to >= self.waypoints.len()) {
+            return Err(String::from(
+                "Gates have to be between two of the path's waypoints",
+            ));
+        }
+        if gates.windows(2).any(|pair| pair[1].from < pair[0].to) {
+            return Err(String::from("Gates can't overlap"));
+        }
+        if self.convoys.iter().any(|convoy| convoy.wave == 0) {
+            return Err(String::from("Convoys can't leave before wave 1"));
+        }
+        if self.puzzle.is_some() && self.waves.is_empty() {
+            return Err(String::from("Puzzle levels need a wave to stop"));
+        }
+        Ok(())
+    }
+
+    pub fn path_points(&self) -> Vec<Point> {
+        // Convert the waypoints from tiles to pixels
+        self.waypoints
+            .iter()
+            .map(|waypoint| waypoint.scale(TILE_SIZE))
+            .collect()
+    }
+
+    pub fn lane_points(&self) -> Vec<Point> {
+        self.lane
+            .iter()
+            .map(|waypoint| waypoint.scale(TILE_SIZE))
+            .collect()
+    }
+
+    pub fn path_teleporters(&self) -> Result<Vec<Teleporter>, String> {
+        // Work out where along the path each teleporter is, in order, where one can't start until the last one ends
+        let path = Path::new(self.path_points());
+        let mut teleporters = self
+            .teleporters
+            .iter()
+            .map(|(entry, exit)| {
+                Teleporter::between(&path, &entry.scale(TILE_SIZE), &exit.scale(TILE_SIZE))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        teleporters.sort_by(|a, b| a.entry.total_cmp(&b.entry));
+        if teleporters
+            .windows(2)
+            .any(|pair| pair[1].entry < pair[0].exit)
+        {
+            return Err(String::from("Teleporters can't overlap"));
+        }
+        Ok(teleporters)
+    }
+
+    pub fn path_layers(&self) -> Vec<Layer> {
+        // One layer for each stretch of the path between two waypoints
+        (0..self.waypoints.len().saturating_sub(1))
+            .map(|i| self.layers.get(i).copied().unwrap_or_default())
+            .collect()
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Always write the newest version of the format
+        writeln!(f, "version {}", LEVEL_VERSION)?;
+        writeln!(f, "name {}", self.name)?;
+        writeln!(f, "author {}", self.author)?;
+        writeln!(f, "difficulty {}", self.difficulty)?;
+        writeln!(f, "map {} {}", self.map.size.0, self.map.size.1)?;
+        if self.map.blocked_tiles().next().is_some()
+            || self.map.water_tiles().next().is_some()
+            || self.map.elevated_tiles().next().is_some()
+        {
+            for y in 0..self.map.size.1 {
+                let row: String = (0..self.map.size.0)
+                    .map(|x| self.map.symbol(x, y))
+                    .collect();
+                writeln!(f, "row {}", row)?;
+            }
+        }
+        for (i, waypoint) in self.waypoints.iter().enumerate() {
+            write!(f, "waypoint {} {}", waypoint.x, waypoint.y)?;
+            match self.layers.get(i) {
+                Some(layer) if *layer != Layer::Ground => writeln!(f, " {}", layer.name())?,
+                _ => writeln!(f)?,
+            }
+        }
+        for waypoint in self.lane.iter() {
+            writeln!(f, "lane {} {}", waypoint.x, waypoint.y)?;
+        }
+        for (entry, exit) in self.teleporters.iter() {
+            writeln!(
+                f,
+                "teleporter {} {} {} {}",
+                entry.x, entry.y, exit.x, exit.y
+            )?;
+        }
+        for hazard in self.hazards.iter() {
+            writeln!(f, "hazard {}", hazard)?;
+        }
+        for wind in self.winds.iter() {
+            writeln!(f, "wind {}", wind)?;
+        }
+        for gate in self.gates.iter() {
+            writeln!(f, "gate {}", gate)?;
+            for point in gate.detour.iter() {
+                writeln!(f, "detour {} {}", point.x, point.y)?;
+            }
+        }
+        for slot in self.slots.iter() {
+            writeln!(f, "slot {} {}", slot.x, slot.y)?;
+        }
+        for wave in self.waves.iter() {
+            for (i, group) in wave.groups.iter().enumerate() {
+                // Only the options that differ from the defaults are written out
+                let key = if i == 0 { "wave" } else { "group" };
+                write!(f, "{} {} {}", key, group.enemy_type, group.count)?;
+                if group.formation != Formation::Clump {
+                    write!(f, " {}", group.formation.name())?;
+                }
+                if group.spacing != DEFAULT_SPACING {
+                    write!(f, " spacing {}", group.spacing)?;
+                }
+                if group.delay != 0. {
+                    write!(f, " delay {}", group.delay)?;
+                }
+                writeln!(f)?;
+            }
+            for modifier in wave.modifiers.iter() {
+                writeln!(f, "modifier {}", modifier)?;
+            }
+        }
+        for objective in self.objectives.iter() {
+            writeln!(f, "objective {}", objective)?;
+        }
+        for objective in self.bonus_objectives.iter() {
+            writeln!(f, "bonus {}", objective)?;
+        }
+        for convoy in self.convoys.iter() {
+            writeln!(f, "convoy {} {}", convoy.wave, convoy.count)?;
+        }
+        for (behavior, tower_type) in self.behaviors.iter() {
+            writeln!(f, "behavior {} {}", behavior, tower_type)?;
+        }
+        if let Some(resources) = self.puzzle {
+            writeln!(f, "puzzle {}", resources)?;
+        }
+        Ok(())
+    }
+}
+
+fn migrate_v1(level: &mut Level) {
+    // Version 1 levels stored waypoints in pixels, before maps were measured in tiles
+    for waypoint in level.waypoints.iter_mut() {
+        *waypoint = waypoint.scale(1. / TILE_SIZE);
+    }
+}
+
+fn parse_group(value: &str) -> Result<GroupSpec, String> {
+    // Groups are written as an enemy type and count, followed by any of a formation, `spacing <seconds>`, and
+    // `delay <seconds>`, like `Goblin 5 staggered spacing 0.5 delay 3`
+    let mut words: Vec<&str> = value.split_whitespace().collect();
+    let mut group = GroupSpec {
+        enemy_type: String::new(),
+        count: 0,
+        formation: Formation::Clump,
+        spacing: DEFAULT_SPACING,
+        delay: 0.,
+    };
+    loop {
+        match words[..] {
+            [.., _, _, "spacing", seconds] => group.spacing = parse_number(seconds)?,
+            [.., _, _, "delay", seconds] => group.delay = parse_number(seconds)?,
+            [.., _, _, name] => match Formation::from_name(name) {
+                Some(formation) => {
+                    group.formation = formation;
+                    words.pop();
+                    continue;
+                }
+                None => break,
+            },
+            _ => break,
+        }
+        words.truncate(words.len() - 2);
+    }
+    match words.split_last() {
+        Some((count, enemy_type)) if !enemy_type.is_empty() => {
+            group.count = parse_number(count)?;
+            group.enemy_type = enemy_type.join(" ");
+            Ok(group)
+        }
+        _ => Err(format!("Expected an enemy type and count: {}", value)),
+    }
+}
+
+fn parse_pair(value: &str) -> Result<(&str, &str), String> {
+    value
+        .split_once(' ')
+        .ok_or_else(|| format!("Expected two numbers: {}", value))
+}
+
+pub fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Expected a number: {}", value))
+}
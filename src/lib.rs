@@ -0,0 +1,3612 @@
+mod abilities;
+mod adaptive;
+mod analytics;
+mod assets;
+mod autosave;
+mod balance;
+mod batch;
+mod beats;
+pub mod behaviors;
+mod benchmark;
+mod binary;
+mod bot;
+mod camera;
+mod campaign;
+mod checkpoints;
+mod commands;
+mod composer;
+mod content;
+mod convoy;
+mod coverage;
+mod crash;
+mod daily;
+mod damage;
+mod debug;
+mod dialog;
+mod director;
+mod display;
+mod economy;
+mod elevation;
+mod encyclopedia;
+mod error;
+mod events;
+mod gates;
+mod ghosts;
+mod gui;
+mod hazards;
+mod hud;
+mod input;
+mod items;
+mod json;
+mod label;
+mod leaderboard;
+mod level;
+mod lighting;
+mod loading;
+mod loot;
+mod math;
+mod menu;
+mod modifiers;
+mod mutators;
+mod naval;
+mod notifications;
+mod objectives;
+mod particles;
+mod photo;
+mod pool;
+mod renderer;
+mod replay;
+mod reverse;
+mod rewind;
+mod run;
+mod saves;
+mod settings;
+mod shop;
+mod slots;
+pub mod snapshot;
+#[cfg(feature = "software-renderer")]
+mod software;
+mod spatial;
+mod splits;
+mod stats;
+mod storage;
+mod summary;
+mod telemetry;
+mod teleporters;
+mod theme;
+mod tiled;
+mod ui;
+mod winds;
+mod workshop;
+
+// What other crates can build on: levels and the games they start, tower behaviors, and snapshots of a game
+pub use abilities::{Ability, Effect};
+pub use content::{default_level, new_game};
+pub use level::{Layer, Level};
+pub use loot::{DropChance, Loot};
+pub use modifiers::Stats;
+pub use naval::Terrain;
+
+use analytics::Overlay;
+use assets::Assets;
+use autosave::AUTOSAVE_PATH;
+use batch::RectBatch;
+use beats::BeatMap;
+use behaviors::TowerBehavior;
+use bot::{GreedyBot, Strategy};
+use camera::{Mode, Pointer};
+use campaign::{CampaignSave, CAMPAIGN_SAVE_PATH, CAMPAIGN_WAVES};
+use checkpoints::Checkpoint;
+use commands::{Command, CommandQueue};
+use convoy::{ConvoySpec, ConvoyUnit};
+use daily::DailyChallenge;
+use debug::{DebugChoice, DebugStats};
+use dialog::{Confirmation, Dialog};
+use director::Director;
+use display::{DisplayMode, Layout, LAYOUT_SIZE};
+use economy::RewardCurve;
+use encyclopedia::{Encyclopedia, ENCYCLOPEDIA_PATH};
+use error::Error;
+use events::{EventLog, EventReader, GameEvent};
+use gates::{Gate, Route};
+use ghosts::Ghost;
+use gui::Gui;
+use hazards::Hazard;
+use hud::Hud;
+use input::{Action, Bindings, Input, Preset, BINDINGS_PATH};
+use items::{ItemEffect, ItemType, Wall, INVENTORY_SIZE};
+use label::{Align, TextStyle};
+use leaderboard::{Leaderboard, LEADERBOARD_PATH};
+use level::{Formation, Group, TileMap, Wave, WaveModifier, DEFAULT_SPACING};
+use loading::{Loaded, Loader};
+use loot::{LootDrop, LOOT_PICKUP_RADIUS};
+use menu::{Menu, MenuChoice};
+use mutators::Mutator;
+use naval::Lane;
+use notifications::{notify, Kind};
+use objectives::{ClearWaves, Objective};
+use particles::{Particle, Shot};
+use photo::Photo;
+use piston_window::*;
+use pool::Pool;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use renderer::{BatchedRenderer, PistonRenderer};
+use replay::Replay;
+use reverse::Attacker;
+use rewind::Rewind;
+use run::RunSetup;
+use saves::{SaveSlots, SAVE_SLOTS};
+use settings::{Settings, SettingsChoice, SETTINGS_PATH};
+use shop::{Offer, Shop};
+use spatial::SpatialIndex;
+use splits::{PersonalBests, SPLITS_PATH};
+use stats::{format_time, LifetimeStats, RunStats, STATS_PATH};
+use summary::{WaveSummary, WaveTally};
+use telemetry::{Telemetry, TELEMETRY_PATH};
+use teleporters::Teleporter;
+use ui::{Frame, Ui};
+use winds::Wind;
+use workshop::CommunityLevel;
+const CURSOR_SPEED: f32 = 3.;
+// Name of the game, shown in the window's title
+const GAME_TITLE: &str = "Tower Defense";
+// Number of fixed simulation ticks per second
+const UPDATES_PER_SECOND: u64 = 120;
+// Seconds between enemy spawns
+const SPAWN_INTERVAL: f32 = 1.;
+// Seconds each wave spends spawning enemies
+const WAVE_DURATION: f32 = 20.;
+// Seconds the player gets to build between waves
+const BUILD_DURATION: f32 = 15.;
+// Seconds before a wave starts that the player is warned it's coming
+const WAVE_WARNING_TIME: f32 = 5.;
+// Size of one map unit, in pixels
+const TILE_SIZE: f32 = 25.;
+// Number of enemies each tile of path can hold before they start slowing each other down
+const PATH_CAPACITY_PER_TILE: f32 = 2.;
+// Slowest fraction of their normal speed that congestion can reduce enemies to
+const MIN_CONGESTION_SPEED: f32 = 0.4;
+// Fraction of a tower's cost refunded when it's sold
+const SELL_REFUND: f32 = 0.5;
+// Most hits from shredding towers whose armor reduction stacks on one enemy
+const MAX_SHRED_STACKS: i32 = 5;
+// Seconds shredded armor stays stripped after the last shredding hit
+const SHRED_DURATION: f32 = 4.;
+// Number of events the combat log panel shows at once
+const COMBAT_LOG_LINES: usize = 10;
+// Font the game's text is drawn with, relative to the game's folder
+const FONT_PATH: &str = "assets/fonts/Atkinson-Hyperlegible-Regular-102.otf";
+// File fatal errors are written to, for players to send along with bug reports
+const ERROR_LOG_PATH: &str = "error.log";
+// Most frames to render per second while the window is in the background
+const BACKGROUND_FPS: u64 = 5;
+
+pub struct Game {
+    // Stores the current state of the game, including the player's resources and the enemy units on the map
+    state: GameState,
+    // Stores the list of tower types that the player can build
+    tower_types: Vec<TowerType>,
+    // Stores the list of enemy types that will appear in the game
+    enemy_types: Vec<EnemyType>,
+    // Stores the list of consumable item types the player can carry
+    item_types: Vec<ItemType>,
+    // Stores the path enemies follow from their spawn point to the player's base
+    path: Path,
+    // Stores the water lane boats follow to the player's base, if the level has one
+    lane: Option<Lane>,
+    // Stores the map's tiles, which towers can't be built on where blocked
+    map: TileMap,
+    // Stores the hazards moving about the map, which hurt the enemies they touch
+    hazards: Vec<Hazard>,
+    // Stores the areas where wind or conveyors push enemies along
+    winds: Vec<Wind>,
+    // Stores the path as the level lays it out, which the path is rebuilt from whenever a gate opens or closes
+    route: Route,
+    // Stores the gates the player can close to send enemies round a detour
+    gates: Vec<Gate>,
+    // Stores the tiles towers can be built on, or none if they can be built on any open tile
+    slots: Vec<Point>,
+    // Stores the waves the level sends before falling back to the default progression
+    waves: Vec<Wave>,
+    // Stores how enemy rewards scale as the waves go on
+    reward_curve: RewardCurve,
+    // Stores the New Game+ level of the run, or zero for a normal run
+    prestige: u32,
+    // Stores the rules of the run, as changed by any mutators
+    rules: Rules,
+    // Stores what the player has to do to win the level
+    objectives: Vec<Box<dyn Objective>>,
+    // Stores the optional objectives that earn stars if they're complete when the level is won, without costing the
+    // run anything if they fail
+    bonus_objectives: Vec<Box<dyn Objective>>,
+    // Stores the convoys the level sends out from the player's base, and the waves they leave on
+    convoys: Vec<ConvoySpec>,
+    // Stores the player's funds and send queue in reverse mode, where the player is the one sending enemies
+    attacker: Option<Attacker>,
+    // Stores the copies of the last few seconds of the run a rewind rolls back to, and the rewinds left
+    rewind: Rewind,
+    // Stores the copies of the run taken every few waves, oldest first, for a player who loses to restart from
+    checkpoints: Vec<Checkpoint>,
+}
+
+struct Rules {
+    // Stores whether the player may sell towers
+    selling_allowed: bool,
+    // Stores whether the run is a sandbox with unlimited resources, player-controlled waves, and instant recharges for
+    // tower abilities and gate levers, though not for towers' shots, so layouts are tested at their real strength
+    sandbox: bool,
+    // Stores the factor the run's score is multiplied by
+    score_multiplier: f32,
+    // Stores the names of the mutators applied to the run
+    mutators: Vec<String>,
+    // Stores whether the player sends the enemies against a defense built by the AI, instead of defending
+    reverse: bool,
+    // Stores whether damage beyond what it takes to defeat an enemy carries over to the nearest other one
+    overkill: bool,
+    // Stores the beats of the music track enemies enter on in rhythm mode, or none to send them at their own pace
+    beats: Option<BeatMap>,
+    // Stores whether enemies get tougher or weaker between waves depending on how well the player is doing
+    adaptive: bool,
+    // Stores whether the pacing director spaces a wave's spawns out or closer together depending on how busy it is
+    director: bool,
+    // Stores whether the run goes on until the player loses, with waves composed past the end of the level's own
+    endless: bool,
+    // Stores whether the run is a puzzle, with no income and the build phase lasting until the player starts the
+    // wave
+    puzzle: bool,
+    // Stores whether the player has rewound the run, which then plays on just for practice
+    rewound: bool,
+}
+
+impl Rules {
+    fn new() -> Self {
+        Rules {
+            selling_allowed: true,
+            sandbox: false,
+            score_multiplier: 1.,
+            mutators: Vec::new(),
+            reverse: false,
+            overkill: false,
+            beats: None,
+            adaptive: false,
+            director: true,
+            endless: false,
+            puzzle: false,
+            rewound: false,
+        }
+    }
+}
+
+impl Game {
+    fn new(
+        tower_types: Vec<TowerType>,
+        enemy_types: Vec<EnemyType>,
+        item_types: Vec<ItemType>,
+        path: Path,
+        map: TileMap,
+        waves: Vec<Wave>,
+        prestige: u32,
+    ) -> Self {
+        let mut state = GameState::new();
+
+        // Announce the towers the run can build, which unlocks any new ones in the encyclopedia
+        for tower_type in tower_types.iter() {
+            if tower_type.prestige_required <= prestige {
+                state.events.push(GameEvent::TowerAvailable {
+                    tower: tower_type.name.clone(),
+                });
+            }
+        }
+
+        // Enemies get tougher and faster with each New Game+ level
+        let mut enemy_types = enemy_types;
+        for enemy_type in enemy_types.iter_mut() {
+            enemy_type.max_hit_points += enemy_type.max_hit_points * prestige as i32 / 2;
+            enemy_type.speed *= 1. + 0.1 * prestige as f32;
+        }
+
+        Game {
+            state,
+            tower_types,
+            enemy_types,
+            item_types,
+            path,
+            lane: None,
+            map,
+            hazards: Vec::new(),
+            winds: Vec::new(),
+            route: Route::default(),
+            gates: Vec::new(),
+            slots: Vec::new(),
+            waves,
+            reward_curve: RewardCurve::default(),
+            prestige,
+            rules: Rules::new(),
+            objectives: vec![Box::new(ClearWaves)],
+            bonus_objectives: Vec::new(),
+            convoys: Vec::new(),
+            attacker: None,
+            rewind: Rewind::default(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    fn apply_mutator(&mut self, mutator: &dyn Mutator) {
+        mutator.apply(self);
+        self.rules.score_multiplier *= mutator.score_multiplier();
+        self.rules.mutators.push(mutator.name());
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        // Stop the simulation once the game is over
+        if self.state.outcome.is_some() {
+            return;
+        }
+        self.compose_endless_waves();
+        self.state.tick += 1;
+
+        // Update the game state, including spawning new enemies and advancing existing ones towards the player's base
+        let (building, lives) = (self.state.building, self.state.lives);
+        self.update_winds();
+        self.state.update(
+            &self.enemy_types,
+            &self.path,
+            self.lane.as_ref(),
+            &self.waves,
+            &self.rules,
+            dt,
+        );
+        if building && !self.state.building {
+            self.send_convoys();
+            self.weigh_landing();
+        }
+        self.update_convoy(dt);
+        self.update_attacker(building, lives);
+        self.update_hazards(dt);
+        self.update_gates(dt);
+
+        // Reveal stealthed enemies that are within range of a detection tower
+        let (towers, map) = (&self.state.towers, &self.map);
+        for_each_enemy(&mut self.state.enemies, self.state.parallel, |enemy| {
+            enemy.revealed = towers.iter().any(|tower| {
+                tower.tower_type.detects_stealth
+                    && map.can_hit(&tower.position, tower.stats.range, &enemy.position)
+            });
+        });
+
+        // Find each ready tower's targets, which can be done for all towers at once, where most towers hit one
+        // enemy per shot but a volley hits several, going for the enemies with the least of their route left to
+        // the base after any teleporters
+        for tower in self.state.towers.iter_mut() {
+            tower.cooldown -= dt;
+            tower.update_ability(dt);
+            tower.recent_damage *= (-dt / analytics::ANALYTICS_WINDOW).exp();
+            if let Some(behavior) = tower.tower_type.behavior {
+                behavior.on_tick(tower, dt);
+            }
+        }
+        self.resolve_tower_stats();
+        let (enemies, map) = (&self.state.enemies, &self.map);
+        let (path, lane) = (&self.path, self.lane.as_ref());
+        let find_targets = |tower: &Tower| {
+            if tower.cooldown > 0. {
+                return Vec::new();
+            }
+            let mut candidates: Vec<(f32, usize)> = enemies
+                .iter()
+                .enumerate()
+                .filter(|(_, enemy)| {
+                    enemy.is_targetable()
+                        && !(tower.tower_type.ground_only && enemy.layer == Layer::Bridge)
+                        && map.can_hit(&tower.position, tower.stats.range, &enemy.position)
+                })
+                .map(|(i, enemy)| (enemy.route(path, lane).remaining(enemy.distance), i))
+                .collect();
+            candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let candidates: Vec<usize> = candidates.into_iter().map(|(_, i)| i).collect();
+            match tower.tower_type.behavior {
+                Some(behavior) => {
+                    behavior.choose_target(tower, enemies, &candidates, tower.targets_per_shot())
+                }
+                None => candidates
+                    .into_iter()
+                    .take(tower.targets_per_shot())
+                    .collect(),
+            }
+        };
+        let targets: Vec<Vec<usize>> = if self.state.parallel {
+            self.state.towers.par_iter().map(find_targets).collect()
+        } else {
+            self.state.towers.iter().map(find_targets).collect()
+        };
+
+        // Apply damage in tower order, so the outcome doesn't depend on how the search was scheduled
+        for (tower, targets) in self.state.towers.iter_mut().zip(targets) {
+            if targets.is_empty() {
+                continue;
+            }
+            let damage = damage::shot_damage(tower);
+            let center = Point::new(TILE_SIZE / 2., TILE_SIZE / 2.);
+            for &i in targets.iter() {
+                self.state.shots.push(Shot::new(
+                    tower.position.plus(&center),
+                    self.state.enemies[i].position.plus(&center),
+                    tower.tower_type.light.unwrap_or([1.0, 0.85, 0.5, 1.0]),
+                ));
+                let dealt = damage::resolve_hit(
+                    &mut self.state.enemies,
+                    i,
+                    damage,
+                    tower,
+                    self.rules.overkill,
+                );
+                tower.recent_damage += dealt as f32;
+            }
+            // Towers reload at their usual rate even in a sandbox, where only the player's own cooldowns are instant
+            tower.cooldown = 1. / tower.stats.rate_of_fire;
+            if let Some(behavior) = tower.tower_type.behavior {
+                behavior.on_fire(tower, &mut self.state.enemies, &targets);
+            }
+        }
+
+        // Let life-stealing enemies feed on the ones that were just defeated
+        let defeated: Vec<Point> = self
+            .state
+            .enemies
+            .iter()
+            .filter(|enemy| !enemy.is_alive())
+            .map(|enemy| enemy.position)
+            .collect();
+        for position in defeated {
+            for enemy in self
+                .state
+                .enemies
+                .iter_mut()
+                .filter(|enemy| enemy.is_alive())
+            {
+                enemy.steal_life(&position);
+            }
+        }
+
+        // Defeated enemies pay out their reward and have a chance to drop loot, except in a puzzle, where the
+        // player only has what they started with
+        let mut total_reward = 0;
+        for enemy in self.state.enemies.iter().filter(|enemy| !enemy.is_alive()) {
+            let reward = if self.rules.puzzle {
+                0
+            } else {
+                self.reward_curve
+                    .reward(enemy.enemy_type.reward, self.state.wave)
+            };
+            total_reward += reward;
+            let tower = enemy.last_hit_by.and_then(|id| {
+                self.state
+                    .towers
+                    .iter_mut()
+                    .find(|tower| tower.id == id)
+                    .map(|tower| {
+                        tower.kills += 1;
+                        (tower.tower_type.name.clone(), tower.id)
+                    })
+            });
+            self.state.events.push(GameEvent::EnemyKilled {
+                enemy: enemy.enemy_type.name.clone(),
+                tower,
+                reward,
+            });
+            if let Some(loot) = loot::roll(&enemy.enemy_type.drops, &mut self.state.rng)
+                .filter(|_| !self.rules.puzzle)
+            {
+                self.state.loot.push(LootDrop::new(enemy.position, loot));
+            }
+            particles::burst(
+                &mut self.state.particles,
+                &mut self.state.particle_pool,
+                enemy.position,
+            );
+        }
+
+        // Remove defeated enemies from the game, keeping them around to be reused
+        self.state
+            .enemy_pool
+            .release_where(&mut self.state.enemies, |enemy| enemy.is_alive());
+        self.state.resources += total_reward;
+
+        // Check if the player has won or lost the game, which is lost whenever the lives run out
+        self.state.outcome = if self.state.lives <= 0 {
+            Some(Outcome::Lost)
+        } else {
+            objectives::evaluate(&self.objectives, self)
+        };
+        if self.rules.reverse {
+            self.state.outcome = self.state.outcome.map(Outcome::reversed);
+        }
+        if self.state.outcome == Some(Outcome::Won) {
+            self.state.stats.splits.push(self.state.stats.playtime);
+        }
+
+        // Let subscribers react to everything that happened since the last update
+        self.state
+            .events
+            .dispatch(&mut self.state.stats_reader, &mut self.state.stats);
+        self.state.events.dispatch(
+            &mut self.state.wave_tally_reader,
+            &mut self.state.wave_tally,
+        );
+    }
+
+    fn score(&self) -> i32 {
+        // Reward each wave survived, plus a bonus for every life left at the end
+        let waves_survived = if self.rules.endless {
+            self.state.wave as i32 - 1
+        } else {
+            self.state.wave.min(CAMPAIGN_WAVES + 1) as i32 - 1
+        };
+        let score = waves_survived * 100 + self.state.lives.max(0) * 25;
+        (score as f32 * self.rules.score_multiplier).round() as i32
+    }
+
+    fn spawn_enemy(&mut self, enemy_type: usize) {
+        // Spawn an enemy of the given type on demand, for experimenting in a sandbox
+        if let Some(enemy_type) = self.enemy_types.get(enemy_type) {
+            self.state
+                .spawn_enemy(enemy_type, &self.path, self.lane.as_ref(), 0.);
+        }
+    }
+
+    fn select_tower_type(&mut self, tower_type: usize) {
+        // Late-game tower tiers are only available in New Game+
+        if let Some(selected) = self.tower_types.get(tower_type) {
+            if selected.prestige_required <= self.prestige {
+                self.state.selected_tower_type = tower_type;
+            }
+        }
+    }
+
+    fn collect_loot(&mut self, position: Point) {
+        // Pick up the first loot drop under the given position, if any
+        let Some(i) = self.state.loot.iter().position(|drop| {
+            drop.position
+                .plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.))
+                .distance_to(&position)
+                < LOOT_PICKUP_RADIUS
+        }) else {
+            return;
+        };
+        match self.state.loot.remove(i).loot {
+            Loot::Resources(amount) => self.state.resources += amount,
+            Loot::DamageBoost {
+                multiplier,
+                duration,
+            } => {
+                self.state.set_damage_boost(multiplier);
+                self.state.damage_boost_timer = duration;
+            }
+            Loot::SpellCharge => self.state.spell_charges += 1,
+            Loot::Item(item) => {
+                if self.state.inventory.len() < INVENTORY_SIZE {
+                    self.state.inventory.push(item);
+                }
+            }
+        }
+    }
+
+    fn tower_cost(&self, tower_type: usize) -> i32 {
+        // Apply any discount the player has bought for this tower type
+        let discount = self
+            .state
+            .tower_discounts
+            .get(tower_type)
+            .copied()
+            .unwrap_or(0.);
+        (self.tower_types[tower_type].cost as f32 * (1. - discount)).round() as i32
+    }
+
+    fn spend(&mut self, amount: i32) -> bool {
+        // Take resources from the player if they can afford it, which they always can in a sandbox
+        if self.rules.sandbox {
+            return true;
+        }
+        if self.state.resources < amount {
+            notify("Not enough resources", Kind::Warning);
+            return false;
+        }
+        self.state.resources -= amount;
+        true
+    }
+
+    fn build_tower(&mut self, selected: usize, position: Point) {
+        // Place a tower of the given type at the given position, unless scenery is in the way, snapping it to the
+        // slot there on a level with slots
+        if selected >= self.tower_types.len() {
+            return;
+        }
+        let Some(position) = self.build_site(position) else {
+            return;
+        };
+        let center = position.plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.));
+        let tile = center.scale(1. / TILE_SIZE);
+        if self
+            .map
+            .is_blocked(tile.x.floor() as i32, tile.y.floor() as i32)
+        {
+            return;
+        }
+        if !self.terrain_allows(selected, &position) {
+            let warning = if self.tower_types[selected].floating {
+                "Floating towers can only be built on water"
+            } else {
+                "Only floating towers can be built on water"
+            };
+            notify(warning, Kind::Warning);
+            return;
+        }
+        if self.spend(self.tower_cost(selected)) {
+            self.state.towers_placed += 1;
+            self.state.towers.push(Tower::new(
+                self.state.towers_placed,
+                position,
+                self.tower_types[selected].clone(),
+            ));
+            self.state.events.push(GameEvent::TowerPlaced {
+                tower: self.tower_types[selected].name.clone(),
+                number: self.state.towers_placed,
+            });
+            if let Some(discount) = self.state.tower_discounts.get_mut(selected) {
+                *discount = 0.;
+            }
+        }
+    }
+
+    fn sell_tower_at(&mut self, position: &Point) {
+        // Sell the tower at the given position for part of what it cost
+        if !self.rules.selling_allowed {
+            return;
+        }
+        if let Some(i) = self.tower_at(position) {
+            let tower = self.state.towers.remove(i);
+            self.state.resources += (tower.tower_type.cost as f32 * SELL_REFUND).round() as i32;
+            self.state.stats.towers_sold += 1;
+        }
+    }
+
+    fn tower_at(&self, position: &Point) -> Option<usize> {
+        // Find the tower at the given position, if there is one
+        self.state
+            .towers
+            .iter()
+            .position(|tower| tower.position.distance_to(position) < TILE_SIZE)
+    }
+
+    fn toggle_shop(&mut self) {
+        // The shop is only open for business during the build phase, and never in a puzzle
+        if self.state.shop.open || !self.state.building || self.rules.puzzle {
+            self.state.shop.open = false;
+            return;
+        }
+        self.state.shop.restock_if_due(
+            self.state.wave,
+            self.item_types.len(),
+            self.tower_types.len(),
+            &mut self.state.rng,
+        );
+        self.state.shop.open = true;
+    }
+
+    fn buy(&mut self, offer: usize) {
+        // Buy the given offer if it's still in stock and the player can afford it
+        let Some(shop_offer) = self.state.shop.offers.get(offer) else {
+            return;
+        };
+        let (offer_kind, price) = (shop_offer.offer, shop_offer.price);
+        if shop_offer.sold
+            || matches!(offer_kind, Offer::Item(_)) && self.state.inventory.len() >= INVENTORY_SIZE
+            || !self.spend(price)
+        {
+            return;
+        }
+        match offer_kind {
+            Offer::Item(item) => self.state.inventory.push(item),
+            Offer::TowerDiscount {
+                tower_type,
+                discount,
+            } => {
+                if self.state.tower_discounts.len() <= tower_type {
+                    self.state.tower_discounts.resize(tower_type + 1, 0.);
+                }
+                self.state.tower_discounts[tower_type] = discount;
+            }
+            Offer::ExtraLife => self.state.lives += 1,
+        }
+        self.state.shop.offers[offer].sold = true;
+    }
+
+    fn use_item(&mut self, slot: usize, position: Point) {
+        // Use up the item in the given inventory slot at the given position, though sandbox items never run out
+        if slot >= self.state.inventory.len() {
+            return;
+        }
+        let item = if self.rules.sandbox {
+            &self.item_types[self.state.inventory[slot]]
+        } else {
+            &self.item_types[self.state.inventory.remove(slot)]
+        };
+        match item.effect {
+            ItemEffect::Airstrike { damage, radius } => {
+                for enemy in self.state.enemies.iter_mut() {
+                    if enemy.position.distance_to(&position) < radius {
+                        enemy.apply_damage(damage);
+                        enemy.last_hit_by = None;
+                    }
+                }
+            }
+            ItemEffect::Wall { duration } => self.state.walls.push(Wall {
+                position,
+                time_left: duration,
+            }),
+            ItemEffect::ResourceCache(amount) => self.state.resources += amount,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GameState {
+    // Stores the player's current resources
+    resources: i32,
+    // Stores the player's current number of lives
+    lives: i32,
+    // Stores the list of towers that the player has placed
+    towers: Vec<Tower>,
+    // Stores the list of enemy units on the map
+    enemies: Vec<Enemy>,
+    cursor_position: Point,
+    // Stores the index of the tower type the player will place next
+    selected_tower_type: usize,
+    // Stores the current wave number, starting from 1
+    wave: usize,
+    // Stores whether the game is in the build phase before the current wave
+    building: bool,
+    // Stores the time left in the current build phase or wave, in seconds
+    wave_timer: f32,
+    // Stores the time until the next enemy spawn, in seconds
+    spawn_timer: f32,
+    // Stores the loot dropped by defeated enemies that hasn't been collected yet
+    loot: Vec<LootDrop>,
+    // Stores the number of spell charges the player has collected
+    spell_charges: u32,
+    // Stores the multiplier applied to every tower's damage by a collected buff
+    damage_boost: f32,
+    // Stores the time until the damage buff wears off, in seconds
+    damage_boost_timer: f32,
+    // Stores a count that goes up whenever a modifier on every tower changes, so each tower knows its stats are stale
+    modifier_generation: u64,
+    // Stores the number of times a tower's stats have been resolved, for the debug panel
+    stat_resolves: usize,
+    // Stores the random number generator used for gameplay rolls such as loot drops
+    rng: ChaCha12Rng,
+    // Stores the item types, by index, of the consumables the player is carrying
+    inventory: Vec<usize>,
+    // Stores the walls the player has placed on the map
+    walls: Vec<Wall>,
+    // Stores the shop the player can visit between waves
+    shop: Shop,
+    // Stores the discount, by tower type, the player has bought for their next tower of that type
+    tower_discounts: Vec<f32>,
+    // Stores whether the player has won or lost, once the game is over
+    outcome: Option<Outcome>,
+    // Stores the statistics of the run so far
+    stats: RunStats,
+    // Stores the number of towers placed so far this run, used to number them
+    towers_placed: usize,
+    // Stores a log of recent gameplay events
+    events: EventLog,
+    // Stores how far the run's statistics have read through the event log
+    stats_reader: EventReader,
+    // Stores what's happened so far in the current wave, for the summary shown after it
+    wave_tally: WaveTally,
+    // Stores how far the wave's tally has read through the event log
+    wave_tally_reader: EventReader,
+    // Stores how far the encyclopedia has read through the event log, for the entries it unlocks
+    unlocks_reader: EventReader,
+    // Stores the names of the enemy types that have appeared on the map so far this run
+    sighted: std::collections::BTreeSet<String>,
+    // Stores whether independent per-enemy and per-tower work is spread across threads
+    parallel: bool,
+    // Stores defeated and leaked enemies, to be reused by the next ones spawned
+    enemy_pool: Pool<Enemy>,
+    // Stores the short-lived cosmetic particles on the map
+    particles: Vec<Particle>,
+    // Stores the towers' shots still flying to their targets, which are only for show
+    shots: Vec<Shot>,
+    // Stores expired particles, to be reused by the next ones spawned
+    particle_pool: Pool<Particle>,
+    // Stores the number of simulation ticks played so far this run, which replays are timed by
+    tick: u64,
+    // Stores the enemies that have been sent but are still waiting for their place in formation
+    spawn_queue: Vec<QueuedSpawn>,
+    // Stores the friendly convoy units making their way across the map
+    convoy: Vec<ConvoyUnit>,
+    // Stores the number of convoy units that made it across the map
+    convoy_arrived: u32,
+    // Stores the number of convoy units destroyed by enemies
+    convoy_lost: u32,
+    // Stores the factor adaptive difficulty scales enemies' hit points by, and their speed by less
+    difficulty: f32,
+    // Stores how the pacing director is speeding up or slowing down the current wave's spawns
+    director: Director,
+    // Stores the player's lives when the current wave started, to tell how many it's cost them
+    wave_start_lives: i32,
+}
+
+// An enemy waiting to enter the map as part of a formation
+#[derive(Clone)]
+struct QueuedSpawn {
+    // Stores the index of the enemy's type
+    enemy_type: usize,
+    // Stores the time until the enemy enters the map, in seconds
+    delay: f32,
+    // Stores how far to the side of the path the enemy enters, in pixels
+    offset: f32,
+}
+
+fn default_group(wave: usize, enemy_types: usize) -> Group {
+    // The group sent by waves past the end of the level's own, cycling through the enemy types in growing numbers
+    Group {
+        enemy_type: wave % enemy_types,
+        count: wave,
+        formation: Formation::Clump,
+        spacing: DEFAULT_SPACING,
+        delay: 0.,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Won,
+    Lost,
+}
+
+impl GameState {
+    fn new() -> Self {
+        // Initialize the game state with the player's starting resources and lives, and an empty list of towers and enemies
+        let resources = 100;
+        let lives = 10;
+        let towers = Vec::new();
+        let enemies = Vec::new();
+        let camera_position = Point::new(0., 0.);
+
+        GameState {
+            resources,
+            lives,
+            towers,
+            enemies,
+            cursor_position: camera_position,
+            selected_tower_type: 0,
+            wave: 1,
+            building: true,
+            wave_timer: BUILD_DURATION,
+            spawn_timer: 0.,
+            loot: Vec::new(),
+            spell_charges: 0,
+            damage_boost: 1.,
+            damage_boost_timer: 0.,
+            modifier_generation: 0,
+            stat_resolves: 0,
+            rng: ChaCha12Rng::from_entropy(),
+            inventory: Vec::new(),
+            walls: Vec::new(),
+            shop: Shop::new(),
+            tower_discounts: Vec::new(),
+            outcome: None,
+            stats: RunStats::default(),
+            towers_placed: 0,
+            events: EventLog::new(),
+            stats_reader: EventReader::default(),
+            wave_tally: WaveTally::default(),
+            wave_tally_reader: EventReader::default(),
+            unlocks_reader: EventReader::default(),
+            sighted: Default::default(),
+            parallel: true,
+            enemy_pool: Pool::new(),
+            particles: Vec::new(),
+            shots: Vec::new(),
+            particle_pool: Pool::new(),
+            tick: 0,
+            spawn_queue: Vec::new(),
+            convoy: Vec::new(),
+            convoy_arrived: 0,
+            convoy_lost: 0,
+            difficulty: 1.,
+            director: Director::default(),
+            wave_start_lives: lives,
+        }
+    }
+
+    fn spawn_enemy(
+        &mut self,
+        enemy_type: &EnemyType,
+        path: &Path,
+        lane: Option<&Lane>,
+        offset: f32,
+    ) {
+        // Boats and amphibious enemies set off down the water lane, or along the land path if there isn't one
+        let lane = lane.filter(|_| enemy_type.terrain != Terrain::Land);
+        let route = lane.map_or(path, |lane| &lane.path);
+
+        // Recycle a released enemy where possible, rather than allocating a new one
+        self.enemy_pool.spawn(
+            &mut self.enemies,
+            || Enemy::new(enemy_type.clone(), route),
+            |enemy| enemy.respawn(enemy_type, route),
+        );
+        if let Some(enemy) = self.enemies.last_mut() {
+            enemy.on_lane = lane.is_some();
+            enemy.step_aside(offset, route);
+        }
+        if !self.sighted.contains(&enemy_type.name) {
+            self.sighted.insert(enemy_type.name.clone());
+            self.events.push(GameEvent::EnemySighted {
+                enemy: enemy_type.name.clone(),
+            });
+        }
+    }
+
+    fn start_wave(&mut self) {
+        // End the build phase early
+        if self.building {
+            self.wave_timer = 0.;
+        }
+    }
+
+    fn update(
+        &mut self,
+        enemy_types: &[EnemyType],
+        path: &Path,
+        lane: Option<&Lane>,
+        waves: &[Wave],
+        rules: &Rules,
+        dt: f32,
+    ) {
+        self.stats.playtime += dt;
+
+        // Alternate between build phases and waves, moving on to the next wave after each one
+        // In a sandbox or a puzzle, the build phase lasts until the player starts the next wave themselves
+        if !((rules.sandbox || rules.puzzle) && self.building) {
+            let warned = self.wave_timer <= WAVE_WARNING_TIME;
+            self.wave_timer -= dt;
+            if self.building && !warned && self.wave_timer <= WAVE_WARNING_TIME {
+                notify(format!("Wave {} incoming!", self.wave), Kind::Warning);
+            }
+        }
+        if self.wave_timer <= 0. {
+            if self.building {
+                self.building = false;
+                self.shop.open = false;
+                self.wave_timer += WAVE_DURATION;
+                self.spawn_timer = 0.;
+                self.wave_start_lives = self.lives;
+                self.events.push(GameEvent::WaveStarted { wave: self.wave });
+            } else {
+                if rules.adaptive {
+                    self.adapt_difficulty();
+                }
+                self.building = true;
+                self.wave += 1;
+                self.wave_timer += BUILD_DURATION;
+                self.stats.splits.push(self.stats.playtime);
+
+                // Warn the player about anything unusual in the next wave while there's still time to build for it
+                if let Some(announcement) = waves
+                    .get(self.wave - 1)
+                    .and_then(|wave| wave.announcement())
+                {
+                    notify(
+                        format!("Wave {}: {}", self.wave, announcement),
+                        Kind::Warning,
+                    );
+                }
+            }
+        }
+
+        // Expire uncollected loot and temporary buffs
+        for drop in self.loot.iter_mut() {
+            drop.time_left -= dt;
+        }
+        self.loot.retain(|drop| drop.time_left > 0.);
+        self.damage_boost_timer -= dt;
+        if self.damage_boost_timer <= 0. {
+            self.set_damage_boost(1.);
+        }
+        for wall in self.walls.iter_mut() {
+            wall.time_left -= dt;
+        }
+        self.walls.retain(|wall| wall.time_left > 0.);
+        for particle in self.particles.iter_mut() {
+            particle.update(dt);
+        }
+        self.particle_pool
+            .release_where(&mut self.particles, |particle| particle.time_left > 0.);
+        for shot in self.shots.iter_mut() {
+            shot.time_left -= dt;
+        }
+        self.shots.retain(|shot| shot.time_left > 0.);
+
+        // Send the groups of enemies the level's waves say, or based on the current wave number once they run
+        // out, each lined up in its formation once its delay into the wave has passed, unless the player is the
+        // one sending them
+        let pace = if rules.director && !self.building {
+            self.direct_pacing(dt)
+        } else {
+            1.
+        };
+        if !self.building {
+            self.spawn_timer -= dt * pace;
+        }
+        if !self.building && !rules.reverse && self.spawn_timer <= 0. {
+            self.spawn_timer += SPAWN_INTERVAL;
+            let default_wave = [default_group(self.wave, enemy_types.len())];
+            let groups = waves
+                .get(self.wave - 1)
+                .map_or(&default_wave[..], |wave| &wave.groups);
+            let elapsed = WAVE_DURATION - self.wave_timer;
+            for group in groups.iter().filter(|group| elapsed >= group.delay) {
+                for i in 0..group.count {
+                    let (delay, offset) = group.formation.place(i, group.spacing);
+
+                    // In rhythm mode, the group enters on the beats of the track, one beat apart where its formation
+                    // would space it out
+                    let delay = match &rules.beats {
+                        Some(beats) => {
+                            let step = (delay / group.spacing.max(f32::EPSILON)).round() as usize;
+                            beats.beat_after(self.stats.playtime, step) - self.stats.playtime
+                        }
+                        None => delay,
+                    };
+                    self.spawn_queue.push(QueuedSpawn {
+                        enemy_type: group.enemy_type,
+                        delay,
+                        offset,
+                    });
+                }
+            }
+        }
+
+        // Let queued enemies onto the map as their turn comes
+        for spawn in self.spawn_queue.iter_mut() {
+            spawn.delay -= dt * pace;
+        }
+        let (due, waiting): (Vec<QueuedSpawn>, Vec<QueuedSpawn>) =
+            std::mem::take(&mut self.spawn_queue)
+                .into_iter()
+                .partition(|spawn| spawn.delay <= 0.);
+        self.spawn_queue = waiting;
+        let modifiers = waves
+            .get(self.wave - 1)
+            .filter(|_| !self.building)
+            .map_or(&[][..], |wave| &wave.modifiers);
+        for spawn in due {
+            self.spawn_enemy(&enemy_types[spawn.enemy_type], path, lane, spawn.offset);
+            if let Some(enemy) = self.enemies.last_mut() {
+                for modifier in modifiers.iter() {
+                    enemy.apply_modifier(modifier);
+                }
+                if rules.adaptive {
+                    enemy.scale_difficulty(self.difficulty);
+                }
+            }
+        }
+
+        // Remember where enemies were before this tick, so rendering can interpolate from there
+        for enemy in self.enemies.iter_mut() {
+            enemy.previous_position = enemy.position;
+        }
+
+        // Steer swarm enemies apart so they spread out along the path
+        self.steer_swarms(dt);
+
+        // Slow down enemies on crowded stretches of the path, where boats on the water lane have room to spread out
+        let index = SpatialIndex::new(
+            TILE_SIZE * 2.,
+            self.enemies.iter().map(|enemy| enemy.position),
+        );
+        let congestion = path.congestion(&index);
+        for enemy in self.enemies.iter_mut() {
+            enemy.congestion = if enemy.on_lane {
+                1.
+            } else {
+                congestion[path.segment_at(enemy.distance)]
+            };
+        }
+
+        // Advance all existing enemies towards the player's base, unless a wall is in their way or they've stopped
+        // to attack a convoy
+        let walls = &self.walls;
+        for_each_enemy(&mut self.enemies, self.parallel, |enemy| {
+            if !enemy.attacking
+                && !walls
+                    .iter()
+                    .any(|wall| wall.blocks(&enemy.position, &enemy.heading, TILE_SIZE))
+            {
+                enemy.advance(enemy.route(path, lane), dt);
+            }
+            if let Some(lane) = lane {
+                enemy.come_ashore(path, lane);
+            }
+        });
+
+        // Enemies that reach the end of the path cost the player lives, more for stronger enemies
+        for enemy in self
+            .enemies
+            .iter()
+            .filter(|enemy| enemy.distance >= enemy.route(path, lane).length())
+        {
+            self.lives -= enemy.enemy_type.lives_cost;
+            self.events.push(GameEvent::BaseDamaged {
+                enemy: enemy.enemy_type.name.clone(),
+                lives_lost: enemy.enemy_type.lives_cost,
+            });
+        }
+        self.enemy_pool.release_where(&mut self.enemies, |enemy| {
+            enemy.distance < enemy.route(path, lane).length()
+        });
+
+        // Tick passive behaviors such as regeneration
+        for_each_enemy(&mut self.enemies, self.parallel, |enemy| {
+            enemy.update_behaviors(dt)
+        });
+    }
+
+    fn steer_swarms(&mut self, dt: f32) {
+        // Calculate each swarm enemy's steering from its neighbors before applying any of it
+        let enemies = &self.enemies;
+        let steer = |(i, enemy): (usize, &Enemy)| {
+            let flocking = enemy.enemy_type.flocking?;
+            let mut separation = Point::new(0., 0.);
+            let mut average_velocity = Point::new(0., 0.);
+            let mut neighbors = 0;
+            for (j, other) in enemies.iter().enumerate() {
+                if i == j || other.enemy_type.flocking.is_none() {
+                    continue;
+                }
+                let away = enemy.position.minus(&other.position);
+                let distance = away.length();
+                if distance >= flocking.radius {
+                    continue;
+                }
+                // Enemies spawned on the same spot push apart in a direction picked from their indices
+                let away = if distance > 0.01 {
+                    away.scale(1. / distance)
+                } else {
+                    let angle = (i + j) as f32 * 2.4;
+                    let direction = enemy
+                        .heading
+                        .scale(math::cos(angle))
+                        .plus(&enemy.heading.normal().scale(math::sin(angle)));
+                    if i < j {
+                        direction
+                    } else {
+                        direction.scale(-1.)
+                    }
+                };
+                separation = separation.plus(&away.scale(1. - distance / flocking.radius));
+                average_velocity = average_velocity.plus(&other.velocity);
+                neighbors += 1;
+            }
+            if neighbors == 0 {
+                return Some(enemy.velocity.scale(1. - dt.min(1.)));
+            }
+            let alignment = average_velocity
+                .scale(1. / neighbors as f32)
+                .minus(&enemy.velocity);
+            Some(
+                enemy
+                    .velocity
+                    .plus(&separation.scale(flocking.separation * TILE_SIZE * dt))
+                    .plus(&alignment.scale((flocking.alignment * dt).min(1.))),
+            )
+        };
+        let steering: Vec<Option<Point>> = if self.parallel {
+            enemies.par_iter().enumerate().map(steer).collect()
+        } else {
+            enemies.iter().enumerate().map(steer).collect()
+        };
+
+        for (enemy, velocity) in self.enemies.iter_mut().zip(steering) {
+            if let Some(velocity) = velocity {
+                enemy.velocity = velocity;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TowerType {
+    // Stores the tower's name
+    pub name: String,
+    // Stores the tower's description in the encyclopedia
+    pub lore: &'static str,
+    // Stores the tower's cost in resources
+    pub cost: i32,
+    // Stores the tower's damage per shot
+    pub damage: i32,
+    // Stores the tower's
+    pub range: f32,
+    // Stores the tower's rate of fire, in shots per second
+    pub rate_of_fire: f32,
+    // Stores whether the tower reveals stealthed enemies within its range
+    pub detects_stealth: bool,
+    // Stores the New Game+ level needed to build the tower
+    pub prestige_required: u32,
+    // Stores the ability the player can trigger on the tower, if it has one
+    pub ability: Option<Ability>,
+    // Stores how much armor each hit strips from its target, stacking up to MAX_SHRED_STACKS hits
+    pub armor_shred: i32,
+    // Stores whether the tower can only hit enemies on the ground, and not ones up on bridges
+    pub ground_only: bool,
+    // Stores whether the tower sits on a floating platform, so it's built on water instead of land
+    pub floating: bool,
+    // Stores the behavior that changes how the tower picks targets or what its shots do, if it has one
+    pub behavior: Option<&'static dyn TowerBehavior>,
+    // Stores the color of the light a magical tower and its shots give off, if it gives off any
+    pub light: Option<[f32; 4]>,
+}
+
+#[derive(Clone)]
+pub struct Tower {
+    // Stores the tower's number, counting up from 1 in the order towers were placed
+    pub id: usize,
+    // Stores the tower's position on the map
+    pub position: Point,
+    // Stores the tower's type
+    pub tower_type: TowerType,
+    // Stores the tower's stats with every modifier on it applied, as of the last tick
+    pub stats: Stats,
+    // Stores the modifier generation the tower's stats were resolved in, or nothing once a modifier on the tower
+    // itself has changed since
+    pub stats_generation: Option<u64>,
+    // Stores the time until the tower can fire again, in seconds
+    pub cooldown: f32,
+    // Stores the time until the tower's ability can be triggered again, in seconds
+    pub ability_cooldown: f32,
+    // Stores the time left before the tower's triggered ability wears off, in seconds
+    pub ability_time_left: f32,
+    // Stores the damage the tower has done lately, fading away over ANALYTICS_WINDOW seconds
+    pub recent_damage: f32,
+    // Stores the number of enemies the tower has landed the final hit on
+    pub kills: u32,
+}
+
+impl Tower {
+    fn new(id: usize, position: Point, tower_type: TowerType) -> Self {
+        Tower {
+            id,
+            position,
+            stats: Stats::base(&tower_type),
+            stats_generation: None,
+            tower_type,
+            cooldown: 0.,
+            ability_cooldown: 0.,
+            ability_time_left: 0.,
+            recent_damage: 0.,
+            kills: 0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct EnemyType {
+    // Stores the enemy's name
+    pub name: String,
+    // Stores the enemy's description in the encyclopedia
+    pub lore: &'static str,
+    // Stores the enemy's maximum hit points
+    pub max_hit_points: i32,
+    // Stores the enemy's speed, in units per second
+    pub speed: f32,
+    // Stores the enemy's reward in resources upon defeat
+    pub reward: i32,
+    // Stores the number of lives the player loses if the enemy reaches the base
+    pub lives_cost: i32,
+    // Stores the damage taken off every hit on the enemy, down to a minimum of 1
+    pub armor: i32,
+    // Stores the hit points the enemy regenerates per second while out of combat
+    pub regeneration: f32,
+    // Stores how long the enemy must go undamaged before it starts regenerating, in seconds
+    pub regeneration_delay: f32,
+    // Stores the hit points the enemy heals when a nearby enemy dies
+    pub life_steal: i32,
+    // Stores the distance within which the enemy can feed on a dying enemy
+    pub life_steal_range: f32,
+    // Stores whether the enemy can only be targeted when revealed by a detection tower
+    pub stealth: bool,
+    // Stores how the enemy moves along the path
+    pub movement: Movement,
+    // Stores whether the enemy walks the land path, sails the water lane, or starts on the lane and comes ashore
+    pub terrain: Terrain,
+    // Stores the half-angle of the enemy's frontal shield, in radians, or zero if it has no shield
+    pub shield_angle: f32,
+    // Stores how the enemy steers relative to nearby swarm members, if it moves in a swarm
+    pub flocking: Option<Flocking>,
+    // Stores the loot the enemy may drop when defeated
+    pub drops: Vec<DropChance>,
+}
+
+impl Clone for EnemyType {
+    fn clone(&self) -> Self {
+        EnemyType {
+            name: self.name.clone(),
+            drops: self.drops.clone(),
+            ..*self
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        // Copy into the existing name and drop table, so recycled enemies don't allocate
+        let mut name = std::mem::take(&mut self.name);
+        let mut drops = std::mem::take(&mut self.drops);
+        name.clone_from(&source.name);
+        drops.clone_from(&source.drops);
+        *self = EnemyType {
+            name,
+            drops,
+            ..*source
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Flocking {
+    // Stores the distance within which swarm members influence each other
+    pub radius: f32,
+    // Stores how strongly swarm members push away from each other
+    pub separation: f32,
+    // Stores how quickly swarm members match their neighbors' velocity
+    pub alignment: f32,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Movement {
+    // Walks steadily along the path
+    #[default]
+    Walk,
+    // Walks, but every `interval` seconds jumps `distance` units further along the path
+    Blink {
+        interval: f32,
+        distance: f32,
+    },
+    // Walks, but every `interval` seconds burrows underground for `duration` seconds, where it can't be targeted
+    Burrow {
+        interval: f32,
+        duration: f32,
+    },
+}
+
+#[derive(Clone)]
+pub struct Enemy {
+    // Stores the enemy's position on the map
+    pub position: Point,
+    // Stores the enemy's position at the start of the last simulation tick
+    pub previous_position: Point,
+    // Stores how far along the path the enemy has travelled, in pixels
+    pub distance: f32,
+    // Stores the unit vector of the direction the enemy is facing
+    pub heading: Point,
+    // Stores the enemy's sideways displacement from the middle of the path
+    pub offset: f32,
+    // Stores the steering velocity added on top of the enemy's walk, used by swarms
+    pub velocity: Point,
+    // Stores the fraction of its normal speed the enemy moves at due to crowding
+    pub congestion: f32,
+    // Stores the enemy's current hit points
+    pub hit_points: i32,
+    // Stores the enemy's type
+    pub enemy_type: EnemyType,
+    // Stores the time since the enemy last took damage, in seconds
+    pub time_since_damaged: f32,
+    // Stores partially regenerated hit points that haven't added up to a whole point yet
+    pub regenerated: f32,
+    // Stores whether the enemy is within range of a detection tower
+    pub revealed: bool,
+    // Stores the time until the enemy's next blink or burrow state change, in seconds
+    pub movement_timer: f32,
+    // Stores whether the enemy is currently underground
+    pub burrowed: bool,
+    // Stores the number of the tower that last damaged the enemy, if a tower did
+    pub last_hit_by: Option<usize>,
+    // Stores whether the enemy has stopped to attack a convoy unit
+    pub attacking: bool,
+    // Stores how much of the enemy's armor has been stripped by shredding towers
+    pub armor_shred: i32,
+    // Stores the time until the shredded armor grows back, in seconds
+    pub shred_time_left: f32,
+    // Stores the layer of the path the enemy is on, where it crosses over or under itself
+    pub layer: Layer,
+    // Stores whether the enemy is following the water lane rather than the land path
+    pub on_lane: bool,
+    // Stores the push of any wind or conveyor the enemy is in, in pixels per second
+    pub wind: Point,
+}
+
+fn for_each_enemy(
+    enemies: &mut [Enemy],
+    parallel: bool,
+    update: impl Fn(&mut Enemy) + Send + Sync,
+) {
+    // Run an independent per-enemy update, spreading it across threads when allowed
+    if parallel {
+        enemies.par_iter_mut().for_each(update);
+    } else {
+        enemies.iter_mut().for_each(update);
+    }
+}
+
+impl Enemy {
+    fn new(enemy_type: EnemyType, path: &Path) -> Self {
+        let position = path.point_at(0.); // Place the enemy at the start of the map
+        let hit_points = enemy_type.max_hit_points;
+        let movement_timer = match enemy_type.movement {
+            Movement::Walk => 0.,
+            Movement::Blink { interval, .. } | Movement::Burrow { interval, .. } => interval,
+        };
+        Enemy {
+            position,
+            previous_position: position,
+            distance: 0.,
+            heading: path.heading_at(0.),
+            offset: 0.,
+            velocity: Point::new(0., 0.),
+            congestion: 1.,
+            hit_points,
+            enemy_type,
+            time_since_damaged: 0.,
+            regenerated: 0.,
+            revealed: false,
+            movement_timer,
+            burrowed: false,
+            last_hit_by: None,
+            attacking: false,
+            armor_shred: 0,
+            shred_time_left: 0.,
+            layer: path.layer_at(0.),
+            on_lane: false,
+            wind: Point::new(0., 0.),
+        }
+    }
+
+    fn step_aside(&mut self, offset: f32, path: &Path) {
+        // Move the enemy to the side of the path, keeping it within the path's width
+        self.offset = offset.clamp(-TILE_SIZE / 2., TILE_SIZE / 2.);
+        self.position = path
+            .point_at(self.distance)
+            .plus(&self.heading.normal().scale(self.offset));
+        self.previous_position = self.position;
+    }
+
+    fn apply_modifier(&mut self, modifier: &WaveModifier) {
+        // Change this enemy's copy of its type, so the change lasts as long as the enemy does
+        match modifier {
+            WaveModifier::Faster(percent) => self.enemy_type.speed *= 1. + percent / 100.,
+            WaveModifier::Tougher(percent) => {
+                let max_hit_points = self.enemy_type.max_hit_points as f32 * (1. + percent / 100.);
+                self.enemy_type.max_hit_points = max_hit_points.round() as i32;
+                self.hit_points = self.enemy_type.max_hit_points;
+            }
+            WaveModifier::Only(_) => {}
+        }
+    }
+
+    fn respawn(&mut self, enemy_type: &EnemyType, path: &Path) {
+        // Reset a released enemy into a fresh one of the given type, reusing its allocations
+        let mut recycled = std::mem::take(&mut self.enemy_type);
+        recycled.clone_from(enemy_type);
+        *self = Enemy::new(recycled, path);
+    }
+
+    fn advance(&mut self, path: &Path, dt: f32) {
+        // Move the enemy towards the player's base
+        let start = self.distance;
+        self.distance += self.enemy_type.speed * self.congestion * TILE_SIZE * dt;
+
+        // Apply the enemy's special movement behavior
+        self.movement_timer -= dt;
+        match self.enemy_type.movement {
+            Movement::Walk => {}
+            Movement::Blink { interval, distance } => {
+                if self.movement_timer <= 0. {
+                    self.movement_timer += interval;
+                    self.distance += distance * TILE_SIZE;
+                }
+            }
+            Movement::Burrow { interval, duration } => {
+                if self.movement_timer <= 0. {
+                    self.burrowed = !self.burrowed;
+                    self.movement_timer += if self.burrowed { duration } else { interval };
+                }
+            }
+        }
+
+        // Apply any steering, and the push of any wind or conveyor, keeping the enemy within the width of the path
+        let push = self.velocity.plus(&self.wind);
+        self.distance += push.dot(&self.heading) * dt;
+        self.offset = (self.offset + push.dot(&self.heading.normal()) * dt)
+            .clamp(-TILE_SIZE / 2., TILE_SIZE / 2.);
+
+        // Carry the enemy through any teleporter it reached, without drawing it sliding across the map in between
+        let teleported = path.teleport(start, self.distance);
+        let jumped = teleported != self.distance;
+        self.distance = teleported;
+
+        self.heading = path.heading_at(self.distance);
+        self.position = path
+            .point_at(self.distance)
+            .plus(&self.heading.normal().scale(self.offset));
+        self.layer = path.layer_at(self.distance);
+        if jumped {
+            self.previous_position = self.position;
+        }
+    }
+
+    fn update_behaviors(&mut self, dt: f32) {
+        // Shredded armor grows back all at once if no shredding tower has hit the enemy for a while
+        self.shred_time_left -= dt;
+        if self.shred_time_left <= 0. {
+            self.armor_shred = 0;
+        }
+
+        // Regenerate hit points once the enemy has been out of combat for long enough
+        self.time_since_damaged += dt;
+        if self.time_since_damaged < self.enemy_type.regeneration_delay {
+            return;
+        }
+        self.regenerated += self.enemy_type.regeneration * dt;
+        let whole_points = self.regenerated.floor();
+        self.regenerated -= whole_points;
+        self.heal(whole_points as i32);
+    }
+
+    fn steal_life(&mut self, dying_position: &Point) {
+        // Heal from an enemy that died close enough to feed on
+        if self.enemy_type.life_steal > 0
+            && self.position.distance_to(dying_position) < self.enemy_type.life_steal_range
+        {
+            self.heal(self.enemy_type.life_steal);
+        }
+    }
+
+    fn heal(&mut self, amount: i32) {
+        self.hit_points = (self.hit_points + amount).min(self.enemy_type.max_hit_points);
+    }
+
+    fn apply_hit(&mut self, damage: i32, source: &Point) -> bool {
+        // Hits arriving within the shield's arc in front of the enemy are blocked
+        if self.enemy_type.shield_angle > 0.
+            && self.heading.angle_to(&source.minus(&self.position)) < self.enemy_type.shield_angle
+        {
+            return false;
+        }
+        self.apply_damage(damage);
+        true
+    }
+
+    fn shred_armor(&mut self, amount: i32) {
+        // Each hit strips more armor, up to a limit, and keeps what's been stripped from growing back for longer
+        if amount <= 0 {
+            return;
+        }
+        self.armor_shred = (self.armor_shred + amount).min(amount * MAX_SHRED_STACKS);
+        self.shred_time_left = SHRED_DURATION;
+    }
+
+    fn armor(&self) -> i32 {
+        (self.enemy_type.armor - self.armor_shred).max(0)
+    }
+
+    fn apply_damage(&mut self, damage: i32) {
+        // Armor takes damage off every hit, though any hit still does at least 1
+        let damage = if damage > 0 {
+            (damage - self.armor()).max(1)
+        } else {
+            damage
+        };
+        self.hit_points -= damage;
+        self.time_since_damaged = 0.;
+    }
+
+    fn is_targetable(&self) -> bool {
+        // Nothing can hit an enemy while it's burrowed or passing through a tunnel
+        !self.burrowed && self.layer != Layer::Tunnel && (!self.enemy_type.stealth || self.revealed)
+    }
+
+    fn is_alive(&self) -> bool {
+        self.hit_points > 0
+    }
+
+    fn interpolated_position(&self, alpha: f32) -> Point {
+        // Blend between the last two simulation ticks by how far we are into the next one
+        self.previous_position
+            .plus(&self.position.minus(&self.previous_position).scale(alpha))
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    fn new(x: f32, y: f32) -> Self {
+        Point { x, y }
+    }
+
+    fn distance_to(&self, other: &Point) -> f32 {
+        // Calculate the distance between two points using the Pythagorean theorem
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+
+    fn plus(&self, other: &Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+
+    fn minus(&self, other: &Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+
+    fn scale(&self, factor: f32) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+
+    fn dot(&self, other: &Point) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    fn distance_to_segment(&self, start: &Point, end: &Point) -> f32 {
+        // Project the point onto the segment, clamped to its ends, and measure the distance to that
+        let segment = end.minus(start);
+        let length_squared = segment.dot(&segment);
+        if length_squared == 0. {
+            return self.distance_to(start);
+        }
+        let t = (self.minus(start).dot(&segment) / length_squared).clamp(0., 1.);
+        self.distance_to(&start.plus(&segment.scale(t)))
+    }
+
+    fn normal(&self) -> Point {
+        // Rotate the vector a quarter turn
+        Point::new(-self.y, self.x)
+    }
+
+    fn length(&self) -> f32 {
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
+
+    fn angle_to(&self, other: &Point) -> f32 {
+        // Calculate the unsigned angle between two vectors, in radians
+        let lengths = self.length() * other.length();
+        if lengths == 0. {
+            return 0.;
+        }
+        math::acos(self.dot(other) / lengths)
+    }
+}
+
+pub struct Path {
+    // Stores the points enemies walk through, from their spawn point to the player's base
+    waypoints: Vec<Point>,
+    // Stores the layer of each segment between two waypoints, where any left out are on the ground
+    layers: Vec<Layer>,
+    // Stores the teleporters along the path, in order
+    teleporters: Vec<Teleporter>,
+}
+
+impl Path {
+    fn new(waypoints: Vec<Point>) -> Self {
+        Path {
+            waypoints,
+            layers: Vec::new(),
+            teleporters: Vec::new(),
+        }
+    }
+
+    fn with_layers(mut self, layers: Vec<Layer>) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    fn segment_layer(&self, segment: usize) -> Layer {
+        self.layers.get(segment).copied().unwrap_or_default()
+    }
+
+    fn layer_at(&self, distance: f32) -> Layer {
+        self.segment_layer(self.segment_at(distance))
+    }
+
+    fn length(&self) -> f32 {
+        self.waypoints
+            .windows(2)
+            .map(|segment| segment[0].distance_to(&segment[1]))
+            .sum()
+    }
+
+    fn point_at(&self, distance: f32) -> Point {
+        // Walk the path's segments until we find the one containing the given distance, skipping any with no length
+        let mut remaining = distance.max(0.);
+        for segment in self.waypoints.windows(2) {
+            let length = segment[0].distance_to(&segment[1]);
+            if length > 0. && remaining <= length {
+                let t = remaining / length;
+                return Point::new(
+                    segment[0].x + (segment[1].x - segment[0].x) * t,
+                    segment[0].y + (segment[1].y - segment[0].y) * t,
+                );
+            }
+            remaining -= length;
+        }
+        self.waypoints.last().copied().unwrap_or_default()
+    }
+
+    fn segment_at(&self, distance: f32) -> usize {
+        // Find the index of the segment containing the given distance, skipping any with no length
+        let mut remaining = distance.max(0.);
+        let last = self.waypoints.len().saturating_sub(2);
+        for (i, segment) in self.waypoints.windows(2).enumerate() {
+            let length = segment[0].distance_to(&segment[1]);
+            if length > 0. && remaining <= length {
+                return i;
+            }
+            remaining -= length;
+        }
+        last
+    }
+
+    fn capacity(&self, segment: usize) -> f32 {
+        // Longer segments can hold more enemies before becoming congested
+        let length = self.waypoints[segment].distance_to(&self.waypoints[segment + 1]);
+        (length / TILE_SIZE * PATH_CAPACITY_PER_TILE).max(1.)
+    }
+
+    fn congestion(&self, index: &SpatialIndex) -> Vec<f32> {
+        // Calculate the speed multiplier of each segment from how far over capacity it is
+        self.waypoints
+            .windows(2)
+            .enumerate()
+            .map(|(i, segment)| {
+                let count = index.count_near_segment(&segment[0], &segment[1], TILE_SIZE / 2.);
+                let capacity = self.capacity(i);
+                if count as f32 <= capacity {
+                    1.
+                } else {
+                    (capacity / count as f32).max(MIN_CONGESTION_SPEED)
+                }
+            })
+            .collect()
+    }
+
+    fn heading_at(&self, distance: f32) -> Point {
+        // Find the direction of the segment containing the given distance
+        let mut remaining = distance.max(0.);
+        let mut heading = Point::new(0., 0.);
+        for segment in self.waypoints.windows(2) {
+            let length = segment[0].distance_to(&segment[1]);
+            if length > 0. {
+                let direction = segment[1].minus(&segment[0]);
+                heading = Point::new(direction.x / length, direction.y / length);
+            }
+            if remaining <= length {
+                break;
+            }
+            remaining -= length;
+        }
+        heading
+    }
+}
+
+fn on_screen(view_size: [f64; 2], position: &Point, size: f32) -> bool {
+    // Check whether a square of the given size, with its top left corner at the given position, overlaps the view
+    let (x, y) = (position.x as f64, position.y as f64);
+    x + (size as f64) >= 0. && y + (size as f64) >= 0. && x <= view_size[0] && y <= view_size[1]
+}
+
+fn window_title(level: &Level) -> String {
+    format!(
+        "{} - {} by {} (difficulty {})",
+        GAME_TITLE, level.name, level.author, level.difficulty
+    )
+}
+
+fn load_level(args: &[String]) -> Level {
+    // Play the level file given with `--level`, or the built-in one
+    match args.windows(2).find(|pair| pair[0] == "--level") {
+        Some(pair) => Level::load(&pair[1]).unwrap_or_else(|error| {
+            eprintln!("Couldn't load level {}: {}", pair[1], error);
+            content::default_level()
+        }),
+        None => content::default_level(),
+    }
+}
+
+fn load_beats(args: &[String]) -> Option<BeatMap> {
+    // Time the enemies to a music track in rhythm mode, with `--beats <file>` giving a beat map or a WAV file to find
+    // the beats in
+    let pair = args.windows(2).find(|pair| pair[0] == "--beats")?;
+    BeatMap::load(&pair[1])
+        .map_err(|error| eprintln!("Couldn't load beats {}: {}", pair[1], error))
+        .ok()
+}
+
+pub fn play() {
+    // Play the game as set up on the command line, with any tower behaviors registered before this as well as the
+    // built-in ones, which are made available before any level naming one is loaded without replacing a registered
+    // behavior of the same name
+    behaviors::register_built_in();
+
+    // Explain fatal errors in plain words, and keep a copy in a log file in case the console isn't visible, with panics
+    // written up as crash reports
+    crash::install();
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        let log = format!("Tower Defense stopped because of an error:\n{}\n", error);
+        if let Err(log_error) = std::fs::write(ERROR_LOG_PATH, log) {
+            eprintln!("Couldn't write {}: {}", ERROR_LOG_PATH, log_error);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn save_recording(commands: &mut CommandQueue, game: &Game, path: &str) {
+    // Save the replay being recorded, once, up to where the game is now
+    if let Some(mut replay) = commands.recording.take() {
+        replay.finish(game);
+        match replay.save(path) {
+            Ok(()) => println!("Saved replay to {}", path),
+            Err(error) => eprintln!("Couldn't save replay: {}", error),
+        }
+    }
+}
+
+fn run() -> Result<(), Error> {
+    // Compare the serial and parallel simulation instead of playing, with `--benchmark`
+    if std::env::args().any(|arg| arg == "--benchmark") {
+        return benchmark::run();
+    }
+    // Play many headless games with bots for balance testing instead, with the `balance` subcommand
+    if std::env::args().nth(1).as_deref() == Some("balance") {
+        return balance::run(&std::env::args().collect::<Vec<String>>());
+    }
+
+    // Load the player's settings, writing out the defaults on first launch so they can be edited, and leaving an
+    // existing file as it is until a setting changes
+    let mut settings = Settings::load(SETTINGS_PATH);
+    if !std::path::Path::new(SETTINGS_PATH).exists() {
+        if let Err(error) = settings.save(SETTINGS_PATH) {
+            eprintln!("Couldn't save settings: {}", error);
+        }
+    }
+    theme::set(settings.theme);
+
+    // The level and beats are only read here for the commands that don't open a window, and otherwise on a
+    // background thread behind the loading screen
+    let args: Vec<String> = std::env::args().collect();
+    let level = || load_level(&args);
+
+    // Share levels through files instead of playing, with `--export-level <file>` and `--import-level <file>`
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--export-level") {
+        match workshop::export(&level(), &pair[1]) {
+            Ok(checksum) => println!("Exported {} (checksum {:016x})", pair[1], checksum),
+            Err(error) => eprintln!("Couldn't export level: {}", error),
+        }
+        return Ok(());
+    }
+    // Export the personal best splits on the level for other split timers, with `--export-splits <file>`
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--export-splits") {
+        let level = level();
+        match PersonalBests::load(SPLITS_PATH).best(&level.name) {
+            Some(best) => match splits::export(&level.name, best, &pair[1]) {
+                Ok(()) => println!("Exported splits to {}", pair[1]),
+                Err(error) => eprintln!("Couldn't export splits: {}", error),
+            },
+            None => eprintln!("No personal best on {} to export yet", level.name),
+        }
+        return Ok(());
+    }
+    // Save the beats found in the track given with `--beats` as a beat map instead of playing, with
+    // `--export-beats <file>`
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--export-beats") {
+        match &load_beats(&args) {
+            Some(beats) => match std::fs::write(&pair[1], beats.to_string()) {
+                Ok(()) => println!("Exported {} beats to {}", beats.beats.len(), pair[1]),
+                Err(error) => eprintln!("Couldn't export beats: {}", error),
+            },
+            None => eprintln!("No beats to export, give a track with --beats"),
+        }
+        return Ok(());
+    }
+    // Play the level headless with the greedy bot instead, with `--simulate <runs>`
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--simulate") {
+        let runs = level::parse_number(&pair[1]).map_err(Error::Level)?;
+        return bot::simulate(&level(), runs);
+    }
+    // Draw the level's map to an image without opening a window, with `--render-map <file>`, in builds with the
+    // `software-renderer` feature
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--render-map") {
+        #[cfg(feature = "software-renderer")]
+        {
+            let level = level();
+            let game = content::new_game(&level, 0).map_err(Error::Level)?;
+            match software::render_map(&game, &pair[1]) {
+                Ok(()) => println!("Rendered {} to {}", level.name, pair[1]),
+                Err(error) => eprintln!("Couldn't render the map: {}", error),
+            }
+        }
+        #[cfg(not(feature = "software-renderer"))]
+        eprintln!(
+            "Couldn't render {}: this build doesn't have the software-renderer feature",
+            pair[1]
+        );
+        return Ok(());
+    }
+    // Draw a frame of the level being played by the greedy bot to an image, a little way into the run, with
+    // `--render-frame <file>`, in builds with the `software-renderer` feature
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--render-frame") {
+        #[cfg(feature = "software-renderer")]
+        {
+            let level = level();
+            let mut game = content::new_game(&level, 0).map_err(Error::Level)?;
+            bot::play_for(
+                &mut game,
+                &mut GreedyBot::new(),
+                software::RENDER_FRAME_TIME,
+            );
+            match software::render_frame(&game, &pair[1]) {
+                Ok(()) => println!("Rendered {} to {}", level.name, pair[1]),
+                Err(error) => eprintln!("Couldn't render the frame: {}", error),
+            }
+        }
+        #[cfg(not(feature = "software-renderer"))]
+        eprintln!(
+            "Couldn't render {}: this build doesn't have the software-renderer feature",
+            pair[1]
+        );
+        return Ok(());
+    }
+    // Play a recorded replay back headless and check it stays in step with the recording, with `--replay <file>`
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--replay") {
+        let replay = Replay::load(&pair[1]).map_err(Error::Level)?;
+
+        // Write the replay out as JSON, with `--json <file>`, to read through what it recorded
+        if let Some(json) = args.windows(2).find(|json| json[0] == "--json") {
+            match std::fs::write(&json[1], replay.to_json().to_string()) {
+                Ok(()) => println!("Wrote {} as JSON to {}", pair[1], json[1]),
+                Err(error) => eprintln!("Couldn't write {}: {}", json[1], error),
+            }
+        }
+        let dump_tick = match args.windows(2).find(|pair| pair[0] == "--dump-tick") {
+            Some(pair) => Some(level::parse_number(&pair[1]).map_err(Error::Level)?),
+            None => None,
+        };
+        let playback = replay.play(dump_tick).map_err(Error::Level)?;
+        let game = &playback.game;
+        if playback.diverged {
+            eprintln!(
+                "Replay diverged on tick {}: checksum {:016x}, recorded {:016x}",
+                game.state.tick,
+                replay::checksum(game),
+                replay.checksums[game.state.tick as usize - 1]
+            );
+        } else if dump_tick.is_none() && replay::checksum(game) != replay.checksum {
+            eprintln!(
+                "Replay diverged by the end: checksum {:016x} after {} ticks, recorded {:016x} after {}",
+                replay::checksum(game),
+                game.state.tick,
+                replay.checksum,
+                replay.ticks
+            );
+        } else {
+            println!(
+                "Replay matches after {} ticks (checksum {:016x})",
+                game.state.tick,
+                replay::checksum(game)
+            );
+        }
+
+        // Dump the state where playback stopped, with `--dump <file>`, to diff against a dump of the same tick
+        // from another machine
+        if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--dump") {
+            match std::fs::write(&pair[1], replay::dump(game)) {
+                Ok(()) => println!("Dumped tick {} to {}", game.state.tick, pair[1]),
+                Err(error) => eprintln!("Couldn't write {}: {}", pair[1], error),
+            }
+        }
+
+        // Write a snapshot of what the player would see there as JSON, with `--snapshot <file>`, for tools that
+        // show or check replays
+        if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--snapshot") {
+            match std::fs::write(&pair[1], game.snapshot().to_json().to_string()) {
+                Ok(()) => println!(
+                    "Wrote a snapshot of tick {} to {}",
+                    game.state.tick, pair[1]
+                ),
+                Err(error) => eprintln!("Couldn't write {}: {}", pair[1], error),
+            }
+        }
+        return Ok(());
+    }
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--import-level") {
+        match workshop::import(&pair[1]) {
+            Ok(imported) => println!(
+                "Imported {} by {} to {} (checksum {:016x})",
+                imported.level.name, imported.level.author, imported.path, imported.checksum
+            ),
+            Err(error) => eprintln!("Couldn't import level: {}", error),
+        }
+        return Ok(());
+    }
+
+    let mut window: PistonWindow = WindowSettings::new(GAME_TITLE, display::window_size(&settings))
+        .exit_on_esc(false)
+        .vsync(settings.vsync)
+        .build()
+        .map_err(|error| Error::Window(error.to_string()))?;
+
+    // Show the window the way the player last chose, with everything scaled to fit it
+    display::apply(&window, &settings);
+    let mut layout = Layout::fit(&window);
+    let mut monitors = display::monitor_count(&window);
+    let mut window_scale = settings.ui_scale;
+
+    // Load assets through one cache, looking in any mod directories given with `--mod <dir>` first, then the
+    // theme's own
+    let mut assets = Assets::new();
+    for pair in args.windows(2).filter(|pair| pair[0] == "--mod") {
+        assets.add_override_dir(&pair[1]);
+    }
+    assets.set_theme_dir(settings.theme.asset_dir(), &mut window);
+    assets.set_filtering(settings.texture_filter, &mut window);
+
+    // Read the font, the level, and the beats, and decode the save slots' pictures on background threads, behind a
+    // loading screen, then turn the font and pictures into textures, which only the main thread can do. Closing the
+    // window while they load quits
+    let mut saves = SaveSlots::load();
+    let mut loader = Loader::new();
+    let level_args = args.clone();
+    loader.spawn(move || Loaded::Level(Box::new(load_level(&level_args))));
+    let beat_args = args.clone();
+    loader.spawn(move || Loaded::Beats(load_beats(&beat_args)));
+    let font_path = assets.find(FONT_PATH);
+    loader.spawn(move || {
+        let font = assets::read_font(&font_path);
+        Loaded::Font(FONT_PATH.to_string(), font_path, font)
+    });
+    for slot in 0..SAVE_SLOTS {
+        loader.spawn(move || Loaded::Thumbnail(slot, saves::read_thumbnail(slot)));
+    }
+    let Some(loaded) = loader.finish(&mut window) else {
+        return Ok(());
+    };
+    let (mut level, mut beats) = (None, None);
+    for asset in loaded {
+        match asset {
+            Loaded::Level(loaded) => level = Some(*loaded),
+            Loaded::Beats(loaded) => beats = Some(loaded),
+            Loaded::Font(relative, path, font) => {
+                assets.add_font(&mut window, &relative, path, font)?;
+            }
+            Loaded::Thumbnail(slot, picture) => {
+                saves.set_thumbnail(slot, picture.as_ref(), &mut window)
+            }
+        }
+    }
+    let font = assets.font(&mut window, FONT_PATH)?;
+    let level = level.unwrap_or_else(|| load_level(&args));
+    let beats = beats.unwrap_or_else(|| load_beats(&args));
+    window.set_title(window_title(&level));
+
+    // Start in New Game+ at the highest level the player has unlocked, unless playing the daily challenge
+    let mut campaign = CampaignSave::load(CAMPAIGN_SAVE_PATH);
+    let mut leaderboard = Leaderboard::load(LEADERBOARD_PATH);
+    let mut lifetime_stats = LifetimeStats::load(STATS_PATH);
+    let mut telemetry = Telemetry::load(TELEMETRY_PATH);
+    let mut personal_bests = PersonalBests::load(SPLITS_PATH);
+    let speedrun = args.iter().any(|arg| arg == "--speedrun");
+    let mut showing_stats = false;
+    let mut encyclopedia = Encyclopedia::load(ENCYCLOPEDIA_PATH);
+    let mut wave_summary: Option<WaveSummary> = None;
+    let mut showing_cheat_sheet = false;
+    let mut showing_debug = false;
+    let mut showing_settings = false;
+    let mut overlay = Overlay::default();
+    let mut showing_controls = false;
+    let mut paused = false;
+    let mut community_levels: Option<Vec<CommunityLevel>> = None;
+    let mut showing_combat_log = false;
+    let mut combat_log_scroll = 0;
+    let daily = args
+        .iter()
+        .any(|arg| arg == "--daily")
+        .then(DailyChallenge::today);
+    let mut setup = RunSetup {
+        level,
+        prestige: if daily.is_some() {
+            0
+        } else {
+            campaign.completions
+        },
+        daily: daily.map(|daily| daily.day),
+        mutators: args
+            .windows(2)
+            .filter(|pair| pair[0] == "--mutator")
+            .map(|pair| pair[1].clone())
+            .collect(),
+        sandbox: args.iter().any(|arg| arg == "--sandbox"),
+        reverse: args.iter().any(|arg| arg == "--reverse"),
+        beats,
+    };
+    let mut game = match setup.start() {
+        Ok(game) => game,
+        Err(error) => {
+            eprintln!("Couldn't start level {}: {}", setup.level.name, error);
+            setup.level = content::default_level();
+            setup.start().map_err(Error::Level)?
+        }
+    };
+    let mut category = setup.category();
+    let mut sandbox_enemy_type = 0;
+
+    // Record everything the player does to a replay, with `--record <file>`, starting from a known seed
+    let mut commands = CommandQueue::new();
+    let recording_path = args
+        .windows(2)
+        .find(|pair| pair[0] == "--record")
+        .map(|pair| pair[1].clone());
+    if recording_path.is_some() {
+        let replay = Replay::new(setup.clone(), rand::random());
+        game = replay.start().map_err(Error::Level)?;
+        commands.recording = Some(replay);
+    }
+
+    // Let the greedy bot play by itself as a demo, with `--demo`
+    let mut demo_bot = args.iter().any(|arg| arg == "--demo").then(GreedyBot::new);
+
+    // Show where the towers went in the best previous run of the level, and keep the builds of this one in case it
+    // does better, which only counts for runs that go on the leaderboard
+    let mut ghost = Ghost::load(&setup.level.name);
+    if !setup.sandbox && !setup.reverse && demo_bot.is_none() {
+        commands.ghost = Some(Replay::new(setup.clone(), 0));
+    }
+
+    // Start on the main menu, which plays a demo behind itself when left alone, unless the bot's already playing
+    let mut menu = Menu::new(demo_bot.is_none());
+
+    // Offer to pick up a run that was interrupted by a crash or by closing the window, from a backup if the autosave is damaged
+    let (mut interrupted_run, mut autosave_error) =
+        if setup.sandbox || setup.reverse || demo_bot.is_some() || recording_path.is_some() {
+            (None, None)
+        } else {
+            match autosave::load(AUTOSAVE_PATH) {
+                Ok(run) => (run, None),
+                Err(error) => (autosave::load_backup(AUTOSAVE_PATH), Some(error)),
+            }
+        };
+
+    // Simulate in fixed ticks, and interpolate between them when rendering
+    window.set_ups(UPDATES_PER_SECOND);
+    window.set_max_fps(settings.fps_cap());
+
+    // Large waves are drawn as one batch rather than a draw call per enemy
+    let mut enemy_batch = RectBatch::new();
+    let mut hud = Hud::new();
+    let mut ui = Ui::new();
+    // The settings and debug panels are laid out by egui, with the enemies drawn last frame counted for the latter
+    let mut gui = Gui::new();
+    let mut enemies_drawn = 0;
+    let mut input = Input::new();
+    let mut bindings = Bindings::load(BINDINGS_PATH, settings.controls);
+    let mut rebinding = None;
+    let mut settings_changed = false;
+
+    let mut mouse_position = Point::new(0., 0.);
+    let mut pointer = Pointer::new();
+    pointer.set_integer_scaling(settings.integer_scaling);
+    let mut photo: Option<Photo> = None;
+    let mut dialog: Option<Dialog> = None;
+    // Offer to show the report of a crash last time, once
+    let crash_report = crash::unseen_report();
+    if crash_report.is_some() {
+        dialog = Some(Dialog::new(Confirmation::ViewCrashReport));
+    }
+    let mut dialog_answer = None;
+    let mut retrying = false;
+    let mut restarting_from = None;
+    while let Some(event) = window.next() {
+        // Lay everything out afresh whenever the window changes size, and read the mouse in the layout's pixels
+        if event.resize_args().is_some() {
+            layout = Layout::fit(&window);
+        }
+        let event = layout.map_event(event);
+
+        // Carry out or drop the action waiting on the confirmation dialog once the player answers it, from the
+        // keyboard or by clicking one of its buttons last frame
+        let key_answer = dialog.as_ref().and_then(|dialog| dialog.handle(&event));
+        if let (Some(answer), Some(open)) = (dialog_answer.take().or(key_answer), dialog.take()) {
+            match open.confirmation {
+                _ if !answer => {}
+                Confirmation::Sell(position) => {
+                    let defer = paused && settings.queue_while_paused;
+                    commands.issue(Command::Sell { position }, &mut game, defer);
+                }
+                Confirmation::Quit => window.set_should_close(true),
+                Confirmation::DiscardRun => {
+                    if let Some(run) = interrupted_run.take() {
+                        // The discarded run still counts towards kills and playtime, like any abandoned run
+                        lifetime_stats.add_run(&run.game.state.stats);
+                        if let Err(error) = lifetime_stats.save(STATS_PATH) {
+                            eprintln!("Couldn't save statistics: {}", error);
+                        }
+                        autosave::discard(AUTOSAVE_PATH);
+                    }
+                    autosave_error = None;
+                }
+                Confirmation::OverwriteSlot(slot) => saves.saving = Some(slot),
+                Confirmation::DeleteSlot(slot) => saves.delete(slot),
+                Confirmation::LoadSlot(slot) => saves.loading = Some(slot),
+                Confirmation::ViewCrashReport => {
+                    if let Some(path) = &crash_report {
+                        crash::open(path);
+                    }
+                }
+            }
+        }
+        if key_answer.is_some() {
+            continue;
+        }
+
+        // Type the new name of a save slot being renamed, before any key can trigger an action
+        if saves.handle_renaming(&event) {
+            continue;
+        }
+
+        // Escape quits, checking with the player first when it would leave a run in progress
+        if let Some(Button::Keyboard(Key::Escape)) = event.press_args() {
+            let running =
+                !menu.open && game.state.outcome.is_none() && game.state.stats.playtime > 0.;
+            if running {
+                dialog = Some(Dialog::new(Confirmation::Quit));
+            } else {
+                window.set_should_close(true);
+            }
+            continue;
+        }
+
+        // Ask whether to resume the interrupted run, or explain why it can't be, before anything else happens
+        if interrupted_run.is_some() || autosave_error.is_some() {
+            ui.handle(&event);
+            match (event.press_args(), interrupted_run.take()) {
+                (Some(Button::Keyboard(Key::Y)), Some(run)) => {
+                    if let Some(warning) = run.warning {
+                        notify(warning, Kind::Warning);
+                    }
+                    window.set_title(window_title(&run.setup.level));
+                    setup = run.setup;
+                    game = run.game;
+                    category = setup.category();
+                    ghost = Ghost::load(&setup.level.name);
+                    commands.ghost = None;
+                    autosave_error = None;
+                    menu.open = false;
+                }
+                (Some(Button::Keyboard(Key::N)), Some(run)) => {
+                    interrupted_run = Some(run);
+                    dialog = Some(Dialog::new(Confirmation::DiscardRun));
+                }
+                (Some(Button::Keyboard(_)), None) => autosave_error = None,
+                (_, run) => {
+                    let mut lines = Vec::new();
+                    if let Some(error) = &autosave_error {
+                        lines.push(String::from("Your autosave couldn't be loaded:"));
+                        lines.push(error.clone());
+                    }
+                    match &run {
+                        Some(run) => {
+                            lines.push(if autosave_error.is_some() {
+                                String::from("Resume your run from a backup instead?")
+                            } else {
+                                String::from("Resume your interrupted run?")
+                            });
+                            lines.push(format!(
+                                "{} - wave {}, {} lives, {} resources",
+                                run.setup.level.name,
+                                run.game.state.wave,
+                                run.game.state.lives,
+                                run.game.state.resources
+                            ));
+                            if let Some(warning) = &run.warning {
+                                lines.push(format!("{}.", warning));
+                            }
+                            lines.push(String::from("Press Y to resume, or N to start a new run"));
+                        }
+                        None => lines.push(String::from("Press any key to start a new run")),
+                    }
+                    window.draw_2d(&event, |c, g, device| {
+                        let glyphs = &mut *font.borrow_mut();
+                        clear(theme::palette().background, g);
+                        let style = TextStyle {
+                            size: 16,
+                            wrap_width: Some(560.0),
+                            ..Default::default()
+                        };
+                        let mut y = 160.0;
+                        for line in lines.iter() {
+                            y = label::draw(line, &style, 40.0, y, glyphs, c, g) + 30.0;
+                        }
+                        if let Some(dialog) = &dialog {
+                            let mut frame = Frame {
+                                c,
+                                g: &mut *g,
+                                glyphs: &mut *glyphs,
+                            };
+                            dialog_answer = dialog.draw(&mut ui, &mut frame);
+                        }
+                        ui.end_frame();
+                        glyphs.factory.encoder.flush(device);
+                    });
+                    interrupted_run = run;
+                }
+            }
+            continue;
+        }
+
+        // While the window is in the background, draw only a few frames a second to save battery, and pause a run in
+        // progress unless the settings say otherwise
+        if let Some(focused) = event.focus_args() {
+            if focused {
+                window.set_max_fps(settings.fps_cap());
+            } else {
+                window.set_max_fps(BACKGROUND_FPS);
+                let running = !menu.open && !paused && game.state.outcome.is_none();
+                if settings.pause_on_focus_loss && running {
+                    paused = true;
+                    notify("Paused while the window was in the background", Kind::Info);
+                }
+            }
+        }
+
+        if let Some([x, y]) = event.mouse_cursor_args() {
+            mouse_position = Point::new(x as f32, y as f32);
+            let (width, height) = game.map.size;
+            pointer.drag_to(
+                &mouse_position,
+                &Point::new(width as f32 * TILE_SIZE, height as f32 * TILE_SIZE),
+                &Point::new(LAYOUT_SIZE[0] as f32, LAYOUT_SIZE[1] as f32),
+            );
+        }
+        // The mouse wheel zooms in photo mode, around the point under the mouse
+        if let (Some(_), Some([_, notches])) = (&photo, event.mouse_scroll_args()) {
+            Photo::zoom(&mut pointer, &mouse_position, notches);
+        }
+        // Any input on the main menu stops the demo playing behind it, without doing anything else
+        if menu.open && menu.handle(&event) {
+            continue;
+        }
+        // Clicks and typing taken by the egui panels reach nothing behind them, and they can't be used while the
+        // confirmation dialog is waiting for an answer
+        if dialog.is_none() && gui.handle(&event) {
+            continue;
+        }
+        // Clicks on menus and panels don't reach the map behind them, and nothing reaches the game until the
+        // main menu is closed
+        let clicked_ui = ui.handle(&event);
+
+        // Dragging with the middle mouse button, or the one that cancels, pans the camera, and just clicking the one
+        // that cancels stops placing or selecting, where the buttons are swapped for left-handed players, and in photo
+        // mode the left button pans too
+        let cancel = if settings.controls == Preset::LeftHanded || photo.is_some() {
+            MouseButton::Left
+        } else {
+            MouseButton::Right
+        };
+        if !menu.open
+            && dialog.is_none()
+            && pointer.handle(&event, &mouse_position, cancel, clicked_ui)
+        {
+            continue;
+        }
+        let world_mouse = pointer.to_world(&mouse_position);
+        let chord = input
+            .chord(&event)
+            .filter(|chord| !(clicked_ui && matches!(chord.button, Button::Mouse(_))))
+            .filter(|_| !menu.open && dialog.is_none());
+        if let (Some(action), Some(chord)) = (rebinding, chord) {
+            // While rebinding an action, the next key or mouse button pressed becomes its binding
+            rebinding = None;
+            bindings.bind(action, chord);
+            if let Err(error) = bindings.save(BINDINGS_PATH) {
+                eprintln!("Couldn't save bindings: {}", error);
+            }
+        } else if let Some(action) = chord
+            .and_then(|chord| bindings.action(&chord))
+            .filter(|action| photo.is_none() || *action == Action::PhotoMode)
+        {
+            // Building and selling wait until the game is unpaused, unless the settings say otherwise
+            let defer = paused && settings.queue_while_paused;
+            match action {
+                Action::MoveUp => game.state.cursor_position.y -= CURSOR_SPEED,
+                Action::MoveLeft => game.state.cursor_position.x -= CURSOR_SPEED,
+                Action::MoveDown => game.state.cursor_position.y += CURSOR_SPEED,
+                Action::MoveRight => game.state.cursor_position.x += CURSOR_SPEED,
+                Action::Choose(i) if game.state.shop.open => {
+                    commands.issue(Command::Buy(i), &mut game, defer)
+                }
+                Action::Choose(i) if game.rules.reverse => {
+                    commands.issue(Command::QueueEnemy(i), &mut game, defer)
+                }
+                Action::Choose(i) => {
+                    pointer.mode = Mode::Placing(i);
+                    commands.issue(Command::SelectTowerType(i), &mut game, defer)
+                }
+                Action::ToggleShop => commands.issue(Command::ToggleShop, &mut game, defer),
+                Action::UseItem(slot) => commands.issue(
+                    Command::UseItem {
+                        slot,
+                        position: game.state.cursor_position,
+                    },
+                    &mut game,
+                    defer,
+                ),
+                Action::PlaceTower => commands.issue(
+                    Command::Build {
+                        tower_type: game.state.selected_tower_type,
+                        position: game.state.cursor_position,
+                    },
+                    &mut game,
+                    defer,
+                ),
+                Action::PlaceTowerAtMouse => commands.issue(
+                    Command::Build {
+                        tower_type: game.state.selected_tower_type,
+                        position: world_mouse.minus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.)),
+                    },
+                    &mut game,
+                    defer,
+                ),
+                Action::SellTower => {
+                    let position = game.state.cursor_position;
+                    if game.sale_needs_confirming(&position) {
+                        dialog = Some(Dialog::new(Confirmation::Sell(position)));
+                    } else {
+                        commands.issue(Command::Sell { position }, &mut game, defer);
+                    }
+                }
+                Action::UseAbility => commands.issue(
+                    Command::UseAbility {
+                        position: game.state.cursor_position,
+                    },
+                    &mut game,
+                    defer,
+                ),
+                Action::Undo => commands.undo(&mut game),
+                Action::Redo => commands.redo(&mut game),
+                Action::CollectLoot => {
+                    // Clicking a gate's lever pulls it, clicking while placing builds the tower, clicking a tower
+                    // selects it, and clicking anywhere else picks up loot
+                    let corner = world_mouse.minus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.));
+                    let command = match (game.lever_at(&world_mouse), pointer.mode) {
+                        (Some(gate), _) => Command::PullLever(gate),
+                        (None, Mode::Placing(tower_type)) => {
+                            pointer.mode = Mode::Idle;
+                            Command::Build {
+                                tower_type,
+                                position: corner,
+                            }
+                        }
+                        _ => {
+                            if let Some(tower) = game
+                                .tower_at(&corner)
+                                .map(|i| &game.state.towers[i])
+                                .filter(|_| !game.rules.reverse)
+                            {
+                                pointer.mode = Mode::Selecting(tower.id);
+                                game.state.cursor_position = tower.position;
+                            }
+                            Command::CollectLoot {
+                                position: world_mouse,
+                            }
+                        }
+                    };
+                    commands.issue(command, &mut game, defer)
+                }
+                Action::Pause => {
+                    paused = !paused;
+                    if !paused {
+                        commands.flush(&mut game);
+                    }
+                }
+                Action::ToggleStats => showing_stats = !showing_stats,
+                Action::ToggleCheatSheet => showing_cheat_sheet = !showing_cheat_sheet,
+                Action::ToggleSaves => saves.open = !saves.open,
+                Action::ToggleEncyclopedia => encyclopedia.toggle(),
+                Action::ToggleDebug => showing_debug = !showing_debug,
+                Action::ToggleSettings => showing_settings = !showing_settings,
+                Action::ToggleControls => showing_controls = !showing_controls,
+                Action::ReloadAssets => {
+                    let reloaded = assets.reload_changed(&mut window);
+                    notify(format!("Reloaded {} changed assets", reloaded), Kind::Info);
+                }
+                Action::ToggleCommunity => {
+                    // Rescan the community levels each time the browser opens
+                    community_levels = match community_levels {
+                        Some(_) => None,
+                        None => Some(workshop::browse()),
+                    }
+                }
+                Action::ToggleCombatLog => showing_combat_log = !showing_combat_log,
+                Action::ToggleOverlay => {
+                    overlay = overlay.next();
+                    notify(
+                        format!("Analytics overlay: {}", overlay.description()),
+                        Kind::Info,
+                    );
+                }
+                Action::Retry if game.rules.puzzle => retrying = true,
+                // Rewinding would leave a recording out of step with the run, and a finished run has already been
+                // counted
+                Action::Rewind
+                    if commands.recording.is_none()
+                        && demo_bot.is_none()
+                        && !game.rules.reverse
+                        && game.state.outcome.is_none() =>
+                {
+                    let tick = game.state.tick;
+                    match game.rewind() {
+                        Ok(()) => {
+                            // The undo history and the ghost's record of the run no longer match it
+                            commands = CommandQueue::new();
+                            wave_summary = None;
+                            let seconds = (tick - game.state.tick) / UPDATES_PER_SECOND;
+                            notify(
+                                format!(
+                                    "Rewound {} seconds, {} rewinds left, and the run is now just for practice",
+                                    seconds, game.rewind.charges
+                                ),
+                                Kind::Info,
+                            );
+                        }
+                        Err(error) => notify(error, Kind::Warning),
+                    }
+                }
+                Action::ToggleGhost => {
+                    settings.ghost = !settings.ghost;
+                    settings_changed = true;
+                    let message = match (settings.ghost, &ghost) {
+                        (false, _) => "Ghost of best run: off",
+                        (true, Some(_)) => "Ghost of best run: on",
+                        (true, None) => "Ghost of best run: on, once this level has been finished",
+                    };
+                    notify(message, Kind::Info);
+                }
+                Action::ScrollLogUp if showing_combat_log => {
+                    combat_log_scroll = (combat_log_scroll + COMBAT_LOG_LINES)
+                        .min(game.state.events.len().saturating_sub(COMBAT_LOG_LINES))
+                }
+                Action::ScrollLogDown if showing_combat_log => {
+                    combat_log_scroll = combat_log_scroll.saturating_sub(COMBAT_LOG_LINES)
+                }
+                Action::PreviousEnemy if game.rules.sandbox => {
+                    sandbox_enemy_type =
+                        (sandbox_enemy_type + game.enemy_types.len() - 1) % game.enemy_types.len()
+                }
+                Action::NextEnemy if game.rules.sandbox => {
+                    sandbox_enemy_type = (sandbox_enemy_type + 1) % game.enemy_types.len()
+                }
+                Action::SpawnEnemy if game.rules.sandbox => {
+                    commands.issue(Command::SpawnEnemy(sandbox_enemy_type), &mut game, defer)
+                }
+                Action::StartWave if game.rules.sandbox || game.rules.puzzle => {
+                    commands.issue(Command::StartWave, &mut game, defer)
+                }
+                Action::PhotoMode => photo::toggle(&mut photo, &mut pointer, &mut paused),
+                _ => {}
+            }
+        }
+        // Work out how far we are between the last simulation tick and the next one
+        let alpha = event.render_args().map_or(1., |args| {
+            (args.ext_dt * UPDATES_PER_SECOND as f64).clamp(0., 1.) as f32
+        });
+        let mut chosen_tower_type = None;
+        let mut chosen_offer = None;
+        let mut selling = false;
+        let mut triggering_ability = false;
+        let mut debug_choice = None;
+        let mut settings_choice = None;
+        let mut chosen_binding = None;
+        let mut menu_choice = None;
+        let mut sent_command = None;
+        let mut photo_choice = None;
+        let mut dismissing_summary = false;
+        let mut slot_choice = None;
+        let can_save = !menu.open
+            && !game.rules.sandbox
+            && !game.rules.reverse
+            && game.state.outcome.is_none();
+        let can_load = commands.recording.is_none() && demo_bot.is_none();
+
+        // Lay out the egui panels once a frame, before anything's drawn, where the game's own panels are shown, keeping
+        // a click from egui's first pass if it lays the panels out twice
+        if event.render_args().is_some() {
+            let panels = !saves.open && !menu.open && photo.is_none();
+            gui.frame(&mut window, &layout, |ctx| {
+                if panels && showing_debug {
+                    let stats = DebugStats {
+                        enemies_drawn,
+                        hud_rebuilds: hud.rebuilds,
+                        assets: assets.summary(),
+                    };
+                    debug_choice = debug::show(ctx, &game, &stats).or(debug_choice.take());
+                }
+                if panels && showing_settings {
+                    settings_choice =
+                        settings::show(ctx, &mut settings, monitors, &mut settings_changed)
+                            .or(settings_choice.take());
+                }
+            });
+        }
+        let draw_size = window.draw_size();
+        window.draw_2d(&event, |window_context, g, device| {
+            let screen = layout.apply(window_context);
+            let glyphs = &mut *font.borrow_mut();
+            let palette = theme::palette();
+            clear(palette.background, g);
+
+            // Nothing but the confirmation dialog can be clicked while it's waiting for an answer
+            if dialog.is_some() {
+                ui.lock();
+            }
+
+            // Show the demo rather than the player's game while one is playing behind the main menu
+            let game = menu.demo.as_ref().map_or(&game, |demo| &demo.game);
+
+            // The map is drawn scrolled and zoomed by the camera, and the HUD and panels over it in place
+            let camera = pointer.view_offset();
+            let c = screen
+                .zoom(pointer.zoom as f64)
+                .trans(-camera.x as f64, -camera.y as f64);
+
+            // Draw the map's scenery, water, high ground, and path
+            renderer::draw_terrain(game, &mut PistonRenderer { c, g });
+
+            // Draw the gates, wind, teleporters, and tower slots
+            renderer::draw_scenery(game, &mut PistonRenderer { c, g });
+
+            // Draw the player's base
+            renderer::draw_base(&mut PistonRenderer { c, g });
+
+            // Draw the player's cursor and the HUD, except in photo mode
+            if photo.is_none() {
+                let transform = c.transform.trans(
+                    game.state.cursor_position.x.into(),
+                    game.state.cursor_position.y.into(),
+                );
+                ellipse(palette.cursor, [0.0, 0.0, 25.0, 25.0], transform, g);
+
+                // The HUD's text is only rebuilt when something on it changed
+                hud.update(game, setup.daily.is_some(), sandbox_enemy_type);
+                for line in hud.lines.iter() {
+                    let style = TextStyle {
+                        size: line.size,
+                        shadow: Some(palette.text_shadow),
+                        ..Default::default()
+                    };
+                    label::draw(&line.text, &style, 0.0, line.y, glyphs, screen, g);
+                }
+            }
+
+            // Skip anything that falls outside the window, which shows more of the map the further out it's zoomed
+            let [width, height] = c.get_view_size();
+            let view_size = [width / pointer.zoom as f64, height / pointer.zoom as f64];
+
+            // Color the towers by how well they're doing underneath them, while the analytics overlay is on
+            if photo.is_none() {
+                analytics::draw(overlay, &game.state.towers, c, g, glyphs);
+            }
+
+            // Draw the best previous run's towers faintly under the player's own, as they stood at the same moment
+            if let Some(ghost) = ghost
+                .as_ref()
+                .filter(|_| settings.ghost && menu.demo.is_none())
+            {
+                ghost.draw(game.state.tick, c, g);
+            }
+
+            // Draw the player's towers
+            let mut world = PistonRenderer { c, g };
+            for tower in game
+                .state
+                .towers
+                .iter()
+                .filter(|tower| on_screen(view_size, &tower.position.minus(&camera), TILE_SIZE))
+            {
+                renderer::draw_tower(tower, &mut world);
+            }
+
+            // Draw the commands waiting for the game to be unpaused, as faint towers or crossed-out ones
+            for command in commands.pending() {
+                match command {
+                    Command::Build { position, .. } => {
+                        let transform = c.transform.trans(position.x.into(), position.y.into());
+                        ellipse(
+                            theme::faded(palette.tower, 0.4),
+                            [0.0, 0.0, 25.0, 25.0],
+                            transform,
+                            g,
+                        );
+                    }
+                    Command::Sell { position } => {
+                        let transform = c.transform.trans(position.x.into(), position.y.into());
+                        for cross in [[0.0, 0.0, 25.0, 25.0], [25.0, 0.0, 0.0, 25.0]] {
+                            line([0.8, 0.1, 0.1, 1.0], 2.0, cross, transform, g);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            // Draw the enemies and everything else that moves, with the many rectangles among them batched together,
+            // then darken the map for night with the lights glowing through it
+            let mut world = BatchedRenderer {
+                piston: PistonRenderer { c, g },
+                batch: &mut enemy_batch,
+            };
+            enemies_drawn = renderer::draw_units(
+                game,
+                alpha,
+                |position, size| on_screen(view_size, &position.minus(&camera), size),
+                &mut world,
+            );
+            world.flush();
+            let time = game.state.stats.playtime;
+            lighting::draw(game, settings.lighting as f32, time, c, g);
+
+            // Draw a faint tower under the mouse while placing one, and a ring round the selected tower, each with
+            // the range the tower reaches
+            let ghost = match pointer.mode {
+                Mode::Placing(tower_type) => game.tower_types.get(tower_type).map(|tower_type| {
+                    let position = world_mouse.minus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.));
+                    let position = game.slot_at(&position).unwrap_or(position);
+                    (position, tower_type.range, [0.5, 0.5, 0.5, 0.4])
+                }),
+                Mode::Selecting(id) => game
+                    .state
+                    .towers
+                    .iter()
+                    .find(|tower| tower.id == id)
+                    .map(|tower| (tower.position, tower.stats.range, [0.2, 0.5, 0.8, 1.0])),
+                Mode::Idle | Mode::Panning => None,
+            };
+            if let (Mode::Placing(_), Some((position, range, _))) = (pointer.mode, ghost) {
+                let coverage = game.coverage(Some((position, range)));
+                coverage::draw(&coverage, &position, c, g, glyphs);
+            }
+            if let Some((position, range, color)) = ghost {
+                let range = game.map.range_from(&position, range) as f64;
+                let (x, y) = (
+                    position.x as f64 + TILE_SIZE as f64 / 2.0,
+                    position.y as f64 + TILE_SIZE as f64 / 2.0,
+                );
+                Ellipse::new_border(color, 1.0).draw(
+                    [x - range, y - range, range * 2.0, range * 2.0],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+                ellipse(color, [x - 12.5, y - 12.5, 25.0, 25.0], c.transform, g);
+            }
+
+            // Everything from here on stays in place on the screen as the camera pans, inside the layout
+            layout.draw_bars(window_context, g);
+            let c = screen;
+
+            // A frame being kept as a save slot's picture shows just the map
+            if saves.saving.is_some() {
+                ui.end_frame();
+                glyphs.factory.encoder.flush(device);
+                return;
+            }
+
+            // Photo mode only draws its filters over the map, with its panel and notifications on top unless the
+            // frame is being saved as a screenshot
+            if let Some(photo) = &mut photo {
+                photo.draw_filters(c, g);
+                theme::draw_overlay(c, g);
+                if !photo.capturing {
+                    let mut frame = Frame {
+                        c,
+                        g: &mut *g,
+                        glyphs: &mut *glyphs,
+                    };
+                    photo_choice = photo.draw_panel(&mut ui, &mut frame);
+                    if let Some(dialog) = &dialog {
+                        dialog_answer = dialog.draw(&mut ui, &mut frame);
+                    }
+                    notifications::draw(c, g, glyphs);
+                }
+                ui.end_frame();
+                glyphs.factory.encoder.flush(device);
+                return;
+            }
+
+            // Draw the inventory bar along the bottom of the screen
+            for (slot, key) in ["Z", "X", "C", "V", "B"].iter().enumerate() {
+                let x = 10.0 + slot as f64 * 125.0;
+                rectangle(palette.widget, [x, 440.0, 120.0, 35.0], c.transform, g);
+                let label = match game.state.inventory.get(slot) {
+                    Some(&item) => format!("{}: {}", key, game.item_types[item].name),
+                    None => format!("{}: -", key),
+                };
+                label::text(
+                    palette.text,
+                    14,
+                    &label,
+                    glyphs,
+                    c.transform.trans(x + 5.0, 463.0),
+                    g,
+                );
+            }
+
+            // Draw the outcome once the game is over, or a reminder that it's paused
+            if let Some(outcome) = game.state.outcome {
+                let message = match outcome {
+                    Outcome::Won if campaign.new_game_plus_unlocked() => {
+                        "You win! New Game+ unlocked"
+                    }
+                    Outcome::Won => "You win!",
+                    Outcome::Lost => "You lose!",
+                };
+                let style = TextStyle {
+                    size: 32,
+                    align: Align::Center,
+                    outline: Some(palette.text_shadow),
+                    ..Default::default()
+                };
+                label::draw(message, &style, 320.0, 240.0, glyphs, c, g);
+
+                // Puzzles don't go on the leaderboard, and offer a retry in place of the scores
+                if !game.rules.puzzle {
+                    label::draw(
+                        &format!(
+                            "Score: {}  Best: {}",
+                            game.score(),
+                            leaderboard.best(&category).unwrap_or(0)
+                        ),
+                        &TextStyle { size: 20, ..style },
+                        320.0,
+                        270.0,
+                        glyphs,
+                        c,
+                        g,
+                    );
+                    let mut time = format!("Time: {}", format_time(game.state.stats.playtime));
+                    if let Some(best) = leaderboard.best_time(&category) {
+                        time += &format!("  Fastest win: {}", format_time(best));
+                    }
+                    label::draw(
+                        &time,
+                        &TextStyle { size: 20, ..style },
+                        320.0,
+                        295.0,
+                        glyphs,
+                        c,
+                        g,
+                    );
+                }
+            } else if paused {
+                let style = TextStyle {
+                    size: 32,
+                    align: Align::Center,
+                    outline: Some(palette.text_shadow),
+                    ..Default::default()
+                };
+                label::draw("Paused (P to resume)", &style, 320.0, 240.0, glyphs, c, g);
+                let queued = commands.pending().len();
+                if queued > 0 {
+                    label::draw(
+                        &format!("{} queued commands will run when unpaused", queued),
+                        &TextStyle { size: 20, ..style },
+                        320.0,
+                        270.0,
+                        glyphs,
+                        c,
+                        g,
+                    );
+                }
+            }
+
+            // Draw the speedrun splits against the personal best on this level
+            if speedrun {
+                splits::draw(
+                    &game.state.stats.splits,
+                    personal_bests.best(&setup.level.name),
+                    game.state.stats.playtime,
+                    c,
+                    g,
+                    glyphs,
+                );
+            }
+
+            // Draw the combat log down the right side of the screen, newest events first
+            if showing_combat_log {
+                rectangle(
+                    palette.panel,
+                    [380.0, 175.0, 255.0, 20.0 * COMBAT_LOG_LINES as f64 + 10.0],
+                    c.transform,
+                    g,
+                );
+                for (i, event) in game
+                    .state
+                    .events
+                    .recent()
+                    .skip(combat_log_scroll)
+                    .take(COMBAT_LOG_LINES)
+                    .enumerate()
+                {
+                    label::text(
+                        palette.text,
+                        14,
+                        &event.to_string(),
+                        glyphs,
+                        c.transform.trans(385.0, 195.0 + i as f64 * 20.0),
+                        g,
+                    );
+                }
+            }
+
+            // Draw a tooltip for the enemy under the mouse, with what it costs if it reaches the base
+            if let Some(enemy) = game.state.enemies.iter().find(|enemy| {
+                let position = enemy.interpolated_position(alpha);
+                (0.0..TILE_SIZE).contains(&(world_mouse.x - position.x))
+                    && (0.0..TILE_SIZE).contains(&(world_mouse.y - position.y))
+            }) {
+                let lives = if enemy.enemy_type.lives_cost == 1 {
+                    "life"
+                } else {
+                    "lives"
+                };
+                let mut lines = vec![
+                    enemy.enemy_type.name.clone(),
+                    format!(
+                        "HP: {}/{}",
+                        enemy.hit_points, enemy.enemy_type.max_hit_points
+                    ),
+                    format!("Armor: {}/{}", enemy.armor(), enemy.enemy_type.armor),
+                    format!("Leaking costs {} {}", enemy.enemy_type.lives_cost, lives),
+                ];
+
+                // With a tower selected, preview how long it would take that tower to defeat the enemy alone
+                let selected = match pointer.mode {
+                    Mode::Selecting(id) => game.state.towers.iter().find(|tower| tower.id == id),
+                    _ => None,
+                };
+                if let Some(tower) = selected {
+                    lines.push(match damage::preview_kill(tower, enemy) {
+                        Some(preview) => format!(
+                            "{} shot{} to kill, {:.1}s",
+                            preview.shots,
+                            if preview.shots == 1 { "" } else { "s" },
+                            preview.seconds
+                        ),
+                        None => String::from("Tower can't kill it"),
+                    });
+                }
+                let (x, y) = (
+                    mouse_position.x as f64 + 12.0,
+                    mouse_position.y as f64 + 12.0,
+                );
+                let height = 6.0 + lines.len() as f64 * 16.0;
+                rectangle(palette.panel, [x, y, 140.0, height], c.transform, g);
+                let style = TextStyle {
+                    size: 12,
+                    ..Default::default()
+                };
+                for (i, line) in lines.iter().enumerate() {
+                    let y = y + 14.0 + i as f64 * 16.0;
+                    label::draw(line, &style, x + 5.0, y, glyphs, c, g);
+                }
+            }
+
+            // Draw the menus and panels the player can click on
+            {
+                let mut frame = Frame {
+                    c,
+                    g: &mut *g,
+                    glyphs: &mut *glyphs,
+                };
+                let style = TextStyle {
+                    size: 14,
+                    ..Default::default()
+                };
+
+                // Draw the save screen or the main menu in place of everything else while either is open
+                if saves.open {
+                    slot_choice = saves.draw(can_save, can_load, &mut ui, &mut frame);
+                } else if menu.open {
+                    // Mutators can't be changed while recording, which has the run's setup already
+                    let mutators = commands
+                        .recording
+                        .is_none()
+                        .then_some(setup.mutators.as_slice());
+                    menu_choice = menu.draw(&setup.level.name, mutators, &mut ui, &mut frame);
+                } else {
+                    // Draw the build menu along the bottom of the screen, or the enemies to send in reverse mode
+                    if game.rules.reverse {
+                        sent_command = reverse::draw_send_panel(game, &mut ui, &mut frame);
+                    } else {
+                        // Buttons narrow to fit levels with more than the usual four towers
+                        ui.panel([0.0, 445.0, 640.0, 35.0], &mut frame);
+                        let width = 640.0 / game.tower_types.len().max(4) as f64;
+                        for (i, tower_type) in game.tower_types.iter().enumerate() {
+                            if ui.button(
+                                &format!("build {}", i),
+                                &format!("{} ({})", tower_type.name, game.tower_cost(i)),
+                                [5.0 + i as f64 * width, 450.0, width - 5.0, 25.0],
+                                tower_type.prestige_required <= game.prestige,
+                                game.state.selected_tower_type == i,
+                                &mut frame,
+                            ) {
+                                chosen_tower_type = Some(i);
+                            }
+                        }
+                    }
+
+                    // Draw the panel for the tower under the player's cursor, except in reverse mode where the towers are the AI's
+                    if let Some(i) = game
+                        .tower_at(&game.state.cursor_position)
+                        .filter(|_| !game.rules.reverse)
+                    {
+                        // The panel grows upwards to fit a line for each modifier on the tower's stats, and a button
+                        // for the tower's ability, if it has one
+                        let tower = &game.state.towers[i];
+                        let breakdown = tower.breakdown(game.state.damage_boost);
+                        let top = if tower.tower_type.ability.is_some() {
+                            300.0
+                        } else {
+                            330.0
+                        } - breakdown.len() as f64 * 16.0;
+                        ui.panel([5.0, top, 160.0, 440.0 - top], &mut frame);
+                        let mut lines = vec![
+                            format!("{} #{}", tower.tower_type.name, tower.id),
+                            format!("Damage: {}", damage::shot_damage(tower)),
+                            format!(
+                                "Range: {:.0}",
+                                game.map.range_from(&tower.position, tower.stats.range)
+                            ),
+                            format!("Fire rate: {:.1}/s", tower.stats.rate_of_fire),
+                        ];
+                        lines.extend(breakdown);
+                        for (i, line) in lines.iter().enumerate() {
+                            let y = top + 18.0 + i as f64 * 16.0;
+                            label::draw(line, &style, 12.0, y, frame.glyphs, c, frame.g);
+                        }
+                        if let Some(ability) = tower.tower_type.ability {
+                            let caption = if tower.ability_time_left > 0. {
+                                format!(
+                                    "{} ({:.0}s left)",
+                                    ability.name,
+                                    tower.ability_time_left.ceil()
+                                )
+                            } else if tower.ability_ready() {
+                                ability.name.to_string()
+                            } else {
+                                format!("{} ({:.0}s)", ability.name, tower.ability_cooldown.ceil())
+                            };
+                            triggering_ability = ui.button(
+                                "ability",
+                                &caption,
+                                [12.0, 378.0, 146.0, 25.0],
+                                tower.ability_ready(),
+                                tower.ability_time_left > 0.,
+                                &mut frame,
+                            );
+                        }
+                        let refund = (tower.tower_type.cost as f32 * SELL_REFUND).round();
+                        selling = ui.button(
+                            "sell",
+                            &format!("Sell (+{})", refund),
+                            [12.0, 408.0, 146.0, 25.0],
+                            game.rules.selling_allowed,
+                            false,
+                            &mut frame,
+                        );
+                    }
+
+                    // Draw the shop window over the map while it's open
+                    if game.state.shop.open {
+                        ui.panel([170.0, 150.0, 300.0, 150.0], &mut frame);
+                        let title = TextStyle { size: 20, ..style };
+                        label::draw("Shop", &title, 180.0, 175.0, frame.glyphs, c, frame.g);
+                        for (i, shop_offer) in game.state.shop.offers.iter().enumerate() {
+                            let name = match shop_offer.offer {
+                                Offer::Item(item) => game.item_types[item].name.clone(),
+                                Offer::TowerDiscount {
+                                    tower_type,
+                                    discount,
+                                } => format!(
+                                    "{:.0}% off {}",
+                                    discount * 100.,
+                                    game.tower_types[tower_type].name
+                                ),
+                                Offer::ExtraLife => String::from("Extra life"),
+                            };
+                            let caption = if shop_offer.sold {
+                                format!("{}: {} (sold)", i + 1, name)
+                            } else {
+                                format!("{}: {} ({})", i + 1, name, shop_offer.price)
+                            };
+                            if ui.button(
+                                &format!("offer {}", i),
+                                &caption,
+                                [180.0, 187.0 + i as f64 * 30.0, 280.0, 25.0],
+                                !shop_offer.sold,
+                                false,
+                                &mut frame,
+                            ) {
+                                chosen_offer = Some(i);
+                            }
+                        }
+                    }
+
+                    // Draw the summary of the wave that just ended until it's dismissed or times out
+                    if let Some(summary) = &wave_summary {
+                        dismissing_summary = summary.draw(&mut ui, &mut frame);
+                    }
+
+                    // Draw the settings and debug panels egui laid out
+                    gui.draw(&layout, window_context, frame.g);
+
+                    // Draw the controls panel over everything else, since it covers the screen
+                    if showing_controls {
+                        chosen_binding = input::draw(&bindings, rebinding, &mut ui, &mut frame);
+                    }
+                }
+
+                // Offer to try a finished puzzle again straight away
+                if game.rules.puzzle
+                    && game.state.outcome.is_some()
+                    && !menu.open
+                    && ui.button(
+                        "retry puzzle",
+                        "Retry (Ctrl+R)",
+                        [255.0, 280.0, 130.0, 25.0],
+                        true,
+                        false,
+                        &mut frame,
+                    )
+                {
+                    retrying = true;
+                }
+
+                // Checkpoints aren't offered while the run's being recorded, which would leave the recording out
+                // of step
+                let offered = commands.recording.is_none() && demo_bot.is_none() && !menu.open;
+                restarting_from = checkpoints::draw(game, offered, &mut ui, &mut frame);
+                encyclopedia.draw(!menu.open && !saves.open, &mut ui, &mut frame);
+
+                // Draw the confirmation dialog over every other panel
+                if let Some(dialog) = &dialog {
+                    dialog_answer = dialog.draw(&mut ui, &mut frame);
+                }
+                ui.end_frame();
+            }
+
+            // Draw any notifications above the build menu
+            notifications::draw(c, g, glyphs);
+
+            // Draw the statistics screen over everything else while it's open
+            if let Some(levels) = &community_levels {
+                workshop::draw(levels, c, g, glyphs);
+            }
+            if showing_stats {
+                stats::draw(&lifetime_stats, c, g, glyphs);
+            }
+            if showing_cheat_sheet {
+                input::draw_cheat_sheet(&bindings, c, g, glyphs);
+            }
+
+            theme::draw_overlay(c, g);
+            glyphs.factory.encoder.flush(device);
+        });
+
+        // Read back a frame once it's been drawn in full, but before it's shown, to save it as a screenshot or as the
+        // picture of a save slot
+        if event.render_args().is_some() {
+            let size = [draw_size.width as u32, draw_size.height as u32];
+            photo::save_drawn_frame(&mut photo, size);
+            saves.save_drawn_frame(&setup, &game, size, &mut window);
+        }
+
+        // Carry out whatever the player clicked on this frame
+        if dismissing_summary {
+            wave_summary = None;
+        }
+
+        if let Some(choice) = photo_choice {
+            photo::choose(&mut photo, choice, &mut pointer, &mut paused);
+        }
+        match menu_choice {
+            Some(MenuChoice::Play) => menu = Menu::new(false),
+            Some(MenuChoice::Saves) => saves.open = true,
+            Some(MenuChoice::Quit) => window.set_should_close(true),
+            Some(MenuChoice::ToggleMutator(name)) => {
+                // Start the run over with the mutator added or taken away, which the player hasn't played any of yet
+                setup.toggle_mutator(name);
+                match setup.start() {
+                    Ok(new_game) => {
+                        game = new_game;
+                        category = setup.category();
+                        if let Some(ghost) = &mut commands.ghost {
+                            *ghost = Replay::new(setup.clone(), 0);
+                        }
+                    }
+                    Err(error) => {
+                        notify(format!("Couldn't start the run: {}", error), Kind::Warning)
+                    }
+                }
+            }
+            None => {}
+        }
+        // Saving over a slot or loading one in place of a run in progress is checked with the player first
+        let running = !menu.open && game.state.outcome.is_none() && game.state.stats.playtime > 0.;
+        if let Some(confirmation) = slot_choice.and_then(|choice| saves.choose(choice, running)) {
+            dialog = Some(Dialog::new(confirmation));
+        }
+        if let Some(run) = saves.take_loaded() {
+            window.set_title(window_title(&run.setup.level));
+            setup = run.setup;
+            game = run.game;
+            category = setup.category();
+            ghost = Ghost::load(&setup.level.name);
+            commands = CommandQueue::new();
+            pointer.mode = Mode::Idle;
+            menu = Menu::new(false);
+        }
+        if let Some(i) = restarting_from.take() {
+            checkpoints::restart(&mut game, i);
+            commands = CommandQueue::new();
+            pointer.mode = Mode::Idle;
+            wave_summary = None;
+        }
+        if std::mem::take(&mut retrying) {
+            // Start the puzzle over from the beginning, without asking, since it's so quick to get back to
+            match setup.start() {
+                Ok(new_game) => {
+                    game = new_game;
+                    commands = CommandQueue::new();
+                    pointer.mode = Mode::Idle;
+                    wave_summary = None;
+                }
+                Err(error) => notify(
+                    format!("Couldn't restart the puzzle: {}", error),
+                    Kind::Warning,
+                ),
+            }
+        }
+        let defer = paused && settings.queue_while_paused;
+        if let Some(tower_type) = chosen_tower_type {
+            pointer.mode = Mode::Placing(tower_type);
+            commands.issue(Command::SelectTowerType(tower_type), &mut game, defer);
+        }
+        if let Some(command) = sent_command {
+            commands.issue(command, &mut game, defer);
+        }
+        if let Some(offer) = chosen_offer {
+            commands.issue(Command::Buy(offer), &mut game, defer);
+        }
+        if triggering_ability {
+            let command = Command::UseAbility {
+                position: game.state.cursor_position,
+            };
+            commands.issue(command, &mut game, defer);
+        }
+        if selling {
+            let position = game.state.cursor_position;
+            if game.sale_needs_confirming(&position) {
+                dialog = Some(Dialog::new(Confirmation::Sell(position)));
+            } else {
+                commands.issue(Command::Sell { position }, &mut game, defer);
+            }
+        }
+        if chosen_binding.is_some() {
+            rebinding = chosen_binding;
+        }
+        match debug_choice {
+            Some(DebugChoice::ToggleParallel) => game.state.parallel = !game.state.parallel,
+            Some(DebugChoice::ReloadAssets) => {
+                let reloaded = assets.reload_changed(&mut window);
+                notify(format!("Reloaded {} changed assets", reloaded), Kind::Info);
+            }
+            None => {}
+        }
+        match settings_choice {
+            Some(SettingsChoice::Display) => {
+                settings.display = settings.display.next();
+                display::apply(&window, &settings);
+                layout = Layout::fit(&window);
+                settings_changed = true;
+            }
+            Some(SettingsChoice::Monitor) => {
+                monitors = display::monitor_count(&window);
+                settings.monitor = (settings.monitor + 1) % monitors.max(1);
+                display::apply(&window, &settings);
+                layout = Layout::fit(&window);
+                settings_changed = true;
+            }
+            // Picking a control scheme replaces every binding with the scheme's own
+            Some(SettingsChoice::ControlScheme) => {
+                settings.controls = settings.controls.next();
+                bindings = Bindings::preset(settings.controls);
+                if let Err(error) = bindings.save(BINDINGS_PATH) {
+                    eprintln!("Couldn't save bindings: {}", error);
+                }
+                settings_changed = true;
+            }
+            Some(SettingsChoice::Theme) => {
+                settings.theme = settings.theme.next();
+                theme::set(settings.theme);
+                assets.set_theme_dir(settings.theme.asset_dir(), &mut window);
+                settings_changed = true;
+            }
+            Some(SettingsChoice::TextureFilter) => {
+                settings.texture_filter = settings.texture_filter.next();
+                assets.set_filtering(settings.texture_filter, &mut window);
+                settings_changed = true;
+            }
+            Some(SettingsChoice::Controls) => {
+                showing_settings = false;
+                showing_controls = true;
+            }
+            Some(SettingsChoice::Close) => showing_settings = false,
+            None => {}
+        }
+        if settings_changed {
+            window.set_max_fps(settings.fps_cap());
+            pointer.set_integer_scaling(settings.integer_scaling);
+        }
+        // Save the settings once the panel closes, rather than on every step of a slider
+        if settings_changed && !showing_settings {
+            settings_changed = false;
+            if settings.display == DisplayMode::Windowed && window_scale != settings.ui_scale {
+                window_scale = settings.ui_scale;
+                display::apply(&window, &settings);
+                layout = Layout::fit(&window);
+            }
+            if let Err(error) = settings.save(SETTINGS_PATH) {
+                eprintln!("Couldn't save settings: {}", error);
+            }
+        }
+
+        event.update(|args| {
+            notifications::update(args.dt as f32);
+
+            // The player's run doesn't start until they leave the main menu
+            if menu.open {
+                menu.update(&setup, args.dt as f32);
+                return;
+            }
+
+            // Update the game state, leaving it and the run's clock alone while the game is paused
+            if paused {
+                return;
+            }
+            let was_over = game.state.outcome.is_some();
+            let was_building = game.state.building;
+            if let Some(bot) = &mut demo_bot {
+                for command in bot.decide(&game) {
+                    commands.issue(command, &mut game, false);
+                }
+            }
+            // Step by exactly one tick, like replays and simulations do, so recordings play back the same
+            crash::guard(&setup, &mut game, |game| {
+                game.update(1. / UPDATES_PER_SECOND as f32)
+            });
+            if let Some(replay) = &mut commands.recording {
+                replay.record_tick(&game);
+            }
+
+            // A recording ends with the run
+            if game.state.outcome.is_some() {
+                if let Some(path) = &recording_path {
+                    save_recording(&mut commands, &game, path);
+                }
+            }
+
+            // A demo starts over once it's finished, without counting towards anything
+            if demo_bot.is_some() {
+                if game.state.outcome.is_some() {
+                    match setup.start() {
+                        Ok(new_game) => game = new_game,
+                        Err(error) => eprintln!("Couldn't restart the demo: {}", error),
+                    }
+                }
+                return;
+            }
+
+            // Keep the last few seconds of the run to rewind to
+            game.remember_moment();
+
+            // Unlock the encyclopedia entries of towers and enemies met for the first time
+            game.state
+                .events
+                .dispatch(&mut game.state.unlocks_reader, &mut encyclopedia);
+            encyclopedia.update(1. / UPDATES_PER_SECOND as f32);
+
+            // Sum up each wave once it's over, for as long as the player leaves the summary up
+            if wave_summary
+                .as_mut()
+                .is_some_and(|summary| !summary.update(1. / UPDATES_PER_SECOND as f32))
+            {
+                wave_summary = None;
+            }
+            if !was_building && game.state.building && game.state.outcome.is_none() {
+                wave_summary = Some(WaveSummary::new(
+                    game.state.wave - 1,
+                    game.state.wave_tally.clone(),
+                ));
+                game.take_checkpoint();
+            }
+
+            // Sandbox, reverse, and puzzle runs don't count towards the leaderboard or campaign, and aren't autosaved
+            if game.rules.sandbox || game.rules.reverse || game.rules.puzzle {
+                return;
+            }
+
+            // Autosave at the start of each wave, so a crash loses at most the wave in progress
+            if was_building && !game.state.building && game.state.outcome.is_none() {
+                if let Err(error) = autosave::save(AUTOSAVE_PATH, &setup, &game) {
+                    eprintln!("Couldn't autosave: {}", error);
+                }
+            }
+            if was_over || game.state.outcome.is_none() {
+                return;
+            }
+
+            // A finished run can't be resumed
+            autosave::discard(AUTOSAVE_PATH);
+
+            // A rewound run was just for practice, so doesn't count towards anything
+            if game.rules.rewound {
+                return;
+            }
+
+            // Add the finished run to the player's statistics across all runs
+            lifetime_stats.add_run(&game.state.stats);
+            lifetime_stats.add_result(&category, game.state.outcome == Some(Outcome::Won));
+            if let Err(error) = lifetime_stats.save(STATS_PATH) {
+                eprintln!("Couldn't save statistics: {}", error);
+            }
+
+            // Add the run to the anonymous balance data if the player has opted in, sending just this run's data on
+            // if there's somewhere set to send it, with the totals kept here
+            if settings.telemetry {
+                let run = telemetry.record(&setup.level.name, &game);
+                if let Err(error) = telemetry.save(TELEMETRY_PATH) {
+                    eprintln!("Couldn't save balance data: {}", error);
+                }
+                if !settings.telemetry_endpoint.is_empty() {
+                    telemetry::upload(&settings.telemetry_endpoint, run.to_json().to_string());
+                }
+            }
+
+            // Record the run's score on the leaderboard for its category
+            if leaderboard
+                .best(&category)
+                .is_none_or(|best| game.score() > best)
+            {
+                notify(
+                    format!("New best score: {}", game.score()),
+                    Kind::Achievement,
+                );
+            }
+            leaderboard.record(&category, game.score());
+            if game.state.outcome == Some(Outcome::Won) {
+                let seconds = game.state.stats.playtime;
+                if leaderboard
+                    .best_time(&category)
+                    .is_none_or(|best| seconds < best)
+                {
+                    notify(
+                        format!("New fastest win: {}", format_time(seconds)),
+                        Kind::Achievement,
+                    );
+                }
+                leaderboard.record_time(&category, seconds);
+
+                // Keep the splits of the fastest unmodified win on each level to race against
+                if game.rules.mutators.is_empty()
+                    && personal_bests.record(&setup.level.name, &game.state.stats.splits)
+                {
+                    if speedrun {
+                        notify("New personal best splits", Kind::Achievement);
+                    }
+                    if let Err(error) = personal_bests.save(SPLITS_PATH) {
+                        eprintln!("Couldn't save splits: {}", error);
+                    }
+                }
+            }
+            if let Err(error) = leaderboard.save(LEADERBOARD_PATH) {
+                eprintln!("Couldn't save leaderboard: {}", error);
+            }
+
+            // Keep the highest scoring unmodified run on each level as the ghost later runs of it are shown
+            if setup.daily.is_none()
+                && game.rules.mutators.is_empty()
+                && ghost
+                    .as_ref()
+                    .is_none_or(|ghost| game.score() > ghost.score)
+            {
+                if let Some(mut replay) = commands.ghost.take() {
+                    replay.finish(&game);
+                    match Ghost::save(&setup.level.name, &replay, game.score()) {
+                        Ok(best) => ghost = Some(best),
+                        Err(error) => eprintln!("Couldn't save the ghost: {}", error),
+                    }
+                }
+            }
+
+            // Record a campaign win the moment it happens, unlocking the next New Game+ level
+            if setup.daily.is_none() && game.state.outcome == Some(Outcome::Won) {
+                let completions = campaign.completions;
+                campaign.record_win(game.prestige);
+                if campaign.completions > completions {
+                    notify(
+                        format!("New Game+ {} unlocked", campaign.completions),
+                        Kind::Achievement,
+                    );
+                }
+
+                // Each bonus objective complete at the end of the run earns a star for the level
+                if !game.bonus_objectives.is_empty() {
+                    let stars = objectives::stars_earned(&game.bonus_objectives, &game);
+                    let message = format!(
+                        "{} of {} bonus objectives complete",
+                        stars,
+                        game.bonus_objectives.len()
+                    );
+                    if campaign.record_stars(&setup.level.name, stars) {
+                        notify(
+                            format!("{}, {} stars in total", message, campaign.total_stars()),
+                            Kind::Achievement,
+                        );
+                    } else {
+                        notify(message, Kind::Info);
+                    }
+                }
+                if let Err(error) = campaign.save(CAMPAIGN_SAVE_PATH) {
+                    eprintln!("Couldn't save campaign progress: {}", error);
+                }
+            }
+        });
+    }
+
+    if let Some(path) = &recording_path {
+        save_recording(&mut commands, &game, path);
+    }
+
+    // Save a run the player closed the window on so it can be resumed, or count it as abandoned if that fails
+    if interrupted_run.is_none()
+        && autosave_error.is_none()
+        && game.state.outcome.is_none()
+        && !game.rules.sandbox
+        && !game.rules.reverse
+        && demo_bot.is_none()
+        && !menu.open
+    {
+        if let Err(error) = autosave::save(AUTOSAVE_PATH, &setup, &game) {
+            eprintln!("Couldn't autosave: {}", error);
+            lifetime_stats.add_run(&game.state.stats);
+            if let Err(error) = lifetime_stats.save(STATS_PATH) {
+                eprintln!("Couldn't save statistics: {}", error);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use piston_window::draw_state::Blend;
+use piston_window::*;
+
+use crate::hazards::Hazard;
+use crate::{Game, Point, TILE_SIZE};
+
+// How dark the map gets at full lighting intensity, for a night-time feel
+const NIGHT_DARKNESS: f32 = 0.5;
+// Color of the night laid over the map
+const NIGHT_COLOR: [f32; 3] = [0.02, 0.03, 0.12];
+// Number of rings a light's glow is drawn in, brightest in the middle
+const GLOW_RINGS: usize = 5;
+// Radius of the aura round a magical tower, in pixels
+const AURA_RADIUS: f64 = 45.;
+// How much brighter a tower's aura is while its ability is active
+const ABILITY_BOOST: f32 = 1.8;
+// Radius of the glow round a flying shot, in pixels
+const SHOT_GLOW_RADIUS: f64 = 10.;
+// Color of the glow from flames and burst particles
+const FIRE_LIGHT: [f32; 4] = [1.0, 0.5, 0.1, 1.0];
+// Color of the glow from teleporters
+const TELEPORTER_LIGHT: [f32; 4] = [0.6, 0.2, 0.9, 1.0];
+
+fn glow(
+    color: [f32; 4],
+    center: Point,
+    radius: f64,
+    strength: f32,
+    blend: &DrawState,
+    c: Context,
+    g: &mut G2d,
+) {
+    // Add light in rings that shrink towards the middle, so it's brightest there and fades out to the edge, with the
+    // color scaled by the strength since additive blending ignores alpha
+    let (x, y) = (center.x as f64, center.y as f64);
+    let per_ring = strength * color[3] / GLOW_RINGS as f32;
+    let color = [
+        color[0] * per_ring,
+        color[1] * per_ring,
+        color[2] * per_ring,
+        0.0,
+    ];
+    for ring in 0..GLOW_RINGS {
+        let r = radius * (1.0 - ring as f64 / GLOW_RINGS as f64);
+        Ellipse::new(color).draw([x - r, y - r, r * 2.0, r * 2.0], blend, c.transform, g);
+    }
+}
+
+pub fn draw(game: &Game, intensity: f32, time: f32, c: Context, g: &mut G2d) {
+    // Darken the map for night, then add the light given off by magical towers, shots in flight, flames, teleporters,
+    // and bursts, so they glow in the dark, all scaled by the intensity, where nothing is drawn at all at zero
+    if intensity <= 0. {
+        return;
+    }
+    let [width, height] = c.get_view_size();
+    let [r, gr, b] = NIGHT_COLOR;
+    rectangle(
+        [r, gr, b, NIGHT_DARKNESS * intensity],
+        [0.0, 0.0, width, height],
+        c.reset().transform,
+        g,
+    );
+
+    let blend = c.draw_state.blend(Blend::Add);
+    let center = Point::new(TILE_SIZE / 2., TILE_SIZE / 2.);
+    for tower in game.state.towers.iter() {
+        if let Some(light) = tower.tower_type.light {
+            let boost = if tower.ability_time_left > 0. {
+                ABILITY_BOOST
+            } else {
+                1.
+            };
+            let pulse = 0.85 + 0.15 * (time * 2. + tower.id as f32).sin();
+            let position = tower.position.plus(&center);
+            glow(
+                light,
+                position,
+                AURA_RADIUS,
+                0.5 * intensity * boost * pulse,
+                &blend,
+                c,
+                g,
+            );
+        }
+    }
+    for shot in game.state.shots.iter() {
+        glow(
+            shot.color,
+            shot.position(),
+            SHOT_GLOW_RADIUS,
+            intensity,
+            &blend,
+            c,
+            g,
+        );
+    }
+    for hazard in game.hazards.iter() {
+        if let Hazard::Flame { pivot, length } = hazard {
+            let tip = Hazard::flame_tip(pivot, *length, time).plus(&center);
+            let flicker = 0.75 + 0.25 * (time * 30.).sin();
+            glow(
+                FIRE_LIGHT,
+                tip,
+                TILE_SIZE as f64,
+                0.6 * intensity * flicker,
+                &blend,
+                c,
+                g,
+            );
+        }
+    }
+    for teleporter in game.path.teleporters.iter() {
+        for distance in [teleporter.entry, teleporter.exit] {
+            let end = game.path.point_at(distance);
+            glow(
+                TELEPORTER_LIGHT,
+                end.plus(&center),
+                TILE_SIZE as f64,
+                0.4 * intensity,
+                &blend,
+                c,
+                g,
+            );
+        }
+    }
+    for particle in game.state.particles.iter() {
+        glow(
+            FIRE_LIGHT,
+            particle.position,
+            6.0,
+            0.3 * intensity,
+            &blend,
+            c,
+            g,
+        );
+    }
+}
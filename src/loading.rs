@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use ::image::RgbaImage;
+use piston_window::*;
+use rusttype::Font;
+
+use crate::beats::BeatMap;
+use crate::display::{Layout, LAYOUT_SIZE};
+use crate::level::Level;
+use crate::theme;
+
+// Size of the loading screen's progress bar, in pixels
+const PROGRESS_BAR_SIZE: [f64; 2] = [320.0, 16.0];
+
+// Something loaded on a background thread, ready to be handed to the main thread, which owns the window's graphics
+// and turns what needs them into textures there
+pub enum Loaded {
+    // The font asked for by the given path, read from the file it was found at, or why it couldn't be
+    Font(String, PathBuf, Result<Font<'static>, String>),
+    // A save slot's picture of the map, decoded, if it has one
+    Thumbnail(usize, Option<RgbaImage>),
+    // The level to play, read and checked
+    Level(Box<Level>),
+    // The beats of the music track for rhythm mode, found in it if it's a WAV file, if one was given
+    Beats(Option<BeatMap>),
+}
+
+// Loads assets on background threads, so reading and decoding them doesn't freeze the window
+pub struct Loader {
+    // Stores where each job sends what it loaded, cloned into every job's thread
+    sender: Sender<Loaded>,
+    // Stores where what the jobs loaded arrives, in the order they finish
+    receiver: Receiver<Loaded>,
+    // Stores the number of jobs started
+    jobs: usize,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Loader {
+            sender,
+            receiver,
+            jobs: 0,
+        }
+    }
+
+    pub fn spawn(&mut self, job: impl FnOnce() -> Loaded + Send + 'static) {
+        // Start the job on a thread of its own, which finishes by sending what it loaded
+        let sender = self.sender.clone();
+        self.jobs += 1;
+        thread::spawn(move || {
+            // The loader only stops listening once the window's been closed, when nothing's waiting on the job
+            let _ = sender.send(job());
+        });
+    }
+
+    pub fn finish(self, window: &mut PistonWindow) -> Option<Vec<Loaded>> {
+        // Show a progress bar until every job has finished, then hand over everything they loaded, or nothing if the
+        // window's closed first. A job that panicked never sends anything, which ends the wait early with whatever
+        // the other jobs loaded, leaving its asset to be loaded the slow way
+        let Loader {
+            sender,
+            receiver,
+            jobs,
+        } = self;
+        drop(sender);
+        let mut loaded = Vec::new();
+        while loaded.len() < jobs {
+            let event = window.next()?;
+            loop {
+                match receiver.try_recv() {
+                    Ok(asset) => loaded.push(asset),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Some(loaded),
+                }
+            }
+            let progress = loaded.len() as f64 / jobs as f64;
+            let layout = Layout::fit(window);
+            window.draw_2d(&event, |c, g, _| {
+                draw(progress, layout, c, g);
+            });
+        }
+        Some(loaded)
+    }
+}
+
+fn draw(progress: f64, layout: Layout, window_context: Context, g: &mut G2d) {
+    // A bar across the middle of the screen, filling up as the jobs finish
+    let palette = theme::palette();
+    clear(palette.background, g);
+    let c = layout.apply(window_context);
+    let [width, height] = PROGRESS_BAR_SIZE;
+    let [x, y] = [
+        (LAYOUT_SIZE[0] - width) / 2.0,
+        (LAYOUT_SIZE[1] - height) / 2.0,
+    ];
+    rectangle(
+        palette.border,
+        [x - 2.0, y - 2.0, width + 4.0, height + 4.0],
+        c.transform,
+        g,
+    );
+    rectangle(palette.widget, [x, y, width, height], c.transform, g);
+    rectangle(
+        palette.hover,
+        [x, y, width * progress, height],
+        c.transform,
+        g,
+    );
+    layout.draw_bars(window_context, g);
+}
@@ -0,0 +1,56 @@
+use rand::Rng;
+
+use crate::Point;
+
+// Seconds a loot drop stays on the map before disappearing
+pub const LOOT_LIFETIME: f32 = 5.;
+// Distance from a loot drop's center within which a click collects it
+pub const LOOT_PICKUP_RADIUS: f32 = 15.;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Loot {
+    // Grants the player resources immediately
+    Resources(i32),
+    // Multiplies the damage of every tower for `duration` seconds
+    DamageBoost { multiplier: f32, duration: f32 },
+    // Grants the player a spell charge
+    SpellCharge,
+    // Adds the item type with the given index to the player's inventory
+    Item(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DropChance {
+    // Stores the probability of the drop, from 0 to 1
+    pub chance: f32,
+    // Stores what the drop gives the player
+    pub loot: Loot,
+}
+
+#[derive(Clone)]
+pub struct LootDrop {
+    // Stores where the drop lies on the map
+    pub position: Point,
+    // Stores what the drop gives the player when collected
+    pub loot: Loot,
+    // Stores the time until the drop disappears, in seconds
+    pub time_left: f32,
+}
+
+impl LootDrop {
+    pub fn new(position: Point, loot: Loot) -> Self {
+        LootDrop {
+            position,
+            loot,
+            time_left: LOOT_LIFETIME,
+        }
+    }
+}
+
+pub fn roll(drop_table: &[DropChance], rng: &mut impl Rng) -> Option<Loot> {
+    // Each entry in the table gets its own roll, and the first success wins
+    drop_table
+        .iter()
+        .find(|drop| rng.gen::<f32>() < drop.chance)
+        .map(|drop| drop.loot)
+}
@@ -0,0 +1,86 @@
+// The standard library hands sin, cos, acos, and exp to the platform's math library, and leaves how precise powi is
+// unspecified, so they're all free to round differently from one platform to the next. These stick to arithmetic
+// and square roots, which IEEE 754 requires to round the same way everywhere, so the simulation plays out
+// identically on every platform and replays and lockstep games stay in sync.
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+// Number of terms of each series summed, enough for every bit of an f32 over the reduced range
+const SERIES_TERMS: u32 = 14;
+
+fn reduce(angle: f32) -> f64 {
+    // Bring the angle into [-pi, pi], where the series converge quickly
+    let angle = angle as f64;
+    angle - TAU * (angle / TAU).round()
+}
+
+pub fn sin(angle: f32) -> f32 {
+    let x = reduce(angle);
+    let (mut term, mut sum) = (x, x);
+    for n in 1..SERIES_TERMS {
+        term *= -x * x / ((2 * n) * (2 * n + 1)) as f64;
+        sum += term;
+    }
+    sum as f32
+}
+
+pub fn cos(angle: f32) -> f32 {
+    let x = reduce(angle);
+    let (mut term, mut sum) = (1., 1.);
+    for n in 1..SERIES_TERMS {
+        term *= -x * x / ((2 * n - 1) * (2 * n)) as f64;
+        sum += term;
+    }
+    sum as f32
+}
+
+fn atan(x: f64) -> f64 {
+    // Fold the argument down below tan(pi/8) with the half-angle identity, so the series needs few terms
+    if x < 0. {
+        return -atan(-x);
+    }
+    if x > 1. {
+        return FRAC_PI_2 - atan(1. / x);
+    }
+    let half = x / (1. + (1. + x * x).sqrt());
+    let (mut power, mut sum) = (half, half);
+    for n in 1..SERIES_TERMS {
+        power *= -half * half;
+        sum += power / (2 * n + 1) as f64;
+    }
+    2. * sum
+}
+
+pub fn exp(x: f32) -> f32 {
+    // Halve the exponent until the series converges quickly, then square the sum back up, with anything beyond
+    // what an f32 can hold clamped to where it's already zero or infinite
+    let mut x = (x as f64).clamp(-128., 128.);
+    let mut squarings = 0;
+    while x.abs() > 0.5 {
+        x /= 2.;
+        squarings += 1;
+    }
+    let (mut term, mut sum) = (1., 1.);
+    for n in 1..SERIES_TERMS {
+        term *= x / n as f64;
+        sum += term;
+    }
+    for _ in 0..squarings {
+        sum *= sum;
+    }
+    sum as f32
+}
+
+pub fn powi(base: f32, exponent: usize) -> f32 {
+    // Multiply the factors in one at a time, in order
+    (0..exponent).fold(1., |power, _| power * base)
+}
+
+pub fn acos(x: f32) -> f32 {
+    // Anything outside [-1, 1] is treated as the nearest end, rather than giving NaN
+    let x = (x as f64).clamp(-1., 1.);
+    if x == -1. {
+        return PI as f32;
+    }
+    (2. * atan(((1. - x) / (1. + x)).sqrt())) as f32
+}
@@ -0,0 +1,187 @@
+use piston_window::*;
+
+use crate::bot::{GreedyBot, Strategy};
+use crate::commands::CommandQueue;
+use crate::label::{self, Align, TextStyle};
+use crate::mutators;
+use crate::run::RunSetup;
+use crate::theme;
+use crate::ui::{Frame, Ui};
+use crate::Game;
+
+// Seconds the main menu sits idle before a demo game starts playing behind it
+const ATTRACT_DELAY: f32 = 30.;
+
+pub struct Demo {
+    // Stores the game the bot is playing
+    pub game: Game,
+    // Stores the bot playing it
+    bot: GreedyBot,
+    // Stores the commands the bot has issued
+    commands: CommandQueue,
+}
+
+pub enum MenuChoice {
+    Play,
+    Saves,
+    Quit,
+    ToggleMutator(&'static str),
+}
+
+pub struct Menu {
+    // Stores whether the menu is showing, which it does until the player starts playing
+    pub open: bool,
+    // Stores how long the menu has gone without any input, in seconds
+    idle: f32,
+    // Stores the demo game playing behind the menu while it's idle, if there is one
+    pub demo: Option<Demo>,
+}
+
+impl Menu {
+    pub fn new(open: bool) -> Self {
+        Menu {
+            open,
+            idle: 0.,
+            demo: None,
+        }
+    }
+
+    pub fn handle(&mut self, event: &Event) -> bool {
+        // Any input wakes the menu up, and input that stops a demo isn't used for anything else
+        if event.press_args().is_none() && event.mouse_cursor_args().is_none() {
+            return false;
+        }
+        self.idle = 0.;
+        event.press_args().is_some() && self.demo.take().is_some()
+    }
+
+    pub fn update(&mut self, setup: &RunSetup, dt: f32) {
+        // Start a demo once the menu's been idle long enough, starting it over whenever a run ends
+        self.idle += dt;
+        if self.idle >= ATTRACT_DELAY
+            && self
+                .demo
+                .as_ref()
+                .is_none_or(|demo| demo.game.state.outcome.is_some())
+        {
+            match setup.start() {
+                Ok(game) => {
+                    self.demo = Some(Demo {
+                        game,
+                        bot: GreedyBot::new(),
+                        commands: CommandQueue::new(),
+                    })
+                }
+                Err(error) => {
+                    eprintln!("Couldn't start the demo: {}", error);
+                    self.idle = 0.;
+                }
+            }
+        }
+        if let Some(demo) = &mut self.demo {
+            for command in demo.bot.decide(&demo.game) {
+                demo.commands.issue(command, &mut demo.game, false);
+            }
+            demo.game.update(dt);
+        }
+    }
+
+    pub fn draw(
+        &self,
+        level: &str,
+        mutators: Option<&[String]>,
+        ui: &mut Ui,
+        frame: &mut Frame,
+    ) -> Option<MenuChoice> {
+        // Show the menu, or just a reminder of how to leave the demo while one is playing, with a switch for each
+        // mutator that's lit while it's picked, if the run's mutators can still be changed
+        let c = frame.c;
+        let style = TextStyle {
+            size: 20,
+            align: Align::Center,
+            outline: Some(theme::palette().text_shadow),
+            ..Default::default()
+        };
+        if self.demo.is_some() {
+            label::draw(
+                "Demo - press any key",
+                &style,
+                320.0,
+                40.0,
+                frame.glyphs,
+                c,
+                frame.g,
+            );
+            return None;
+        }
+        ui.panel([120.0, 40.0, 400.0, 400.0], frame);
+        label::draw(
+            "Tower Defense",
+            &TextStyle { size: 32, ..style },
+            320.0,
+            90.0,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+        label::draw(level, &style, 320.0, 120.0, frame.glyphs, c, frame.g);
+        if ui.button(
+            "play",
+            "Play",
+            [220.0, 145.0, 200.0, 30.0],
+            true,
+            false,
+            frame,
+        ) {
+            return Some(MenuChoice::Play);
+        }
+        if ui.button(
+            "saved runs",
+            "Saved runs",
+            [220.0, 185.0, 200.0, 30.0],
+            true,
+            false,
+            frame,
+        ) {
+            return Some(MenuChoice::Saves);
+        }
+        if ui.button(
+            "quit",
+            "Quit",
+            [220.0, 225.0, 200.0, 30.0],
+            true,
+            false,
+            frame,
+        ) {
+            return Some(MenuChoice::Quit);
+        }
+        let picked = mutators?;
+        label::draw(
+            "Mutators",
+            &TextStyle { size: 16, ..style },
+            320.0,
+            285.0,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+        let mut choice = None;
+        for (i, name) in mutators::NAMES.into_iter().enumerate() {
+            let Some(mutator) = mutators::from_name(name) else {
+                continue;
+            };
+            let rect = [
+                140.0 + (i % 2) as f64 * 190.0,
+                300.0 + (i / 2) as f64 * 30.0,
+                170.0,
+                24.0,
+            ];
+            let selected = picked.iter().any(|picked| picked == name);
+            let id = format!("mutator {}", name);
+            if ui.button(&id, &mutator.name(), rect, true, selected, frame) {
+                choice = Some(MenuChoice::ToggleMutator(name));
+            }
+        }
+        choice
+    }
+}
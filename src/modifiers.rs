@@ -0,0 +1,224 @@
+use std::fmt;
+
+use crate::{Game, GameState, Tower, TowerType};
+
+// Where a modifier comes from, which decides where it goes in the stack: the tower's own changes first, then whatever
+// affects every tower at once. Upgrades, auras, research, mutators, and debuffs each take their place in the order
+// as the game gains them
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    // The tower's ability, with the given name, while it's active
+    Ability(&'static str),
+    // A boost picked up from loot, for every tower until it wears off
+    Loot,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Ability(name) => write!(f, "{}", name),
+            Source::Loot => write!(f, "Loot boost"),
+        }
+    }
+}
+
+// A change to a tower's stats, as the factor it multiplies each one by
+#[derive(Clone, Copy, PartialEq)]
+pub struct Modifier {
+    // Stores what the change comes from
+    pub source: Source,
+    // Stores the factor the tower's damage is multiplied by
+    pub damage: f32,
+    // Stores the factor the tower's range is multiplied by
+    pub range: f32,
+    // Stores the factor the tower's rate of fire is multiplied by
+    pub rate_of_fire: f32,
+}
+
+impl Modifier {
+    pub fn new(source: Source) -> Self {
+        // A modifier that doesn't change anything yet, for the stats it does change to be filled in
+        Modifier {
+            source,
+            damage: 1.,
+            range: 1.,
+            rate_of_fire: 1.,
+        }
+    }
+
+    fn changes(&self) -> Vec<String> {
+        // Describe each stat the modifier changes, like "x2 damage"
+        [
+            (self.damage, "damage"),
+            (self.range, "range"),
+            (self.rate_of_fire, "fire rate"),
+        ]
+        .into_iter()
+        .filter(|(factor, _)| *factor != 1.)
+        .map(|(factor, stat)| format!("x{} {}", factor, stat))
+        .collect()
+    }
+}
+
+// A tower's stats once every modifier on it has been applied, which is what it fights with
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Stats {
+    // Stores the damage of each shot, before the target's armor
+    pub damage: f32,
+    // Stores the range, before any high ground the tower stands on extends it
+    pub range: f32,
+    // Stores the rate of fire, in shots per second
+    pub rate_of_fire: f32,
+}
+
+impl Stats {
+    pub fn base(tower_type: &TowerType) -> Self {
+        Stats {
+            damage: tower_type.damage as f32,
+            range: tower_type.range,
+            rate_of_fire: tower_type.rate_of_fire,
+        }
+    }
+
+    pub fn resolve(tower_type: &TowerType, modifiers: &[Modifier]) -> Self {
+        // Start from the tower type's own values and apply each modifier in turn, in the order of the stack
+        modifiers
+            .iter()
+            .fold(Stats::base(tower_type), |stats, modifier| Stats {
+                damage: stats.damage * modifier.damage,
+                range: stats.range * modifier.range,
+                rate_of_fire: stats.rate_of_fire * modifier.rate_of_fire,
+            })
+    }
+}
+
+impl Tower {
+    pub fn modifiers(&self, damage_boost: f32) -> Vec<Modifier> {
+        // Gather everything changing the tower's stats right now, ordered into a stack by where each comes from, with
+        // the sort keeping modifiers from the same place in the order they were gathered
+        let mut modifiers: Vec<Modifier> = self.ability_modifier().into_iter().collect();
+        if damage_boost != 1. {
+            modifiers.push(Modifier {
+                damage: damage_boost,
+                ..Modifier::new(Source::Loot)
+            });
+        }
+        modifiers.sort_by_key(|modifier| modifier.source);
+        modifiers
+    }
+
+    pub fn invalidate_stats(&mut self) {
+        // Called whenever a modifier on this tower alone changes, so its stats are resolved again next tick
+        self.stats_generation = None;
+    }
+
+    pub fn breakdown(&self, damage_boost: f32) -> Vec<String> {
+        // One line for each modifier in the stack, saying what it changes, for the tower panel
+        self.modifiers(damage_boost)
+            .iter()
+            .map(|modifier| format!("{}: {}", modifier.source, modifier.changes().join(", ")))
+            .collect()
+    }
+}
+
+impl GameState {
+    pub fn set_damage_boost(&mut self, damage_boost: f32) {
+        // A new boost changes every tower's stats at once
+        if self.damage_boost != damage_boost {
+            self.damage_boost = damage_boost;
+            self.modifier_generation += 1;
+        }
+    }
+}
+
+impl Game {
+    pub fn resolve_tower_stats(&mut self) {
+        // Work out the stats every tower fights with this tick, keeping the ones already worked out unless a modifier
+        // on the tower or on every tower has changed since
+        let state = &mut self.state;
+        let generation = Some(state.modifier_generation);
+        for tower in state.towers.iter_mut() {
+            if tower.stats_generation == generation {
+                continue;
+            }
+            tower.stats = Stats::resolve(&tower.tower_type, &tower.modifiers(state.damage_boost));
+            tower.stats_generation = generation;
+            state.stat_resolves += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content;
+    use crate::Point;
+
+    fn game_with_towers() -> Game {
+        // A game with an Archer Tower and a Mage Tower, whose ability is an Overcharge doubling its damage, with their
+        // stats resolved once already
+        let mut game = content::new_game(&content::default_level(), 0).unwrap();
+        for (i, x) in [0., 100.].into_iter().enumerate() {
+            let tower_type = game.tower_types[i].clone();
+            game.state
+                .towers
+                .push(Tower::new(i + 1, Point::new(x, 0.), tower_type));
+        }
+        game.resolve_tower_stats();
+        game
+    }
+
+    #[test]
+    fn unchanged_stats_are_not_resolved_again() {
+        let mut game = game_with_towers();
+        assert_eq!(game.state.stat_resolves, 2);
+        let stats: Vec<Stats> = game.state.towers.iter().map(|tower| tower.stats).collect();
+        game.resolve_tower_stats();
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 2);
+        assert!(game
+            .state
+            .towers
+            .iter()
+            .zip(stats)
+            .all(|(tower, stats)| tower.stats == stats));
+    }
+
+    #[test]
+    fn ability_invalidates_only_its_tower() {
+        let mut game = game_with_towers();
+        game.use_ability(Point::new(100., 0.));
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 3);
+        assert_eq!(game.state.towers[1].stats.damage, 20.);
+        assert_eq!(game.state.towers[0].stats.damage, 5.);
+
+        // Wearing off brings the damage back down, and nothing is resolved while it's still going
+        game.state.towers[1].update_ability(1.);
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 3);
+        game.state.towers[1].update_ability(10.);
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 4);
+        assert_eq!(game.state.towers[1].stats.damage, 10.);
+    }
+
+    #[test]
+    fn damage_boost_invalidates_every_tower_once() {
+        let mut game = game_with_towers();
+        game.state.set_damage_boost(2.);
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 4);
+        assert_eq!(game.state.towers[0].stats.damage, 10.);
+
+        // Setting the boost it already has changes nothing
+        game.state.set_damage_boost(2.);
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 4);
+
+        game.state.set_damage_boost(1.);
+        game.resolve_tower_stats();
+        assert_eq!(game.state.stat_resolves, 6);
+        assert_eq!(game.state.towers[0].stats.damage, 5.);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::Game;
+
+// Names every mutator can be picked by, on the command line or the main menu, in the order the menu lists them
+pub const NAMES: [&str; 7] = [
+    "no-selling",
+    "half-resources",
+    "two-towers",
+    "overkill",
+    "adaptive",
+    "no-director",
+    "endless",
+];
+
+pub trait Mutator {
+    // Returns a short description of how the mutator changes the rules
+    fn name(&self) -> String;
+    // Returns the factor the run's score is multiplied by for playing with the mutator
+    fn score_multiplier(&self) -> f32;
+    // Changes the game's rules, content, or starting state
+    fn apply(&self, game: &mut Game);
+}
+
+pub struct NoSelling;
+
+impl Mutator for NoSelling {
+    fn name(&self) -> String {
+        String::from("No selling")
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        1.2
+    }
+
+    fn apply(&self, game: &mut Game) {
+        game.rules.selling_allowed = false;
+    }
+}
+
+pub struct HalfStartingResources;
+
+impl Mutator for HalfStartingResources {
+    fn name(&self) -> String {
+        String::from("Half starting resources")
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        1.3
+    }
+
+    fn apply(&self, game: &mut Game) {
+        game.state.resources /= 2;
+    }
+}
+
+pub struct LimitedTowerTypes(pub usize);
+
+impl Mutator for LimitedTowerTypes {
+    fn name(&self) -> String {
+        format!("Only {} tower types", self.0)
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        1.5
+    }
+
+    fn apply(&self, game: &mut Game) {
+        game.tower_types.truncate(self.0.max(1));
+    }
+}
+
+pub struct Overkill;
+
+impl Mutator for Overkill {
+    fn name(&self) -> String {
+        String::from("Overkill carries over")
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        0.9
+    }
+
+    fn apply(&self, game: &mut Game) {
+        game.rules.overkill = true;
+    }
+}
+
+pub struct Adaptive;
+
+impl Mutator for Adaptive {
+    fn name(&self) -> String {
+        String::from("Adaptive difficulty")
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        1.
+    }
+
+    fn apply(&self, game: &mut Game) {
+        game.rules.adaptive = true;
+    }
+}
+
+pub struct SteadyPacing;
+
+impl Mutator for SteadyPacing {
+    fn name(&self) -> String {
+        String::from("Steady pacing")
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        1.
+    }
+
+    fn apply(&self, game: &mut Game) {
+        // Waves send their enemies on the level's own timing, however busy the map gets
+        game.rules.director = false;
+    }
+}
+
+pub struct Endless;
+
+impl Mutator for Endless {
+    fn name(&self) -> String {
+        String::from("Endless")
+    }
+
+    fn score_multiplier(&self) -> f32 {
+        1.
+    }
+
+    fn apply(&self, game: &mut Game) {
+        // An endless run has nothing to win, only waves to survive for as long as the player can
+        game.rules.endless = true;
+        game.objectives.clear();
+        game.compose_endless_waves();
+    }
+}
+
+pub fn from_name(name: &str) -> Option<Box<dyn Mutator>> {
+    // Look up a mutator by the name used to select it on the command line
+    match name {
+        "no-selling" => Some(Box::new(NoSelling)),
+        "half-resources" => Some(Box::new(HalfStartingResources)),
+        "two-towers" => Some(Box::new(LimitedTowerTypes(2))),
+        "overkill" => Some(Box::new(Overkill)),
+        "adaptive" => Some(Box::new(Adaptive)),
+        "no-director" => Some(Box::new(SteadyPacing)),
+        "endless" => Some(Box::new(Endless)),
+        _ => None,
+    }
+}
@@ -0,0 +1,161 @@
+use crate::damage;
+use crate::{Enemy, Game, Path, Point, TILE_SIZE};
+
+// Distance from the land path within which an amphibious enemy can come ashore from the lane, in pixels
+const LANDING_RANGE: f32 = TILE_SIZE * 1.5;
+// Distance between the points along the lane checked for a place to come ashore, in pixels
+const LANDING_STEP: f32 = 5.;
+// Extra pixels each pixel of a trip counts as for every point of damage per second the towers covering it deal, so
+// amphibious enemies lean towards landings that keep them out of the heaviest fire
+const DAMAGE_AVERSION: f32 = 0.01;
+
+// Which of the level's routes an enemy can travel
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Terrain {
+    // Walks the land path
+    #[default]
+    Land,
+    // Sails the water lane, and never leaves it
+    Water,
+    // Swims the water lane, coming ashore onto the land path wherever that makes for the shortest trip to the base
+    Amphibious,
+}
+
+// The route boats take across the water to the player's base
+pub struct Lane {
+    // Stores the path boats follow, from their spawn point to the player's base
+    pub path: Path,
+    // Stores how far along the lane amphibious enemies come ashore, and how far along the land path that puts them,
+    // if coming ashore ever makes for a cheaper trip than staying in the water
+    pub landing: Option<(f32, f32)>,
+}
+
+impl Path {
+    pub fn nearest(&self, point: &Point) -> (f32, f32) {
+        // Find how far along the path its closest point to the given one is, and how far away that point is
+        let mut travelled = 0.;
+        let mut nearest = (0., f32::INFINITY);
+        for segment in self.waypoints.windows(2) {
+            let length = segment[0].distance_to(&segment[1]);
+            let along = segment[1].minus(&segment[0]);
+            let t = if length > 0. {
+                (point.minus(&segment[0]).dot(&along) / (length * length)).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let gap = segment[0].plus(&along.scale(t)).distance_to(point);
+            if gap < nearest.1 {
+                nearest = (travelled + t * length, gap);
+            }
+            travelled += length;
+        }
+        nearest
+    }
+}
+
+fn weighted_lengths(path: &Path, exposure: &impl Fn(&Point) -> f32) -> Vec<f32> {
+    // Add up the cost of travelling the path to each point checked along it, LANDING_STEP apart, where every pixel
+    // costs more the more damage the towers covering it deal
+    let mut total = 0.;
+    let mut lengths = vec![0.];
+    for i in 0..(path.length() / LANDING_STEP) as usize {
+        let point = path.point_at((i as f32 + 0.5) * LANDING_STEP);
+        total += LANDING_STEP * (1. + DAMAGE_AVERSION * exposure(&point));
+        lengths.push(total);
+    }
+    lengths
+}
+
+fn choose_landing(
+    path: &Path,
+    land: &Path,
+    exposure: &impl Fn(&Point) -> f32,
+) -> Option<(f32, f32)> {
+    // Of every point where the lane comes close enough to the land path, pick the one leaving the cheapest trip, if
+    // it's any cheaper than staying in the water all the way
+    let lane_costs = weighted_lengths(path, exposure);
+    let land_costs = weighted_lengths(land, exposure);
+    let land_total = land_costs.last().copied().unwrap_or(0.);
+    let trip = |(i, land_distance): (usize, f32)| {
+        let from = ((land_distance / LANDING_STEP) as usize).min(land_costs.len() - 1);
+        lane_costs[i] + land_total - land_costs[from]
+    };
+    let stay = lane_costs.last().copied().unwrap_or(0.);
+    (0..lane_costs.len() - 1)
+        .filter_map(|i| {
+            let (land_distance, gap) = land.nearest(&path.point_at(i as f32 * LANDING_STEP));
+            (gap < LANDING_RANGE).then_some((i, land_distance))
+        })
+        .min_by(|a, b| trip(*a).total_cmp(&trip(*b)))
+        .filter(|landing| trip(*landing) < stay)
+        .map(|(i, land_distance)| (i as f32 * LANDING_STEP, land_distance))
+}
+
+impl Lane {
+    pub fn new(path: Path, land: &Path) -> Self {
+        // Until there are towers to weigh the routes by, amphibious enemies just take the shortest trip
+        let landing = choose_landing(&path, land, &|_: &Point| 0.);
+        Lane { path, landing }
+    }
+}
+
+impl Enemy {
+    pub fn route<'a>(&self, path: &'a Path, lane: Option<&'a Lane>) -> &'a Path {
+        match lane {
+            Some(lane) if self.on_lane => &lane.path,
+            _ => path,
+        }
+    }
+
+    pub fn come_ashore(&mut self, path: &Path, lane: &Lane) {
+        // Amphibious enemies leave the lane for the land path once they reach the landing, keeping any distance
+        // they overshot it by, while ones already well past it when it moved stay in the water
+        if !self.on_lane || self.enemy_type.terrain != Terrain::Amphibious {
+            return;
+        }
+        let Some((lane_distance, land_distance)) = lane.landing.filter(|landing| {
+            self.distance >= landing.0 && self.distance < landing.0 + LANDING_RANGE
+        }) else {
+            return;
+        };
+        self.on_lane = false;
+        self.distance = land_distance + self.distance - lane_distance;
+        self.heading = path.heading_at(self.distance);
+        self.position = path.point_at(self.distance);
+        self.layer = path.layer_at(self.distance);
+    }
+}
+
+impl Game {
+    pub fn weigh_landing(&mut self) {
+        // Pick where amphibious enemies come ashore by the damage the towers would deal them along each way to the
+        // base as well as its length, so they slip past whichever side of the defense is weakest
+        let Some(lane) = &self.lane else {
+            return;
+        };
+        let (towers, map) = (&self.state.towers, &self.map);
+        let exposure = |point: &Point| {
+            towers
+                .iter()
+                .filter(|tower| map.can_hit(&tower.position, tower.stats.range, point))
+                .map(|tower| damage::shot_damage(tower) as f32 * tower.stats.rate_of_fire)
+                .sum::<f32>()
+        };
+        let landing = choose_landing(&lane.path, &self.path, &exposure);
+        if let Some(lane) = &mut self.lane {
+            lane.landing = landing;
+        }
+    }
+
+    pub fn terrain_allows(&self, tower_type: usize, position: &Point) -> bool {
+        // Floating towers can only go on water, and every other tower only on land
+        let center = position.plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.));
+        let tile = center.scale(1. / TILE_SIZE);
+        let water = self
+            .map
+            .is_water(tile.x.floor() as i32, tile.y.floor() as i32);
+        self.tower_types
+            .get(tower_type)
+            .is_some_and(|tower_type| tower_type.floating == water)
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Mutex;
+
+use piston_window::*;
+
+use crate::label::{self, Align, TextStyle};
+
+// Seconds a notification stays on screen
+const NOTIFICATION_DURATION: f32 = 3.;
+// Seconds notifications take to fade in and out
+const NOTIFICATION_FADE: f32 = 0.4;
+// Most notifications shown at once, with older ones making way for new ones
+const MAX_NOTIFICATIONS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    // Something happened the player might want to know about
+    Info,
+    // Something the player tried didn't work, or something dangerous is coming
+    Warning,
+    // The player accomplished something worth celebrating
+    Achievement,
+}
+
+struct Notification {
+    // Stores the notification's message
+    text: String,
+    // Stores what sort of notification it is, which decides its color
+    kind: Kind,
+    // Stores how long the notification has been on screen, in seconds
+    age: f32,
+}
+
+// Notifications waiting to be shown or on screen, oldest first, shared so any module can post one
+static QUEUE: Mutex<Vec<Notification>> = Mutex::new(Vec::new());
+
+pub fn notify(text: impl Into<String>, kind: Kind) {
+    // Show a message to the player for a few seconds, without repeating one that's already showing
+    let text = text.into();
+    let mut queue = QUEUE.lock().unwrap_or_else(|error| error.into_inner());
+    if let Some(existing) = queue
+        .iter_mut()
+        .find(|notification| notification.text == text)
+    {
+        existing.age = existing.age.min(NOTIFICATION_FADE);
+        return;
+    }
+    if queue.len() == MAX_NOTIFICATIONS {
+        queue.remove(0);
+    }
+    queue.push(Notification {
+        text,
+        kind,
+        age: 0.,
+    });
+}
+
+pub fn update(dt: f32) {
+    // Age the notifications on screen, dropping the ones that have finished fading out
+    let mut queue = QUEUE.lock().unwrap_or_else(|error| error.into_inner());
+    for notification in queue.iter_mut() {
+        notification.age += dt;
+    }
+    queue.retain(|notification| notification.age < NOTIFICATION_DURATION);
+}
+
+pub fn draw(c: Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    // Stack notifications upwards from above the build menu, newest at the bottom
+    let queue = QUEUE.lock().unwrap_or_else(|error| error.into_inner());
+    for (i, notification) in queue.iter().rev().enumerate() {
+        let fade_in = notification.age / NOTIFICATION_FADE;
+        let fade_out = (NOTIFICATION_DURATION - notification.age) / NOTIFICATION_FADE;
+        let alpha = fade_in.min(fade_out).clamp(0., 1.);
+        let color = match notification.kind {
+            Kind::Info => [0.9, 0.9, 0.95, alpha],
+            Kind::Warning => [1.0, 0.85, 0.7, alpha],
+            Kind::Achievement => [0.95, 0.9, 0.5, alpha],
+        };
+        let y = 405.0 - i as f64 * 35.0;
+        rectangle(color, [180.0, y, 280.0, 30.0], c.transform, g);
+        let style = TextStyle {
+            size: 14,
+            color: [0.0, 0.0, 0.0, alpha],
+            align: Align::Center,
+            ..Default::default()
+        };
+        label::draw(&notification.text, &style, 320.0, y + 20.0, glyphs, c, g);
+    }
+}
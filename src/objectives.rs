@@ -0,0 +1,201 @@
+use crate::level::parse_number;
+use crate::stats::format_time;
+use crate::{Game, Outcome, CAMPAIGN_WAVES};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+    Pending,
+    Complete,
+    Failed,
+}
+
+pub trait Objective {
+    // Returns a short description of what the player has to do, shown on the HUD
+    fn describe(&self) -> String;
+    // Returns how far the player is towards completing the objective, from 0 to 1
+    fn progress(&self, game: &Game) -> f32;
+    // Checks whether the objective has been completed or failed
+    fn status(&self, game: &Game) -> Status;
+}
+
+fn waves_cleared(game: &Game) -> bool {
+    game.state.wave > CAMPAIGN_WAVES && game.state.enemies.is_empty()
+}
+
+fn wave_progress(game: &Game) -> f32 {
+    (game.state.wave - 1) as f32 / CAMPAIGN_WAVES as f32
+}
+
+// Win by clearing every wave of the campaign, which is what levels ask for unless they say otherwise
+pub struct ClearWaves;
+
+impl Objective for ClearWaves {
+    fn describe(&self) -> String {
+        format!("Clear all {} waves", CAMPAIGN_WAVES)
+    }
+
+    fn progress(&self, game: &Game) -> f32 {
+        wave_progress(game)
+    }
+
+    fn status(&self, game: &Game) -> Status {
+        if waves_cleared(game) {
+            Status::Complete
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+// Win by holding out for the given number of seconds, however many waves that takes
+pub struct Survive(pub f32);
+
+impl Objective for Survive {
+    fn describe(&self) -> String {
+        format!("Survive for {}", format_time(self.0))
+    }
+
+    fn progress(&self, game: &Game) -> f32 {
+        game.state.stats.playtime / self.0
+    }
+
+    fn status(&self, game: &Game) -> Status {
+        if game.state.stats.playtime >= self.0 {
+            Status::Complete
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+// Clear every wave without ever falling below the given number of lives
+pub struct KeepLives(pub i32);
+
+impl Objective for KeepLives {
+    fn describe(&self) -> String {
+        format!("Keep at least {} lives through every wave", self.0)
+    }
+
+    fn progress(&self, game: &Game) -> f32 {
+        wave_progress(game)
+    }
+
+    fn status(&self, game: &Game) -> Status {
+        if game.state.lives < self.0 {
+            Status::Failed
+        } else if waves_cleared(game) {
+            Status::Complete
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+// Get every unit of the level's convoys across the map without losing any of them
+pub struct Escort;
+
+impl Objective for Escort {
+    fn describe(&self) -> String {
+        String::from("Escort every convoy unit across the map")
+    }
+
+    fn progress(&self, game: &Game) -> f32 {
+        game.state.convoy_arrived as f32 / game.convoy_size().max(1) as f32
+    }
+
+    fn status(&self, game: &Game) -> Status {
+        if game.state.convoy_lost > 0 {
+            Status::Failed
+        } else if game.state.convoy_arrived >= game.convoy_size() {
+            Status::Complete
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+// Clear every wave without selling a single tower
+pub struct NoSelling;
+
+impl Objective for NoSelling {
+    fn describe(&self) -> String {
+        String::from("Don't sell any towers")
+    }
+
+    fn progress(&self, game: &Game) -> f32 {
+        wave_progress(game)
+    }
+
+    fn status(&self, game: &Game) -> Status {
+        if game.state.stats.towers_sold > 0 {
+            Status::Failed
+        } else if waves_cleared(game) {
+            Status::Complete
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+// Stop every enemy a puzzle level sends, where a single one getting through fails it
+pub struct SolvePuzzle;
+
+impl Objective for SolvePuzzle {
+    fn describe(&self) -> String {
+        String::from("Stop every enemy")
+    }
+
+    fn progress(&self, game: &Game) -> f32 {
+        (game.state.wave - 1) as f32 / game.waves.len().max(1) as f32
+    }
+
+    fn status(&self, game: &Game) -> Status {
+        if game.state.stats.leaks > 0 {
+            Status::Failed
+        } else if game.state.wave > game.waves.len() && game.state.enemies.is_empty() {
+            Status::Complete
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+pub fn from_spec(spec: &str) -> Result<Box<dyn Objective>, String> {
+    // Build an objective from how it's written in a level file, like `survive 300`
+    let (name, value) = spec.split_once(' ').unwrap_or((spec, ""));
+    match name {
+        "waves" => Ok(Box::new(ClearWaves)),
+        "survive" => Ok(Box::new(Survive(parse_number(value)?))),
+        "lives" => Ok(Box::new(KeepLives(parse_number(value)?))),
+        "escort" => Ok(Box::new(Escort)),
+        "no-selling" => Ok(Box::new(NoSelling)),
+        _ => Err(format!("Unknown objective: {}", spec)),
+    }
+}
+
+pub fn evaluate(objectives: &[Box<dyn Objective>], game: &Game) -> Option<Outcome> {
+    // The run is lost as soon as any objective fails, and won once every one of them is complete, while a run without
+    // any objectives, like an endless one, can only be lost
+    if objectives.is_empty() {
+        return None;
+    }
+    let statuses: Vec<Status> = objectives
+        .iter()
+        .map(|objective| objective.status(game))
+        .collect();
+    if statuses.contains(&Status::Failed) {
+        Some(Outcome::Lost)
+    } else if statuses.iter().all(|&status| status == Status::Complete) {
+        Some(Outcome::Won)
+    } else {
+        None
+    }
+}
+
+pub fn stars_earned(bonus_objectives: &[Box<dyn Objective>], game: &Game) -> u32 {
+    // Each bonus objective that's complete is worth a star, while failed ones just don't count
+    bonus_objectives
+        .iter()
+        .filter(|objective| objective.status(game) == Status::Complete)
+        .count() as u32
+}
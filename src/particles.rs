@@ -0,0 +1,71 @@
+use crate::pool::Pool;
+use crate::Point;
+use std::f32::consts::TAU;
+
+// Seconds a particle lasts before fading out
+pub const PARTICLE_LIFETIME: f32 = 0.5;
+// Number of particles in the burst left by a defeated enemy
+const BURST_SIZE: usize = 8;
+// Speed burst particles fly out at, in pixels per second
+const BURST_SPEED: f32 = 60.;
+// Seconds a tower's shot takes to fly to its target
+pub const SHOT_DURATION: f32 = 0.12;
+
+#[derive(Clone, Copy)]
+pub struct Particle {
+    // Stores where the particle is on the map
+    pub position: Point,
+    // Stores the particle's velocity, in pixels per second
+    velocity: Point,
+    // Stores how long the particle has left, in seconds
+    pub time_left: f32,
+}
+
+impl Particle {
+    pub fn update(&mut self, dt: f32) {
+        self.position = self.position.plus(&self.velocity.scale(dt));
+        self.time_left -= dt;
+    }
+}
+
+pub fn burst(particles: &mut Vec<Particle>, pool: &mut Pool<Particle>, position: Point) {
+    // Scatter particles evenly in every direction from the given point
+    for i in 0..BURST_SIZE {
+        let angle = i as f32 * TAU / BURST_SIZE as f32;
+        let particle = Particle {
+            position,
+            velocity: Point::new(angle.cos(), angle.sin()).scale(BURST_SPEED),
+            time_left: PARTICLE_LIFETIME,
+        };
+        pool.spawn(particles, || particle, |recycled| *recycled = particle);
+    }
+}
+
+// A bolt flying from a tower to what it hit, purely for show, since the hit lands the moment the tower fires
+#[derive(Clone, Copy)]
+pub struct Shot {
+    // Stores where the shot was fired from
+    pub from: Point,
+    // Stores where the shot is headed
+    pub to: Point,
+    // Stores the shot's color
+    pub color: [f32; 4],
+    // Stores how long the shot has left to fly, in seconds
+    pub time_left: f32,
+}
+
+impl Shot {
+    pub fn new(from: Point, to: Point, color: [f32; 4]) -> Self {
+        Shot {
+            from,
+            to,
+            color,
+            time_left: SHOT_DURATION,
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        let progress = 1. - (self.time_left / SHOT_DURATION).clamp(0., 1.);
+        self.from.plus(&self.to.minus(&self.from).scale(progress))
+    }
+}
@@ -0,0 +1,273 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use piston_window::*;
+
+use crate::camera::{Mode, Pointer};
+use crate::label::{self, TextStyle};
+use crate::notifications::{notify, Kind};
+use crate::ui::{Frame, Ui};
+use crate::Point;
+
+// How much each notch of the mouse wheel zooms by
+const ZOOM_STEP: f32 = 1.1;
+// Number of bands the vignette darkens towards the edges of the view in
+const VIGNETTE_BANDS: usize = 12;
+// How far in from the edges of the view the vignette reaches, as a share of its shorter side
+const VIGNETTE_REACH: f64 = 0.3;
+// How dark the vignette's outermost band is
+const VIGNETTE_STRENGTH: f32 = 0.25;
+
+// A color laid over the whole view in photo mode
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tint {
+    None,
+    Warm,
+    Cool,
+    Dusk,
+}
+
+impl Tint {
+    fn name(&self) -> &'static str {
+        match self {
+            Tint::None => "none",
+            Tint::Warm => "warm",
+            Tint::Cool => "cool",
+            Tint::Dusk => "dusk",
+        }
+    }
+
+    fn next(&self) -> Tint {
+        match self {
+            Tint::None => Tint::Warm,
+            Tint::Warm => Tint::Cool,
+            Tint::Cool => Tint::Dusk,
+            Tint::Dusk => Tint::None,
+        }
+    }
+
+    fn color(&self) -> Option<[f32; 4]> {
+        match self {
+            Tint::None => None,
+            Tint::Warm => Some([1.0, 0.6, 0.2, 0.18]),
+            Tint::Cool => Some([0.2, 0.45, 1.0, 0.18]),
+            Tint::Dusk => Some([0.45, 0.15, 0.55, 0.25]),
+        }
+    }
+}
+
+// What the player clicked on the photo mode panel
+pub enum PhotoChoice {
+    NextTint,
+    ToggleVignette,
+    Capture,
+    Close,
+}
+
+// Photo mode, where the game is paused with the HUD hidden so the player can frame a screenshot of the map from
+// anywhere at any zoom
+pub struct Photo {
+    // Stores the color laid over the view
+    pub tint: Tint,
+    // Stores whether the edges of the view are darkened
+    pub vignette: bool,
+    // Stores whether the next frame drawn is saved as a screenshot, without the panel in it
+    pub capturing: bool,
+    // Stores where the camera was before photo mode, to go back to after
+    camera: Point,
+    // Stores whether the game was already paused before photo mode
+    was_paused: bool,
+}
+
+impl Photo {
+    pub fn start(pointer: &mut Pointer, paused: bool) -> Photo {
+        // Let the camera go anywhere, dropping whatever the player was placing or selecting
+        let photo = Photo {
+            tint: Tint::None,
+            vignette: false,
+            capturing: false,
+            camera: pointer.camera,
+            was_paused: paused,
+        };
+        pointer.free = true;
+        pointer.mode = Mode::Idle;
+        photo
+    }
+
+    pub fn finish(self, pointer: &mut Pointer) -> bool {
+        // Put the camera back as it was, returning whether the game should stay paused
+        pointer.camera = self.camera;
+        pointer.zoom = 1.;
+        pointer.free = false;
+        self.was_paused
+    }
+
+    pub fn zoom(pointer: &mut Pointer, mouse: &Point, notches: f64) {
+        pointer.zoom_at(mouse, ZOOM_STEP.powf(notches as f32));
+    }
+
+    pub fn draw_filters(&self, c: Context, g: &mut G2d) {
+        // Lay the tint over the whole view, then darken it in bands towards the edges for the vignette
+        let [width, height] = c.get_view_size();
+        if let Some(color) = self.tint.color() {
+            rectangle(color, [0.0, 0.0, width, height], c.transform, g);
+        }
+        if !self.vignette {
+            return;
+        }
+        let band = width.min(height) * VIGNETTE_REACH / VIGNETTE_BANDS as f64;
+        for i in 0..VIGNETTE_BANDS {
+            let fade = 1. - i as f32 / VIGNETTE_BANDS as f32;
+            let color = [0.0, 0.0, 0.0, VIGNETTE_STRENGTH * fade * fade];
+            let inset = i as f64 * band;
+            let (inner_width, inner_height) = (width - inset * 2.0, height - inset * 2.0);
+            for rect in [
+                [inset, inset, inner_width, band],
+                [inset, height - inset - band, inner_width, band],
+                [inset, inset + band, band, inner_height - band * 2.0],
+                [
+                    width - inset - band,
+                    inset + band,
+                    band,
+                    inner_height - band * 2.0,
+                ],
+            ] {
+                rectangle(color, rect, c.transform, g);
+            }
+        }
+    }
+
+    pub fn draw_panel(&self, ui: &mut Ui, frame: &mut Frame) -> Option<PhotoChoice> {
+        // Draw the filters and buttons along the bottom of the screen, where the build menu usually is
+        let mut choice = None;
+        ui.panel([0.0, 420.0, 640.0, 60.0], frame);
+        let style = TextStyle {
+            size: 12,
+            ..Default::default()
+        };
+        label::draw(
+            "Photo mode: scroll to zoom, drag to pan",
+            &style,
+            10.0,
+            437.0,
+            frame.glyphs,
+            frame.c,
+            frame.g,
+        );
+        let buttons = [
+            (
+                "photo tint",
+                format!("Tint: {}", self.tint.name()),
+                PhotoChoice::NextTint,
+            ),
+            (
+                "photo vignette",
+                format!("Vignette: {}", if self.vignette { "on" } else { "off" }),
+                PhotoChoice::ToggleVignette,
+            ),
+            (
+                "photo capture",
+                String::from("Capture"),
+                PhotoChoice::Capture,
+            ),
+            ("photo close", String::from("Close"), PhotoChoice::Close),
+        ];
+        for (i, (id, caption, button)) in buttons.into_iter().enumerate() {
+            if ui.button(
+                id,
+                &caption,
+                [5.0 + i as f64 * 160.0, 445.0, 155.0, 25.0],
+                true,
+                false,
+                frame,
+            ) {
+                choice = Some(button);
+            }
+        }
+        choice
+    }
+}
+
+pub fn toggle(photo: &mut Option<Photo>, pointer: &mut Pointer, paused: &mut bool) {
+    // Go into photo mode, pausing the game, or back out of it, where the game stays paused only if it already was
+    match photo.take() {
+        Some(taken) => *paused = taken.finish(pointer),
+        None => {
+            *photo = Some(Photo::start(pointer, *paused));
+            *paused = true;
+        }
+    }
+}
+
+pub fn choose(
+    photo: &mut Option<Photo>,
+    choice: PhotoChoice,
+    pointer: &mut Pointer,
+    paused: &mut bool,
+) {
+    // Act on what the player clicked on the panel, where closing it leaves photo mode
+    let Some(taken) = photo else {
+        return;
+    };
+    match choice {
+        PhotoChoice::NextTint => taken.tint = taken.tint.next(),
+        PhotoChoice::ToggleVignette => taken.vignette = !taken.vignette,
+        PhotoChoice::Capture => taken.capturing = true,
+        PhotoChoice::Close => toggle(photo, pointer, paused),
+    }
+}
+
+pub fn save_drawn_frame(photo: &mut Option<Photo>, size: [u32; 2]) {
+    // Save the frame that's just been drawn as a screenshot if one was asked for, telling the player where it went
+    let Some(photo) = photo.as_mut().filter(|photo| photo.capturing) else {
+        return;
+    };
+    photo.capturing = false;
+    match capture(size) {
+        Ok(path) => notify(format!("Saved screenshot to {}", path), Kind::Info),
+        Err(error) => notify(
+            format!("Couldn't save screenshot: {}", error),
+            Kind::Warning,
+        ),
+    }
+}
+
+fn capture(size: [u32; 2]) -> Result<String, String> {
+    // Save the frame that was just drawn as a screenshot named after the time it was taken
+    let rows = read_frame(size);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let path = format!("screenshot-{}.png", millis);
+    let [width, height] = size;
+    ::image::save_buffer(&path, &rows, width, height, ::image::ColorType::Rgba8)
+        .map_err(|error| error.to_string())?;
+    Ok(path)
+}
+
+pub fn read_frame(size: [u32; 2]) -> Vec<u8> {
+    // Read back the frame that was just drawn, once it's finished but before it's shown, which OpenGL hands over
+    // from the bottom row up, as opaque RGBA pixels from the top row down
+    let [width, height] = size;
+    let row = width as usize * 4;
+    let mut pixels = vec![0u8; row * height as usize];
+    // Safety: the window's OpenGL context is current while a frame is drawn, with its functions loaded by the
+    // window, and the buffer holds a whole frame of the size asked for
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+    let mut rows: Vec<u8> = pixels.chunks_exact(row).rev().flatten().copied().collect();
+    for pixel in rows.chunks_exact_mut(4) {
+        pixel[3] = u8::MAX;
+    }
+    rows
+}
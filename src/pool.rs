@@ -0,0 +1,56 @@
+#[derive(Clone)]
+pub struct Pool<T> {
+    // Stores released items, kept around so their allocations can be reused
+    free: Vec<T>,
+    // Stores how many items had to be created from scratch
+    pub created: usize,
+    // Stores how many items were handed out again after being released
+    pub reused: usize,
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Self {
+        Pool {
+            free: Vec::new(),
+            created: 0,
+            reused: 0,
+        }
+    }
+
+    pub fn spawn(
+        &mut self,
+        live: &mut Vec<T>,
+        create: impl FnOnce() -> T,
+        reset: impl FnOnce(&mut T),
+    ) {
+        // Reset a released item if there is one, and only create a new one when the pool is empty
+        let item = match self.free.pop() {
+            Some(mut item) => {
+                reset(&mut item);
+                self.reused += 1;
+                item
+            }
+            None => {
+                self.created += 1;
+                create()
+            }
+        };
+        live.push(item);
+    }
+
+    pub fn release_where(&mut self, live: &mut Vec<T>, mut keep: impl FnMut(&T) -> bool) {
+        // Move the kept items to the front in their original order, and the rest into the pool
+        let mut kept = 0;
+        for i in 0..live.len() {
+            if keep(&live[i]) {
+                live.swap(kept, i);
+                kept += 1;
+            }
+        }
+        self.free.extend(live.drain(kept..));
+    }
+
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
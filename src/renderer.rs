@@ -0,0 +1,546 @@
+use piston_window::*;
+
+use crate::batch::RectBatch;
+use crate::convoy::CONVOY_HIT_POINTS;
+use crate::hazards::{Hazard, BOULDER_RADIUS, FLAME_WIDTH};
+use crate::level::Layer;
+use crate::loot::LOOT_LIFETIME;
+use crate::particles::PARTICLE_LIFETIME;
+use crate::theme;
+use crate::{Game, Path, Point, Tower, TILE_SIZE};
+
+// Width of the path enemies follow, drawn down the middle of its tiles
+pub const PATH_WIDTH: f64 = TILE_SIZE as f64 / 2.0;
+
+// Something the map can be drawn onto, in map coordinates, so the game's look doesn't depend on what puts it on
+// screen
+pub trait Renderer {
+    // Fills the rectangle given as x, y, width, and height
+    fn rectangle(&mut self, color: [f32; 4], rect: [f64; 4]);
+    // Fills the ellipse that fits inside the rectangle given as x, y, width, and height
+    fn ellipse(&mut self, color: [f32; 4], rect: [f64; 4]);
+    // Draws a line between the points given as x1, y1, x2, y2, reaching `radius` out to either side of them
+    fn line(&mut self, color: [f32; 4], radius: f64, line: [f64; 4]);
+    // Draws the arc of the ellipse that fits inside the rectangle from one angle to another, in radians clockwise
+    // from the right, reaching `radius` out to either side of it
+    fn arc(&mut self, color: [f32; 4], radius: f64, angles: [f64; 2], rect: [f64; 4]);
+}
+
+// Draws with Piston's 2D graphics, through whatever transform the context has, like the camera's
+pub struct PistonRenderer<'a, 'b> {
+    // Stores the context giving the transform and draw state
+    pub c: Context,
+    // Stores the graphics backend being drawn with
+    pub g: &'a mut G2d<'b>,
+}
+
+impl Renderer for PistonRenderer<'_, '_> {
+    fn rectangle(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        rectangle(color, rect, self.c.transform, self.g);
+    }
+
+    fn ellipse(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        ellipse(color, rect, self.c.transform, self.g);
+    }
+
+    fn line(&mut self, color: [f32; 4], radius: f64, line: [f64; 4]) {
+        piston_window::line(color, radius, line, self.c.transform, self.g);
+    }
+
+    fn arc(&mut self, color: [f32; 4], radius: f64, angles: [f64; 2], rect: [f64; 4]) {
+        circle_arc(
+            color,
+            radius,
+            angles[0],
+            angles[1],
+            rect,
+            self.c.transform,
+            self.g,
+        );
+    }
+}
+
+// Draws with Piston's 2D graphics like PistonRenderer, but queues rectangles up to go in one draw call, for the many
+// enemies and particles on screen at once, drawing them before any other shape so everything stays in order
+pub struct BatchedRenderer<'a, 'b> {
+    // Stores the renderer anything but rectangles is drawn with
+    pub piston: PistonRenderer<'a, 'b>,
+    // Stores the rectangles queued since anything was last drawn
+    pub batch: &'a mut RectBatch,
+}
+
+impl BatchedRenderer<'_, '_> {
+    pub fn flush(&mut self) {
+        self.batch.draw(&self.piston.c.draw_state, self.piston.g);
+    }
+}
+
+impl Renderer for BatchedRenderer<'_, '_> {
+    fn rectangle(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        self.batch.push(color, rect, self.piston.c.transform);
+    }
+
+    fn ellipse(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        self.flush();
+        self.piston.ellipse(color, rect);
+    }
+
+    fn line(&mut self, color: [f32; 4], radius: f64, line: [f64; 4]) {
+        self.flush();
+        self.piston.line(color, radius, line);
+    }
+
+    fn arc(&mut self, color: [f32; 4], radius: f64, angles: [f64; 2], rect: [f64; 4]) {
+        self.flush();
+        self.piston.arc(color, radius, angles, rect);
+    }
+}
+
+fn line_between(from: &Point, to: &Point, offset: f64) -> [f64; 4] {
+    [
+        from.x as f64 + offset,
+        from.y as f64 + offset,
+        to.x as f64 + offset,
+        to.y as f64 + offset,
+    ]
+}
+
+fn border(color: [f32; 4], radius: f64, rect: [f64; 4], renderer: &mut impl Renderer) {
+    // Outline the rectangle with strips reaching `radius` to either side of its edges
+    let [x, y, width, height] = rect;
+    let thickness = radius * 2.0;
+    renderer.rectangle(
+        color,
+        [x - radius, y - radius, width + thickness, thickness],
+    );
+    renderer.rectangle(
+        color,
+        [
+            x - radius,
+            y + height - radius,
+            width + thickness,
+            thickness,
+        ],
+    );
+    renderer.rectangle(
+        color,
+        [x - radius, y + radius, thickness, height - thickness],
+    );
+    renderer.rectangle(
+        color,
+        [
+            x + width - radius,
+            y + radius,
+            thickness,
+            height - thickness,
+        ],
+    );
+}
+
+fn tile_rect(x: u32, y: u32) -> [f64; 4] {
+    [
+        x as f64 * TILE_SIZE as f64,
+        y as f64 * TILE_SIZE as f64,
+        TILE_SIZE as f64,
+        TILE_SIZE as f64,
+    ]
+}
+
+pub fn draw_path(
+    path: &Path,
+    layer: Layer,
+    color: [f32; 4],
+    width: f64,
+    renderer: &mut impl Renderer,
+) {
+    // Draw the segments of the path on the given layer, as a line down the middle of their tiles
+    for (i, segment) in path.waypoints.windows(2).enumerate() {
+        if path.segment_layer(i) != layer {
+            continue;
+        }
+        renderer.line(
+            color,
+            width,
+            [
+                segment[0].x as f64 + TILE_SIZE as f64 / 2.0,
+                segment[0].y as f64 + TILE_SIZE as f64 / 2.0,
+                segment[1].x as f64 + TILE_SIZE as f64 / 2.0,
+                segment[1].y as f64 + TILE_SIZE as f64 / 2.0,
+            ],
+        );
+    }
+}
+
+pub fn draw_terrain(game: &Game, renderer: &mut impl Renderer) {
+    // Draw the scenery blocking the map's tiles, then the water, and the lane boats follow across it
+    let palette = theme::palette();
+    for (x, y) in game.map.blocked_tiles() {
+        renderer.rectangle(palette.scenery, tile_rect(x, y));
+    }
+    for (x, y) in game.map.water_tiles() {
+        renderer.rectangle(palette.water, tile_rect(x, y));
+    }
+    if let Some(lane) = &game.lane {
+        draw_path(
+            &lane.path,
+            Layer::Ground,
+            palette.lane,
+            TILE_SIZE as f64 / 4.0,
+            renderer,
+        );
+    }
+
+    // Shade high ground darker the higher it is, and valleys a cool blue
+    for (x, y, elevation) in game.map.elevated_tiles() {
+        let color = if elevation < 0 {
+            [0.3, 0.4, 0.7, 0.15]
+        } else {
+            [0.5, 0.4, 0.25, 0.12 * elevation as f32]
+        };
+        renderer.rectangle(color, tile_rect(x, y));
+    }
+
+    // Draw the path the enemies follow, with tunnels showing faintly under the ground, which is drawn over them
+    // where they cross, while bridges are drawn later on top of the enemies below them
+    draw_path(
+        &game.path,
+        Layer::Tunnel,
+        [0.5, 0.45, 0.4, 0.4],
+        PATH_WIDTH,
+        renderer,
+    );
+    draw_path(
+        &game.path,
+        Layer::Ground,
+        palette.path,
+        PATH_WIDTH,
+        renderer,
+    );
+}
+
+pub fn draw_bridges(game: &Game, renderer: &mut impl Renderer) {
+    // Draw the stretches of path on bridges as planks with darker edges
+    draw_path(
+        &game.path,
+        Layer::Bridge,
+        [0.4, 0.3, 0.2, 1.0],
+        PATH_WIDTH + 6.0,
+        renderer,
+    );
+    draw_path(
+        &game.path,
+        Layer::Bridge,
+        [0.7, 0.55, 0.35, 1.0],
+        PATH_WIDTH,
+        renderer,
+    );
+}
+
+pub fn draw_base(renderer: &mut impl Renderer) {
+    renderer.rectangle(theme::palette().base, [0.0, 0.0, 50.0, 50.0]);
+}
+
+pub fn draw_tower(tower: &Tower, renderer: &mut impl Renderer) {
+    renderer.ellipse(
+        theme::palette().tower,
+        [
+            tower.position.x as f64,
+            tower.position.y as f64,
+            TILE_SIZE as f64,
+            TILE_SIZE as f64,
+        ],
+    );
+}
+
+fn draw_swirl(position: &Point, color: [f32; 4], turn: f64, renderer: &mut impl Renderer) {
+    // Draw a teleporter tile as arcs of growing size spinning around its center
+    let center = [
+        position.x as f64 + TILE_SIZE as f64 / 2.0,
+        position.y as f64 + TILE_SIZE as f64 / 2.0,
+    ];
+    for i in 0..3 {
+        let radius = TILE_SIZE as f64 * (0.2 + 0.12 * i as f64);
+        let start = turn + i as f64 * std::f64::consts::TAU / 3.0;
+        renderer.arc(
+            color,
+            1.5,
+            [start, start + std::f64::consts::PI],
+            [
+                center[0] - radius,
+                center[1] - radius,
+                radius * 2.0,
+                radius * 2.0,
+            ],
+        );
+    }
+}
+
+pub fn draw_scenery(game: &Game, renderer: &mut impl Renderer) {
+    // Draw the way round each gate enemies aren't taking faintly, with a bar across the path where a gate is closed,
+    // and each gate's lever thrown the way the gate is, grayed out while it resets
+    let palette = theme::palette();
+    let half_tile = TILE_SIZE as f64 / 2.0;
+    for gate in game.gates.iter() {
+        let unused = gate.stretch(&game.route, !gate.closed);
+        for segment in unused.windows(2) {
+            renderer.line(
+                theme::faded(palette.path, 0.3),
+                PATH_WIDTH,
+                line_between(&segment[0], &segment[1], half_tile),
+            );
+        }
+        let fork = game.route.waypoints[gate.from];
+        let along = game.route.waypoints[gate.from + 1].minus(&fork);
+        if gate.closed && along.length() > 0. {
+            let direction = along.scale(1. / along.length());
+            let middle = fork.plus(&direction.scale(TILE_SIZE));
+            let across = direction.normal().scale(TILE_SIZE / 2.);
+            let (start, end) = (middle.minus(&across), middle.plus(&across));
+            renderer.line(
+                [0.7, 0.1, 0.1, 1.0],
+                3.0,
+                line_between(&start, &end, half_tile),
+            );
+        }
+        let (x, y) = (gate.lever.x as f64, gate.lever.y as f64);
+        let color = if gate.cooldown > 0. {
+            [0.5, 0.5, 0.5, 1.0]
+        } else {
+            [0.45, 0.3, 0.15, 1.0]
+        };
+        renderer.rectangle(color, [x + 6.0, y + 15.0, 13.0, 6.0]);
+        let tilt = if gate.closed { 6.0 } else { -6.0 };
+        renderer.line(color, 2.0, [x + 12.5, y + 15.0, x + 12.5 + tilt, y + 4.0]);
+    }
+
+    // Draw the wind and conveyor areas, with streaks drifting across each tile the way they push
+    let time = game.state.stats.playtime;
+    for wind in game.winds.iter() {
+        renderer.rectangle(
+            [0.6, 0.8, 0.9, 0.25],
+            [
+                wind.corner.x as f64,
+                wind.corner.y as f64,
+                wind.size.x as f64,
+                wind.size.y as f64,
+            ],
+        );
+        let strength = wind.force.length();
+        if strength <= 0. {
+            continue;
+        }
+        let direction = wind.force.scale(1. / strength);
+        let drift = ((time * strength / TILE_SIZE).fract() - 0.5) * TILE_SIZE / 2.;
+        let columns = (wind.size.x / TILE_SIZE).ceil() as i32;
+        let rows = (wind.size.y / TILE_SIZE).ceil() as i32;
+        for (x, y) in (0..columns).flat_map(|x| (0..rows).map(move |y| (x, y))) {
+            let center = wind.corner.plus(&Point::new(
+                (x as f32 + 0.5) * TILE_SIZE,
+                (y as f32 + 0.5) * TILE_SIZE,
+            ));
+            let tail = center.plus(&direction.scale(drift - 4.));
+            let head = center.plus(&direction.scale(drift + 4.));
+            renderer.line([0.3, 0.5, 0.7, 0.8], 1.0, line_between(&tail, &head, 0.0));
+        }
+    }
+
+    // Draw each teleporter's entry and exit, swirling in opposite directions
+    let turn = time as f64 * 3.0;
+    for teleporter in game.path.teleporters.iter() {
+        let entry = game.path.point_at(teleporter.entry);
+        let exit = game.path.point_at(teleporter.exit);
+        draw_swirl(&entry, [0.5, 0.1, 0.7, 1.0], turn, renderer);
+        draw_swirl(&exit, [0.8, 0.5, 1.0, 1.0], -turn, renderer);
+    }
+
+    // Draw the slots towers can be built on, filled in faintly while they're free
+    for slot in game.slots.iter() {
+        let square = [
+            slot.x as f64 + 1.0,
+            slot.y as f64 + 1.0,
+            TILE_SIZE as f64 - 2.0,
+            TILE_SIZE as f64 - 2.0,
+        ];
+        if game.tower_at(slot).is_none() {
+            renderer.rectangle([0.9, 0.8, 0.5, 0.4], square);
+        }
+        border([0.6, 0.5, 0.3, 1.0], 1.0, square, renderer);
+    }
+}
+
+pub fn draw_units(
+    game: &Game,
+    alpha: f32,
+    visible: impl Fn(&Point, f32) -> bool,
+    renderer: &mut impl Renderer,
+) -> usize {
+    // Draw everything that moves, blended between the last two ticks by alpha, skipping what isn't visible, and
+    // returning how many enemies were drawn. Enemies go a layer at a time from the bottom up, with the bridges going
+    // over the enemies on the ground and under the ones crossing them
+    let palette = theme::palette();
+    let mut enemies_drawn = 0;
+    for layer in [Layer::Tunnel, Layer::Ground, Layer::Bridge] {
+        if layer == Layer::Bridge {
+            draw_bridges(game, renderer);
+        }
+        for enemy in game
+            .state
+            .enemies
+            .iter()
+            .filter(|enemy| enemy.layer == layer)
+        {
+            let position = enemy.interpolated_position(alpha);
+            if !visible(&position, TILE_SIZE) {
+                continue;
+            }
+            enemies_drawn += 1;
+            // Stealthed and burrowed enemies, and those in tunnels, are drawn faintly while they can't be targeted
+            let color = if enemy.burrowed {
+                [0.4, 0.25, 0.1, 0.3]
+            } else if enemy.layer == Layer::Tunnel {
+                [0.3, 0.3, 0.3, 0.3]
+            } else if enemy.is_targetable() {
+                palette.enemy
+            } else {
+                theme::faded(palette.enemy, 0.3)
+            };
+            renderer.rectangle(color, [position.x as f64, position.y as f64, 25.0, 25.0]);
+        }
+    }
+    for particle in game
+        .state
+        .particles
+        .iter()
+        .filter(|particle| visible(&particle.position, 4.))
+    {
+        let fade = particle.time_left / PARTICLE_LIFETIME;
+        renderer.rectangle(
+            [1.0, 0.6, 0.0, fade],
+            [
+                particle.position.x as f64,
+                particle.position.y as f64,
+                4.0,
+                4.0,
+            ],
+        );
+    }
+
+    // Draw the convoy units with their health bars above them
+    for unit in game.state.convoy.iter().filter(|unit| unit.on_map()) {
+        let position = unit.interpolated_position(alpha);
+        if !visible(&position, TILE_SIZE) {
+            continue;
+        }
+        let (x, y) = (position.x as f64, position.y as f64);
+        renderer.rectangle([0.2, 0.5, 1.0, 1.0], [x + 2.5, y + 2.5, 20.0, 20.0]);
+        let health = (unit.hit_points / CONVOY_HIT_POINTS).clamp(0., 1.) as f64;
+        renderer.rectangle([0.6, 0.0, 0.0, 1.0], [x, y - 6.0, 25.0, 3.0]);
+        renderer.rectangle([0.0, 0.8, 0.0, 1.0], [x, y - 6.0, 25.0 * health, 3.0]);
+    }
+
+    // Draw shielded enemies' shields across their front
+    for enemy in game.state.enemies.iter() {
+        let position = enemy.interpolated_position(alpha);
+        if enemy.enemy_type.shield_angle > 0. && !enemy.burrowed && visible(&position, TILE_SIZE) {
+            let (hx, hy) = (enemy.heading.x as f64, enemy.heading.y as f64);
+            let (cx, cy) = (
+                position.x as f64 + 12.5 + hx * 16.0,
+                position.y as f64 + 12.5 + hy * 16.0,
+            );
+            renderer.line(
+                [0.2, 0.3, 0.9, 1.0],
+                2.0,
+                [
+                    cx - hy * 12.0,
+                    cy + hx * 12.0,
+                    cx + hy * 12.0,
+                    cy - hx * 12.0,
+                ],
+            );
+        }
+    }
+
+    // Draw the hazards, with boulders spinning as they roll and flames flickering
+    let time = game.state.stats.playtime;
+    let half_tile = TILE_SIZE as f64 / 2.0;
+    for hazard in game.hazards.iter() {
+        match hazard {
+            Hazard::Boulder { from, to } => {
+                let (position, rolled) = Hazard::boulder_at(from, to, time);
+                let radius = BOULDER_RADIUS as f64;
+                let (x, y) = (position.x as f64 + half_tile, position.y as f64 + half_tile);
+                let rect = [x - radius, y - radius, radius * 2.0, radius * 2.0];
+                renderer.ellipse([0.45, 0.4, 0.35, 1.0], rect);
+                let spin = (rolled / BOULDER_RADIUS) as f64;
+                renderer.line(
+                    [0.25, 0.2, 0.15, 1.0],
+                    1.5,
+                    [
+                        x - spin.cos() * radius,
+                        y - spin.sin() * radius,
+                        x + spin.cos() * radius,
+                        y + spin.sin() * radius,
+                    ],
+                );
+            }
+            Hazard::Flame { pivot, length } => {
+                let tip = Hazard::flame_tip(pivot, *length, time);
+                let flicker = 0.75 + 0.25 * (time * 30.).sin();
+                renderer.line(
+                    [1.0, 0.45, 0.0, flicker],
+                    FLAME_WIDTH as f64,
+                    line_between(pivot, &tip, half_tile),
+                );
+                let rect = [
+                    pivot.x as f64 + half_tile - 5.0,
+                    pivot.y as f64 + half_tile - 5.0,
+                    10.0,
+                    10.0,
+                ];
+                renderer.ellipse([0.3, 0.3, 0.3, 1.0], rect);
+            }
+        }
+    }
+
+    // Draw the walls the player has placed
+    for wall in game
+        .state
+        .walls
+        .iter()
+        .filter(|wall| visible(&wall.position, TILE_SIZE))
+    {
+        renderer.rectangle(
+            [0.4, 0.4, 0.4, 1.0],
+            [wall.position.x as f64, wall.position.y as f64, 25.0, 25.0],
+        );
+    }
+
+    // Draw uncollected loot, fading out as it's about to disappear
+    for drop in game
+        .state
+        .loot
+        .iter()
+        .filter(|drop| visible(&drop.position, TILE_SIZE))
+    {
+        let alpha = (drop.time_left / LOOT_LIFETIME).clamp(0.3, 1.);
+        renderer.ellipse(
+            [1.0, 0.8, 0.0, alpha],
+            [
+                drop.position.x as f64 + 4.5,
+                drop.position.y as f64 + 4.5,
+                16.0,
+                16.0,
+            ],
+        );
+    }
+
+    // Draw the towers' shots in flight
+    for shot in game.state.shots.iter() {
+        let position = shot.position();
+        renderer.ellipse(
+            shot.color,
+            [position.x as f64 - 2.5, position.y as f64 - 2.5, 5.0, 5.0],
+        );
+    }
+    enemies_drawn
+}
@@ -0,0 +1,539 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::beats::BeatMap;
+use crate::binary::{Reader, Writer};
+use crate::commands::{Command, CommandQueue};
+use crate::json::Json;
+use crate::level::{parse_number, Level};
+use crate::run::RunSetup;
+use crate::{storage, Game, UPDATES_PER_SECOND};
+
+// Version of the replay format written by this build, where version 1 replays were text and version 2 replays
+// couldn't be played in rhythm mode
+const REPLAY_VERSION: u64 = 3;
+
+// Something the player did that changed the game, as recorded in a replay
+#[derive(Clone, Copy)]
+pub enum Input {
+    Command(Command),
+    Undo,
+    Redo,
+}
+
+pub struct Replay {
+    // Stores how the recorded run was set up
+    pub setup: RunSetup,
+    // Stores the seed the run's random numbers were rolled from
+    pub seed: u64,
+    // Stores everything the player did, with the tick it was done on, in order
+    pub inputs: Vec<(u64, Input)>,
+    // Stores the number of ticks the recording lasted
+    pub ticks: u64,
+    // Stores the checksum of the game's state at the end of the recording
+    pub checksum: u64,
+    // Stores the checksum of the game's state after each tick, so playback can tell where it first went wrong
+    pub checksums: Vec<u64>,
+}
+
+pub struct Playback {
+    // Stores the game as it was when playback stopped
+    pub game: Game,
+    // Stores whether playback stopped because the game's state stopped matching the recording
+    pub diverged: bool,
+}
+
+impl Replay {
+    pub fn new(setup: RunSetup, seed: u64) -> Self {
+        Replay {
+            setup,
+            seed,
+            inputs: Vec::new(),
+            ticks: 0,
+            checksum: 0,
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn start(&self) -> Result<Game, String> {
+        // Start the run the recording begins from, which is the same every time for the same seed
+        let mut game = self.setup.start()?;
+        game.state.rng = ChaCha12Rng::seed_from_u64(self.seed);
+        Ok(game)
+    }
+
+    pub fn record_tick(&mut self, game: &Game) {
+        self.checksums.push(checksum(game));
+    }
+
+    pub fn finish(&mut self, game: &Game) {
+        // Mark where the recording ends, and what the game should look like there when it's played back
+        self.ticks = game.state.tick;
+        self.checksum = checksum(game);
+    }
+
+    pub fn play(&self, until: Option<u64>) -> Result<Playback, String> {
+        // Feed the recorded inputs to a fresh run on the ticks they were made on, through the same command queue,
+        // until the end of the recording or the given tick, or the first tick that doesn't match the recording
+        let end = until.map_or(self.ticks, |until| until.min(self.ticks));
+        let mut game = self.start()?;
+        let mut commands = CommandQueue::new();
+        let mut inputs = self.inputs.iter().peekable();
+        let dt = 1. / UPDATES_PER_SECOND as f32;
+        loop {
+            while let Some((_, input)) = inputs.next_if(|(tick, _)| *tick == game.state.tick) {
+                match *input {
+                    Input::Command(command) => commands.issue(command, &mut game, false),
+                    Input::Undo => commands.undo(&mut game),
+                    Input::Redo => commands.redo(&mut game),
+                }
+            }
+            if game.state.tick >= end || game.state.outcome.is_some() {
+                return Ok(Playback {
+                    game,
+                    diverged: false,
+                });
+            }
+            game.update(dt);
+            if self
+                .checksums
+                .get(game.state.tick as usize - 1)
+                .is_some_and(|&recorded| recorded != checksum(&game))
+            {
+                return Ok(Playback {
+                    game,
+                    diverged: true,
+                });
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        // Long runs record a checksum every tick, so replays are written packed, with each input's tick written
+        // as how long after the last input it came
+        let mut writer = Writer::new();
+        writer.number(REPLAY_VERSION);
+        writer.fixed(self.seed);
+        writer.number(self.ticks);
+        writer.fixed(self.checksum);
+        writer.number(self.setup.prestige as u64);
+        writer.flag(self.setup.daily.is_some());
+        writer.number(self.setup.daily.unwrap_or_default());
+        writer.number(self.setup.mutators.len() as u64);
+        for mutator in self.setup.mutators.iter() {
+            writer.string(mutator);
+        }
+        writer.flag(self.setup.sandbox);
+        writer.flag(self.setup.reverse);
+        writer.number(self.inputs.len() as u64);
+        let mut last = 0;
+        for (tick, input) in self.inputs.iter() {
+            writer.number(tick - last);
+            writer.string(&input.to_string());
+            last = *tick;
+        }
+        writer.number(self.checksums.len() as u64);
+        for checksum in self.checksums.iter() {
+            writer.fixed(*checksum);
+        }
+        writer.string(&self.setup.level.to_string());
+        writer.string(
+            &self
+                .setup
+                .beats
+                .as_ref()
+                .map_or(String::new(), BeatMap::to_string),
+        );
+        storage::write_packed(path, &writer.bytes)
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        // Replays from before packing are text, and start by saying so
+        let contents =
+            storage::read_packed(path)?.ok_or_else(|| format!("{} doesn't exist", path))?;
+        if contents.starts_with(b"version ") {
+            return Replay::parse(&String::from_utf8_lossy(&contents));
+        }
+        Replay::unpack(&contents)
+    }
+
+    pub fn to_json(&self) -> Json {
+        // Write the replay out readably for debugging, with checksums in hex like the dumps and the debug panel
+        let field = |name: &str, value: Json| (name.to_string(), value);
+        let hex = |checksum: u64| Json::String(format!("{:016x}", checksum));
+        let setup = &self.setup;
+        Json::Object(vec![
+            field("version", Json::Number(REPLAY_VERSION as f64)),
+            field("seed", Json::String(self.seed.to_string())),
+            field("ticks", Json::Number(self.ticks as f64)),
+            field("checksum", hex(self.checksum)),
+            field("level", Json::String(setup.level.name.clone())),
+            field("prestige", Json::Number(setup.prestige as f64)),
+            field(
+                "daily",
+                setup
+                    .daily
+                    .map_or(Json::Null, |day| Json::Number(day as f64)),
+            ),
+            field(
+                "mutators",
+                Json::Array(setup.mutators.iter().cloned().map(Json::String).collect()),
+            ),
+            field("sandbox", Json::Bool(setup.sandbox)),
+            field("reverse", Json::Bool(setup.reverse)),
+            field(
+                "beats",
+                setup
+                    .beats
+                    .as_ref()
+                    .map_or(Json::Null, |beats| Json::Number(beats.beats.len() as f64)),
+            ),
+            field(
+                "inputs",
+                Json::Array(
+                    self.inputs
+                        .iter()
+                        .map(|(tick, input)| {
+                            Json::Object(vec![
+                                field("tick", Json::Number(*tick as f64)),
+                                field("input", Json::String(input.to_string())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            field(
+                "checksums",
+                Json::Array(
+                    self.checksums
+                        .iter()
+                        .map(|&checksum| hex(checksum))
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    fn unpack(contents: &[u8]) -> Result<Replay, String> {
+        let mut reader = Reader::new(contents);
+        let version = reader.number()?;
+        if !(2..=REPLAY_VERSION).contains(&version) {
+            return Err(String::from("Replay version isn't supported"));
+        }
+        let seed = reader.fixed()?;
+        let ticks = reader.number()?;
+        let checksum = reader.fixed()?;
+        let prestige = reader.number()? as u32;
+        let daily = reader.flag()?;
+        let day = reader.number()?;
+        let mutators = (0..reader.number()?)
+            .map(|_| reader.string())
+            .collect::<Result<Vec<_>, _>>()?;
+        let sandbox = reader.flag()?;
+        let reverse = reader.flag()?;
+        let mut inputs = Vec::new();
+        let mut tick = 0;
+        for _ in 0..reader.number()? {
+            tick += reader.number()?;
+            inputs.push((tick, reader.string()?.parse()?));
+        }
+        let checksums = (0..reader.number()?)
+            .map(|_| reader.fixed())
+            .collect::<Result<Vec<_>, _>>()?;
+        let level = Level::parse(&reader.string()?)?;
+        let beats = match version {
+            2 => String::new(),
+            _ => reader.string()?,
+        };
+        let setup = RunSetup {
+            level,
+            prestige,
+            daily: daily.then_some(day),
+            mutators,
+            sandbox,
+            reverse,
+            beats: (!beats.is_empty())
+                .then(|| BeatMap::parse(&beats))
+                .transpose()?,
+        };
+        Ok(Replay {
+            setup,
+            seed,
+            inputs,
+            ticks,
+            checksum,
+            checksums,
+        })
+    }
+
+    fn parse(contents: &str) -> Result<Replay, String> {
+        let (head, level) = contents
+            .split_once("\nlevel\n")
+            .ok_or("Replay is missing its level")?;
+        let lines: Vec<(&str, &str)> = head
+            .lines()
+            .map(|line| line.split_once(' ').unwrap_or((line, "")))
+            .collect();
+        match lines.first() {
+            Some(("version", "1")) => {}
+            _ => return Err(String::from("Replay version isn't supported")),
+        }
+        let setup = RunSetup {
+            level: Level::parse(level)?,
+            prestige: 0,
+            daily: None,
+            mutators: Vec::new(),
+            sandbox: false,
+            reverse: false,
+            beats: None,
+        };
+        let mut replay = Replay::new(setup, 0);
+        for &(key, value) in lines.iter() {
+            match key {
+                "seed" => replay.seed = parse_number(value)?,
+                "ticks" => replay.ticks = parse_number(value)?,
+                "state" => replay.checksum = parse_checksum(value)?,
+                "hash" => replay.checksums.push(parse_checksum(value)?),
+                "prestige" => replay.setup.prestige = parse_number(value)?,
+                "daily" => replay.setup.daily = Some(parse_number(value)?),
+                "mutator" => replay.setup.mutators.push(value.to_string()),
+                "sandbox" => replay.setup.sandbox = true,
+                "reverse" => replay.setup.reverse = true,
+                "input" => {
+                    let (tick, input) = value.split_once(' ').unwrap_or((value, ""));
+                    replay.inputs.push((parse_number(tick)?, input.parse()?));
+                }
+                _ => {}
+            }
+        }
+        Ok(replay)
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Input::Command(command) => write!(f, "{}", command),
+            Input::Undo => write!(f, "undo"),
+            Input::Redo => write!(f, "redo"),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Input, String> {
+        Ok(match text {
+            "undo" => Input::Undo,
+            "redo" => Input::Redo,
+            command => Input::Command(Command::parse(command)?),
+        })
+    }
+}
+
+fn parse_checksum(value: &str) -> Result<u64, String> {
+    u64::from_str_radix(value, 16).map_err(|_| format!("Expected a checksum, found {:?}", value))
+}
+
+pub fn checksum(game: &Game) -> u64 {
+    // Fold everything the simulation decides into one number, byte by byte in a fixed order so it's the same
+    // on every platform
+    let state = &game.state;
+    let mut bytes = Vec::new();
+    bytes.extend(state.tick.to_le_bytes());
+    bytes.extend(state.resources.to_le_bytes());
+    bytes.extend(state.lives.to_le_bytes());
+    bytes.extend((state.wave as u64).to_le_bytes());
+    bytes.push(state.building as u8);
+    bytes.extend(state.wave_timer.to_le_bytes());
+    bytes.extend(state.spawn_timer.to_le_bytes());
+    bytes.extend(state.director.pace.to_le_bytes());
+    bytes.extend(state.director.recent_leaks.to_le_bytes());
+    bytes.extend(state.director.lives_seen.unwrap_or(-1).to_le_bytes());
+    for tower in state.towers.iter() {
+        bytes.extend((tower.id as u64).to_le_bytes());
+        bytes.extend(tower.position.x.to_le_bytes());
+        bytes.extend(tower.position.y.to_le_bytes());
+        bytes.extend(tower.cooldown.to_le_bytes());
+        bytes.extend(tower.ability_cooldown.to_le_bytes());
+        bytes.extend(tower.ability_time_left.to_le_bytes());
+    }
+    for enemy in state.enemies.iter() {
+        bytes.extend(enemy.hit_points.to_le_bytes());
+        bytes.extend(enemy.position.x.to_le_bytes());
+        bytes.extend(enemy.position.y.to_le_bytes());
+        bytes.extend(enemy.distance.to_le_bytes());
+        bytes.extend(enemy.armor_shred.to_le_bytes());
+        bytes.extend(enemy.shred_time_left.to_le_bytes());
+        bytes.push(enemy.on_lane as u8);
+    }
+    for gate in game.gates.iter() {
+        bytes.push(gate.closed as u8);
+        bytes.extend(gate.cooldown.to_le_bytes());
+    }
+    for unit in state.convoy.iter() {
+        bytes.extend(unit.hit_points.to_le_bytes());
+        bytes.extend(unit.distance.to_le_bytes());
+        bytes.extend(unit.delay.to_le_bytes());
+    }
+    if let Some(attacker) = &game.attacker {
+        bytes.extend(attacker.funds.to_le_bytes());
+        for &enemy_type in attacker.queue.iter() {
+            bytes.extend((enemy_type as u64).to_le_bytes());
+        }
+    }
+    storage::checksum(&bytes)
+}
+
+pub fn dump(game: &Game) -> String {
+    // Write out everything the checksum covers, one value per line, so dumps from two machines can be diffed
+    let state = &game.state;
+    let mut lines = vec![
+        format!("tick {}", state.tick),
+        format!("checksum {:016x}", checksum(game)),
+        format!("resources {}", state.resources),
+        format!("lives {}", state.lives),
+        format!("wave {}", state.wave),
+        format!("building {}", state.building),
+        format!("wave_timer {}", state.wave_timer),
+        format!("spawn_timer {}", state.spawn_timer),
+        format!(
+            "director {} {} {:?}",
+            state.director.pace, state.director.recent_leaks, state.director.lives_seen
+        ),
+    ];
+    for tower in state.towers.iter() {
+        lines.push(format!(
+            "tower {} {} {} {} {} {} {}",
+            tower.id,
+            tower.tower_type.name,
+            tower.position.x,
+            tower.position.y,
+            tower.cooldown,
+            tower.ability_cooldown,
+            tower.ability_time_left
+        ));
+    }
+    for (i, enemy) in state.enemies.iter().enumerate() {
+        lines.push(format!(
+            "enemy {} {} {} {} {} {} {} {}",
+            i,
+            enemy.enemy_type.name,
+            enemy.hit_points,
+            enemy.position.x,
+            enemy.position.y,
+            enemy.distance,
+            enemy.armor_shred,
+            enemy.shred_time_left
+        ));
+    }
+    for (i, gate) in game.gates.iter().enumerate() {
+        lines.push(format!("gate {} {} {}", i, gate.closed, gate.cooldown));
+    }
+    for (i, unit) in state.convoy.iter().enumerate() {
+        lines.push(format!(
+            "convoy {} {} {} {}",
+            i, unit.hit_points, unit.distance, unit.delay
+        ));
+    }
+    if let Some(attacker) = &game.attacker {
+        lines.push(format!("funds {}", attacker.funds));
+        for enemy_type in attacker.queue.iter() {
+            lines.push(format!("queued {}", enemy_type));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autosave;
+    use crate::bot::{GreedyBot, Strategy};
+    use crate::content;
+
+    // Ticks of the recorded game, enough for a few waves of fighting
+    const RECORDED_TICKS: u64 = 12000;
+    // Ticks a run resumed from an autosave is played alongside the one that was saved
+    const RESUMED_TICKS: u64 = 300;
+
+    #[test]
+    fn replay_matches_recording() {
+        // Record the greedy bot playing, taking back and redoing a tower now and then, and autosaving at the start of
+        // each wave like a real run, which mustn't change anything the replay doesn't
+        let setup = RunSetup {
+            level: content::default_level(),
+            prestige: 0,
+            daily: None,
+            mutators: Vec::new(),
+            sandbox: false,
+            reverse: false,
+            beats: None,
+        };
+        let replay = Replay::new(setup, 7);
+        let mut game = replay.start().unwrap();
+        let mut commands = CommandQueue::new();
+        commands.recording = Some(replay);
+        let mut bot = GreedyBot::new();
+        let dt = 1. / UPDATES_PER_SECOND as f32;
+        let path = std::env::temp_dir().join("tower-defense-test.sav");
+        let path = path.to_str().unwrap();
+        let mut autosaves = 0;
+        let mut resumed: Option<(Game, CommandQueue, u64)> = None;
+        while game.state.tick < RECORDED_TICKS && game.state.outcome.is_none() {
+            for command in bot.decide(&game) {
+                commands.issue(command, &mut game, false);
+                if let Some((resumed, resumed_commands, _)) = &mut resumed {
+                    resumed_commands.issue(command, resumed, false);
+                }
+            }
+            // The resumed run has none of the saved run's history to take back
+            if game.state.tick.is_multiple_of(500) && resumed.is_none() {
+                commands.undo(&mut game);
+                commands.redo(&mut game);
+            }
+            let was_building = game.state.building;
+            game.update(dt);
+            if let Some((resumed_game, _, ticks_left)) = &mut resumed {
+                resumed_game.update(dt);
+                assert_eq!(checksum(resumed_game), checksum(&game));
+                *ticks_left -= 1;
+                if *ticks_left == 0 {
+                    resumed = None;
+                }
+            }
+            if was_building && !game.state.building {
+                // A resumed run picks up exactly where it was, random numbers and all, and plays on the same
+                let setup = &commands.recording.as_ref().unwrap().setup;
+                autosave::save(path, setup, &game).unwrap();
+                let loaded = autosave::load(path).unwrap().unwrap();
+                assert!(loaded.game.state.rng == game.state.rng);
+                assert_eq!(checksum(&loaded.game), checksum(&game));
+                resumed = Some((loaded.game, CommandQueue::new(), RESUMED_TICKS));
+                autosaves += 1;
+            }
+            commands.recording.as_mut().unwrap().record_tick(&game);
+        }
+        autosave::discard(path);
+        let mut replay = commands.recording.take().unwrap();
+        replay.finish(&game);
+        assert!(!replay.inputs.is_empty());
+        assert!(autosaves > 1);
+
+        // Play it back through the file format, and end up in exactly the same place
+        let path = std::env::temp_dir().join("tower-defense-test.replay");
+        let path = path.to_str().unwrap();
+        replay.save(path).unwrap();
+        let loaded = Replay::load(path).unwrap();
+        std::fs::remove_file(path).ok();
+        let playback = loaded.play(None).unwrap();
+        assert!(!playback.diverged);
+        assert_eq!(playback.game.state.tick, replay.ticks);
+        assert_eq!(checksum(&playback.game), replay.checksum);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::bot::{GreedyBot, Strategy};
+use crate::commands::Command;
+use crate::label::{self, TextStyle};
+use crate::level::{Formation, DEFAULT_SPACING};
+use crate::notifications::{notify, Kind};
+use crate::ui::{Frame, Ui};
+use crate::{Game, Outcome, QueuedSpawn};
+
+// Funds the player starts with to send enemies
+const STARTING_FUNDS: i32 = 150;
+// Funds the player is given at the start of every build phase after the first
+const WAVE_ALLOWANCE: i32 = 100;
+// Funds the player earns for every life the defense loses
+const LEAK_BOUNTY: i32 = 15;
+// Multiple of an enemy's reward it costs to send, so the defense can't earn back more than was spent on it
+const SEND_COST_FACTOR: i32 = 2;
+// Most enemies that can be waiting in the send queue at once
+const MAX_QUEUE: usize = 30;
+
+// The player's side of a reverse run, where they send the enemies against a defense the AI builds
+pub struct Attacker {
+    // Stores the funds the player has to send enemies with
+    pub funds: i32,
+    // Stores the enemy types, by index, the player has paid for but not sent yet, in the order they'll go
+    pub queue: Vec<usize>,
+    // Stores the bot building the defense
+    defender: GreedyBot,
+}
+
+impl Outcome {
+    pub fn reversed(self) -> Outcome {
+        match self {
+            Outcome::Won => Outcome::Lost,
+            Outcome::Lost => Outcome::Won,
+        }
+    }
+}
+
+impl Game {
+    pub fn start_reverse(&mut self) {
+        // Hand the defense to the AI and the waves to the player, who wins by running the base out of lives
+        self.rules.reverse = true;
+        self.attacker = Some(Attacker {
+            funds: STARTING_FUNDS,
+            queue: Vec::new(),
+            defender: GreedyBot::new(),
+        });
+    }
+
+    pub fn send_cost(&self, enemy_type: usize) -> i32 {
+        self.enemy_types[enemy_type].reward * SEND_COST_FACTOR
+    }
+
+    pub fn queue_enemy(&mut self, enemy_type: usize) {
+        // Pay for an enemy up front, adding it to the back of the queue
+        if enemy_type >= self.enemy_types.len() {
+            return;
+        }
+        let cost = self.send_cost(enemy_type);
+        let Some(attacker) = &mut self.attacker else {
+            return;
+        };
+        if attacker.queue.len() >= MAX_QUEUE {
+            notify("The send queue is full", Kind::Warning);
+        } else if attacker.funds < cost {
+            notify("Not enough funds", Kind::Warning);
+        } else {
+            attacker.funds -= cost;
+            attacker.queue.push(enemy_type);
+        }
+    }
+
+    pub fn send_wave(&mut self) {
+        // Send everything in the queue onto the map in a line, which can only be done while a wave is on
+        let Some(attacker) = &mut self.attacker else {
+            return;
+        };
+        if self.state.building {
+            notify("Enemies can only be sent during a wave", Kind::Warning);
+            return;
+        }
+        for (i, enemy_type) in attacker.queue.drain(..).enumerate() {
+            let (delay, offset) = Formation::Line.place(i, DEFAULT_SPACING);
+            self.state.spawn_queue.push(QueuedSpawn {
+                enemy_type,
+                delay,
+                offset,
+            });
+        }
+    }
+
+    pub fn update_attacker(&mut self, was_building: bool, lives: i32) {
+        let Some(mut attacker) = self.attacker.take() else {
+            return;
+        };
+
+        // Pay the player their allowance as each build phase starts, and a bounty for every life the base loses
+        if !was_building && self.state.building {
+            attacker.funds += WAVE_ALLOWANCE;
+        }
+        attacker.funds += (lives - self.state.lives).max(0) * LEAK_BOUNTY;
+
+        // Let the AI build up its defense, through the same commands a player would use
+        for command in attacker.defender.decide(self) {
+            self.apply(command);
+        }
+        self.attacker = Some(attacker);
+    }
+}
+
+pub fn draw_send_panel(game: &Game, ui: &mut Ui, frame: &mut Frame) -> Option<Command> {
+    // Draw the enemies the player can send along the bottom of the screen, with the queue and a button to send it
+    let attacker = game.attacker.as_ref()?;
+    let mut command = None;
+    ui.panel([0.0, 410.0, 640.0, 70.0], frame);
+    let width = 632.0 / game.enemy_types.len().max(8) as f64;
+    for (i, enemy_type) in game.enemy_types.iter().enumerate() {
+        let cost = game.send_cost(i);
+        if ui.button(
+            &format!("queue {}", i),
+            &format!("{} {}", enemy_type.name, cost),
+            [5.0 + i as f64 * width, 415.0, width - 2.0, 25.0],
+            attacker.funds >= cost,
+            false,
+            frame,
+        ) {
+            command = Some(Command::QueueEnemy(i));
+        }
+    }
+    let style = TextStyle {
+        size: 14,
+        ..Default::default()
+    };
+    let status = format!(
+        "Funds: {}   Queued: {}",
+        attacker.funds,
+        attacker.queue.len()
+    );
+    label::draw(&status, &style, 10.0, 465.0, frame.glyphs, frame.c, frame.g);
+    if ui.button(
+        "send",
+        "Send",
+        [535.0, 447.0, 100.0, 25.0],
+        !game.state.building && !attacker.queue.is_empty(),
+        false,
+        frame,
+    ) {
+        command = Some(Command::SendWave);
+    }
+    command
+}
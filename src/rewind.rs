@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+use crate::naval::Lane;
+use crate::{Game, GameState, UPDATES_PER_SECOND};
+
+// Seconds of play a rewind rolls back, at most
+const REWIND_SECONDS: u64 = 10;
+// Number of times a run can be rewound
+const REWIND_CHARGES: u32 = 3;
+
+// Everything about the run that changes as it plays, copied to roll back to later
+#[derive(Clone)]
+pub struct Moment {
+    // Stores the game state as it was
+    state: GameState,
+    // Stores whether each gate was closed, and the time until its lever could be pulled again
+    gates: Vec<(bool, f32)>,
+}
+
+// Copies of the run taken every second over the last few, and how many more times it can be rolled back to one
+pub struct Rewind {
+    // Stores the copies, oldest first, going back no further than a rewind can
+    moments: VecDeque<Moment>,
+    // Stores the number of rewinds the run has left
+    pub charges: u32,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Rewind {
+            moments: VecDeque::new(),
+            charges: REWIND_CHARGES,
+        }
+    }
+}
+
+impl Game {
+    pub fn remember_moment(&mut self) {
+        // Copy the run once a second, dropping the copies from further back than a rewind goes
+        let tick = self.state.tick;
+        if self.rewind.charges == 0 || !tick.is_multiple_of(UPDATES_PER_SECOND) {
+            return;
+        }
+        let moments = &mut self.rewind.moments;
+        while moments
+            .front()
+            .is_some_and(|moment| moment.state.tick + REWIND_SECONDS * UPDATES_PER_SECOND < tick)
+        {
+            moments.pop_front();
+        }
+        let moment = self.capture();
+        self.rewind.moments.push_back(moment);
+    }
+
+    pub fn capture(&self) -> Moment {
+        Moment {
+            state: self.state.clone(),
+            gates: self
+                .gates
+                .iter()
+                .map(|gate| (gate.closed, gate.cooldown))
+                .collect(),
+        }
+    }
+
+    pub fn restore(&mut self, moment: Moment) {
+        // Put the run back the way it was, dropping the copies taken since, which are no longer its past
+        self.rewind.moments.clear();
+
+        // The player's settings carry on as they are now
+        let (parallel, cursor_position) = (self.state.parallel, self.state.cursor_position);
+        self.state = moment.state;
+        self.state.parallel = parallel;
+        self.state.cursor_position = cursor_position;
+
+        // Lay the path out the way the gates had it, as enemies' distances along it depend on
+        if !self.gates.is_empty() {
+            for (gate, (closed, cooldown)) in self.gates.iter_mut().zip(moment.gates) {
+                gate.closed = closed;
+                gate.cooldown = cooldown;
+            }
+            self.path = self.route.build(&self.gates).0;
+            self.lane = self
+                .lane
+                .take()
+                .map(|lane| Lane::new(lane.path, &self.path));
+        }
+        self.weigh_landing();
+    }
+
+    pub fn rewind(&mut self) -> Result<(), String> {
+        // Roll the run back to the oldest copy kept, which is a rewind's worth ago unless the run or the last rewind
+        // was more recent than that, then start copying it afresh
+        if self.rewind.charges == 0 {
+            return Err(String::from("No rewinds left"));
+        }
+        let Some(moment) = self.rewind.moments.pop_front() else {
+            return Err(String::from("Nothing to rewind to yet"));
+        };
+        self.rewind.charges -= 1;
+        self.rules.rewound = true;
+        self.restore(moment);
+        Ok(())
+    }
+}
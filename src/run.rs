@@ -0,0 +1,80 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::beats::BeatMap;
+use crate::content;
+use crate::daily::DailyChallenge;
+use crate::level::Level;
+use crate::mutators;
+use crate::Game;
+
+#[derive(Clone)]
+pub struct RunSetup {
+    // Stores the level the run is played on
+    pub level: Level,
+    // Stores the New Game+ level of the run, or zero for a normal run
+    pub prestige: u32,
+    // Stores the day of the daily challenge being played, if it is one
+    pub daily: Option<u64>,
+    // Stores the names of the mutators picked for the run, on the command line or the main menu
+    pub mutators: Vec<String>,
+    // Stores whether the run is a sandbox
+    pub sandbox: bool,
+    // Stores whether the player sends the enemies against a defense built by the AI
+    pub reverse: bool,
+    // Stores the beats of the music track enemy spawns are timed to, in rhythm mode
+    pub beats: Option<BeatMap>,
+}
+
+impl RunSetup {
+    pub fn start(&self) -> Result<Game, String> {
+        let mut game = content::new_game(&self.level, self.prestige)?;
+
+        // Every player gets the same seed and modifiers for the daily challenge
+        if let Some(day) = self.daily {
+            let daily = DailyChallenge::for_day(day);
+            game.state.rng = ChaCha12Rng::seed_from_u64(daily.seed);
+            for modifier in daily.modifiers.iter() {
+                game.apply_mutator(modifier);
+            }
+        }
+
+        // Apply the mutators picked on the command line, like `--mutator no-selling`, or on the main menu
+        game.rules.sandbox = self.sandbox;
+        game.rules.beats = self.beats.clone();
+        if self.reverse {
+            game.start_reverse();
+        }
+        for name in self.mutators.iter() {
+            match mutators::from_name(name) {
+                Some(mutator) => game.apply_mutator(mutator.as_ref()),
+                None => eprintln!("Unknown mutator: {}", name),
+            }
+        }
+
+        // Competitive runs, and ones whose spawns are timed by hand, keep to the level's own timing without the
+        // pacing director
+        if self.daily.is_some() || self.sandbox || self.reverse || self.beats.is_some() {
+            game.rules.director = false;
+        }
+        Ok(game)
+    }
+
+    pub fn toggle_mutator(&mut self, name: &str) {
+        // Add the mutator to the run if it isn't picked yet, or take it away if it is
+        if self.mutators.iter().any(|picked| picked == name) {
+            self.mutators.retain(|picked| picked != name);
+        } else {
+            self.mutators.push(name.to_string());
+        }
+    }
+
+    pub fn category(&self) -> String {
+        // Daily challenges each get their own leaderboard, and so do endless runs, whose scores aren't capped
+        match self.daily {
+            Some(day) => DailyChallenge::for_day(day).leaderboard_category(),
+            None if self.mutators.iter().any(|name| name == "endless") => String::from("endless"),
+            None => String::from("campaign"),
+        }
+    }
+}
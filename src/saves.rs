@@ -0,0 +1,438 @@
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ::image::RgbaImage;
+use piston_window::*;
+
+use crate::autosave::{self, SavedRun};
+use crate::dialog::Confirmation;
+use crate::label::{self, TextStyle};
+use crate::level::parse_number;
+use crate::notifications::{notify, Kind};
+use crate::photo;
+use crate::run::RunSetup;
+use crate::storage;
+use crate::theme;
+use crate::ui::{Frame, Ui};
+use crate::Game;
+
+// Folder the player's save slots are kept in
+const SAVES_DIR: &str = "saves";
+// Number of save slots the save screen offers
+pub const SAVE_SLOTS: usize = 5;
+// Size of the picture of the map kept with each save, in pixels
+const THUMBNAIL_SIZE: [u32; 2] = [64, 48];
+// Longest name a save slot can be given, in characters
+const MAX_NAME_LENGTH: usize = 24;
+
+// What's in a save slot, shown on the save screen without loading the run itself
+pub struct SlotInfo {
+    // Stores the name the player gave the save
+    pub name: String,
+    // Stores when the run was saved, in seconds since the Unix epoch
+    pub saved: u64,
+    // Stores the name of the run's level
+    pub level: String,
+    // Stores the wave the run was on
+    pub wave: usize,
+}
+
+// What the player clicked on the save screen
+pub enum SlotChoice {
+    Save(usize),
+    Load(usize),
+    Rename(usize),
+    Delete(usize),
+    Close,
+}
+
+// The player's save slots, each holding a run they saved by hand, alongside the autosave
+pub struct SaveSlots {
+    // Stores what's in each slot, if anything
+    pub slots: Vec<Option<SlotInfo>>,
+    // Stores each slot's picture of the map, if it has one
+    thumbnails: Vec<Option<G2dTexture>>,
+    // Stores the slot being renamed and the name typed so far, while the player is typing one
+    pub renaming: Option<(usize, String)>,
+    // Stores whether the save screen is open
+    pub open: bool,
+    // Stores the slot the run is saved to once the next frame has been drawn, to keep as the slot's picture
+    pub saving: Option<usize>,
+    // Stores the slot whose run is loaded in place of the one being played
+    pub loading: Option<usize>,
+}
+
+fn slot_path(slot: usize, extension: &str) -> String {
+    format!("{}/slot-{}.{}", SAVES_DIR, slot + 1, extension)
+}
+
+pub fn read_thumbnail(slot: usize) -> Option<RgbaImage> {
+    // Decode the slot's picture of the map, if it has one, which doesn't need the window
+    ::image::open(slot_path(slot, "png"))
+        .ok()
+        .map(|picture| picture.to_rgba8())
+}
+
+impl SaveSlots {
+    pub fn load() -> Self {
+        // Slots that can't be read are shown empty, with the reason logged, and their pictures of the map are
+        // decoded separately, which can be done off the main thread with read_thumbnail
+        let mut saves = SaveSlots {
+            slots: Vec::new(),
+            thumbnails: Vec::new(),
+            renaming: None,
+            open: false,
+            saving: None,
+            loading: None,
+        };
+        for slot in 0..SAVE_SLOTS {
+            saves.slots.push(read_info(slot).unwrap_or_else(|error| {
+                eprintln!("Couldn't read save slot {}: {}", slot + 1, error);
+                None
+            }));
+            saves.thumbnails.push(None);
+        }
+        saves
+    }
+
+    pub fn set_thumbnail(
+        &mut self,
+        slot: usize,
+        picture: Option<&RgbaImage>,
+        window: &mut PistonWindow,
+    ) {
+        self.thumbnails[slot] = picture.and_then(|picture| {
+            Texture::from_image(
+                &mut window.create_texture_context(),
+                picture,
+                &TextureSettings::new(),
+            )
+            .ok()
+        });
+    }
+
+    fn load_thumbnail(&mut self, slot: usize, window: &mut PistonWindow) {
+        self.set_thumbnail(slot, read_thumbnail(slot).as_ref(), window);
+    }
+
+    fn save(
+        &mut self,
+        slot: usize,
+        setup: &RunSetup,
+        game: &Game,
+        frame: (&[u8], [u32; 2]),
+        window: &mut PistonWindow,
+    ) -> Result<(), String> {
+        // Save the run, then what's shown about it and a shrunk copy of the frame it was saved on, keeping the
+        // slot's name if it already had one
+        fs::create_dir_all(SAVES_DIR).map_err(|error| error.to_string())?;
+        storage::write_packed(
+            &slot_path(slot, "sav"),
+            autosave::encode(setup, game).as_bytes(),
+        )
+        .map_err(|error| error.to_string())?;
+        let name = self.slots[slot]
+            .as_ref()
+            .map_or_else(|| format!("Save {}", slot + 1), |info| info.name.clone());
+        let info = SlotInfo {
+            name,
+            saved: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            level: setup.level.name.clone(),
+            wave: game.state.wave,
+        };
+        write_info(slot, &info).map_err(|error| error.to_string())?;
+        self.slots[slot] = Some(info);
+        let (pixels, [width, height]) = frame;
+        if let Some(picture) = ::image::RgbaImage::from_raw(width, height, pixels.to_vec()) {
+            let [thumbnail_width, thumbnail_height] = THUMBNAIL_SIZE;
+            ::image::imageops::thumbnail(&picture, thumbnail_width, thumbnail_height)
+                .save(slot_path(slot, "png"))
+                .map_err(|error| error.to_string())?;
+        }
+        self.load_thumbnail(slot, window);
+        Ok(())
+    }
+
+    pub fn save_drawn_frame(
+        &mut self,
+        setup: &RunSetup,
+        game: &Game,
+        size: [u32; 2],
+        window: &mut PistonWindow,
+    ) {
+        // Save the run to the slot waiting for it, with the frame that's just been drawn as its picture, telling the
+        // player how it went
+        let Some(slot) = self.saving.take() else {
+            return;
+        };
+        let pixels = photo::read_frame(size);
+        match self.save(slot, setup, game, (&pixels, size), window) {
+            Ok(()) => notify(format!("Saved the run in slot {}", slot + 1), Kind::Info),
+            Err(error) => notify(format!("Couldn't save the run: {}", error), Kind::Warning),
+        }
+    }
+
+    fn load_run(&self, slot: usize) -> Result<SavedRun, String> {
+        autosave::load(&slot_path(slot, "sav"))?
+            .ok_or_else(|| format!("Save slot {} is empty", slot + 1))
+    }
+
+    pub fn take_loaded(&mut self) -> Option<SavedRun> {
+        // Load the run from the slot waiting to be loaded, closing the save screen, or tell the player why it
+        // couldn't be
+        let slot = self.loading.take()?;
+        match self.load_run(slot) {
+            Ok(mut run) => {
+                if let Some(warning) = run.warning.take() {
+                    notify(warning, Kind::Warning);
+                }
+                notify(format!("Loaded the run in slot {}", slot + 1), Kind::Info);
+                self.open = false;
+                Some(run)
+            }
+            Err(error) => {
+                notify(format!("Couldn't load the save: {}", error), Kind::Warning);
+                None
+            }
+        }
+    }
+
+    pub fn choose(&mut self, choice: SlotChoice, running: bool) -> Option<Confirmation> {
+        // Act on what the player clicked, returning what to check with them first: saving over a slot, loading one
+        // in place of a run in progress, or deleting one
+        match choice {
+            SlotChoice::Save(slot) if self.slots[slot].is_some() => {
+                return Some(Confirmation::OverwriteSlot(slot))
+            }
+            SlotChoice::Save(slot) => self.saving = Some(slot),
+            SlotChoice::Load(slot) if running => return Some(Confirmation::LoadSlot(slot)),
+            SlotChoice::Load(slot) => self.loading = Some(slot),
+            SlotChoice::Rename(slot) => self.start_renaming(slot),
+            SlotChoice::Delete(slot) => return Some(Confirmation::DeleteSlot(slot)),
+            SlotChoice::Close => self.open = false,
+        }
+        None
+    }
+
+    pub fn delete(&mut self, slot: usize) {
+        // Delete the slot's files, which are fine to find already gone
+        for extension in ["sav", "meta", "png"] {
+            let path = slot_path(slot, extension);
+            if let Err(error) = fs::remove_file(&path) {
+                if error.kind() != io::ErrorKind::NotFound {
+                    eprintln!("Couldn't delete {}: {}", path, error);
+                }
+            }
+        }
+        self.slots[slot] = None;
+        self.thumbnails[slot] = None;
+    }
+
+    fn start_renaming(&mut self, slot: usize) {
+        if let Some(info) = &self.slots[slot] {
+            self.renaming = Some((slot, info.name.clone()));
+        }
+    }
+
+    pub fn handle_renaming(&mut self, event: &Event) -> bool {
+        // Type the slot's new name while it's being renamed, with Enter to keep it and Escape to leave the old one,
+        // returning whether the event was used up, which every key press is so it doesn't also trigger an action
+        let Some((slot, name)) = &mut self.renaming else {
+            return false;
+        };
+        if let Some(text) = event.text_args() {
+            for character in text.chars().filter(|character| !character.is_control()) {
+                if name.chars().count() < MAX_NAME_LENGTH {
+                    name.push(character);
+                }
+            }
+            return true;
+        }
+        match event.press_args() {
+            Some(Button::Keyboard(Key::Backspace)) => {
+                name.pop();
+            }
+            Some(Button::Keyboard(Key::Return | Key::NumPadEnter)) => {
+                let (slot, name) = (*slot, name.trim().to_string());
+                self.renaming = None;
+                if let Some(info) = self.slots[slot].as_mut().filter(|_| !name.is_empty()) {
+                    info.name = name;
+                    if let Err(error) = write_info(slot, info) {
+                        eprintln!("Couldn't rename save slot {}: {}", slot + 1, error);
+                    }
+                }
+            }
+            Some(Button::Keyboard(Key::Escape)) => self.renaming = None,
+            Some(Button::Keyboard(_)) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn draw(
+        &self,
+        can_save: bool,
+        can_load: bool,
+        ui: &mut Ui,
+        frame: &mut Frame,
+    ) -> Option<SlotChoice> {
+        // List the slots down the screen, each with its picture, name, level, wave, and when it was saved, and
+        // buttons to save over it, load it, rename it, or delete it
+        let mut choice = None;
+        ui.panel([60.0, 20.0, 520.0, 420.0], frame);
+        let c = frame.c;
+        let style = TextStyle {
+            size: 14,
+            ..Default::default()
+        };
+        label::draw(
+            "Saved runs",
+            &TextStyle { size: 20, ..style },
+            70.0,
+            45.0,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+        for (slot, info) in self.slots.iter().enumerate() {
+            let y = 60.0 + slot as f64 * 70.0;
+            if let Some(thumbnail) = &self.thumbnails[slot] {
+                image(thumbnail, c.transform.trans(70.0, y + 5.0), frame.g);
+            } else {
+                let [width, height] = THUMBNAIL_SIZE;
+                rectangle(
+                    theme::palette().widget,
+                    [70.0, y + 5.0, width as f64, height as f64],
+                    c.transform,
+                    frame.g,
+                );
+            }
+            let lines = match info {
+                Some(info) => {
+                    let name = match &self.renaming {
+                        Some((renaming, name)) if *renaming == slot => format!("{}_", name),
+                        _ => info.name.clone(),
+                    };
+                    [
+                        name,
+                        format!("{} - wave {}", info.level, info.wave),
+                        format_timestamp(info.saved),
+                    ]
+                }
+                None => [
+                    format!("Slot {} - empty", slot + 1),
+                    String::new(),
+                    String::new(),
+                ],
+            };
+            for (i, line) in lines.iter().enumerate() {
+                let size = if i == 0 { 14 } else { 12 };
+                let y = y + 18.0 + i as f64 * 16.0;
+                label::draw(
+                    line,
+                    &TextStyle { size, ..style },
+                    145.0,
+                    y,
+                    frame.glyphs,
+                    c,
+                    frame.g,
+                );
+            }
+            let used = info.is_some();
+            let buttons = [
+                ("save", "Save", can_save, SlotChoice::Save(slot)),
+                ("load", "Load", used && can_load, SlotChoice::Load(slot)),
+                ("rename", "Rename", used, SlotChoice::Rename(slot)),
+                ("delete", "Delete", used, SlotChoice::Delete(slot)),
+            ];
+            for (i, (id, caption, enabled, button)) in buttons.into_iter().enumerate() {
+                let rect = [
+                    355.0 + (i % 2) as f64 * 110.0,
+                    y + 5.0 + (i / 2) as f64 * 27.0,
+                    105.0,
+                    22.0,
+                ];
+                let id = format!("slot {} {}", slot, id);
+                if ui.button(&id, caption, rect, enabled, false, frame) {
+                    choice = Some(button);
+                }
+            }
+        }
+        if ui.button(
+            "close saves",
+            "Close",
+            [480.0, 410.0, 90.0, 25.0],
+            true,
+            false,
+            frame,
+        ) {
+            choice = Some(SlotChoice::Close);
+        }
+        choice
+    }
+}
+
+fn read_info(slot: usize) -> Result<Option<SlotInfo>, String> {
+    // A slot without a save just hasn't been used
+    let Some(contents) = storage::read_checked(&slot_path(slot, "meta"))? else {
+        return Ok(None);
+    };
+    let mut info = SlotInfo {
+        name: format!("Save {}", slot + 1),
+        saved: 0,
+        level: String::new(),
+        wave: 0,
+    };
+    for line in contents.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "name" => info.name = value.to_string(),
+            "saved" => info.saved = parse_number(value)?,
+            "level" => info.level = value.to_string(),
+            "wave" => info.wave = parse_number(value)?,
+            _ => {}
+        }
+    }
+    Ok(Some(info))
+}
+
+fn write_info(slot: usize, info: &SlotInfo) -> io::Result<()> {
+    storage::write_checked(
+        &slot_path(slot, "meta"),
+        &format!(
+            "name {}\nsaved {}\nlevel {}\nwave {}\n",
+            info.name, info.saved, info.level, info.wave
+        ),
+    )
+}
+
+pub fn format_timestamp(seconds: u64) -> String {
+    // Show a time as a UTC date and time, working out the calendar date from the days since the epoch with the
+    // proleptic Gregorian calendar's 400-year cycles
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60
+    )
+}
@@ -0,0 +1,293 @@
+use std::fs;
+use std::io;
+
+use crate::assets::Filtering;
+use crate::display::DisplayMode;
+use crate::input::Preset;
+use crate::storage;
+use crate::theme::Theme;
+
+// File the player's settings are saved to
+pub const SETTINGS_PATH: &str = "settings.cfg";
+// Space between the top of the layout and the settings panel, which is centered across it, in pixels
+const PANEL_MARGIN: f32 = 10.0;
+
+pub struct Settings {
+    // Stores whether to wait for the display's vertical sync before presenting each frame
+    pub vsync: bool,
+    // Stores whether the game is shown in a window, borderless over a whole monitor, or fullscreen
+    pub display: DisplayMode,
+    // Stores the monitor the game is shown on, by its place in the list of connected monitors
+    pub monitor: usize,
+    // Stores how many times bigger than the layout the window opens, on top of the display's own scaling
+    pub ui_scale: f64,
+    // Stores whether to cap the frame rate, which saves battery on laptops
+    pub frame_limiter: bool,
+    // Stores the most frames to render per second while the frame limiter is on
+    pub max_fps: u64,
+    // Stores whether building and selling while paused waits for the game to be unpaused
+    pub queue_while_paused: bool,
+    // Stores whether a run in progress is paused when the window goes into the background
+    pub pause_on_focus_loss: bool,
+    // Stores the built-in bindings the player's own are layered on top of
+    pub controls: Preset,
+    // Stores the theme the game is drawn in
+    pub theme: Theme,
+    // Stores how textures are sampled when they're drawn bigger or smaller than they are
+    pub texture_filter: Filtering,
+    // Stores whether zooming keeps to whole multiples of the map's size, so pixel art stays crisp
+    pub integer_scaling: bool,
+    // Stores how strongly the night-time lighting darkens the map and makes lights glow, from 0 for off up to 1
+    pub lighting: f64,
+    // Stores whether the towers from the best previous run of the level are shown faintly on the map
+    pub ghost: bool,
+    // Stores whether anonymous balance data is kept about each finished run
+    pub telemetry: bool,
+    // Stores the address the balance data is sent to after each run, if any
+    pub telemetry_endpoint: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            vsync: true,
+            display: DisplayMode::default(),
+            monitor: 0,
+            ui_scale: 1.,
+            frame_limiter: true,
+            max_fps: 60,
+            queue_while_paused: true,
+            pause_on_focus_loss: true,
+            controls: Preset::default(),
+            theme: Theme::default(),
+            texture_filter: Filtering::default(),
+            integer_scaling: false,
+            lighting: 0.,
+            ghost: false,
+            telemetry: false,
+            telemetry_endpoint: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &str) -> Self {
+        // Settings missing from the file keep their defaults
+        let mut settings = Settings::default();
+        let Ok(contents) = fs::read_to_string(path) else {
+            return settings;
+        };
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "vsync" => settings.vsync = value.parse().unwrap_or(settings.vsync),
+                "display" => {
+                    settings.display = DisplayMode::from_name(value).unwrap_or(settings.display)
+                }
+                "monitor" => settings.monitor = value.parse().unwrap_or(settings.monitor),
+                "ui_scale" => {
+                    settings.ui_scale = value
+                        .parse::<f64>()
+                        .map_or(settings.ui_scale, |scale| scale.clamp(1., 3.))
+                }
+                "frame_limiter" => {
+                    settings.frame_limiter = value.parse().unwrap_or(settings.frame_limiter)
+                }
+                "max_fps" => settings.max_fps = value.parse().unwrap_or(settings.max_fps),
+                "queue_while_paused" => {
+                    settings.queue_while_paused =
+                        value.parse().unwrap_or(settings.queue_while_paused)
+                }
+                "pause_on_focus_loss" => {
+                    settings.pause_on_focus_loss =
+                        value.parse().unwrap_or(settings.pause_on_focus_loss)
+                }
+                "controls" => {
+                    settings.controls = Preset::from_name(value).unwrap_or(settings.controls)
+                }
+                "theme" => settings.theme = Theme::from_name(value).unwrap_or(settings.theme),
+                "texture_filter" => {
+                    settings.texture_filter =
+                        Filtering::from_name(value).unwrap_or(settings.texture_filter)
+                }
+                "integer_scaling" => {
+                    settings.integer_scaling = value.parse().unwrap_or(settings.integer_scaling)
+                }
+                "lighting" => {
+                    settings.lighting = value
+                        .parse::<f64>()
+                        .map_or(settings.lighting, |lighting| lighting.clamp(0., 1.))
+                }
+                "ghost" => settings.ghost = value.parse().unwrap_or(settings.ghost),
+                "telemetry" => settings.telemetry = value.parse().unwrap_or(settings.telemetry),
+                "telemetry_endpoint" => settings.telemetry_endpoint = value.to_string(),
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        // Leave the file alone when nothing in it would change, so it's only rewritten when a setting is
+        let contents = format!(
+                "vsync={}\ndisplay={}\nmonitor={}\nui_scale={}\nframe_limiter={}\nmax_fps={}\nqueue_while_paused={}\npause_on_focus_loss={}\ncontrols={}\ntheme={}\ntexture_filter={}\ninteger_scaling={}\nlighting={}\nghost={}\ntelemetry={}\ntelemetry_endpoint={}\n",
+                self.vsync,
+                self.display.name(),
+                self.monitor,
+                self.ui_scale,
+                self.frame_limiter,
+                self.max_fps,
+                self.queue_while_paused,
+                self.pause_on_focus_loss,
+                self.controls.name(),
+                self.theme.name(),
+                self.texture_filter.name(),
+                self.integer_scaling,
+                self.lighting,
+                self.ghost,
+                self.telemetry,
+                self.telemetry_endpoint
+        );
+        if fs::read_to_string(path).is_ok_and(|saved| saved == contents) {
+            return Ok(());
+        }
+        storage::write_atomically(path, &contents)
+    }
+
+    pub fn fps_cap(&self) -> u64 {
+        // Without the limiter, render as fast as the event loop allows
+        if self.frame_limiter {
+            self.max_fps.max(1)
+        } else {
+            1000
+        }
+    }
+}
+
+// What the player clicked on in the settings panel that needs more than the settings changing
+pub enum SettingsChoice {
+    Display,
+    Monitor,
+    ControlScheme,
+    Theme,
+    TextureFilter,
+    Controls,
+    Close,
+}
+
+pub fn show(
+    ctx: &egui::Context,
+    settings: &mut Settings,
+    monitors: usize,
+    changed: &mut bool,
+) -> Option<SettingsChoice> {
+    // Show the settings panel, which applies changes as soon as they're made, noting whether any setting changed and
+    // returning the button clicked that the caller has to act on
+    let mut choice = None;
+    egui::Window::new("Settings")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, PANEL_MARGIN])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(format!("Display: {}", settings.display.description()))
+                    .clicked()
+                {
+                    choice = Some(SettingsChoice::Display);
+                }
+                let monitor = egui::Button::new(format!(
+                    "Monitor {} of {}",
+                    settings.monitor + 1,
+                    monitors.max(1)
+                ));
+                if ui.add_enabled(monitors > 1, monitor).clicked() {
+                    choice = Some(SettingsChoice::Monitor);
+                }
+            });
+            *changed |= ui
+                .checkbox(&mut settings.vsync, "Vsync (applies after restarting)")
+                .changed();
+            *changed |= ui
+                .checkbox(&mut settings.frame_limiter, "Limit frame rate")
+                .changed();
+            *changed |= ui
+                .add(egui::Slider::new(&mut settings.max_fps, 30..=240).text("Max FPS"))
+                .changed();
+            *changed |= ui
+                .checkbox(
+                    &mut settings.queue_while_paused,
+                    "Queue building and selling while paused",
+                )
+                .changed();
+            *changed |= ui
+                .checkbox(
+                    &mut settings.pause_on_focus_loss,
+                    "Pause when the window loses focus",
+                )
+                .changed();
+            // Picking a control scheme replaces every binding with the scheme's own
+            if ui
+                .button(format!(
+                    "Control scheme: {}",
+                    settings.controls.description()
+                ))
+                .clicked()
+            {
+                choice = Some(SettingsChoice::ControlScheme);
+            }
+            if ui
+                .button(format!("Theme: {}", settings.theme.description()))
+                .clicked()
+            {
+                choice = Some(SettingsChoice::Theme);
+            }
+            *changed |= ui
+                .checkbox(&mut settings.telemetry, "Share anonymous balance data")
+                .changed();
+            if ui
+                .button(format!(
+                    "Texture filter: {}",
+                    settings.texture_filter.description()
+                ))
+                .clicked()
+            {
+                choice = Some(SettingsChoice::TextureFilter);
+            }
+            *changed |= ui
+                .checkbox(
+                    &mut settings.integer_scaling,
+                    "Integer scaling when zooming",
+                )
+                .changed();
+            *changed |= ui
+                .add(
+                    egui::Slider::new(&mut settings.lighting, 0.0..=1.0)
+                        .text("Night lighting")
+                        .custom_formatter(|value, _| format!("{:.0}%", value * 100.0)),
+                )
+                .changed();
+            // The window only changes size once the panel closes, so it doesn't move the slider while it's being
+            // dragged
+            *changed |= ui
+                .add(
+                    egui::Slider::new(&mut settings.ui_scale, 1.0..=3.0)
+                        .step_by(0.25)
+                        .text("UI scale")
+                        .custom_formatter(|value, _| format!("{:.0}%", value * 100.0)),
+                )
+                .changed();
+            ui.horizontal(|ui| {
+                if ui.button("Controls").clicked() {
+                    choice = Some(SettingsChoice::Controls);
+                }
+                if ui.button("Close").clicked() {
+                    choice = Some(SettingsChoice::Close);
+                }
+            });
+        });
+    choice
+}
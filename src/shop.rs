@@ -0,0 +1,83 @@
+use rand::Rng;
+
+// Number of offers the shop shows at once
+pub const SHOP_SIZE: usize = 3;
+// Number of waves between shop restocks
+pub const SHOP_RESTOCK_WAVES: usize = 3;
+
+#[derive(Clone, Copy)]
+pub enum Offer {
+    // Adds the item type with the given index to the player's inventory
+    Item(usize),
+    // Takes a fraction off the cost of the next tower of the given type the player builds
+    TowerDiscount { tower_type: usize, discount: f32 },
+    // Gives the player another life
+    ExtraLife,
+}
+
+#[derive(Clone)]
+pub struct ShopOffer {
+    // Stores what the player gets for buying the offer
+    pub offer: Offer,
+    // Stores the offer's price in resources
+    pub price: i32,
+    // Stores whether the player has already bought the offer
+    pub sold: bool,
+}
+
+#[derive(Clone)]
+pub struct Shop {
+    // Stores whether the shop window is showing
+    pub open: bool,
+    // Stores the offers currently in stock
+    pub offers: Vec<ShopOffer>,
+    // Stores the wave the shop was last restocked on, if it ever was
+    pub restocked_on: Option<usize>,
+}
+
+impl Shop {
+    pub fn new() -> Self {
+        Shop {
+            open: false,
+            offers: Vec::new(),
+            restocked_on: None,
+        }
+    }
+
+    pub fn restock_if_due(
+        &mut self,
+        wave: usize,
+        item_types: usize,
+        tower_types: usize,
+        rng: &mut impl Rng,
+    ) {
+        // Only refresh the stock every few waves, so unbought offers stick around for a while
+        if matches!(self.restocked_on, Some(restocked_on) if wave < restocked_on + SHOP_RESTOCK_WAVES)
+        {
+            return;
+        }
+        self.restocked_on = Some(wave);
+        self.offers = (0..SHOP_SIZE)
+            .map(|_| match rng.gen_range(0..3) {
+                0 if item_types > 0 => ShopOffer {
+                    offer: Offer::Item(rng.gen_range(0..item_types)),
+                    price: 40,
+                    sold: false,
+                },
+                1 if tower_types > 0 => ShopOffer {
+                    offer: Offer::TowerDiscount {
+                        tower_type: rng.gen_range(0..tower_types),
+                        discount: 0.5,
+                    },
+                    price: 20,
+                    sold: false,
+                },
+                _ => ShopOffer {
+                    offer: Offer::ExtraLife,
+                    price: 60,
+                    sold: false,
+                },
+            })
+            .collect();
+    }
+}
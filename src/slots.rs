@@ -0,0 +1,37 @@
+use crate::notifications::{notify, Kind};
+use crate::{Game, Point, TILE_SIZE};
+
+impl Game {
+    pub fn slot_at(&self, position: &Point) -> Option<Point> {
+        // Find the slot a tower at the given position would stand on, which is the one whose tile its center is in
+        let center = position.plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.));
+        self.slots.iter().copied().find(|slot| {
+            center.x >= slot.x
+                && center.y >= slot.y
+                && center.x < slot.x + TILE_SIZE
+                && center.y < slot.y + TILE_SIZE
+        })
+    }
+
+    pub fn build_site(&self, position: Point) -> Option<Point> {
+        // Work out where a tower built at the given position would go, which on a level with slots is the free slot
+        // under it, and anywhere on a level without
+        if self.slots.is_empty() {
+            return Some(position);
+        }
+        match self.slot_at(&position) {
+            Some(slot) if self.tower_at(&slot).is_some() => {
+                notify("There's already a tower on that slot", Kind::Warning);
+                None
+            }
+            Some(slot) => Some(slot),
+            None => {
+                notify(
+                    "Towers can only be built on this level's slots",
+                    Kind::Warning,
+                );
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::json::Json;
+use crate::{Game, Outcome, Point, CAMPAIGN_WAVES};
+
+// A copy of everything a player could see about the game at one moment, which UI layers, bots, and tools can read
+// as long as they like without holding on to the game itself
+#[derive(Clone)]
+pub struct Snapshot {
+    // Stores the number of simulation ticks played when the snapshot was taken
+    pub tick: u64,
+    // Stores how long the run had been played, in seconds
+    pub playtime: f32,
+    // Stores whether the player had won or lost, if the game was over
+    pub outcome: Option<Outcome>,
+    // Stores where the run was in its waves
+    pub wave: WaveStatus,
+    // Stores what the player had to spend and to lose
+    pub economy: Economy,
+    // Stores the player's towers, in the order they were placed
+    pub towers: Vec<TowerView>,
+    // Stores the enemies on the map
+    pub enemies: Vec<EnemyView>,
+    // Stores where the uncollected loot lay
+    pub loot: Vec<Point>,
+    // Stores the map's gates
+    pub gates: Vec<GateView>,
+}
+
+#[derive(Clone)]
+pub struct WaveStatus {
+    // Stores the current wave number, starting from 1
+    pub number: usize,
+    // Stores the number of waves in the campaign
+    pub total: usize,
+    // Stores whether the game was in the build phase before the wave
+    pub building: bool,
+    // Stores the time left in the build phase or wave, in seconds
+    pub time_left: f32,
+    // Stores the number of enemies on the map or still waiting to enter it
+    pub enemies_left: usize,
+    // Stores anything unusual about the wave, as announced to the player
+    pub announcement: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Economy {
+    // Stores the player's resources, or none in a sandbox where they're unlimited
+    pub resources: Option<i32>,
+    // Stores the player's lives
+    pub lives: i32,
+    // Stores the player's spell charges
+    pub spell_charges: u32,
+    // Stores the multiplier on every tower's damage from a collected buff, and the time until it wears off
+    pub damage_boost: Option<(f32, f32)>,
+    // Stores the multiplier on the rewards enemies give in the current wave
+    pub bounty: f32,
+    // Stores what the next tower of each type costs, after any discounts
+    pub tower_costs: Vec<i32>,
+    // Stores the names of the items the player is carrying
+    pub inventory: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct TowerView {
+    // Stores the tower's number, counting up from 1 in the order towers were placed
+    pub id: usize,
+    // Stores the name of the tower's type
+    pub name: String,
+    // Stores the tower's position on the map
+    pub position: Point,
+    // Stores how far the tower reaches from where it stands, in pixels
+    pub range: f32,
+    // Stores the time until the tower can fire again, in seconds
+    pub cooldown: f32,
+    // Stores the time until the tower's ability can be triggered again, in seconds
+    pub ability_cooldown: f32,
+}
+
+#[derive(Clone)]
+pub struct EnemyView {
+    // Stores the name of the enemy's type
+    pub name: String,
+    // Stores the enemy's position on the map
+    pub position: Point,
+    // Stores the enemy's hit points
+    pub hit_points: i32,
+    // Stores the enemy's maximum hit points
+    pub max_hit_points: i32,
+    // Stores how far the enemy still has to go to reach the player's base, in pixels
+    pub remaining: f32,
+    // Stores whether towers could hit the enemy
+    pub targetable: bool,
+    // Stores whether the enemy was on the water lane rather than the land path
+    pub on_lane: bool,
+}
+
+#[derive(Clone)]
+pub struct GateView {
+    // Stores where the gate's lever is
+    pub lever: Point,
+    // Stores whether the gate was closed
+    pub closed: bool,
+    // Stores the time until the lever can be pulled again, in seconds
+    pub cooldown: f32,
+}
+
+impl Game {
+    pub fn snapshot(&self) -> Snapshot {
+        let state = &self.state;
+        Snapshot {
+            tick: state.tick,
+            playtime: state.stats.playtime,
+            outcome: state.outcome,
+            wave: WaveStatus {
+                number: state.wave,
+                total: CAMPAIGN_WAVES,
+                building: state.building,
+                time_left: state.wave_timer,
+                enemies_left: state.enemies.len() + state.spawn_queue.len(),
+                announcement: self
+                    .waves
+                    .get(state.wave - 1)
+                    .and_then(|wave| wave.announcement()),
+            },
+            economy: Economy {
+                resources: (!self.rules.sandbox).then_some(state.resources),
+                lives: state.lives,
+                spell_charges: state.spell_charges,
+                damage_boost: (state.damage_boost_timer > 0.)
+                    .then_some((state.damage_boost, state.damage_boost_timer)),
+                bounty: self.reward_curve.multiplier(state.wave),
+                tower_costs: (0..self.tower_types.len())
+                    .map(|tower_type| self.tower_cost(tower_type))
+                    .collect(),
+                inventory: state
+                    .inventory
+                    .iter()
+                    .map(|&item| self.item_types[item].name.clone())
+                    .collect(),
+            },
+            towers: state
+                .towers
+                .iter()
+                .map(|tower| TowerView {
+                    id: tower.id,
+                    name: tower.tower_type.name.clone(),
+                    position: tower.position,
+                    range: self.map.range_from(&tower.position, tower.stats.range),
+                    cooldown: tower.cooldown,
+                    ability_cooldown: tower.ability_cooldown,
+                })
+                .collect(),
+            enemies: state
+                .enemies
+                .iter()
+                .filter(|enemy| enemy.is_alive())
+                .map(|enemy| EnemyView {
+                    name: enemy.enemy_type.name.clone(),
+                    position: enemy.position,
+                    hit_points: enemy.hit_points,
+                    max_hit_points: enemy.enemy_type.max_hit_points,
+                    remaining: enemy
+                        .route(&self.path, self.lane.as_ref())
+                        .remaining(enemy.distance),
+                    targetable: enemy.is_targetable(),
+                    on_lane: enemy.on_lane,
+                })
+                .collect(),
+            loot: state.loot.iter().map(|drop| drop.position).collect(),
+            gates: self
+                .gates
+                .iter()
+                .map(|gate| GateView {
+                    lever: gate.lever,
+                    closed: gate.closed,
+                    cooldown: gate.cooldown,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn point(point: &Point) -> Json {
+    Json::Array(vec![
+        Json::Number(point.x as f64),
+        Json::Number(point.y as f64),
+    ])
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+    )
+}
+
+impl Snapshot {
+    pub fn to_json(&self) -> Json {
+        // Write the snapshot out for tools outside the game, with positions as [x, y] pairs in pixels
+        let outcome = match self.outcome {
+            Some(Outcome::Won) => Json::String(String::from("won")),
+            Some(Outcome::Lost) => Json::String(String::from("lost")),
+            None => Json::Null,
+        };
+        let wave = object(vec![
+            ("number", Json::Number(self.wave.number as f64)),
+            ("total", Json::Number(self.wave.total as f64)),
+            ("building", Json::Bool(self.wave.building)),
+            ("time_left", Json::Number(self.wave.time_left as f64)),
+            ("enemies_left", Json::Number(self.wave.enemies_left as f64)),
+            (
+                "announcement",
+                self.wave
+                    .announcement
+                    .clone()
+                    .map_or(Json::Null, Json::String),
+            ),
+        ]);
+        let economy = &self.economy;
+        let economy = object(vec![
+            (
+                "resources",
+                economy
+                    .resources
+                    .map_or(Json::Null, |resources| Json::Number(resources as f64)),
+            ),
+            ("lives", Json::Number(economy.lives as f64)),
+            ("spell_charges", Json::Number(economy.spell_charges as f64)),
+            (
+                "damage_boost",
+                economy
+                    .damage_boost
+                    .map_or(Json::Null, |(boost, time_left)| {
+                        object(vec![
+                            ("multiplier", Json::Number(boost as f64)),
+                            ("time_left", Json::Number(time_left as f64)),
+                        ])
+                    }),
+            ),
+            ("bounty", Json::Number(economy.bounty as f64)),
+            (
+                "tower_costs",
+                Json::Array(
+                    economy
+                        .tower_costs
+                        .iter()
+                        .map(|&cost| Json::Number(cost as f64))
+                        .collect(),
+                ),
+            ),
+            (
+                "inventory",
+                Json::Array(
+                    economy
+                        .inventory
+                        .iter()
+                        .cloned()
+                        .map(Json::String)
+                        .collect(),
+                ),
+            ),
+        ]);
+        let towers = self
+            .towers
+            .iter()
+            .map(|tower| {
+                object(vec![
+                    ("id", Json::Number(tower.id as f64)),
+                    ("name", Json::String(tower.name.clone())),
+                    ("position", point(&tower.position)),
+                    ("range", Json::Number(tower.range as f64)),
+                    ("cooldown", Json::Number(tower.cooldown as f64)),
+                    (
+                        "ability_cooldown",
+                        Json::Number(tower.ability_cooldown as f64),
+                    ),
+                ])
+            })
+            .collect();
+        let enemies = self
+            .enemies
+            .iter()
+            .map(|enemy| {
+                object(vec![
+                    ("name", Json::String(enemy.name.clone())),
+                    ("position", point(&enemy.position)),
+                    ("hit_points", Json::Number(enemy.hit_points as f64)),
+                    ("max_hit_points", Json::Number(enemy.max_hit_points as f64)),
+                    ("remaining", Json::Number(enemy.remaining as f64)),
+                    ("targetable", Json::Bool(enemy.targetable)),
+                    ("on_lane", Json::Bool(enemy.on_lane)),
+                ])
+            })
+            .collect();
+        let gates = self
+            .gates
+            .iter()
+            .map(|gate| {
+                object(vec![
+                    ("lever", point(&gate.lever)),
+                    ("closed", Json::Bool(gate.closed)),
+                    ("cooldown", Json::Number(gate.cooldown as f64)),
+                ])
+            })
+            .collect();
+        object(vec![
+            ("tick", Json::Number(self.tick as f64)),
+            ("playtime", Json::Number(self.playtime as f64)),
+            ("outcome", outcome),
+            ("wave", wave),
+            ("economy", economy),
+            ("towers", Json::Array(towers)),
+            ("enemies", Json::Array(enemies)),
+            ("loot", Json::Array(self.loot.iter().map(point).collect())),
+            ("gates", Json::Array(gates)),
+        ])
+    }
+}
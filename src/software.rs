@@ -0,0 +1,137 @@
+use std::f64::consts::TAU;
+
+use image::{Rgba, RgbaImage};
+
+use crate::renderer::{self, Renderer};
+use crate::theme;
+use crate::{Game, TILE_SIZE};
+
+// Seconds of play before a frame is drawn of a run, enough for the first wave to be under way
+pub const RENDER_FRAME_TIME: f32 = 25.;
+
+// Draws into an image on the CPU, at one pixel per map unit, for putting the map on screen without a window or a GPU
+pub struct SoftwareRenderer {
+    // Stores the picture drawn so far
+    pub image: RgbaImage,
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32, background: [f32; 4]) -> Self {
+        let [r, g, b, _] = background.map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8);
+        SoftwareRenderer {
+            image: RgbaImage::from_pixel(width, height, Rgba([r, g, b, u8::MAX])),
+        }
+    }
+
+    fn fill(&mut self, color: [f32; 4], bounds: [f64; 4], inside: impl Fn(f64, f64) -> bool) {
+        // Blend the color over every pixel in the bounds whose center is inside the shape
+        let [x, y, width, height] = bounds;
+        let (image_width, image_height) = self.image.dimensions();
+        let left = x.floor().max(0.) as u32;
+        let top = y.floor().max(0.) as u32;
+        let right = ((x + width).ceil().max(0.) as u32).min(image_width);
+        let bottom = ((y + height).ceil().max(0.) as u32).min(image_height);
+        let alpha = color[3].clamp(0., 1.);
+        for py in top..bottom {
+            for px in left..right {
+                if !inside(px as f64 + 0.5, py as f64 + 0.5) {
+                    continue;
+                }
+                let pixel = self.image.get_pixel_mut(px, py);
+                for channel in 0..3 {
+                    let below = pixel[channel] as f32 / 255.;
+                    let blended = color[channel].clamp(0., 1.) * alpha + below * (1. - alpha);
+                    pixel[channel] = (blended * 255.).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn rectangle(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        self.fill(color, rect, |_, _| true);
+    }
+
+    fn ellipse(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        let [x, y, width, height] = rect;
+        let (radius_x, radius_y) = (width / 2.0, height / 2.0);
+        let (center_x, center_y) = (x + radius_x, y + radius_y);
+        self.fill(color, rect, |px, py| {
+            let (dx, dy) = ((px - center_x) / radius_x, (py - center_y) / radius_y);
+            dx * dx + dy * dy <= 1.0
+        });
+    }
+
+    fn line(&mut self, color: [f32; 4], radius: f64, line: [f64; 4]) {
+        // Fill everything within the radius of the segment, which rounds off its ends so joins between
+        // segments don't leave gaps
+        let [x1, y1, x2, y2] = line;
+        let bounds = [
+            x1.min(x2) - radius,
+            y1.min(y2) - radius,
+            (x1 - x2).abs() + radius * 2.0,
+            (y1 - y2).abs() + radius * 2.0,
+        ];
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let length_squared = (dx * dx + dy * dy).max(f64::EPSILON);
+        self.fill(color, bounds, |px, py| {
+            let along = (((px - x1) * dx + (py - y1) * dy) / length_squared).clamp(0.0, 1.0);
+            let (nearest_x, nearest_y) = (x1 + dx * along, y1 + dy * along);
+            (px - nearest_x).powi(2) + (py - nearest_y).powi(2) <= radius * radius
+        });
+    }
+
+    fn arc(&mut self, color: [f32; 4], radius: f64, angles: [f64; 2], rect: [f64; 4]) {
+        // Fill everything within the radius of the circle's edge between the two angles, with ellipses taken as
+        // circles as wide as their rectangle, which is all the game draws arcs of
+        let [x, y, width, height] = rect;
+        let circle = width / 2.0;
+        let (center_x, center_y) = (x + circle, y + height / 2.0);
+        let bounds = [
+            x - radius,
+            y - radius,
+            width + radius * 2.0,
+            height + radius * 2.0,
+        ];
+        let [start, end] = angles;
+        let sweep = end - start;
+        self.fill(color, bounds, |px, py| {
+            let (dx, dy) = (px - center_x, py - center_y);
+            let past_start = (dy.atan2(dx) - start).rem_euclid(TAU);
+            ((dx * dx + dy * dy).sqrt() - circle).abs() <= radius && past_start <= sweep
+        });
+    }
+}
+
+fn map_renderer(game: &Game) -> SoftwareRenderer {
+    // Draw the map as it's shown in game, the whole of it, onto an image as big as it is
+    let (width, height) = game.map.size;
+    let mut renderer = SoftwareRenderer::new(
+        (width as f32 * TILE_SIZE) as u32,
+        (height as f32 * TILE_SIZE) as u32,
+        theme::palette().background,
+    );
+    renderer::draw_terrain(game, &mut renderer);
+    renderer::draw_scenery(game, &mut renderer);
+    renderer::draw_base(&mut renderer);
+    for tower in game.state.towers.iter() {
+        renderer::draw_tower(tower, &mut renderer);
+    }
+    renderer
+}
+
+pub fn render_map(game: &Game, path: &str) -> Result<(), String> {
+    // Save the map as an image, without the enemies, HUD, or anything that moves
+    let mut renderer = map_renderer(game);
+    renderer::draw_bridges(game, &mut renderer);
+    renderer.image.save(path).map_err(|error| error.to_string())
+}
+
+pub fn render_frame(game: &Game, path: &str) -> Result<(), String> {
+    // Save a frame of play as an image, with the enemies, shots, and everything else that moves where they are on
+    // this tick, but without the HUD
+    let mut renderer = map_renderer(game);
+    renderer::draw_units(game, 1., |_, _| true, &mut renderer);
+    renderer.image.save(path).map_err(|error| error.to_string())
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::Point;
+
+pub struct SpatialIndex {
+    // Stores the width and height of each grid cell
+    cell_size: f32,
+    // Stores the indices and positions of the entities in each grid cell
+    cells: HashMap<(i32, i32), Vec<(usize, Point)>>,
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f32, positions: impl Iterator<Item = Point>) -> Self {
+        // Bucket every entity into the grid cell containing its position
+        let mut index = SpatialIndex {
+            cell_size,
+            cells: HashMap::new(),
+        };
+        for (i, position) in positions.enumerate() {
+            index
+                .cells
+                .entry(index.cell_of(&position))
+                .or_default()
+                .push((i, position));
+        }
+        index
+    }
+
+    fn cell_of(&self, position: &Point) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn query_rect(&self, min: &Point, max: &Point) -> Vec<(usize, Point)> {
+        // Collect the entities in every cell overlapping the rectangle, then discard those outside it
+        let (min_cell, max_cell) = (self.cell_of(min), self.cell_of(max));
+        let mut found = Vec::new();
+        for x in min_cell.0..=max_cell.0 {
+            for y in min_cell.1..=max_cell.1 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    found.extend(cell.iter().filter(|(_, position)| {
+                        position.x >= min.x
+                            && position.x <= max.x
+                            && position.y >= min.y
+                            && position.y <= max.y
+                    }));
+                }
+            }
+        }
+        found
+    }
+
+    pub fn count_near_segment(&self, start: &Point, end: &Point, width: f32) -> usize {
+        // Count the entities within `width` of the line segment between two points
+        let min = Point::new(start.x.min(end.x) - width, start.y.min(end.y) - width);
+        let max = Point::new(start.x.max(end.x) + width, start.y.max(end.y) + width);
+        self.query_rect(&min, &max)
+            .iter()
+            .filter(|(_, position)| position.distance_to_segment(start, end) <= width)
+            .count()
+    }
+}
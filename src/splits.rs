@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use piston_window::*;
+
+use crate::label::{self, Align, TextStyle};
+use crate::notifications::{notify, Kind};
+use crate::stats::format_time;
+use crate::theme;
+use crate::{storage, CAMPAIGN_WAVES};
+
+// File the player's best splits on each level are saved to
+pub const SPLITS_PATH: &str = "splits.txt";
+// Most splits the overlay shows at once, keeping the most recent
+const SPLITS_SHOWN: usize = 8;
+
+#[derive(Default)]
+pub struct PersonalBests {
+    // Stores the splits of the fastest win on each level, by level name
+    pub runs: BTreeMap<String, Vec<f32>>,
+}
+
+impl PersonalBests {
+    pub fn load(path: &str) -> Self {
+        // Each line holds one level's name and its split times, tab-separated, and unreadable lines are skipped
+        let mut bests = PersonalBests::default();
+        let contents = match storage::read_checked(path) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return bests,
+            Err(error) => {
+                eprintln!("Couldn't load splits: {}", error);
+                notify(
+                    format!(
+                        "Couldn't load personal bests, starting them over: {}",
+                        error
+                    ),
+                    Kind::Warning,
+                );
+                return bests;
+            }
+        };
+        for line in contents.lines() {
+            let mut fields = line.split('\t');
+            let Some(name) = fields.next() else {
+                continue;
+            };
+            if let Ok(splits) = fields.map(str::parse).collect::<Result<Vec<f32>, _>>() {
+                bests.runs.insert(name.to_string(), splits);
+            }
+        }
+        bests
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = String::new();
+        for (name, splits) in self.runs.iter() {
+            contents += name;
+            for split in splits.iter() {
+                contents += &format!("\t{}", split);
+            }
+            contents += "\n";
+        }
+        storage::write_checked(path, &contents)
+    }
+
+    pub fn best(&self, level: &str) -> Option<&[f32]> {
+        self.runs.get(level).map(Vec::as_slice)
+    }
+
+    pub fn record(&mut self, level: &str, splits: &[f32]) -> bool {
+        // Keep the run's splits if it finished faster than the personal best, returning whether it did
+        let Some(&time) = splits.last() else {
+            return false;
+        };
+        if self
+            .best(level)
+            .and_then(|best| best.last())
+            .is_some_and(|&best| best <= time)
+        {
+            return false;
+        }
+        self.runs.insert(level.to_string(), splits.to_vec());
+        true
+    }
+}
+
+fn segment_name(i: usize) -> String {
+    // After a split for each wave comes the moment the run was won, once the last wave is cleared
+    if i < CAMPAIGN_WAVES {
+        format!("Wave {}", i + 1)
+    } else {
+        String::from("Victory")
+    }
+}
+
+fn lss_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.) as f64;
+    let whole = seconds as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        whole / 3600,
+        whole / 60 % 60,
+        whole % 60,
+        ((seconds - whole as f64) * 1e7) as u64
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn export(level: &str, splits: &[f32], path: &str) -> io::Result<()> {
+    // Write the splits in LiveSplit's format, using game time since the clock stops while paused
+    let mut contents = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    contents += "<Run version=\"1.7.0\">\n";
+    contents += "  <GameName>tower-defense</GameName>\n";
+    contents += &format!("  <CategoryName>{}</CategoryName>\n", escape(level));
+    contents += "  <Offset>00:00:00</Offset>\n";
+    contents += "  <AttemptCount>0</AttemptCount>\n";
+    contents += "  <Segments>\n";
+    let mut previous = 0.;
+    for (i, &split) in splits.iter().enumerate() {
+        contents += "    <Segment>\n";
+        contents += &format!("      <Name>{}</Name>\n", escape(&segment_name(i)));
+        contents += "      <SplitTimes>\n";
+        contents += "        <SplitTime name=\"Personal Best\">\n";
+        contents += &format!("          <GameTime>{}</GameTime>\n", lss_time(split));
+        contents += "        </SplitTime>\n";
+        contents += "      </SplitTimes>\n";
+        contents += &format!(
+            "      <BestSegmentTime>\n        <GameTime>{}</GameTime>\n      </BestSegmentTime>\n",
+            lss_time(split - previous)
+        );
+        contents += "    </Segment>\n";
+        previous = split;
+    }
+    contents += "  </Segments>\n";
+    contents += "</Run>\n";
+    fs::write(path, contents)
+}
+
+pub fn draw(
+    splits: &[f32],
+    best: Option<&[f32]>,
+    playtime: f32,
+    c: Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+) {
+    // List the run's splits, plus the one in progress, each against the personal best's at the same point
+    let mut rows: Vec<(usize, f32)> = splits.iter().copied().enumerate().collect();
+    if splits.len() <= CAMPAIGN_WAVES {
+        rows.push((splits.len(), playtime));
+    }
+    let skipped = rows.len().saturating_sub(SPLITS_SHOWN);
+
+    rectangle(
+        theme::palette().panel,
+        [480.0, 125.0, 155.0, 25.0 + SPLITS_SHOWN as f64 * 16.0],
+        c.transform,
+        g,
+    );
+    let style = TextStyle {
+        size: 12,
+        ..Default::default()
+    };
+    label::draw("Splits", &style, 485.0, 140.0, glyphs, c, g);
+    for (row, &(i, time)) in rows.iter().skip(skipped).enumerate() {
+        let y = 156.0 + row as f64 * 16.0;
+        label::draw(&segment_name(i), &style, 485.0, y, glyphs, c, g);
+        label::draw(
+            &format_time(time),
+            &TextStyle {
+                align: Align::Right,
+                ..style
+            },
+            580.0,
+            y,
+            glyphs,
+            c,
+            g,
+        );
+        // Show how far ahead of or behind the personal best the run was, in green or red
+        if let Some(best) = best.and_then(|best| best.get(i)) {
+            let delta = time - best;
+            let color = if delta <= 0. {
+                [0.1, 0.6, 0.1, 1.0]
+            } else {
+                [0.8, 0.1, 0.1, 1.0]
+            };
+            label::draw(
+                &format!("{:+.1}", delta),
+                &TextStyle {
+                    color,
+                    align: Align::Right,
+                    ..style
+                },
+                630.0,
+                y,
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
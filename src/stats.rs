@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use piston_window::*;
+
+use crate::events::{GameEvent, Subscriber};
+use crate::label;
+use crate::notifications::{notify, Kind};
+use crate::storage;
+use crate::theme;
+
+// File the player's statistics across all runs are saved to
+pub const STATS_PATH: &str = "stats.txt";
+
+#[derive(Clone, Default)]
+pub struct RunStats {
+    // Stores the number of enemies killed this run, by enemy type name
+    pub kills: BTreeMap<String, u32>,
+    // Stores the number of towers built this run, by tower type name
+    pub towers_built: BTreeMap<String, u32>,
+    // Stores the number of towers sold this run
+    pub towers_sold: u32,
+    // Stores how long the run has been played, in seconds
+    pub playtime: f32,
+    // Stores the run's clock at the end of each wave, then at the moment it was won
+    pub splits: Vec<f32>,
+    // Stores the number of lives lost to enemies reaching the base this run
+    pub leaks: u32,
+    // Stores the number of lives lost to enemies reaching the base this run, by enemy type name
+    pub leaked: BTreeMap<String, u32>,
+}
+
+impl Subscriber for RunStats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EnemyKilled { enemy, .. } => {
+                *self.kills.entry(enemy.clone()).or_default() += 1;
+            }
+            GameEvent::TowerPlaced { tower, .. } => {
+                *self.towers_built.entry(tower.clone()).or_default() += 1;
+            }
+            GameEvent::BaseDamaged { enemy, lives_lost } => {
+                self.leaks += *lives_lost as u32;
+                *self.leaked.entry(enemy.clone()).or_default() += *lives_lost as u32;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LevelRecord {
+    // Stores the number of runs finished on the level
+    pub played: u32,
+    // Stores the number of those runs the player won
+    pub won: u32,
+}
+
+#[derive(Default)]
+pub struct LifetimeStats {
+    // Stores the number of enemies killed across all runs, by enemy type name
+    pub kills: BTreeMap<String, u32>,
+    // Stores the number of towers built across all runs, by tower type name
+    pub towers_built: BTreeMap<String, u32>,
+    // Stores the total time spent playing, in seconds
+    pub playtime: f32,
+    // Stores the results of finished runs, by level
+    pub levels: BTreeMap<String, LevelRecord>,
+}
+
+impl LifetimeStats {
+    pub fn load(path: &str) -> Self {
+        // Each line holds one tab-separated record, and unreadable lines are skipped
+        let mut stats = LifetimeStats::default();
+        let contents = match storage::read_checked(path) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return stats,
+            Err(error) => {
+                eprintln!("Couldn't load statistics: {}", error);
+                notify(
+                    format!("Couldn't load statistics, starting them over: {}", error),
+                    Kind::Warning,
+                );
+                return stats;
+            }
+        };
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["kills", name, count] => {
+                    stats
+                        .kills
+                        .insert(name.to_string(), count.parse().unwrap_or(0));
+                }
+                ["built", name, count] => {
+                    stats
+                        .towers_built
+                        .insert(name.to_string(), count.parse().unwrap_or(0));
+                }
+                ["playtime", seconds] => stats.playtime = seconds.parse().unwrap_or(0.),
+                ["level", name, played, won] => {
+                    stats.levels.insert(
+                        name.to_string(),
+                        LevelRecord {
+                            played: played.parse().unwrap_or(0),
+                            won: won.parse().unwrap_or(0),
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = format!("playtime\t{}\n", self.playtime);
+        for (name, count) in self.kills.iter() {
+            contents += &format!("kills\t{}\t{}\n", name, count);
+        }
+        for (name, count) in self.towers_built.iter() {
+            contents += &format!("built\t{}\t{}\n", name, count);
+        }
+        for (name, record) in self.levels.iter() {
+            contents += &format!("level\t{}\t{}\t{}\n", name, record.played, record.won);
+        }
+        storage::write_checked(path, &contents)
+    }
+
+    pub fn add_run(&mut self, run: &RunStats) {
+        for (name, count) in run.kills.iter() {
+            *self.kills.entry(name.clone()).or_default() += count;
+        }
+        for (name, count) in run.towers_built.iter() {
+            *self.towers_built.entry(name.clone()).or_default() += count;
+        }
+        self.playtime += run.playtime;
+    }
+
+    pub fn add_result(&mut self, level: &str, won: bool) {
+        let record = self.levels.entry(level.to_string()).or_default();
+        record.played += 1;
+        if won {
+            record.won += 1;
+        }
+    }
+
+    pub fn favorite_tower(&self) -> Option<&str> {
+        self.towers_built
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+pub fn format_time(seconds: f32) -> String {
+    // Show a duration as minutes and seconds, like a stopwatch
+    let seconds = seconds.max(0.) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn draw(stats: &LifetimeStats, c: Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let palette = theme::palette();
+    // Cover the map with a panel showing the statistics
+    rectangle(palette.panel, [20.0, 20.0, 600.0, 440.0], c.transform, g);
+    let hours = stats.playtime / 3600.;
+    let summary = format!(
+        "Statistics - {:.1} hours played, favorite tower: {}",
+        hours,
+        stats.favorite_tower().unwrap_or("none")
+    );
+    label::text(
+        palette.text,
+        18,
+        &summary,
+        glyphs,
+        c.transform.trans(30.0, 45.0),
+        g,
+    );
+
+    // Draw a bar for each enemy type killed, scaled to the most killed type
+    label::text(
+        palette.text,
+        16,
+        "Enemies killed",
+        glyphs,
+        c.transform.trans(30.0, 75.0),
+        g,
+    );
+    let most_kills = stats.kills.values().copied().max().unwrap_or(1).max(1);
+    for (i, (name, count)) in stats.kills.iter().enumerate() {
+        let y = 85.0 + i as f64 * 20.0;
+        let width = 300.0 * *count as f64 / most_kills as f64;
+        rectangle(
+            [0.8, 0.2, 0.2, 1.0],
+            [150.0, y, width, 15.0],
+            c.transform,
+            g,
+        );
+        label::text(
+            palette.text,
+            14,
+            &format!("{} ({})", name, count),
+            glyphs,
+            c.transform.trans(30.0, y + 13.0),
+            g,
+        );
+    }
+
+    // Draw a bar for the win rate of each level
+    let top = 95.0 + stats.kills.len() as f64 * 20.0;
+    label::text(
+        palette.text,
+        16,
+        "Win rate",
+        glyphs,
+        c.transform.trans(30.0, top),
+        g,
+    );
+    for (i, (name, record)) in stats.levels.iter().enumerate() {
+        let y = top + 10.0 + i as f64 * 20.0;
+        let rate = record.won as f64 / record.played.max(1) as f64;
+        rectangle(palette.widget, [150.0, y, 300.0, 15.0], c.transform, g);
+        rectangle(
+            [0.2, 0.6, 0.2, 1.0],
+            [150.0, y, 300.0 * rate, 15.0],
+            c.transform,
+            g,
+        );
+        label::text(
+            palette.text,
+            14,
+            &format!("{} ({}/{})", name, record.won, record.played),
+            glyphs,
+            c.transform.trans(30.0, y + 13.0),
+            g,
+        );
+    }
+}
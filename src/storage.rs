@@ -0,0 +1,120 @@
+use std::fs;
+use std::io::{self, Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+// Bytes packed files start with, which no text file does
+const PACKED_MAGIC: &[u8] = b"TDZ\x01";
+
+pub fn checksum(bytes: &[u8]) -> u64 {
+    // FNV-1a, which is simple and stable across platforms and builds
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+pub fn write_atomically(path: &str, contents: &str) -> io::Result<()> {
+    write_bytes_atomically(path, contents.as_bytes())
+}
+
+fn write_bytes_atomically(path: &str, contents: &[u8]) -> io::Result<()> {
+    // Write to a temporary file first and then swap it in, so a crash mid-write never leaves a half-written save
+    let temporary = format!("{}.tmp", path);
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+pub fn write_checked(path: &str, contents: &str) -> io::Result<()> {
+    // Finish the file with a checksum of everything before it, so damage can be noticed when it's read back
+    let contents = format!(
+        "{}checksum {:016x}\n",
+        contents,
+        checksum(contents.as_bytes())
+    );
+    write_atomically(path, &contents)
+}
+
+pub fn read_checked(path: &str) -> Result<Option<String>, String> {
+    // A missing file isn't an error, it just hasn't been saved yet
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("Couldn't read {}: {}", path, error)),
+    };
+    check_text(path, contents).map(Some)
+}
+
+fn check_text(path: &str, contents: String) -> Result<String, String> {
+    // Files saved before checksums were added are trusted as they are
+    let body = contents.trim_end_matches('\n');
+    let (body, expected) = match body.rsplit_once('\n') {
+        Some((body, last)) if last.starts_with("checksum ") => (format!("{}\n", body), last),
+        None if body.starts_with("checksum ") => (String::new(), body),
+        _ => return Ok(contents),
+    };
+    let actual = format!("checksum {:016x}", checksum(body.as_bytes()));
+    if expected != actual {
+        return Err(keep_damaged(path));
+    }
+    Ok(body)
+}
+
+fn keep_damaged(path: &str) -> String {
+    // Keep the damaged file around, since the next save would otherwise overwrite it
+    let damaged = format!("{}.damaged", path);
+    if let Err(error) = fs::copy(path, &damaged) {
+        eprintln!("Couldn't keep a copy of damaged {}: {}", path, error);
+    }
+    format!("{} is damaged, and a copy was kept as {}", path, damaged)
+}
+
+pub fn write_packed(path: &str, contents: &[u8]) -> io::Result<()> {
+    // Compress the contents behind a marker, and finish with a checksum of everything before it, like a checked
+    // text file
+    let mut encoder = DeflateEncoder::new(PACKED_MAGIC.to_vec(), Compression::best());
+    encoder.write_all(contents)?;
+    let mut packed = encoder.finish()?;
+    packed.extend(checksum(&packed).to_le_bytes());
+    write_bytes_atomically(path, &packed)
+}
+
+pub fn read_packed(path: &str) -> Result<Option<Vec<u8>>, String> {
+    // Files written before packing was added are read as checked text, and handed back as they are
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("Couldn't read {}: {}", path, error)),
+    };
+    if !contents.starts_with(PACKED_MAGIC) {
+        let text = String::from_utf8(contents)
+            .map_err(|_| format!("{} isn't a file the game wrote", path))?;
+        return check_text(path, text).map(|text| Some(text.into_bytes()));
+    }
+    let (packed, expected) = contents.split_at(contents.len().max(PACKED_MAGIC.len() + 8) - 8);
+    if expected.len() != 8 || checksum(packed).to_le_bytes() != expected {
+        return Err(keep_damaged(path));
+    }
+    let mut unpacked = Vec::new();
+    DeflateDecoder::new(&packed[PACKED_MAGIC.len()..])
+        .read_to_end(&mut unpacked)
+        .map_err(|error| format!("Couldn't unpack {}: {}", path, error))?;
+    Ok(Some(unpacked))
+}
+
+pub fn backup_path(path: &str, backup: usize) -> String {
+    format!("{}.{}", path, backup)
+}
+
+pub fn rotate_backups(path: &str, backups: usize) {
+    // Shift each backup one older, dropping the oldest, and make the current file the newest backup
+    for backup in (1..backups).rev() {
+        let _ = fs::rename(backup_path(path, backup), backup_path(path, backup + 1));
+    }
+    if backups > 0 {
+        let _ = fs::rename(path, backup_path(path, 1));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use crate::events::{GameEvent, Subscriber};
+use crate::label::{self, TextStyle};
+use crate::ui::{Frame, Ui};
+
+// Seconds the summary of a wave stays up if the player doesn't dismiss it
+const SUMMARY_DURATION: f32 = 8.;
+
+// What happened during the current wave, added up from the event log and started over whenever a wave starts
+#[derive(Clone, Default)]
+pub struct WaveTally {
+    // Stores the number of enemies killed this wave
+    killed: u32,
+    // Stores the number of enemies that reached the base this wave
+    leaked: u32,
+    // Stores the number of lives those enemies took
+    lives_lost: i32,
+    // Stores the resources earned from kills this wave
+    earned: i32,
+    // Stores the name and kills this wave of each tower that killed anything, by tower number
+    kills_by_tower: BTreeMap<usize, (String, u32)>,
+}
+
+impl WaveTally {
+    fn best_tower(&self) -> Option<(usize, &str, u32)> {
+        // The tower with the most kills this wave, with the earliest placed winning ties
+        self.kills_by_tower
+            .iter()
+            .max_by(|a, b| a.1 .1.cmp(&b.1 .1).then(b.0.cmp(a.0)))
+            .map(|(&number, (name, kills))| (number, name.as_str(), *kills))
+    }
+}
+
+impl Subscriber for WaveTally {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::WaveStarted { .. } => *self = WaveTally::default(),
+            GameEvent::EnemyKilled { tower, reward, .. } => {
+                self.killed += 1;
+                self.earned += reward;
+                if let Some((name, number)) = tower {
+                    self.kills_by_tower
+                        .entry(*number)
+                        .or_insert_with(|| (name.clone(), 0))
+                        .1 += 1;
+                }
+            }
+            GameEvent::BaseDamaged { lives_lost, .. } => {
+                self.leaked += 1;
+                self.lives_lost += lives_lost;
+            }
+            _ => {}
+        }
+    }
+}
+
+// The panel shown for a little while after a wave ends, summing up how it went
+pub struct WaveSummary {
+    // Stores the number of the wave that ended
+    wave: usize,
+    // Stores what happened during the wave that ended
+    tally: WaveTally,
+    // Stores how many more seconds the panel stays up
+    time_left: f32,
+}
+
+impl WaveSummary {
+    pub fn new(wave: usize, tally: WaveTally) -> Self {
+        WaveSummary {
+            wave,
+            tally,
+            time_left: SUMMARY_DURATION,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) -> bool {
+        // Count down, returning whether the panel should stay up
+        self.time_left -= dt;
+        self.time_left > 0.
+    }
+
+    pub fn draw(&self, ui: &mut Ui, frame: &mut Frame) -> bool {
+        // Draw the summary in the top right corner, out of the way of the map's middle, returning whether the player
+        // dismissed it
+        let tally = &self.tally;
+        ui.panel([400.0, 40.0, 230.0, 150.0], frame);
+        let style = TextStyle {
+            size: 12,
+            ..Default::default()
+        };
+        let leaked = if tally.leaked > 0 {
+            format!(
+                "Enemies leaked: {} (-{} lives)",
+                tally.leaked, tally.lives_lost
+            )
+        } else {
+            String::from("Enemies leaked: none")
+        };
+        let best = match tally.best_tower() {
+            Some((number, name, kills)) => {
+                format!("Best tower: {} #{} ({} kills)", name, number, kills)
+            }
+            None => String::from("Best tower: none"),
+        };
+        let lines = [
+            format!("Enemies killed: {}", tally.killed),
+            leaked,
+            format!("Resources earned: {}", tally.earned),
+            best,
+        ];
+        label::draw(
+            &format!("Wave {} over", self.wave),
+            &TextStyle { size: 16, ..style },
+            410.0,
+            62.0,
+            frame.glyphs,
+            frame.c,
+            frame.g,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            let y = 85.0 + i as f64 * 18.0;
+            label::draw(line, &style, 410.0, y, frame.glyphs, frame.c, frame.g);
+        }
+        ui.button(
+            "dismiss summary",
+            "Dismiss",
+            [530.0, 160.0, 90.0, 22.0],
+            true,
+            false,
+            frame,
+        )
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use crate::json::Json;
+use crate::storage;
+use crate::{Game, Outcome};
+
+// File the anonymous balance data is kept in, for players who've opted in
+pub const TELEMETRY_PATH: &str = "telemetry.json";
+// Longest an upload waits to connect, send, or hear back, so a dead endpoint doesn't leave threads hanging
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Balance data from one finished run, or added up over every one, with nothing in it that could identify the player
+#[derive(Default)]
+pub struct Telemetry {
+    // Stores the number of finished runs, by level name
+    runs: BTreeMap<String, u32>,
+    // Stores the number of those runs that were won, by level name
+    wins: BTreeMap<String, u32>,
+    // Stores the number of towers built, by tower type name
+    tower_picks: BTreeMap<String, u32>,
+    // Stores the number of runs that ended on each wave
+    waves_reached: BTreeMap<String, u32>,
+    // Stores the lives lost in runs that were lost, by the name of the enemy type that took them
+    loss_causes: BTreeMap<String, u32>,
+}
+
+fn counts(json: &Json, key: &str) -> BTreeMap<String, u32> {
+    // Anything that isn't an object of counts is treated as an empty one
+    match json.get(key) {
+        Some(Json::Object(fields)) => fields
+            .iter()
+            .filter_map(|(name, count)| Some((name.clone(), count.as_f64()? as u32)))
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+fn counts_json(counts: &BTreeMap<String, u32>) -> Json {
+    Json::Object(
+        counts
+            .iter()
+            .map(|(name, &count)| (name.clone(), Json::Number(count as f64)))
+            .collect(),
+    )
+}
+
+impl Telemetry {
+    pub fn load(path: &str) -> Self {
+        // Missing or unreadable data just means starting the totals over
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Telemetry::default();
+        };
+        match Json::parse(&contents) {
+            Ok(json) => Telemetry {
+                runs: counts(&json, "runs"),
+                wins: counts(&json, "wins"),
+                tower_picks: counts(&json, "tower_picks"),
+                waves_reached: counts(&json, "waves_reached"),
+                loss_causes: counts(&json, "loss_causes"),
+            },
+            Err(error) => {
+                eprintln!("Couldn't read balance data: {}", error);
+                Telemetry::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        storage::write_atomically(path, &self.to_json().to_string())
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            (String::from("runs"), counts_json(&self.runs)),
+            (String::from("wins"), counts_json(&self.wins)),
+            (String::from("tower_picks"), counts_json(&self.tower_picks)),
+            (
+                String::from("waves_reached"),
+                counts_json(&self.waves_reached),
+            ),
+            (String::from("loss_causes"), counts_json(&self.loss_causes)),
+        ])
+    }
+
+    pub fn record(&mut self, level: &str, game: &Game) -> Telemetry {
+        // Add a finished run to the totals, returning the run's own data, which is all that's sent anywhere, so
+        // whatever adds runs up elsewhere never counts one twice
+        let run = Telemetry::run(level, game);
+        for (totals, counts) in [
+            (&mut self.runs, &run.runs),
+            (&mut self.wins, &run.wins),
+            (&mut self.tower_picks, &run.tower_picks),
+            (&mut self.waves_reached, &run.waves_reached),
+            (&mut self.loss_causes, &run.loss_causes),
+        ] {
+            for (name, count) in counts.iter() {
+                *totals.entry(name.clone()).or_default() += count;
+            }
+        }
+        run
+    }
+
+    fn run(level: &str, game: &Game) -> Telemetry {
+        // The data for one finished run, with the enemies that got through counted as what lost it if it was lost
+        let stats = &game.state.stats;
+        let mut run = Telemetry::default();
+        run.runs.insert(level.to_string(), 1);
+        if game.state.outcome == Some(Outcome::Won) {
+            run.wins.insert(level.to_string(), 1);
+        }
+        run.tower_picks = stats.towers_built.clone();
+        run.waves_reached.insert(game.state.wave.to_string(), 1);
+        if game.state.outcome == Some(Outcome::Lost) {
+            run.loss_causes = stats.leaked.clone();
+        }
+        run
+    }
+}
+
+pub fn upload(endpoint: &str, body: String) {
+    // Send a run's data off in the background, so a slow endpoint never holds up the game, with any failure only logged
+    let endpoint = endpoint.to_string();
+    thread::spawn(move || {
+        if let Err(error) = post(&endpoint, &body) {
+            eprintln!("Couldn't upload balance data to {}: {}", endpoint, error);
+        }
+    });
+}
+
+fn post(endpoint: &str, body: &str) -> Result<(), String> {
+    // POST the JSON to a plain HTTP endpoint, like `http://example.com:8080/balance`, and check it was accepted
+    let rest = endpoint
+        .strip_prefix("http://")
+        .ok_or_else(|| String::from("Only http:// endpoints are supported"))?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let address = address
+        .to_socket_addrs()
+        .map_err(|error| error.to_string())?
+        .next()
+        .ok_or_else(|| format!("Couldn't find {}", host))?;
+    let mut stream =
+        TcpStream::connect_timeout(&address, UPLOAD_TIMEOUT).map_err(|error| error.to_string())?;
+    stream
+        .set_read_timeout(Some(UPLOAD_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(UPLOAD_TIMEOUT)))
+        .map_err(|error| error.to_string())?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        body
+    )
+    .map_err(|error| error.to_string())?;
+    let mut status = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status)
+        .map_err(|error| error.to_string())?;
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(format!("Endpoint answered {}", status.trim())),
+    }
+}
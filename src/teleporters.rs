@@ -0,0 +1,76 @@
+use crate::{Path, Point, TILE_SIZE};
+
+// Distance from the path within which a teleporter tile counts as being on it, in pixels
+pub const TELEPORTER_RANGE: f32 = TILE_SIZE / 2.;
+
+// A pair of tiles on the path, where enemies stepping onto the entry come out of the exit further along
+#[derive(Clone, Copy)]
+pub struct Teleporter {
+    // Stores how far along the path the entry is, in pixels
+    pub entry: f32,
+    // Stores how far along the path the exit is, in pixels
+    pub exit: f32,
+}
+
+impl Teleporter {
+    pub fn between(path: &Path, entry: &Point, exit: &Point) -> Result<Teleporter, String> {
+        // Both tiles have to be on the path, with the exit closer to the base than the entry
+        let (entry_distance, entry_gap) = path.nearest(entry);
+        let (exit_distance, exit_gap) = path.nearest(exit);
+        if entry_gap > TELEPORTER_RANGE || exit_gap > TELEPORTER_RANGE {
+            return Err(String::from("Teleporters have to be on the path"));
+        }
+        if exit_distance <= entry_distance {
+            return Err(String::from(
+                "Teleporters have to send enemies further along the path",
+            ));
+        }
+        Ok(Teleporter {
+            entry: entry_distance,
+            exit: exit_distance,
+        })
+    }
+}
+
+impl Path {
+    pub fn with_teleporters(mut self, teleporters: Vec<Teleporter>) -> Self {
+        self.teleporters = teleporters;
+        self
+    }
+
+    pub fn teleport(&self, from: f32, to: f32) -> f32 {
+        // Move an enemy that went from one distance to the other this tick through any entry it passed, keeping the
+        // distance it overshot the entry by, and on through any further ones that puts it past
+        let mut from = from;
+        let mut to = to;
+        while let Some(teleporter) = self
+            .teleporters
+            .iter()
+            .filter(|teleporter| from < teleporter.entry && teleporter.entry <= to)
+            .min_by(|a, b| a.entry.total_cmp(&b.entry))
+        {
+            from = teleporter.exit;
+            to = teleporter.exit + to - teleporter.entry;
+        }
+        to
+    }
+
+    pub fn skips(&self, distance: f32) -> bool {
+        // Whether enemies never walk the given stretch of path, because a teleporter carries them past it
+        self.teleporters
+            .iter()
+            .any(|teleporter| teleporter.entry < distance && distance < teleporter.exit)
+    }
+
+    pub fn remaining(&self, distance: f32) -> f32 {
+        // How far an enemy at the given distance still has to walk to reach the base, leaving out the stretches
+        // teleporters ahead of it will carry it past
+        let skipped: f32 = self
+            .teleporters
+            .iter()
+            .filter(|teleporter| teleporter.entry >= distance)
+            .map(|teleporter| teleporter.exit - teleporter.entry)
+            .sum();
+        (self.length() - distance - skipped).max(0.)
+    }
+}
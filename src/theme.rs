@@ -0,0 +1,231 @@
+use std::sync::Mutex;
+
+use piston_window::*;
+
+// Pixels between the scanlines drawn over the view by the retro theme
+const SCANLINE_SPACING: f64 = 3.;
+
+// A look for the whole game, which swaps the colors everything is drawn in
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Theme {
+    #[default]
+    Default,
+    Dark,
+    Retro,
+    HighContrast,
+}
+
+// Every theme, in the order the settings panel cycles through them
+pub const THEMES: [Theme; 4] = [
+    Theme::Default,
+    Theme::Dark,
+    Theme::Retro,
+    Theme::HighContrast,
+];
+
+// The colors a theme draws the game in
+#[derive(Clone, Copy)]
+pub struct Palette {
+    // Stores the color behind everything else
+    pub background: [f32; 4],
+    // Stores the color of tiles blocked by scenery
+    pub scenery: [f32; 4],
+    // Stores the color of water tiles
+    pub water: [f32; 4],
+    // Stores the color of the lane boats follow
+    pub lane: [f32; 4],
+    // Stores the color of the path enemies follow
+    pub path: [f32; 4],
+    // Stores the color of the player's base
+    pub base: [f32; 4],
+    // Stores the color of the player's cursor
+    pub cursor: [f32; 4],
+    // Stores the color of the player's towers
+    pub tower: [f32; 4],
+    // Stores the color of enemies towers can hit
+    pub enemy: [f32; 4],
+    // Stores the color of text
+    pub text: [f32; 4],
+    // Stores the color of the shadow under text drawn straight onto the map
+    pub text_shadow: [f32; 4],
+    // Stores the color of panels and tooltips
+    pub panel: [f32; 4],
+    // Stores the color of the borders round panels and widgets
+    pub border: [f32; 4],
+    // Stores the color of buttons and other widgets
+    pub widget: [f32; 4],
+    // Stores the color of a widget under the mouse
+    pub hover: [f32; 4],
+    // Stores the color of a widget being pressed
+    pub pressed: [f32; 4],
+    // Stores the color picking out selected widgets and filled sliders
+    pub accent: [f32; 4],
+    // Stores the color of the text on widgets that can't be used
+    pub disabled_text: [f32; 4],
+    // Stores whether scanlines are drawn over the view, like an old monitor
+    pub scanlines: bool,
+}
+
+const DEFAULT_PALETTE: Palette = Palette {
+    background: [1.0, 1.0, 1.0, 1.0],
+    scenery: [0.3, 0.5, 0.3, 1.0],
+    water: [0.55, 0.75, 0.95, 1.0],
+    lane: [0.35, 0.55, 0.85, 1.0],
+    path: [0.8, 0.7, 0.5, 1.0],
+    base: [0.0, 0.5, 0.0, 1.0],
+    cursor: [0.5, 0.5, 0.5, 1.0],
+    tower: [0.5, 0.5, 0.5, 1.0],
+    enemy: [1.0, 0.0, 0.0, 1.0],
+    text: [0.0, 0.0, 0.0, 1.0],
+    text_shadow: [1.0, 1.0, 1.0, 0.8],
+    panel: [0.95, 0.95, 0.95, 0.9],
+    border: [0.3, 0.3, 0.3, 1.0],
+    widget: [0.85, 0.85, 0.85, 1.0],
+    hover: [0.75, 0.8, 0.9, 1.0],
+    pressed: [0.55, 0.65, 0.8, 1.0],
+    accent: [0.2, 0.5, 0.8, 1.0],
+    disabled_text: [0.5, 0.5, 0.5, 1.0],
+    scanlines: false,
+};
+
+const DARK_PALETTE: Palette = Palette {
+    background: [0.12, 0.13, 0.15, 1.0],
+    scenery: [0.18, 0.3, 0.2, 1.0],
+    water: [0.15, 0.25, 0.4, 1.0],
+    lane: [0.25, 0.4, 0.65, 1.0],
+    path: [0.4, 0.35, 0.28, 1.0],
+    base: [0.2, 0.6, 0.3, 1.0],
+    cursor: [0.6, 0.6, 0.65, 1.0],
+    tower: [0.65, 0.65, 0.7, 1.0],
+    enemy: [0.95, 0.3, 0.3, 1.0],
+    text: [0.9, 0.9, 0.9, 1.0],
+    text_shadow: [0.0, 0.0, 0.0, 0.8],
+    panel: [0.2, 0.21, 0.24, 0.92],
+    border: [0.55, 0.55, 0.6, 1.0],
+    widget: [0.28, 0.29, 0.33, 1.0],
+    hover: [0.3, 0.38, 0.5, 1.0],
+    pressed: [0.25, 0.4, 0.6, 1.0],
+    accent: [0.4, 0.65, 0.95, 1.0],
+    disabled_text: [0.5, 0.5, 0.5, 1.0],
+    scanlines: false,
+};
+
+const RETRO_PALETTE: Palette = Palette {
+    background: [0.02, 0.06, 0.02, 1.0],
+    scenery: [0.1, 0.3, 0.1, 1.0],
+    water: [0.05, 0.2, 0.2, 1.0],
+    lane: [0.1, 0.45, 0.4, 1.0],
+    path: [0.15, 0.45, 0.15, 1.0],
+    base: [0.3, 0.9, 0.3, 1.0],
+    cursor: [0.6, 1.0, 0.6, 1.0],
+    tower: [0.45, 0.95, 0.45, 1.0],
+    enemy: [1.0, 0.7, 0.2, 1.0],
+    text: [0.4, 1.0, 0.4, 1.0],
+    text_shadow: [0.0, 0.0, 0.0, 0.8],
+    panel: [0.02, 0.1, 0.02, 0.92],
+    border: [0.3, 0.9, 0.3, 1.0],
+    widget: [0.05, 0.2, 0.05, 1.0],
+    hover: [0.1, 0.35, 0.1, 1.0],
+    pressed: [0.15, 0.5, 0.15, 1.0],
+    accent: [0.6, 1.0, 0.6, 1.0],
+    disabled_text: [0.2, 0.45, 0.2, 1.0],
+    scanlines: true,
+};
+
+const HIGH_CONTRAST_PALETTE: Palette = Palette {
+    background: [1.0, 1.0, 1.0, 1.0],
+    scenery: [0.0, 0.4, 0.0, 1.0],
+    water: [0.0, 0.35, 1.0, 1.0],
+    lane: [0.0, 0.0, 0.6, 1.0],
+    path: [0.25, 0.25, 0.25, 1.0],
+    base: [0.0, 0.7, 0.0, 1.0],
+    cursor: [1.0, 0.5, 0.0, 1.0],
+    tower: [0.0, 0.0, 0.8, 1.0],
+    enemy: [1.0, 0.0, 0.0, 1.0],
+    text: [0.0, 0.0, 0.0, 1.0],
+    text_shadow: [1.0, 1.0, 1.0, 1.0],
+    panel: [1.0, 1.0, 1.0, 1.0],
+    border: [0.0, 0.0, 0.0, 1.0],
+    widget: [1.0, 1.0, 1.0, 1.0],
+    hover: [1.0, 1.0, 0.0, 1.0],
+    pressed: [1.0, 0.8, 0.0, 1.0],
+    accent: [0.0, 0.0, 1.0, 1.0],
+    disabled_text: [0.4, 0.4, 0.4, 1.0],
+    scanlines: false,
+};
+
+// The theme everything is drawn in, shared so any module drawing something can look its colors up
+static CURRENT: Mutex<Theme> = Mutex::new(Theme::Default);
+
+impl Theme {
+    pub fn name(&self) -> &'static str {
+        // The name the theme is saved under in the settings file, and the folder its own assets are looked for in
+        match self {
+            Theme::Default => "default",
+            Theme::Dark => "dark",
+            Theme::Retro => "retro",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        THEMES.iter().copied().find(|theme| theme.name() == name)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Theme::Default => "Default",
+            Theme::Dark => "Dark",
+            Theme::Retro => "Retro CRT",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn next(&self) -> Theme {
+        let i = THEMES.iter().position(|theme| theme == self).unwrap_or(0);
+        THEMES[(i + 1) % THEMES.len()]
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            Theme::Default => DEFAULT_PALETTE,
+            Theme::Dark => DARK_PALETTE,
+            Theme::Retro => RETRO_PALETTE,
+            Theme::HighContrast => HIGH_CONTRAST_PALETTE,
+        }
+    }
+
+    pub fn asset_dir(&self) -> Option<String> {
+        // Themes other than the default can bring their own assets, which are looked for before the game's
+        (*self != Theme::Default).then(|| format!("themes/{}", self.name()))
+    }
+}
+
+pub fn set(theme: Theme) {
+    *CURRENT.lock().unwrap_or_else(|error| error.into_inner()) = theme;
+}
+
+pub fn palette() -> Palette {
+    CURRENT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .palette()
+}
+
+pub fn draw_overlay(c: Context, g: &mut G2d) {
+    // Draw anything the theme lays over the finished frame, which for the retro theme is scanlines
+    if !palette().scanlines {
+        return;
+    }
+    let [width, height] = c.get_view_size();
+    let mut y = 0.0;
+    while y < height {
+        rectangle([0.0, 0.0, 0.0, 0.25], [0.0, y, width, 1.0], c.transform, g);
+        y += SCANLINE_SPACING;
+    }
+}
+
+pub fn faded(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    // The same color drawn see-through, for things that are there but can't be used yet
+    [color[0], color[1], color[2], alpha]
+}
@@ -0,0 +1,327 @@
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::json::Json;
+use crate::level::{Level, TileMap};
+use crate::Point;
+
+// Names of the tile layers whose tiles block tower placement, compared case-insensitively
+const BLOCKING_LAYERS: [&str; 2] = ["blocked", "collision"];
+
+#[derive(Default)]
+struct TiledObject {
+    // Stores the object's name
+    name: String,
+    // Stores the object's class, such as spawn, waypoint, base, or path
+    kind: String,
+    // Stores the object's center, in pixels
+    center: Point,
+    // Stores the points of the object's polyline, in pixels, if it is one
+    polyline: Vec<Point>,
+}
+
+#[derive(Default)]
+struct TiledMap {
+    // Stores the width and height of the map, in tiles
+    size: (u32, u32),
+    // Stores the width and height of one tile, in pixels
+    tile_size: (f32, f32),
+    // Stores the map's custom properties, as name and value
+    properties: Vec<(String, String)>,
+    // Stores the tiles of the blocking layers, row by row, where zero is an empty tile
+    blocking_tiles: Vec<Vec<u32>>,
+    // Stores the objects in the map's visible object layers
+    objects: Vec<TiledObject>,
+}
+
+pub fn parse_tmx(contents: &str, default_name: &str) -> Result<Level, String> {
+    // Walk the XML, keeping track of which elements we're inside
+    let mut map = TiledMap::default();
+    let mut stack: Vec<String> = Vec::new();
+    let mut in_blocking_layer = false;
+    let mut hidden_group = false;
+    let mut csv = String::new();
+    for event in EventReader::from_str(contents) {
+        match event.map_err(|error| format!("Couldn't read TMX: {}", error))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|attribute| attribute.name.local_name == key)
+                        .map(|attribute| attribute.value.as_str())
+                };
+                let number = |key: &str| attribute(key).and_then(|value| value.parse().ok());
+                let visible = attribute("visible") != Some("0");
+                match name.local_name.as_str() {
+                    "map" => {
+                        map.size = (number("width").unwrap_or(0.) as u32, number("height").unwrap_or(0.) as u32);
+                        map.tile_size = (number("tilewidth").unwrap_or(1.), number("tileheight").unwrap_or(1.));
+                    }
+                    "property" if stack.len() == 2 && stack[0] == "map" => {
+                        if let (Some(key), Some(value)) = (attribute("name"), attribute("value")) {
+                            map.properties.push((key.to_string(), value.to_string()));
+                        }
+                    }
+                    "layer" => {
+                        in_blocking_layer = visible && is_blocking(attribute("name"));
+                        if in_blocking_layer {
+                            map.blocking_tiles.push(Vec::new());
+                        }
+                    }
+                    "data" if in_blocking_layer => match attribute("encoding") {
+                        None | Some("csv") => csv.clear(),
+                        Some(encoding) => {
+                            return Err(format!(
+                                "Tile layers encoded as {} aren't supported, save the map with CSV layers",
+                                encoding
+                            ))
+                        }
+                    },
+                    "tile" if in_blocking_layer && stack.last().map(String::as_str) == Some("data") => {
+                        let gid = attribute("gid").and_then(|gid| gid.parse().ok()).unwrap_or(0);
+                        if let Some(layer) = map.blocking_tiles.last_mut() {
+                            layer.push(gid);
+                        }
+                    }
+                    "objectgroup" => hidden_group = !visible,
+                    "object" if !hidden_group => {
+                        let (x, y) = (number("x").unwrap_or(0.), number("y").unwrap_or(0.));
+                        let (width, height) = (number("width").unwrap_or(0.), number("height").unwrap_or(0.));
+                        map.objects.push(TiledObject {
+                            name: attribute("name").unwrap_or("").to_string(),
+                            kind: attribute("type").or_else(|| attribute("class")).unwrap_or("").to_string(),
+                            center: Point::new(x + width / 2., y + height / 2.),
+                            polyline: Vec::new(),
+                        });
+                    }
+                    "polyline" if !hidden_group => {
+                        if let Some(object) = map.objects.last_mut() {
+                            let origin = object.center;
+                            for pair in attribute("points").unwrap_or("").split_whitespace() {
+                                let (x, y) = pair.split_once(',').unwrap_or((pair, "0"));
+                                let point = Point::new(x.parse().unwrap_or(0.), y.parse().unwrap_or(0.));
+                                object.polyline.push(origin.plus(&point));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                stack.push(name.local_name);
+            }
+            XmlEvent::Characters(text)
+                if in_blocking_layer && stack.last().map(String::as_str) == Some("data") =>
+            {
+                csv.push_str(&text);
+            }
+            XmlEvent::EndElement { name } => {
+                if name.local_name == "data" && in_blocking_layer && !csv.trim().is_empty() {
+                    if let Some(layer) = map.blocking_tiles.last_mut() {
+                        for gid in csv.split(',') {
+                            layer.push(gid.trim().parse().unwrap_or(0));
+                        }
+                    }
+                    csv.clear();
+                }
+                if name.local_name == "layer" {
+                    in_blocking_layer = false;
+                }
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    map.into_level(default_name)
+}
+
+pub fn parse_json(contents: &str, default_name: &str) -> Result<Level, String> {
+    let json = Json::parse(contents)?;
+    let number = |json: &Json, key: &str| json.get(key).and_then(Json::as_f64).unwrap_or(0.) as f32;
+    let mut map = TiledMap {
+        size: (
+            number(&json, "width") as u32,
+            number(&json, "height") as u32,
+        ),
+        tile_size: (number(&json, "tilewidth"), number(&json, "tileheight")),
+        ..Default::default()
+    };
+    for property in json.get("properties").map_or(&[][..], Json::as_array) {
+        let name = property.get("name").and_then(Json::as_str).unwrap_or("");
+        let value = match property.get("value") {
+            Some(Json::String(value)) => value.clone(),
+            Some(Json::Number(value)) => value.to_string(),
+            _ => continue,
+        };
+        map.properties.push((name.to_string(), value));
+    }
+
+    // Group layers can hold other layers, so walk them all
+    let mut layers: Vec<&Json> = json
+        .get("layers")
+        .map_or(&[][..], Json::as_array)
+        .iter()
+        .collect();
+    while let Some(layer) = layers.pop() {
+        if layer.get("visible").and_then(Json::as_bool) == Some(false) {
+            continue;
+        }
+        match layer.get("type").and_then(Json::as_str) {
+            Some("group") => layers.extend(layer.get("layers").map_or(&[][..], Json::as_array)),
+            Some("tilelayer") if is_blocking(layer.get("name").and_then(Json::as_str)) => {
+                let Some(Json::Array(data)) = layer.get("data") else {
+                    return Err(String::from(
+                        "Compressed tile layers aren't supported, save the map with CSV layers",
+                    ));
+                };
+                map.blocking_tiles.push(
+                    data.iter()
+                        .map(|gid| gid.as_f64().unwrap_or(0.) as u32)
+                        .collect(),
+                );
+            }
+            Some("objectgroup") => {
+                for object in layer.get("objects").map_or(&[][..], Json::as_array) {
+                    let origin = Point::new(number(object, "x"), number(object, "y"));
+                    let kind = object
+                        .get("type")
+                        .or_else(|| object.get("class"))
+                        .and_then(Json::as_str)
+                        .unwrap_or("");
+                    map.objects.push(TiledObject {
+                        name: object
+                            .get("name")
+                            .and_then(Json::as_str)
+                            .unwrap_or("")
+                            .to_string(),
+                        kind: kind.to_string(),
+                        center: origin.plus(&Point::new(
+                            number(object, "width") / 2.,
+                            number(object, "height") / 2.,
+                        )),
+                        polyline: object
+                            .get("polyline")
+                            .map_or(&[][..], Json::as_array)
+                            .iter()
+                            .map(|point| {
+                                origin.plus(&Point::new(number(point, "x"), number(point, "y")))
+                            })
+                            .collect(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    map.into_level(default_name)
+}
+
+fn is_blocking(layer_name: Option<&str>) -> bool {
+    layer_name.is_some_and(|name| {
+        BLOCKING_LAYERS
+            .iter()
+            .any(|blocking| name.eq_ignore_ascii_case(blocking))
+    })
+}
+
+impl TiledMap {
+    fn into_level(mut self, default_name: &str) -> Result<Level, String> {
+        if self.size.0 == 0 || self.size.1 == 0 || self.tile_size.0 <= 0. || self.tile_size.1 <= 0.
+        {
+            return Err(String::from("Map is missing its size or tile size"));
+        }
+        let property = |key: &str| {
+            self.properties
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+        };
+
+        let mut map = TileMap::new(self.size);
+        for layer in self.blocking_tiles.iter() {
+            for (i, gid) in layer.iter().enumerate() {
+                if *gid != 0 {
+                    map.set_blocked(i as u32 % self.size.0, i as u32 / self.size.0);
+                }
+            }
+        }
+
+        // The path runs from the spawn point, along any path polyline, through the waypoints in order of their names, to the base
+        let kind = |object: &TiledObject, kind: &str| object.kind.eq_ignore_ascii_case(kind);
+        self.objects
+            .sort_by_key(|object| object.name.parse::<u32>().unwrap_or(u32::MAX));
+        let mut points = Vec::new();
+        points.extend(
+            self.objects
+                .iter()
+                .filter(|object| kind(object, "spawn"))
+                .map(|object| object.center),
+        );
+        for object in self.objects.iter().filter(|object| kind(object, "path")) {
+            points.extend(object.polyline.iter().copied());
+        }
+        points.extend(
+            self.objects
+                .iter()
+                .filter(|object| kind(object, "waypoint"))
+                .map(|object| object.center),
+        );
+        points.extend(
+            self.objects
+                .iter()
+                .filter(|object| kind(object, "base"))
+                .map(|object| object.center),
+        );
+        // A spawn placed on the first point of a path polyline, the natural way to draw one, would otherwise leave a
+        // segment with no length at the start of the path
+        points.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+
+        // Slot objects mark the tiles towers can be built on, if the map has any
+        let slots = self
+            .objects
+            .iter()
+            .filter(|object| kind(object, "slot"))
+            .map(|object| {
+                Point::new(
+                    (object.center.x / self.tile_size.0).floor(),
+                    (object.center.y / self.tile_size.1).floor(),
+                )
+            })
+            .collect();
+
+        // Enemies are positioned by their top left corner, so shift tile centers back by half a tile
+        let waypoints = points
+            .iter()
+            .map(|point| {
+                Point::new(
+                    point.x / self.tile_size.0 - 0.5,
+                    point.y / self.tile_size.1 - 0.5,
+                )
+            })
+            .collect();
+
+        let level = Level {
+            name: property("name").unwrap_or_else(|| default_name.to_string()),
+            author: property("author").unwrap_or_else(|| String::from("Unknown")),
+            difficulty: property("difficulty")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1),
+            map,
+            waypoints,
+            layers: Vec::new(),
+            lane: Vec::new(),
+            teleporters: Vec::new(),
+            hazards: Vec::new(),
+            winds: Vec::new(),
+            gates: Vec::new(),
+            slots,
+            waves: Vec::new(),
+            objectives: Vec::new(),
+            bonus_objectives: Vec::new(),
+            convoys: Vec::new(),
+            behaviors: Vec::new(),
+            puzzle: None,
+        };
+        level.validate()?;
+        Ok(level)
+    }
+}
@@ -0,0 +1,159 @@
+use piston_window::*;
+
+use crate::label::{self, Align, TextStyle};
+use crate::theme;
+
+// Everything a widget needs to draw itself this frame
+pub struct Frame<'a, 'b> {
+    pub c: Context,
+    pub g: &'a mut G2d<'b>,
+    pub glyphs: &'a mut Glyphs,
+}
+
+// An immediate-mode widget layer: widgets are declared while drawing each frame, and report
+// whether the player interacted with them since the last frame. The menus and in-game panels use
+// it, while the settings and debug panels are laid out by egui through the gui module, and the
+// in-world HUD is drawn separately with the label module
+#[derive(Default)]
+pub struct Ui {
+    // Stores the mouse's position in the window, in pixels
+    mouse: [f64; 2],
+    // Stores whether the left mouse button was pressed since the last frame
+    pressed: bool,
+    // Stores whether the left mouse button was released since the last frame
+    released: bool,
+    // Stores the id of the widget the mouse button went down on, which keeps hold of it until release
+    active: Option<String>,
+    // Stores whether the mouse was over a widget or panel last frame
+    hovering: bool,
+    // Stores whether the mouse has been over a widget or panel so far this frame
+    hovered: bool,
+    // Stores whether widgets ignore the mouse, so nothing behind a modal dialog can be clicked
+    locked: bool,
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Ui::default()
+    }
+
+    pub fn handle(&mut self, event: &Event) -> bool {
+        // Track the mouse, returning whether a click landed on the UI rather than the map behind it
+        if let Some([x, y]) = event.mouse_cursor_args() {
+            self.mouse = [x, y];
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = event.press_args() {
+            self.pressed = true;
+            return self.hovering;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = event.release_args() {
+            self.released = true;
+            return self.hovering;
+        }
+        false
+    }
+
+    pub fn end_frame(&mut self) {
+        // Called once all of a frame's widgets are drawn, to forget the clicks they've handled
+        self.hovering = self.hovered;
+        self.hovered = false;
+        self.locked = false;
+        self.pressed = false;
+        if self.released {
+            self.released = false;
+            self.active = None;
+        }
+    }
+
+    pub fn lock(&mut self) {
+        // Widgets drawn from here on ignore the mouse, until the end of the frame or until unlocked
+        self.locked = true;
+    }
+
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
+
+    fn contains(&self, rect: [f64; 4]) -> bool {
+        let [x, y] = self.mouse;
+        x >= rect[0] && x < rect[0] + rect[2] && y >= rect[1] && y < rect[1] + rect[3]
+    }
+
+    fn interact(&mut self, id: &str, rect: [f64; 4], enabled: bool) -> (bool, bool, bool) {
+        // Work out whether the widget is hovered, held down, and clicked, in that order
+        let hovered = self.contains(rect);
+        self.hovered |= hovered;
+        if !enabled || self.locked {
+            return (false, false, false);
+        }
+        if hovered && self.pressed && self.active.is_none() {
+            self.active = Some(id.to_string());
+        }
+        let held = self.active.as_deref() == Some(id);
+        let clicked = held && hovered && self.released;
+        (hovered, held, clicked)
+    }
+
+    pub fn panel(&mut self, rect: [f64; 4], frame: &mut Frame) {
+        // Panels swallow clicks, so the map behind them can't be clicked through them
+        self.hovered |= self.contains(rect);
+        let c = frame.c;
+        let palette = theme::palette();
+        rectangle(palette.panel, rect, c.transform, frame.g);
+        Rectangle::new_border(palette.border, 1.0).draw(rect, &c.draw_state, c.transform, frame.g);
+    }
+
+    pub fn button(
+        &mut self,
+        id: &str,
+        caption: &str,
+        rect: [f64; 4],
+        enabled: bool,
+        selected: bool,
+        frame: &mut Frame,
+    ) -> bool {
+        // Draw a button that reacts to hovering and pressing, returning whether it was clicked
+        let (hovered, held, clicked) = self.interact(id, rect, enabled);
+        let c = frame.c;
+        let palette = theme::palette();
+        let color = if held && hovered {
+            palette.pressed
+        } else if hovered {
+            palette.hover
+        } else {
+            palette.widget
+        };
+        rectangle(color, rect, c.transform, frame.g);
+        let border = if selected {
+            palette.accent
+        } else {
+            palette.border
+        };
+        Rectangle::new_border(border, if selected { 2.0 } else { 1.0 }).draw(
+            rect,
+            &c.draw_state,
+            c.transform,
+            frame.g,
+        );
+        let style = TextStyle {
+            size: 14,
+            color: if enabled {
+                palette.text
+            } else {
+                palette.disabled_text
+            },
+            align: Align::Center,
+            ..Default::default()
+        };
+        label::draw(
+            caption,
+            &style,
+            rect[0] + rect[2] / 2.,
+            rect[1] + rect[3] / 2. + 5.,
+            frame.glyphs,
+            c,
+            frame.g,
+        );
+        clicked
+    }
+}
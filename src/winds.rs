@@ -0,0 +1,79 @@
+use crate::level::{parse_number, Layer};
+use crate::{Game, Point, TILE_SIZE};
+use std::fmt;
+
+// A rectangle of tiles where wind or a conveyor pushes enemies along, with sizes in tiles in a level file and in
+// pixels once the game starts
+#[derive(Clone)]
+pub struct Wind {
+    // Stores the top left corner of the area
+    pub corner: Point,
+    // Stores the width and height of the area
+    pub size: Point,
+    // Stores the push given to enemies in the area, per second, where pushing along the path speeds them up,
+    // against it slows them down, and across it shoves them to one side
+    pub force: Point,
+}
+
+impl Wind {
+    pub fn parse(value: &str) -> Result<Wind, String> {
+        // Winds are written as the area's corner and size followed by the push, like "4 10 3 2 1 0"
+        let numbers = value
+            .split_whitespace()
+            .map(parse_number)
+            .collect::<Result<Vec<f32>, _>>()?;
+        let [x, y, width, height, dx, dy] = numbers[..] else {
+            return Err(format!("Expected six numbers: {}", value));
+        };
+        if width <= 0. || height <= 0. {
+            return Err(format!("Wind needs a width and height: {}", value));
+        }
+        Ok(Wind {
+            corner: Point::new(x, y),
+            size: Point::new(width, height),
+            force: Point::new(dx, dy),
+        })
+    }
+
+    pub fn scale(&self, factor: f32) -> Wind {
+        Wind {
+            corner: self.corner.scale(factor),
+            size: self.size.scale(factor),
+            force: self.force.scale(factor),
+        }
+    }
+
+    pub fn covers(&self, position: &Point) -> bool {
+        // Anything whose center is in the area is caught by it
+        let center = position.plus(&Point::new(TILE_SIZE / 2., TILE_SIZE / 2.));
+        center.x >= self.corner.x
+            && center.y >= self.corner.y
+            && center.x < self.corner.x + self.size.x
+            && center.y < self.corner.y + self.size.y
+    }
+}
+
+impl fmt::Display for Wind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.corner.x, self.corner.y, self.size.x, self.size.y, self.force.x, self.force.y
+        )
+    }
+}
+
+impl Game {
+    pub fn update_winds(&mut self) {
+        // Work out the push on each enemy from the area it's standing in, which it feels as it moves, while
+        // enemies underground are out of the wind
+        let winds = &self.winds;
+        for enemy in self.state.enemies.iter_mut() {
+            enemy.wind = winds
+                .iter()
+                .find(|wind| wind.covers(&enemy.position))
+                .filter(|_| !enemy.burrowed && enemy.layer != Layer::Tunnel)
+                .map_or(Point::new(0., 0.), |wind| wind.force);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use piston_window::*;
+
+use crate::content;
+use crate::label;
+use crate::level::Level;
+use crate::storage;
+use crate::theme;
+
+// Directory imported community levels are kept in
+pub const COMMUNITY_LEVELS_DIR: &str = "community";
+
+pub struct CommunityLevel {
+    // Stores the path of the level file
+    pub path: String,
+    // Stores the level itself
+    pub level: Level,
+    // Stores the checksum of the level file, for comparing against the one it was shared with
+    pub checksum: u64,
+}
+
+fn validate(contents: &str) -> Result<Level, String> {
+    // A level is only valid if it parses and only sends enemies the game knows about
+    let level = Level::parse(contents)?;
+    content::new_game(&level, 0)?;
+    Ok(level)
+}
+
+pub fn export(level: &Level, path: &str) -> Result<u64, String> {
+    let contents = level.to_string();
+    fs::write(path, &contents).map_err(|error| format!("Couldn't write {}: {}", path, error))?;
+    Ok(storage::checksum(contents.as_bytes()))
+}
+
+fn destination(file_name: &Path, checksum: u64) -> Result<PathBuf, String> {
+    // Keep the file's name unless a different level was already imported under it, in which case number it like
+    // bastion-2.lvl, reusing any copy of the same level that's already there
+    let stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = file_name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut number = 1;
+    loop {
+        let destination = match number {
+            1 => Path::new(COMMUNITY_LEVELS_DIR).join(file_name),
+            _ => Path::new(COMMUNITY_LEVELS_DIR).join(format!("{}-{}{}", stem, number, extension)),
+        };
+        match fs::read(&destination) {
+            Ok(existing) if storage::checksum(&existing) != checksum => number += 1,
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                return Err(format!(
+                    "Couldn't read {}: {}",
+                    destination.display(),
+                    error
+                ))
+            }
+            _ => return Ok(destination),
+        }
+    }
+}
+
+pub fn import(path: &str) -> Result<CommunityLevel, String> {
+    // Copy the file unchanged, so its checksum still matches the one it was shared with
+    let contents =
+        fs::read_to_string(path).map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+    let level = validate(&contents)?;
+    let checksum = storage::checksum(contents.as_bytes());
+    let file_name = Path::new(path)
+        .file_name()
+        .ok_or_else(|| format!("Not a file: {}", path))?;
+    fs::create_dir_all(COMMUNITY_LEVELS_DIR)
+        .map_err(|error| format!("Couldn't create {}: {}", COMMUNITY_LEVELS_DIR, error))?;
+    let destination = destination(Path::new(file_name), checksum)?;
+    fs::write(&destination, &contents)
+        .map_err(|error| format!("Couldn't write {}: {}", destination.display(), error))?;
+    Ok(CommunityLevel {
+        path: destination.display().to_string(),
+        level,
+        checksum,
+    })
+}
+
+pub fn browse() -> Vec<CommunityLevel> {
+    // List the imported levels that are still valid, sorted by file name
+    let Ok(entries) = fs::read_dir(COMMUNITY_LEVELS_DIR) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let contents = fs::read_to_string(&path).ok()?;
+            let level = validate(&contents).ok()?;
+            Some(CommunityLevel {
+                path: path.display().to_string(),
+                level,
+                checksum: storage::checksum(contents.as_bytes()),
+            })
+        })
+        .collect()
+}
+
+pub fn draw(levels: &[CommunityLevel], c: Context, g: &mut G2d, glyphs: &mut Glyphs) {
+    let palette = theme::palette();
+    // Cover the map with a panel listing the imported levels
+    rectangle(palette.panel, [20.0, 20.0, 600.0, 440.0], c.transform, g);
+    label::text(
+        palette.text,
+        18,
+        "Community levels - play one with --level <file>",
+        glyphs,
+        c.transform.trans(30.0, 45.0),
+        g,
+    );
+    if levels.is_empty() {
+        label::text(
+            palette.text,
+            14,
+            "No levels imported yet - add one with --import-level <file>",
+            glyphs,
+            c.transform.trans(30.0, 75.0),
+            g,
+        );
+    }
+    for (i, community_level) in levels.iter().enumerate() {
+        let y = 75.0 + i as f64 * 40.0;
+        let level = &community_level.level;
+        label::text(
+            palette.text,
+            16,
+            &format!(
+                "{} by {} (difficulty {})",
+                level.name, level.author, level.difficulty
+            ),
+            glyphs,
+            c.transform.trans(30.0, y),
+            g,
+        );
+        label::text(
+            [0.3, 0.3, 0.3, 1.0],
+            12,
+            &format!(
+                "{}  checksum {:016x}",
+                community_level.path, community_level.checksum
+            ),
+            glyphs,
+            c.transform.trans(30.0, y + 16.0),
+            g,
+        );
+    }
+}